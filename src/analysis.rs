@@ -0,0 +1,1094 @@
+//! Checks of particular aspects of a plan, for verifying them automatically.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::expr::{self, Expr};
+use crate::{ExplainError, ExplainItem, ExplainPlan, Trigger, WorkerDetail};
+
+/// How a partitioned table was scanned by one `Append` or `Merge Append` node,
+/// see [`partition_summary()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionSummary {
+    /// The alias of the partitioned table in the query, e.g. `orders` for
+    /// partitions scanned as `orders_1`, `orders_2` and so on.
+    pub table: String,
+
+    /// Names of the partitions left in the plan, in plan order.
+    pub partitions: Vec<String>,
+
+    /// Number of partitions in the plan which were scanned. Same as the length of
+    /// `partitions` for plans captured without `ANALYZE`.
+    pub scanned: usize,
+
+    /// Number of partitions pruned when the executor started, reported by
+    /// PostgreSQL as `Subplans Removed`, e.g. because of parameter values.
+    pub removed: u64,
+
+    /// Number of partitions in the plan which were never scanned, because they
+    /// were pruned while executing, e.g. based on the result of a subquery. Only
+    /// known with `ANALYZE`.
+    pub never_executed: usize,
+}
+
+impl PartitionSummary {
+    /// Returns the number of partitions pruned by the executor. Partitions pruned
+    /// by the planner don't show up in the plan at all, so are not counted.
+    pub fn pruned(&self) -> u64 {
+        self.removed + self.never_executed as u64
+    }
+}
+
+/// Reports, for each partitioned table scanned through an `Append` or
+/// `Merge Append` node, how many of its partitions were scanned and pruned.
+///
+/// Partitions are recognized by their aliases, which PostgreSQL derives from the
+/// alias of the partitioned table by appending `_1`, `_2` and so on. Partitions
+/// pruned by the planner, e.g. because of constants in the query, are left out of
+/// the plan entirely; compare `partitions` with the catalog to count those.
+///
+/// Example:
+/// ```rust
+/// let plan = query.wrap_explain().explain_analyze(&mut conn)?;
+/// for summary in analysis::partition_summary(&plan) {
+///     assert!(summary.scanned <= 1, "{} scans {:?}", summary.table, summary.partitions);
+/// }
+/// ```
+pub fn partition_summary(plan: &ExplainPlan) -> Vec<PartitionSummary> {
+    let mut summaries = Vec::new();
+    for append in plan
+        .nodes()
+        .filter(|node| matches!(node.node_type.as_str(), "Append" | "Merge Append"))
+    {
+        let first = summaries.len();
+        let members = append.plans.iter().filter(|child| {
+            child.parent_relationship.is_none()
+                || child.parent_relationship.as_deref() == Some("Member")
+        });
+        for member in members {
+            let Some((table, partition)) = member.nodes().find_map(partition_of) else {
+                continue;
+            };
+            let summary = match summaries[first..]
+                .iter()
+                .position(|summary: &PartitionSummary| summary.table == table)
+            {
+                Some(i) => &mut summaries[first + i],
+                None => {
+                    summaries.push(PartitionSummary {
+                        table: table.to_owned(),
+                        partitions: Vec::new(),
+                        scanned: 0,
+                        removed: 0,
+                        never_executed: 0,
+                    });
+                    summaries.last_mut().expect("just pushed")
+                }
+            };
+            summary.partitions.push(partition.to_owned());
+            if member.actual_loops == Some(0) {
+                summary.never_executed += 1;
+            } else {
+                summary.scanned += 1;
+            }
+        }
+        if let Some(summary) = summaries.get_mut(first) {
+            summary.removed = append.subplans_removed.unwrap_or_default();
+        }
+    }
+    summaries
+}
+
+/// Returns the alias of the partitioned table and the name of the partition
+/// scanned by `node`, if it scans a partition.
+fn partition_of(node: &ExplainPlan) -> Option<(&str, &str)> {
+    let relation = node.relation_name.as_deref()?;
+    let (table, number) = node.alias.as_deref()?.rsplit_once('_')?;
+    let is_number = !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit());
+    is_number.then_some((table, relation))
+}
+
+/// Shared buffers found in the cache and read from disk or the OS cache.
+/// Reported with `BUFFERS`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferHits {
+    /// Blocks found in shared buffers.
+    pub hit: u64,
+
+    /// Blocks read into shared buffers.
+    pub read: u64,
+}
+
+impl BufferHits {
+    /// Returns the share of blocks found in the cache, between 0.0 and 1.0, or
+    /// `None` if no blocks were accessed.
+    pub fn ratio(&self) -> Option<f64> {
+        let total = self.hit + self.read;
+        (total > 0).then(|| self.hit as f64 / total as f64)
+    }
+}
+
+impl std::ops::AddAssign for BufferHits {
+    fn add_assign(&mut self, other: Self) {
+        self.hit += other.hit;
+        self.read += other.read;
+    }
+}
+
+impl ExplainPlan {
+    /// Returns the shared buffers accessed by this node and its children, or
+    /// `None` if the plan was captured without `BUFFERS`.
+    pub fn buffer_hits(&self) -> Option<BufferHits> {
+        (self.shared_hit_blocks.is_some() || self.shared_read_blocks.is_some()).then(|| {
+            BufferHits {
+                hit: self.shared_hit_blocks.unwrap_or_default(),
+                read: self.shared_read_blocks.unwrap_or_default(),
+            }
+        })
+    }
+
+    /// Returns the shared buffers accessed by this node itself, excluding those of
+    /// its children, or `None` if the plan was captured without `BUFFERS`.
+    ///
+    /// PostgreSQL reports buffers including all children, like times, so the I/O
+    /// of a `Hash Join` would otherwise be attributed to every node above the scan
+    /// which did it. Counts are totals over all loops, so no per-loop scaling is
+    /// needed, unlike for [`total_actual_time()`](Self::total_actual_time).
+    ///
+    /// Example:
+    /// ```rust
+    /// let options = ExplainOptions::default().analyze(true).buffers(true);
+    /// let plan = query.wrap_explain().options(options).explain(&mut conn)?;
+    /// for node in plan.nodes() {
+    ///     if let Some(own) = node.exclusive_buffer_hits() {
+    ///         println!("{}: {} blocks read", node.node_type, own.read);
+    ///     }
+    /// }
+    /// ```
+    pub fn exclusive_buffer_hits(&self) -> Option<BufferHits> {
+        let mut own = self.buffer_hits()?;
+        for child in self.plans.iter().filter_map(ExplainPlan::buffer_hits) {
+            own.hit = own.hit.saturating_sub(child.hit);
+            own.read = own.read.saturating_sub(child.read);
+        }
+        Some(own)
+    }
+}
+
+/// Returns the buffer cache hit ratio of the whole plan, between 0.0 and 1.0, or
+/// `None` if it was captured without `BUFFERS` or accessed no blocks.
+///
+/// Example:
+/// ```rust
+/// let options = ExplainOptions::default().analyze(true).buffers(true);
+/// let plan = query.wrap_explain().options(options).explain(&mut conn)?;
+/// if analysis::hit_ratio(&plan).is_some_and(|ratio| ratio < 0.9) {
+///     alert(&plan);
+/// }
+/// ```
+pub fn hit_ratio(plan: &ExplainPlan) -> Option<f64> {
+    plan.buffer_hits()?.ratio()
+}
+
+/// Sums the shared buffers accessed by the nodes scanning each relation, by
+/// relation name, e.g. to find the tables which miss the cache most.
+///
+/// Each node counts only the blocks it accessed itself, so the index blocks read
+/// by a `Bitmap Index Scan` are not attributed to the table scanned by the
+/// `Bitmap Heap Scan` above it. Empty for plans captured without `BUFFERS`.
+pub fn relation_buffer_hits(plan: &ExplainPlan) -> BTreeMap<String, BufferHits> {
+    let mut relations = BTreeMap::<String, BufferHits>::new();
+    for node in plan.nodes() {
+        let Some(relation) = node.relation_name.as_ref().or(node.index_name.as_ref()) else {
+            continue;
+        };
+        if let Some(hits) = node.exclusive_buffer_hits() {
+            *relations.entry(relation.clone()).or_default() += hits;
+        }
+    }
+    relations
+}
+
+/// How well the parallel parts of a plan used their workers, see
+/// [`parallel_report()`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParallelReport {
+    /// One entry per `Gather` or `Gather Merge` node, in plan order.
+    pub gathers: Vec<GatherStats>,
+
+    /// Share of the execution time spent below `Gather` nodes, between 0.0 and
+    /// 1.0. `None` for plans captured without `ANALYZE`.
+    pub gather_time_fraction: Option<f64>,
+}
+
+/// Statistics of one `Gather` or `Gather Merge` node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatherStats {
+    /// `Gather` or `Gather Merge`.
+    pub node_type: String,
+
+    /// Number of workers the planner asked for.
+    pub workers_planned: Option<u64>,
+
+    /// Number of workers actually launched, fewer than planned when
+    /// `max_parallel_workers` is exhausted. Reported with `ANALYZE`.
+    pub workers_launched: Option<u64>,
+
+    /// Estimated speedup over running the parallel part in a single process: the
+    /// time all processes spent in it divided by the time the `Gather` took. Ideally
+    /// close to the number of launched workers plus one for the participating
+    /// leader. Reported with `ANALYZE`.
+    pub speedup: Option<f64>,
+
+    /// How unevenly work was spread over the workers: the time of the slowest
+    /// worker divided by the mean, so 1.0 is a perfect balance. Requires
+    /// `ANALYZE`, `VERBOSE` and timing, and at least one worker.
+    pub imbalance: Option<f64>,
+}
+
+/// Reports how the parallel parts of a plan performed, e.g. to find queries which
+/// plan workers but don't get them, or gain little from them.
+///
+/// Example:
+/// ```rust
+/// let options = ExplainOptions::default().analyze(true).verbose(true);
+/// let plan = query.wrap_explain().options(options).explain(&mut conn)?;
+/// for gather in analysis::parallel_report(&plan).gathers {
+///     if gather.workers_launched < gather.workers_planned {
+///         println!("only {:?} of {:?} workers launched", gather.workers_launched, gather.workers_planned);
+///     }
+/// }
+/// ```
+pub fn parallel_report(plan: &ExplainPlan) -> ParallelReport {
+    let gathers: Vec<_> = plan
+        .nodes()
+        .filter(|node| matches!(node.node_type.as_str(), "Gather" | "Gather Merge"))
+        .collect();
+    let gather_time_fraction = plan
+        .actual_total_time
+        .filter(|total| *total > 0.0)
+        .and_then(|total| {
+            let gathered = gathers
+                .iter()
+                .map(|gather| gather.total_actual_time())
+                .sum::<Option<f64>>()?;
+            Some((gathered / total).min(1.0))
+        });
+    ParallelReport {
+        gathers: gathers.into_iter().map(gather_stats).collect(),
+        gather_time_fraction,
+    }
+}
+
+fn gather_stats(gather: &ExplainPlan) -> GatherStats {
+    let child = gather.plans.first();
+    let speedup = child.and_then(|child| {
+        let wall = gather.total_actual_time()?;
+        let work = child.total_actual_time()?;
+        (wall > 0.0).then(|| work / wall)
+    });
+    let imbalance = child
+        .and_then(|child| child.worker_details().ok())
+        .filter(|workers| !workers.is_empty())
+        .and_then(|workers| {
+            let times = workers
+                .iter()
+                .map(WorkerDetail::total_actual_time)
+                .collect::<Option<Vec<f64>>>()?;
+            let mean = times.iter().sum::<f64>() / times.len() as f64;
+            (mean > 0.0).then(|| times.iter().copied().fold(0.0, f64::max) / mean)
+        });
+    GatherStats {
+        node_type: gather.node_type.clone(),
+        workers_planned: gather.workers_planned,
+        workers_launched: gather.workers_launched,
+        speedup,
+        imbalance,
+    }
+}
+
+/// The memory footprint of a plan, see [`memory_estimate()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    /// Memory in kilobytes the nodes reported using for sorts, hash tables,
+    /// `Memoize` caches and tuplestores, summed over parallel workers. Only
+    /// known with `ANALYZE`; memory of sorts and hash tables which spilled to disk
+    /// is not included.
+    pub measured_kb: u64,
+
+    /// Number of sorts, tuplestores and bitmaps the plan may keep in memory at
+    /// once, each limited by `work_mem`, counted once per process running them.
+    pub work_mem_consumers: u64,
+
+    /// Number of hash tables the plan may keep in memory at once, each limited by
+    /// `work_mem * hash_mem_multiplier`, counted once per process building them.
+    pub hash_mem_consumers: u64,
+}
+
+impl MemoryEstimate {
+    /// Returns the most memory in kilobytes the plan may use for its work areas
+    /// with the given settings, e.g. `memory_estimate(&plan).budget_kb(4096, 2.0)`
+    /// for PostgreSQL's defaults.
+    pub fn budget_kb(&self, work_mem_kb: u64, hash_mem_multiplier: f64) -> u64 {
+        let hash_mem_kb = (work_mem_kb as f64 * hash_mem_multiplier) as u64;
+        self.work_mem_consumers * work_mem_kb + self.hash_mem_consumers * hash_mem_kb
+    }
+}
+
+/// Sums the memory indicators of all nodes of a plan into an estimate of its
+/// footprint, e.g. to catch queries which may use many times `work_mem`.
+///
+/// Nodes below a `Gather` count once per launched worker, plus the leader, except
+/// for hash tables shared by a `Parallel Hash`.
+///
+/// Example:
+/// ```rust
+/// let plan = query.wrap_explain().explain(&mut conn)?;
+/// let budget = analysis::memory_estimate(&plan).budget_kb(64 * 1024, 2.0);
+/// assert!(budget < 1024 * 1024, "may use up to {budget} kB");
+/// ```
+pub fn memory_estimate(plan: &ExplainPlan) -> MemoryEstimate {
+    let mut estimate = MemoryEstimate::default();
+    add_memory(&mut estimate, plan, 1);
+    estimate
+}
+
+fn add_memory(estimate: &mut MemoryEstimate, node: &ExplainPlan, processes: u64) {
+    let hashed = matches!(node.strategy.as_deref(), Some("Hashed" | "Mixed"));
+    match node.node_type.as_str() {
+        "Hash" if node.parallel_aware => estimate.hash_mem_consumers += 1,
+        "Hash" | "Memoize" => estimate.hash_mem_consumers += processes,
+        "Aggregate" | "SetOp" if hashed => estimate.hash_mem_consumers += processes,
+        "Sort" | "Incremental Sort" | "Material" | "WindowAgg" | "Bitmap Index Scan"
+        | "Recursive Union" => estimate.work_mem_consumers += processes,
+        _ => {}
+    }
+
+    if node.sort_space_type.as_deref() == Some("Memory") {
+        estimate.measured_kb += node.sort_space_used.unwrap_or_default();
+    }
+    if node.storage.as_deref() == Some("Memory") {
+        estimate.measured_kb += node.maximum_storage.unwrap_or_default();
+    }
+    estimate.measured_kb += node.peak_memory_usage.unwrap_or_default();
+    for worker in node.worker_details().unwrap_or_default() {
+        if worker.sort_space_type.as_deref() == Some("Memory") {
+            estimate.measured_kb += worker.sort_space_used.unwrap_or_default();
+        }
+        estimate.measured_kb += worker.peak_memory_usage.unwrap_or_default();
+    }
+
+    let processes = match node.node_type.as_str() {
+        "Gather" | "Gather Merge" => {
+            1 + node
+                .workers_launched
+                .or(node.workers_planned)
+                .unwrap_or_default()
+        }
+        _ => processes,
+    };
+    for child in &node.plans {
+        add_memory(estimate, child, processes);
+    }
+}
+
+/// Returns the smallest `work_mem` in kilobytes, rounded up to a power of two
+/// and at least 1 MB, with which the sorts, hash tables and bitmaps of the plan
+/// would have fit into memory, or `None` if none of them spilled. Requires
+/// `ANALYZE`.
+///
+/// The memory needed is extrapolated from what spilled: sorts need about twice
+/// their disk space in memory, hash joins their peak memory times the number of
+/// batches, and hash aggregates their peak memory plus their disk usage. Hash
+/// tables may use `work_mem * hash_mem_multiplier`, so pass the server's setting,
+/// 2.0 by default since PostgreSQL 15.
+///
+/// Example:
+/// ```rust
+/// let plan = query.wrap_explain().explain_analyze(&mut conn)?;
+/// if let Some(kb) = analysis::recommended_work_mem(&plan, 2.0) {
+///     conn.batch_execute(&format!("SET work_mem = '{kb}kB'"))?;
+/// }
+/// ```
+pub fn recommended_work_mem(plan: &ExplainPlan, hash_mem_multiplier: f64) -> Option<u64> {
+    let needed = plan
+        .nodes()
+        .filter_map(|node| work_mem_needed(node, hash_mem_multiplier))
+        .max()?;
+    Some(needed.max(1024).next_power_of_two())
+}
+
+/// Returns the `work_mem` in kilobytes `node` would have needed to stay in memory,
+/// if it spilled to disk.
+pub(crate) fn work_mem_needed(node: &ExplainPlan, hash_mem_multiplier: f64) -> Option<u64> {
+    let per_hash_mem = |kb: u64| (kb as f64 / hash_mem_multiplier.max(1.0)).ceil() as u64;
+    match node.node_type.as_str() {
+        "Sort" | "Incremental Sort" => {
+            let disk_kb = |space_type: Option<&str>, used: Option<u64>| {
+                (space_type == Some("Disk")).then(|| used.unwrap_or_default())
+            };
+            let leader = disk_kb(node.sort_space_type.as_deref(), node.sort_space_used);
+            let workers = node
+                .worker_details()
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|worker| {
+                    disk_kb(worker.sort_space_type.as_deref(), worker.sort_space_used)
+                });
+            leader.into_iter().chain(workers).max().map(|kb| kb * 2)
+        }
+        "Hash" => {
+            let batches = node.hash_batches.filter(|batches| *batches > 1)?;
+            Some(per_hash_mem(node.peak_memory_usage? * batches))
+        }
+        "Aggregate" | "SetOp" if node.disk_usage.is_some_and(|kb| kb > 0) => Some(per_hash_mem(
+            node.peak_memory_usage.unwrap_or_default() + node.disk_usage.unwrap_or_default(),
+        )),
+        "Bitmap Heap Scan" => {
+            // A lossy bitmap tracks whole pages; tracking each of them exactly takes
+            // roughly 64 bytes per page.
+            node.lossy_heap_blocks.filter(|blocks| *blocks > 0)?;
+            let blocks = node.exact_heap_blocks.unwrap_or_default() + node.lossy_heap_blocks?;
+            Some((blocks * 64).div_ceil(1024))
+        }
+        _ => None,
+    }
+}
+
+/// Lists the columns each relation is filtered or joined on, by relation name,
+/// e.g. as input for index advisors or schema reviews.
+///
+/// The columns are taken from the conditions of all nodes, see
+/// [`expr::conditions()`]. Qualified columns are attributed to the relation
+/// scanned under that alias; unqualified ones, which PostgreSQL prints in
+/// conditions of scans unless `VERBOSE` is used, to the relation the node scans,
+/// or for a `Bitmap Index Scan`, to the relation of the `Bitmap Heap Scan`
+/// above it. Columns qualified by an alias which doesn't belong to a scanned
+/// relation, e.g. of a subquery, are listed under the alias. Conditions which
+/// [`expr::parse()`] can't parse are skipped.
+///
+/// Example:
+/// ```rust
+/// let plan = query.wrap_explain().explain(&mut conn)?;
+/// for (relation, columns) in analysis::referenced_columns(&plan) {
+///     println!("{relation}: {}", columns.into_iter().collect::<Vec<_>>().join(", "));
+/// }
+/// ```
+pub fn referenced_columns(plan: &ExplainPlan) -> BTreeMap<String, BTreeSet<String>> {
+    let aliases = plan
+        .nodes()
+        .filter_map(|node| Some((node.alias.as_deref()?, node.relation_name.as_deref()?)))
+        .collect::<BTreeMap<_, _>>();
+    let mut columns = BTreeMap::<String, BTreeSet<String>>::new();
+    add_referenced_columns(plan, None, &aliases, &mut columns);
+    columns
+}
+
+fn add_referenced_columns(
+    node: &ExplainPlan,
+    parent_relation: Option<&str>,
+    aliases: &BTreeMap<&str, &str>,
+    columns: &mut BTreeMap<String, BTreeSet<String>>,
+) {
+    let relation = node.relation_name.as_deref().or_else(|| {
+        matches!(
+            node.node_type.as_str(),
+            "Bitmap Index Scan" | "BitmapAnd" | "BitmapOr"
+        )
+        .then_some(parent_relation?)
+    });
+    for (_, condition) in expr::conditions(node) {
+        let Ok(condition) = expr::parse(condition) else {
+            continue;
+        };
+        for column in condition.nodes().filter_map(|expr| match expr {
+            Expr::Column(column) if column.name != "*" => Some(column),
+            _ => None,
+        }) {
+            let owner = match column.relation.as_deref() {
+                Some(alias) => Some(aliases.get(alias).copied().unwrap_or(alias)),
+                None => relation,
+            };
+            if let Some(owner) = owner {
+                columns
+                    .entry(owner.to_owned())
+                    .or_default()
+                    .insert(column.name.clone());
+            }
+        }
+    }
+    for child in &node.plans {
+        add_referenced_columns(child, relation, aliases, columns);
+    }
+}
+
+/// A join of a plan with its inputs, or an input which is not a join, see
+/// [`join_report()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinTree {
+    /// The node type, e.g. `Hash Join` for joins or `Seq Scan` for inputs.
+    pub node_type: String,
+
+    /// For inputs, the alias of the scanned relation, or its name if it has no
+    /// alias. `None` for joins and for inputs without a name, e.g. an `Append`.
+    pub relation: Option<String>,
+
+    /// The join type, e.g. `Inner` or `Left`. `None` for inputs.
+    pub join_type: Option<String>,
+
+    /// Estimated number of rows per loop.
+    pub plan_rows: u64,
+
+    /// Actual number of rows per loop. Reported with `ANALYZE`.
+    pub actual_rows: Option<f64>,
+
+    /// Actual rows divided by estimated rows, see
+    /// [`ExplainPlan::estimate_ratio()`].
+    pub estimate_ratio: Option<f64>,
+
+    /// For joins, the outer input followed by the inner one. Empty for inputs.
+    pub children: Vec<JoinTree>,
+}
+
+impl JoinTree {
+    /// Returns `true` for joins, `false` for their inputs.
+    pub fn is_join(&self) -> bool {
+        !self.children.is_empty()
+    }
+
+    /// Returns the relations joined, or the node types of inputs without a name,
+    /// in join order: outer inputs before inner ones.
+    pub fn relations(&self) -> Vec<&str> {
+        if !self.is_join() {
+            return vec![self.relation.as_deref().unwrap_or(&self.node_type)];
+        }
+        self.children.iter().flat_map(JoinTree::relations).collect()
+    }
+
+    /// Iterates over this tree and all joins and inputs below it, in pre-order.
+    pub fn nodes(&self) -> Box<dyn Iterator<Item = &JoinTree> + '_> {
+        Box::new(std::iter::once(self).chain(self.children.iter().flat_map(JoinTree::nodes)))
+    }
+}
+
+/// Extracts the join trees of a plan: the joins with their join types and row
+/// counts, down to the scans they join, e.g. to see in which order relations are
+/// joined and at which join the row count explodes or the estimate goes wrong.
+///
+/// Nodes passing a single input on, such as `Hash`, `Sort` or `Memoize`, are
+/// skipped, so each join has exactly two children. Joins which are not inputs of
+/// another join, e.g. in subqueries, CTEs or `SubPlan`s, get trees of their own,
+/// outer trees first. Plans without joins return an empty vector.
+///
+/// Example:
+/// ```rust
+/// let options = ExplainOptions::default().analyze(true);
+/// let plan = query.wrap_explain().options(options).explain(&mut conn)?;
+/// for tree in analysis::join_report(&plan) {
+///     println!("join order: {}", tree.relations().join(", "));
+///     for join in tree.nodes().filter(|node| node.is_join()) {
+///         println!("{}: {} rows estimated, {:?} actual", join.node_type, join.plan_rows, join.actual_rows);
+///     }
+/// }
+/// ```
+pub fn join_report(plan: &ExplainPlan) -> Vec<JoinTree> {
+    let mut trees = Vec::new();
+    collect_join_trees(plan, &mut trees);
+    trees
+}
+
+fn collect_join_trees(node: &ExplainPlan, trees: &mut Vec<JoinTree>) {
+    if is_join(node) {
+        let mut nested = Vec::new();
+        trees.push(join_tree(node, &mut nested));
+        trees.append(&mut nested);
+    } else {
+        for child in &node.plans {
+            collect_join_trees(child, trees);
+        }
+    }
+}
+
+fn join_tree(node: &ExplainPlan, nested: &mut Vec<JoinTree>) -> JoinTree {
+    let node = join_input(node, nested);
+    let join = is_join(node);
+    let mut children = Vec::new();
+    for child in &node.plans {
+        if join && !is_subplan(child) {
+            children.push(join_tree(child, nested));
+        } else {
+            collect_join_trees(child, nested);
+        }
+    }
+    JoinTree {
+        node_type: node.node_type.clone(),
+        relation: if join {
+            None
+        } else {
+            node.alias.clone().or_else(|| node.relation_name.clone())
+        },
+        join_type: if join { node.join_type.clone() } else { None },
+        plan_rows: node.plan_rows,
+        actual_rows: node.actual_rows,
+        estimate_ratio: node.estimate_ratio(),
+        children,
+    }
+}
+
+/// Skips nodes which pass a single input on, collecting the joins of their
+/// `SubPlan`s and `InitPlan`s into `nested`.
+fn join_input<'a>(mut node: &'a ExplainPlan, nested: &mut Vec<JoinTree>) -> &'a ExplainPlan {
+    while !is_join(node) && node.relation_name.is_none() && node.alias.is_none() {
+        let mut inputs = node.plans.iter().filter(|child| !is_subplan(child));
+        let (Some(input), None) = (inputs.next(), inputs.next()) else {
+            break;
+        };
+        for subplan in node.plans.iter().filter(|child| is_subplan(child)) {
+            collect_join_trees(subplan, nested);
+        }
+        node = input;
+    }
+    node
+}
+
+fn is_join(node: &ExplainPlan) -> bool {
+    matches!(
+        node.node_type.as_str(),
+        "Nested Loop" | "Hash Join" | "Merge Join"
+    )
+}
+
+/// Returns `true` for `SubPlan`s and `InitPlan`s, which are children of a node
+/// without being its input.
+pub(crate) fn is_subplan(node: &ExplainPlan) -> bool {
+    matches!(
+        node.parent_relationship.as_deref(),
+        Some("SubPlan" | "InitPlan")
+    )
+}
+
+/// The time one trigger took, see [`trigger_report()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerOverhead {
+    /// The statistics reported for the trigger.
+    pub trigger: Trigger,
+
+    /// The trigger's time divided by the execution time of the statement.
+    /// `None` if either is unknown, e.g. because `TIMING` was off.
+    pub fraction: Option<f64>,
+}
+
+/// How much of the execution time of a statement went to triggers, see
+/// [`trigger_report()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerReport {
+    /// Execution time of the statement in milliseconds, triggers included.
+    pub execution_time: Option<f64>,
+
+    /// The triggers fired by the statement, slowest first.
+    pub triggers: Vec<TriggerOverhead>,
+}
+
+impl TriggerReport {
+    /// Returns the time in milliseconds spent in all triggers together.
+    pub fn trigger_time(&self) -> f64 {
+        self.triggers
+            .iter()
+            .filter_map(|overhead| overhead.trigger.time)
+            .sum()
+    }
+
+    /// Returns the fraction of the execution time spent in triggers, or `None`
+    /// if the execution time is unknown.
+    pub fn fraction(&self) -> Option<f64> {
+        share(Some(self.trigger_time()), self.execution_time)
+    }
+}
+
+/// Attributes the execution time of a statement explained with `ANALYZE` to the
+/// triggers it fired, e.g. to quantify what audit triggers or foreign key checks
+/// cost a write path. PostgreSQL reports trigger time separately from the plan
+/// nodes, but includes it in the execution time. Statements without triggers
+/// return a report with no triggers.
+///
+/// Example:
+/// ```rust
+/// let options = ExplainOptions::default().analyze(true);
+/// let item = query.wrap_explain().options(options).explain_item(&mut conn)?;
+/// let report = analysis::trigger_report(&item)?;
+/// for overhead in &report.triggers {
+///     let trigger = &overhead.trigger;
+///     println!("{} ({} calls): {:?}", trigger.name, trigger.calls, overhead.fraction);
+/// }
+/// ```
+///
+/// # Errors
+/// Returns [`ExplainError::Parse`] if the `Triggers` section has an unexpected
+/// shape.
+pub fn trigger_report(item: &ExplainItem) -> Result<TriggerReport, ExplainError> {
+    let mut triggers = item
+        .triggers()?
+        .into_iter()
+        .map(|trigger| TriggerOverhead {
+            fraction: share(trigger.time, item.execution_time),
+            trigger,
+        })
+        .collect::<Vec<_>>();
+    triggers.sort_by(|a, b| {
+        let time = |overhead: &TriggerOverhead| overhead.trigger.time.unwrap_or(-1.0);
+        time(b).total_cmp(&time(a))
+    });
+    Ok(TriggerReport {
+        execution_time: item.execution_time,
+        triggers,
+    })
+}
+
+fn share(part: Option<f64>, total: Option<f64>) -> Option<f64> {
+    let total = total.filter(|total| *total > 0.0)?;
+    Some(part? / total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{item, node};
+
+    #[test]
+    fn referenced_columns_are_collected_per_relation() {
+        let plan = ExplainPlan {
+            hash_cond: Some("(o.user_id = u.id)".to_owned()),
+            plans: vec![
+                ExplainPlan {
+                    alias: Some("o".to_owned()),
+                    filter: Some("((status)::text = ANY ('{new,paid}'::text[]))".to_owned()),
+                    ..ExplainPlan::scan("Seq Scan", "orders")
+                },
+                ExplainPlan {
+                    recheck_cond: Some("(lower(email) ~~ 'a%'::text)".to_owned()),
+                    plans: vec![ExplainPlan {
+                        index_cond: Some("((lower(email) >= 'a'::text) AND (age > 21))".to_owned()),
+                        ..ExplainPlan::new("Bitmap Index Scan")
+                    }],
+                    alias: Some("u".to_owned()),
+                    ..ExplainPlan::scan("Bitmap Heap Scan", "users")
+                },
+                ExplainPlan {
+                    filter: Some("(count(*) > sub.n) AND (unparsable".to_owned()),
+                    ..ExplainPlan::new("Result")
+                },
+            ],
+            ..ExplainPlan::new("Hash Join")
+        };
+        let columns = referenced_columns(&plan);
+        let names = |relation: &str| {
+            columns[relation]
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(columns.len(), 2);
+        assert_eq!(names("orders"), ["status", "user_id"]);
+        assert_eq!(names("users"), ["age", "email", "id"]);
+    }
+
+    #[test]
+    fn join_trees_skip_pass_through_nodes() {
+        let scan = |alias: &str, rows: u64, actual: f64| ExplainPlan {
+            alias: Some(alias.to_owned()),
+            plan_rows: rows,
+            actual_rows: Some(actual),
+            actual_loops: Some(1),
+            ..ExplainPlan::scan("Seq Scan", alias)
+        };
+        let join = |node_type: &str, rows: u64, actual: f64, plans| ExplainPlan {
+            join_type: Some("Inner".to_owned()),
+            plan_rows: rows,
+            actual_rows: Some(actual),
+            actual_loops: Some(1),
+            plans,
+            ..ExplainPlan::new(node_type)
+        };
+        let subquery_join = join(
+            "Merge Join",
+            1,
+            1.0,
+            vec![scan("x", 1, 1.0), scan("y", 1, 1.0)],
+        );
+        let plan = ExplainPlan {
+            plans: vec![join(
+                "Hash Join",
+                10,
+                5000.0,
+                vec![
+                    join(
+                        "Nested Loop",
+                        10,
+                        100.0,
+                        vec![
+                            scan("users", 10, 10.0),
+                            ExplainPlan {
+                                plans: vec![ExplainPlan {
+                                    plans: vec![ExplainPlan {
+                                        parent_relationship: Some("SubPlan".to_owned()),
+                                        plans: vec![subquery_join],
+                                        ..ExplainPlan::new("Result")
+                                    }],
+                                    ..scan("orders", 1, 10.0)
+                                }],
+                                ..ExplainPlan::new("Memoize")
+                            },
+                        ],
+                    ),
+                    node("Hash", vec![scan("items", 50, 50.0)]),
+                ],
+            )],
+            ..ExplainPlan::new("Sort")
+        };
+
+        let trees = join_report(&plan);
+        assert_eq!(trees.len(), 2);
+        let tree = &trees[0];
+        assert_eq!(tree.node_type, "Hash Join");
+        assert_eq!(tree.join_type.as_deref(), Some("Inner"));
+        assert_eq!(tree.estimate_ratio, Some(500.0));
+        assert_eq!(tree.relations(), ["users", "orders", "items"]);
+        assert_eq!(tree.nodes().filter(|node| node.is_join()).count(), 2);
+        assert_eq!(tree.children[1].relation.as_deref(), Some("items"));
+        assert_eq!(trees[1].relations(), ["x", "y"]);
+        assert!(join_report(&ExplainPlan::scan("Seq Scan", "users")).is_empty());
+    }
+
+    #[test]
+    fn trigger_report_attributes_execution_time() {
+        let item = ExplainItem {
+            triggers: Some(serde_json::json!([
+                {"Trigger Name": "audit", "Relation": "users", "Time": 1.0, "Calls": 2},
+                {
+                    "Trigger Name": "RI_ConstraintTrigger_c_16411",
+                    "Constraint Name": "posts_user_id_fkey",
+                    "Relation": "posts",
+                    "Time": 3.0,
+                    "Calls": 2
+                },
+                {"Trigger Name": "untimed", "Calls": 1}
+            ])),
+            execution_time: Some(8.0),
+            ..item(ExplainPlan::new("ModifyTable"))
+        };
+        let report = trigger_report(&item).unwrap();
+        let names = report
+            .triggers
+            .iter()
+            .map(|overhead| overhead.trigger.name.as_str());
+        assert_eq!(
+            names.collect::<Vec<_>>(),
+            ["RI_ConstraintTrigger_c_16411", "audit", "untimed"]
+        );
+        let fractions = report.triggers.iter().map(|overhead| overhead.fraction);
+        assert_eq!(
+            fractions.collect::<Vec<_>>(),
+            [Some(0.375), Some(0.125), None]
+        );
+        assert_eq!(
+            report.triggers[0].trigger.constraint_name.as_deref(),
+            Some("posts_user_id_fkey")
+        );
+        assert_eq!((report.trigger_time(), report.fraction()), (4.0, Some(0.5)));
+
+        let item = ExplainItem::from_text(
+            "Insert on users  (cost=0.00..0.01 rows=0 width=0) (actual time=0.1..0.1 rows=0 loops=1)\n\
+             Trigger audit on users: time=0.5 calls=2\n\
+             Execution Time: 2.0 ms\n",
+        )
+        .unwrap();
+        let report = trigger_report(&item).unwrap();
+        assert_eq!(report.triggers[0].trigger.calls, 2);
+        assert_eq!(report.fraction(), Some(0.25));
+
+        let report = trigger_report(
+            &ExplainItem::from_text("Result  (cost=0.00..0.01 rows=1 width=4)").unwrap(),
+        );
+        assert!(report.unwrap().triggers.is_empty());
+    }
+
+    #[test]
+    fn partition_summary_counts_scanned_and_pruned_partitions() {
+        let scan = |relation: &str, alias: &str, loops: u64| ExplainPlan {
+            node_type: "Seq Scan".to_owned(),
+            parent_relationship: Some("Member".to_owned()),
+            relation_name: Some(relation.to_owned()),
+            alias: Some(alias.to_owned()),
+            actual_loops: Some(loops),
+            ..ExplainPlan::default()
+        };
+        let plan = ExplainPlan {
+            node_type: "Append".to_owned(),
+            subplans_removed: Some(2),
+            plans: vec![
+                ExplainPlan {
+                    node_type: "Result".to_owned(),
+                    parent_relationship: Some("InitPlan".to_owned()),
+                    ..ExplainPlan::default()
+                },
+                scan("m_a", "m_1", 1),
+                scan("m_b", "m_2", 0),
+                scan("m_c", "m_3", 0),
+                scan("users", "users", 1),
+            ],
+            ..ExplainPlan::default()
+        };
+
+        let summaries = partition_summary(&plan);
+        assert_eq!(
+            summaries,
+            [PartitionSummary {
+                table: "m".to_owned(),
+                partitions: vec!["m_a".to_owned(), "m_b".to_owned(), "m_c".to_owned()],
+                scanned: 1,
+                removed: 2,
+                never_executed: 2,
+            }]
+        );
+        assert_eq!(summaries[0].pruned(), 4);
+    }
+
+    #[test]
+    fn hit_ratios_are_computed_per_plan_and_relation() {
+        let node =
+            |node_type: &str, relation: Option<&str>, index: Option<&str>, hit, read| ExplainPlan {
+                node_type: node_type.to_owned(),
+                relation_name: relation.map(str::to_owned),
+                index_name: index.map(str::to_owned),
+                shared_hit_blocks: Some(hit),
+                shared_read_blocks: Some(read),
+                ..ExplainPlan::default()
+            };
+        let plan = ExplainPlan {
+            plans: vec![
+                ExplainPlan {
+                    plans: vec![node("Bitmap Index Scan", None, Some("users_age_idx"), 3, 1)],
+                    ..node("Bitmap Heap Scan", Some("users"), None, 10, 10)
+                },
+                node("Seq Scan", Some("users"), None, 8, 0),
+                node("Seq Scan", Some("orders"), None, 0, 0),
+            ],
+            ..node("Append", None, None, 18, 10)
+        };
+
+        assert_eq!(hit_ratio(&plan), Some(18.0 / 28.0));
+        let relations = relation_buffer_hits(&plan);
+        assert_eq!(relations["users"], BufferHits { hit: 15, read: 9 });
+        assert_eq!(relations["users_age_idx"].ratio(), Some(0.75));
+        assert_eq!(relations["orders"].ratio(), None);
+        assert_eq!(hit_ratio(&ExplainPlan::default()), None);
+    }
+
+    #[test]
+    fn exclusive_buffers_exclude_children() {
+        let scan = |relation: &str, hit, read| ExplainPlan {
+            shared_hit_blocks: Some(hit),
+            shared_read_blocks: Some(read),
+            ..ExplainPlan::scan("Seq Scan", relation)
+        };
+        let plan = ExplainPlan {
+            shared_hit_blocks: Some(30),
+            shared_read_blocks: Some(12),
+            plans: vec![
+                scan("orders", 20, 10),
+                ExplainPlan {
+                    shared_hit_blocks: Some(8),
+                    shared_read_blocks: Some(2),
+                    plans: vec![scan("users", 8, 2)],
+                    ..ExplainPlan::new("Hash")
+                },
+            ],
+            ..ExplainPlan::new("Hash Join")
+        };
+        let own = |node: &ExplainPlan| node.exclusive_buffer_hits();
+        assert_eq!(own(&plan), Some(BufferHits { hit: 2, read: 0 }));
+        assert_eq!(own(&plan.plans[0]), Some(BufferHits { hit: 20, read: 10 }));
+        assert_eq!(own(&plan.plans[1]), Some(BufferHits::default()));
+        assert_eq!(own(&ExplainPlan::new("Result")), None);
+    }
+
+    #[test]
+    fn parallel_report_measures_speedup_and_imbalance() {
+        let timed = |node_type: &str, time, loops| ExplainPlan {
+            node_type: node_type.to_owned(),
+            actual_total_time: Some(time),
+            actual_loops: Some(loops),
+            ..ExplainPlan::default()
+        };
+        let worker = |time| serde_json::json!({"Actual Total Time": time, "Actual Loops": 1});
+        let scan = ExplainPlan {
+            workers: Some(vec![worker(90.0), worker(30.0)]),
+            ..timed("Parallel Seq Scan", 80.0, 3)
+        };
+        let gather = ExplainPlan {
+            workers_planned: Some(4),
+            workers_launched: Some(2),
+            plans: vec![scan],
+            ..timed("Gather", 100.0, 1)
+        };
+        let plan = ExplainPlan {
+            plans: vec![gather],
+            ..timed("Hash Join", 400.0, 1)
+        };
+
+        let report = parallel_report(&plan);
+        assert_eq!(report.gather_time_fraction, Some(0.25));
+        assert_eq!(
+            report.gathers,
+            [GatherStats {
+                node_type: "Gather".to_owned(),
+                workers_planned: Some(4),
+                workers_launched: Some(2),
+                speedup: Some(2.4),
+                imbalance: Some(1.5),
+            }]
+        );
+        assert_eq!(
+            parallel_report(&ExplainPlan::default()),
+            ParallelReport::default()
+        );
+    }
+
+    #[test]
+    fn memory_estimate_counts_work_areas_per_process() {
+        let sort = ExplainPlan {
+            sort_space_type: Some("Memory".to_owned()),
+            sort_space_used: Some(100),
+            workers: Some(vec![
+                serde_json::json!({"Sort Space Type": "Memory", "Sort Space Used": 90}),
+                serde_json::json!({"Sort Space Type": "Disk", "Sort Space Used": 5000}),
+            ]),
+            ..node("Sort", vec![node("Parallel Seq Scan", vec![])])
+        };
+        let parallel_hash = ExplainPlan {
+            parallel_aware: true,
+            peak_memory_usage: Some(300),
+            ..node("Hash", vec![node("Parallel Seq Scan", vec![])])
+        };
+        let gather = ExplainPlan {
+            workers_launched: Some(2),
+            plans: vec![node("Parallel Hash Join", vec![sort, parallel_hash])],
+            ..node("Gather", vec![])
+        };
+        let plan = ExplainPlan {
+            strategy: Some("Hashed".to_owned()),
+            peak_memory_usage: Some(20),
+            ..node("Aggregate", vec![gather])
+        };
+
+        let estimate = memory_estimate(&plan);
+        assert_eq!(
+            estimate,
+            MemoryEstimate {
+                measured_kb: 510,
+                work_mem_consumers: 3,
+                hash_mem_consumers: 2,
+            }
+        );
+        assert_eq!(estimate.budget_kb(4096, 2.0), 3 * 4096 + 2 * 8192);
+    }
+}
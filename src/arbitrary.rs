@@ -0,0 +1,143 @@
+//! [`proptest`] support: an [`Arbitrary`] implementation for [`ExplainPlan`].
+//!
+//! Generated trees are random but structurally valid: leaves are scan nodes,
+//! joins always have exactly an outer and an inner child, and every node's
+//! costs cover the costs of its children, the same way PostgreSQL reports them.
+//!
+//! Example:
+//! ```rust,ignore
+//! use diesel_pg_explain::ExplainPlan;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn node_count_is_positive(plan in any::<ExplainPlan>()) {
+//!         prop_assert!(my_node_count(&plan) > 0);
+//!     }
+//! }
+//! ```
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::ExplainPlan;
+
+const SCAN_NODES: &[&str] = &[
+    "Seq Scan",
+    "Index Scan",
+    "Index Only Scan",
+    "Bitmap Heap Scan",
+    "Function Scan",
+    "Values Scan",
+    "CTE Scan",
+    "Result",
+];
+
+const UNARY_NODES: &[&str] = &[
+    "Sort",
+    "Incremental Sort",
+    "Limit",
+    "Aggregate",
+    "Hash",
+    "Materialize",
+    "Memoize",
+    "Gather",
+    "Gather Merge",
+    "Unique",
+    "WindowAgg",
+];
+
+const JOIN_NODES: &[&str] = &["Nested Loop", "Hash Join", "Merge Join"];
+
+const APPEND_NODES: &[&str] = &["Append", "Merge Append"];
+
+/// Planner estimates of a single node, excluding the costs of its children.
+fn estimates() -> impl Strategy<Value = (f64, f64, u64, u64, bool)> {
+    (
+        0.0..10_000.0f64,
+        0.0..1_000_000.0f64,
+        0..10_000_000u64,
+        0..2_000u64,
+        any::<bool>(),
+    )
+}
+
+fn node(
+    node_type: &str,
+    estimates: (f64, f64, u64, u64, bool),
+    plans: Vec<ExplainPlan>,
+) -> ExplainPlan {
+    let (startup_cost, run_cost, plan_rows, plan_width, parallel_aware) = estimates;
+    let children_startup = plans.iter().map(|p| p.startup_cost).fold(0.0, f64::max);
+    let children_total: f64 = plans.iter().map(|p| p.total_cost).sum();
+    let startup_cost = startup_cost + children_startup;
+
+    ExplainPlan {
+        node_type: node_type.to_owned(),
+        parent_relationship: None,
+        parallel_aware,
+        async_capable: false,
+        startup_cost,
+        total_cost: startup_cost.max(children_total) + run_cost,
+        plan_rows,
+        plan_width,
+        plans,
+        ..ExplainPlan::default()
+    }
+}
+
+fn with_relationships(mut plans: Vec<ExplainPlan>, relationships: &[&str]) -> Vec<ExplainPlan> {
+    for (plan, relationship) in plans.iter_mut().zip(relationships.iter().cycle()) {
+        plan.parent_relationship = Some((*relationship).to_owned());
+    }
+    plans
+}
+
+fn scan() -> impl Strategy<Value = ExplainPlan> {
+    (proptest::sample::select(SCAN_NODES), estimates())
+        .prop_map(|(node_type, estimates)| node(node_type, estimates, Vec::new()))
+}
+
+fn unary(inner: BoxedStrategy<ExplainPlan>) -> impl Strategy<Value = ExplainPlan> {
+    (proptest::sample::select(UNARY_NODES), estimates(), inner).prop_map(
+        |(node_type, estimates, child)| {
+            let plans = with_relationships(vec![child], &["Outer"]);
+            node(node_type, estimates, plans)
+        },
+    )
+}
+
+fn join(inner: BoxedStrategy<ExplainPlan>) -> impl Strategy<Value = ExplainPlan> {
+    let sides = (inner.clone(), inner);
+    (proptest::sample::select(JOIN_NODES), estimates(), sides).prop_map(
+        |(node_type, estimates, (outer, inner))| {
+            let plans = with_relationships(vec![outer, inner], &["Outer", "Inner"]);
+            node(node_type, estimates, plans)
+        },
+    )
+}
+
+fn append(inner: BoxedStrategy<ExplainPlan>) -> impl Strategy<Value = ExplainPlan> {
+    (
+        proptest::sample::select(APPEND_NODES),
+        estimates(),
+        vec(inner, 2..5),
+    )
+        .prop_map(|(node_type, estimates, members)| {
+            let plans = with_relationships(members, &["Member"]);
+            node(node_type, estimates, plans)
+        })
+}
+
+impl Arbitrary for ExplainPlan {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        scan()
+            .prop_recursive(8, 64, 4, |inner| {
+                prop_oneof![unary(inner.clone()), join(inner.clone()), append(inner)]
+            })
+            .boxed()
+    }
+}
@@ -0,0 +1,160 @@
+//! A flat representation of plan trees.
+//!
+//! [`PlanArena`] stores all nodes of a plan in a single vector, in depth-first
+//! pre-order, and links them by index. Analyses which walk large trees many
+//! times benefit from the better cache locality, can move to a node's parent,
+//! and dropping the arena does not recurse however deep the plan is.
+//!
+//! Example:
+//! ```rust
+//! use diesel_pg_explain::arena::PlanArena;
+//!
+//! let arena = PlanArena::from_plan(query.wrap_explain().explain(&mut conn)?);
+//! for (id, node) in arena.iter() {
+//!     if node.node_type == "Seq Scan" {
+//!         let parent = arena.parent(id).map(|parent| &arena[parent].node_type);
+//!         println!("{:?} under {parent:?}", node.relation_name);
+//!     }
+//! }
+//! ```
+
+use std::ops::Index;
+
+use crate::ExplainPlan;
+
+/// Index of a node in a [`PlanArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// Position of the node in depth-first pre-order, the root being 0.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    plan: ExplainPlan,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+/// A plan tree whose nodes live in one vector and refer to each other by [`NodeId`].
+///
+/// The [`ExplainPlan`]s stored in the arena have empty `plans`; use
+/// [`PlanArena::children()`] instead.
+#[derive(Debug)]
+pub struct PlanArena {
+    nodes: Vec<Node>,
+}
+
+impl PlanArena {
+    /// Moves the nodes of `plan` into a new arena, without recursion.
+    pub fn from_plan(plan: ExplainPlan) -> Self {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut stack = vec![(plan, None)];
+        while let Some((mut plan, parent)) = stack.pop() {
+            let id = NodeId(nodes.len());
+            let children = std::mem::take(&mut plan.plans);
+            stack.extend(children.into_iter().rev().map(|child| (child, Some(id))));
+            if let Some(NodeId(parent)) = parent {
+                nodes[parent].children.push(id);
+            }
+            nodes.push(Node {
+                plan,
+                parent,
+                children: Vec::new(),
+            });
+        }
+        Self { nodes }
+    }
+
+    /// Returns the id of the root node.
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Always `false`: an arena holds at least the root node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the node `id`, or `None` if it belongs to another arena.
+    pub fn get(&self, id: NodeId) -> Option<&ExplainPlan> {
+        self.nodes.get(id.0).map(|node| &node.plan)
+    }
+
+    /// Returns the parent of `id`, or `None` for the root.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    /// Returns the children of `id`, in plan order.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id.0].children
+    }
+
+    /// Iterates over the ancestors of `id`, from its parent up to the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), |&id| self.parent(id))
+    }
+
+    /// Iterates over all nodes in depth-first pre-order, like [`ExplainPlan::nodes()`].
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &ExplainPlan)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (NodeId(index), &node.plan))
+    }
+}
+
+impl Index<NodeId> for PlanArena {
+    type Output = ExplainPlan;
+
+    fn index(&self, id: NodeId) -> &ExplainPlan {
+        &self.nodes[id.0].plan
+    }
+}
+
+impl From<ExplainPlan> for PlanArena {
+    fn from(plan: ExplainPlan) -> Self {
+        Self::from_plan(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::node;
+
+    #[test]
+    fn arena_keeps_pre_order_and_links() {
+        let plan = node(
+            "Hash Join",
+            vec![
+                node("Seq Scan", vec![]),
+                node("Hash", vec![node("Index Scan", vec![])]),
+            ],
+        );
+        let expected: Vec<String> = plan.nodes().map(|n| n.node_type.clone()).collect();
+
+        let arena = PlanArena::from_plan(plan);
+        let types: Vec<String> = arena.iter().map(|(_, n)| n.node_type.clone()).collect();
+        assert_eq!(types, expected);
+
+        let (scan, _) = arena.iter().last().unwrap();
+        let hash = arena.parent(scan).unwrap();
+        assert_eq!(arena[hash].node_type, "Hash");
+        assert_eq!(arena.children(arena.root()).len(), 2);
+        assert_eq!(
+            arena.ancestors(scan).collect::<Vec<_>>(),
+            [hash, arena.root()]
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! Support for [`diesel_async`]: explaining queries over an [`AsyncPgConnection`].
+
+use diesel::sql_types::Text;
+use diesel::QueryResult;
+use diesel_async::methods::LoadQuery;
+use diesel_async::{AnsiTransactionManager, AsyncPgConnection, RunQueryDsl, TransactionManager};
+
+use crate::{plan_from_rows, Explain, ExplainError, ExplainPlan, SET_LOCAL_SQL};
+
+impl<Q> Explain<Q> {
+    /// Asynchronous counterpart of [`Explain::explain()`] for
+    /// [`diesel_async::AsyncPgConnection`].
+    ///
+    /// Example:
+    /// ```rust
+    /// let mut conn = AsyncPgConnection::establish(&database_url).await?;
+    /// let plan = my_query.wrap_explain().explain_async(&mut conn).await?;
+    /// ```
+    ///
+    /// The query is borrowed across `.await` points, so wrapping it by reference
+    /// requires it to be `Sync`. Boxed queries are not; pass them by value with
+    /// [`Explain::new()`] instead of [`ExplainWrapped::wrap_explain()`](crate::ExplainWrapped::wrap_explain).
+    ///
+    /// With `ANALYZE`, the statement runs in a transaction which is always rolled
+    /// back, the same way as with [`Explain::explain()`].
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub async fn explain_async<'a>(
+        self,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<ExplainPlan, ExplainError>
+    where
+        Self: LoadQuery<'a, AsyncPgConnection, String> + 'a,
+    {
+        let mode = self.mode;
+        let rows = if self.needs_transaction() {
+            AnsiTransactionManager::begin_transaction(conn).await?;
+            let rows = match set_local(conn, &self.local_settings()).await {
+                Ok(()) => self.load::<String>(conn).await,
+                Err(e) => Err(e),
+            };
+            AnsiTransactionManager::rollback_transaction(conn).await?;
+            rows?
+        } else {
+            self.load::<String>(conn).await?
+        };
+        plan_from_rows(rows, mode)
+    }
+}
+
+/// Asynchronous counterpart of `set_local()`.
+async fn set_local(conn: &mut AsyncPgConnection, settings: &[(String, String)]) -> QueryResult<()> {
+    for (name, value) in settings {
+        diesel::sql_query(SET_LOCAL_SQL)
+            .bind::<Text, _>(name)
+            .bind::<Text, _>(value)
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
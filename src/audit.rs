@@ -0,0 +1,367 @@
+//! Auditing the queries of a project, e.g. in CI, with a `cargo pg-explain`
+//! style tool.
+//!
+//! A project lists its queries in a [`QueryRegistry`], together with cost gates
+//! and lints. [`main()`] explains all of them against the database given by
+//! `DATABASE_URL` and exits with a non-zero code when a query can't be explained,
+//! exceeds its maximum cost or trips a lint.
+//!
+//! The registry is the project's own code, so the tool is a binary of the project,
+//! e.g. `src/bin/cargo-pg-explain.rs`:
+//! ```rust
+//! use std::process::ExitCode;
+//! use diesel_pg_explain::audit::{self, QueryRegistry};
+//!
+//! fn main() -> ExitCode {
+//!     audit::main(|registry: &mut QueryRegistry| {
+//!         registry
+//!             .max_cost(10_000.0)
+//!             .register("adult_users", users::table.filter(users::age.ge(18)))
+//!             .register_with_max_cost("user_by_id", users::table.find(1), 10.0)
+//!             .lint(|plan: &ExplainPlan| {
+//!                 plan.nodes()
+//!                     .filter(|node| node.node_type == "Seq Scan")
+//!                     .map(|node| format!("sequential scan of {:?}", node.relation_name))
+//!                     .collect()
+//!             });
+//!     })
+//! }
+//! ```
+//! Run it with `cargo run --bin cargo-pg-explain`, or as `cargo pg-explain` once
+//! installed with `cargo install --path . --bin cargo-pg-explain`.
+
+use std::fmt;
+use std::process::ExitCode;
+
+use diesel::pg::Pg;
+use diesel::query_builder::QueryFragment;
+use diesel::{Connection, PgConnection};
+
+use crate::{
+    explain_batch, ExplainConfig, ExplainConnection, ExplainError, ExplainOptions, ExplainPlan,
+};
+
+/// A lint run on the plan of every registered query, returning one message per
+/// problem found.
+type Lint = Box<dyn Fn(&ExplainPlan) -> Vec<String>>;
+
+struct RegisteredQuery {
+    name: String,
+    query: Box<dyn QueryFragment<Pg>>,
+    max_cost: Option<f64>,
+}
+
+/// The queries of a project, and the gates their plans must pass.
+#[derive(Default)]
+pub struct QueryRegistry {
+    queries: Vec<RegisteredQuery>,
+    max_cost: Option<f64>,
+    lints: Vec<Lint>,
+}
+
+impl QueryRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `query`, reported as `name`.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        query: impl QueryFragment<Pg> + 'static,
+    ) -> &mut Self {
+        self.queries.push(RegisteredQuery {
+            name: name.into(),
+            query: Box::new(query),
+            max_cost: None,
+        });
+        self
+    }
+
+    /// Adds `query`, which fails the audit if its estimated total cost exceeds
+    /// `max_cost`, instead of the registry-wide [`QueryRegistry::max_cost()`].
+    pub fn register_with_max_cost(
+        &mut self,
+        name: impl Into<String>,
+        query: impl QueryFragment<Pg> + 'static,
+        max_cost: f64,
+    ) -> &mut Self {
+        self.register(name, query);
+        if let Some(registered) = self.queries.last_mut() {
+            registered.max_cost = Some(max_cost);
+        }
+        self
+    }
+
+    /// Fails the audit for queries whose estimated total cost exceeds `cost`,
+    /// unless they were registered with their own maximum.
+    pub fn max_cost(&mut self, cost: f64) -> &mut Self {
+        self.max_cost = Some(cost);
+        self
+    }
+
+    /// Runs `lint` on the plan of every query. Each returned message fails the audit.
+    pub fn lint(&mut self, lint: impl Fn(&ExplainPlan) -> Vec<String> + 'static) -> &mut Self {
+        self.lints.push(Box::new(lint));
+        self
+    }
+
+    /// Explains every registered query with `options` and checks its plan.
+    ///
+    /// Queries are explained with [`explain_batch()`], so changes made with
+    /// [`ExplainOptions::analyze`] are rolled back.
+    ///
+    /// # Errors
+    /// Fails with [`ExplainError::Diesel`] if the transaction the queries are
+    /// explained in cannot be managed. Queries which can't be explained are
+    /// reported as failed [`AuditResult`]s.
+    pub fn audit<C: ExplainConnection>(
+        &self,
+        conn: &mut C,
+        options: ExplainOptions,
+    ) -> Result<Vec<AuditResult>, ExplainError> {
+        let plans = explain_batch(conn, self.queries.iter().map(|q| &q.query), Some(options))?;
+        Ok(self.check(plans))
+    }
+
+    /// Checks the `plans` of the registered queries, in the order of registration.
+    fn check(&self, plans: Vec<Result<ExplainPlan, ExplainError>>) -> Vec<AuditResult> {
+        self.queries
+            .iter()
+            .zip(plans)
+            .map(|(registered, plan)| {
+                let mut problems = Vec::new();
+                if let Ok(plan) = &plan {
+                    if let Some(max) = registered.max_cost.or(self.max_cost) {
+                        if plan.total_cost > max {
+                            problems.push(format!(
+                                "total cost {:.2} exceeds the maximum of {max:.2}",
+                                plan.total_cost
+                            ));
+                        }
+                    }
+                    problems.extend(self.lints.iter().flat_map(|lint| lint(plan)));
+                }
+                AuditResult {
+                    name: registered.name.clone(),
+                    plan,
+                    problems,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The outcome of auditing one registered query.
+#[derive(Debug)]
+pub struct AuditResult {
+    /// The name the query was registered with.
+    pub name: String,
+
+    /// The plan of the query, or why it couldn't be explained.
+    pub plan: Result<ExplainPlan, ExplainError>,
+
+    /// Cost gates and lints the plan failed.
+    pub problems: Vec<String>,
+}
+
+impl AuditResult {
+    /// Returns `true` if the query was explained and passed all checks.
+    pub fn passed(&self) -> bool {
+        self.plan.is_ok() && self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for AuditResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.plan {
+            Ok(plan) if self.problems.is_empty() => {
+                write!(f, "ok    {} (cost {:.2})", self.name, plan.total_cost)
+            }
+            Ok(_) => {
+                write!(f, "FAIL  {}", self.name)?;
+                for problem in &self.problems {
+                    write!(f, "\n      {problem}")?;
+                }
+                Ok(())
+            }
+            Err(e) => write!(f, "FAIL  {}\n      {e}", self.name),
+        }
+    }
+}
+
+const USAGE: &str = "\
+Usage: cargo pg-explain [OPTIONS]
+
+Explains the registered queries of this project and fails if a query can't be
+explained, exceeds its maximum cost or trips a lint.
+
+Options:
+  -d, --database-url <URL>   Database to connect to [default: $DATABASE_URL]
+      --analyze              Run the queries, inside a rolled back transaction
+  -h, --help                 Print this help
+
+Default options are read from DIESEL_PG_EXPLAIN_* environment variables.";
+
+/// Entry point of an audit binary: collects the queries with `register`, audits
+/// them against `DATABASE_URL`, prints the results and returns the exit code,
+/// see the [module documentation](self).
+///
+/// Exits with code 1 if a query fails the audit, and 2 on invalid arguments or
+/// configuration.
+pub fn main(register: impl FnOnce(&mut QueryRegistry)) -> ExitCode {
+    let mut args = std::env::args().skip(1).peekable();
+    // `cargo pg-explain` runs `cargo-pg-explain pg-explain`.
+    args.next_if(|arg| arg == "pg-explain");
+
+    let mut options = match ExplainConfig::from_env() {
+        Ok(config) => config.options,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::from(2);
+        }
+    };
+    let mut database_url = std::env::var("DATABASE_URL").ok();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            "-d" | "--database-url" => database_url = args.next(),
+            "--analyze" => options = options.analyze(true),
+            _ => {
+                eprintln!("error: unknown argument {arg}\n\n{USAGE}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+    let Some(database_url) = database_url else {
+        eprintln!("error: no database given, set DATABASE_URL or pass --database-url");
+        return ExitCode::from(2);
+    };
+
+    let mut registry = QueryRegistry::new();
+    register(&mut registry);
+    let results = PgConnection::establish(&database_url)
+        .map_err(|e| e.to_string())
+        .and_then(|mut conn| {
+            registry
+                .audit(&mut conn, options)
+                .map_err(|e| e.to_string())
+        });
+    let results = match results {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for result in &results {
+        println!("{result}");
+    }
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    println!("\n{} queries audited, {failed} failed", results.len());
+    exit_code(&results)
+}
+
+/// Fails if any query failed the audit.
+fn exit_code(results: &[AuditResult]) -> ExitCode {
+    if results.iter().all(AuditResult::passed) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::dsl::sql;
+    use diesel::sql_types::Integer;
+
+    use super::*;
+
+    fn registry() -> QueryRegistry {
+        let mut registry = QueryRegistry::new();
+        registry
+            .max_cost(100.0)
+            .register("cheap", sql::<Integer>("SELECT 1"))
+            .register_with_max_cost("gated", sql::<Integer>("SELECT 2"), 1000.0)
+            .register("broken", sql::<Integer>("SELECT nope"))
+            .lint(|plan: &ExplainPlan| {
+                plan.nodes()
+                    .filter(|node| node.node_type == "Seq Scan")
+                    .map(|node| format!("sequential scan of {:?}", node.relation_name))
+                    .collect()
+            });
+        registry
+    }
+
+    fn plan(total_cost: f64) -> ExplainPlan {
+        ExplainPlan {
+            total_cost,
+            ..ExplainPlan::new("Result")
+        }
+    }
+
+    #[test]
+    fn registered_queries_are_checked_in_order() {
+        let registry = registry();
+        let results = registry.check(vec![
+            Ok(plan(10.0)),
+            Ok(plan(500.0)),
+            Err(ExplainError::EmptyResult),
+        ]);
+        let names = results.iter().map(|result| result.name.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["cheap", "gated", "broken"]);
+        assert!(results[0].passed());
+        assert!(results[1].passed(), "{:?}", results[1].problems);
+        assert!(!results[2].passed());
+        assert_eq!(results[0].to_string(), "ok    cheap (cost 10.00)");
+        assert_eq!(
+            results[2].to_string(),
+            format!("FAIL  broken\n      {}", ExplainError::EmptyResult)
+        );
+        assert_eq!(exit_code(&results), ExitCode::FAILURE);
+        assert_eq!(exit_code(&results[..2]), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn cost_gates_and_lints_are_aggregated() {
+        let scan = ExplainPlan {
+            total_cost: 2000.0,
+            ..ExplainPlan::scan("Seq Scan", "users")
+        };
+        let results = registry().check(vec![
+            Ok(ExplainPlan {
+                plans: vec![scan.clone()],
+                ..plan(150.0)
+            }),
+            Ok(scan),
+            Ok(plan(1.0)),
+        ]);
+        assert_eq!(
+            results[0].problems,
+            [
+                "total cost 150.00 exceeds the maximum of 100.00",
+                "sequential scan of Some(\"users\")",
+            ]
+        );
+        assert_eq!(
+            results[1].problems,
+            [
+                "total cost 2000.00 exceeds the maximum of 1000.00",
+                "sequential scan of Some(\"users\")",
+            ]
+        );
+        assert_eq!(
+            results[0].to_string(),
+            "FAIL  cheap\n      total cost 150.00 exceeds the maximum of 100.00\n      \
+             sequential scan of Some(\"users\")"
+        );
+        assert!(results[2].passed());
+        assert_eq!(exit_code(&results), ExitCode::FAILURE);
+    }
+}
@@ -0,0 +1,507 @@
+//! Automatic plan capture for every query an application runs, similar to the
+//! server-side `auto_explain` extension but without any server configuration.
+//!
+//! [`AutoExplainConnection`] wraps a Diesel connection and can be used in its
+//! place. Before a statement is executed, it is explained on the same
+//! connection and the plan is handed to a [`PlanSink`]; the statement then runs
+//! unchanged. Plain `EXPLAIN` is used, so statements are never executed twice.
+//!
+//! Example:
+//! ```rust
+//! use diesel_pg_explain::auto::AutoExplainConnection;
+//! use diesel_pg_explain::sink::CaptureContext;
+//!
+//! let conn = PgConnection::establish(&database_url)?;
+//! let mut conn = AutoExplainConnection::new(conn, |ctx: &CaptureContext, plan: &ExplainPlan| {
+//!     println!("{}: cost {}", ctx.sql, plan.total_cost);
+//! });
+//! let names: Vec<String> = users::table.select(users::name).load(&mut conn)?;
+//! ```
+//!
+//! In production, explaining every statement doubles the number of round trips.
+//! [`AutoExplainConnection::sample_rate()`] and
+//! [`AutoExplainConnection::max_per_minute()`] limit capture to a fraction of
+//! the statements, and to a number of plans per minute for each distinct statement:
+//! ```rust
+//! let mut conn = AutoExplainConnection::new(conn, store_plan)
+//!     .sample_rate(0.01)
+//!     .max_per_minute(1);
+//! ```
+//!
+//! Like `auto_explain.log_min_duration`, [`AutoExplainConnection::min_duration()`]
+//! restricts capture to slow statements, and [`AutoExplainConnection::min_cost()`]
+//! to statements the planner considers expensive.
+
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::marker::PhantomData;
+use std::time::{Duration, Instant, SystemTime};
+
+use diesel::connection::{
+    CacheSize, Connection, ConnectionSealed, Instrumentation, LoadConnection, SimpleConnection,
+    TransactionManager, TransactionManagerStatus,
+};
+use diesel::expression::QueryMetadata;
+use diesel::pg::{Pg, PgQueryBuilder};
+use diesel::query_builder::{Query, QueryBuilder, QueryFragment, QueryId};
+use diesel::{ConnectionError, ConnectionResult, QueryResult};
+
+use crate::sink::{CaptureContext, PlanSink};
+use crate::{Explain, ExplainConfig, ExplainError, ExplainItem, ExplainOptions, ExplainPlan};
+
+/// Statement keywords which `EXPLAIN` accepts.
+const EXPLAINABLE: [&str; 8] = [
+    "SELECT", "INSERT", "UPDATE", "DELETE", "MERGE", "WITH", "VALUES", "TABLE",
+];
+
+/// A connection which explains every `SELECT`, `INSERT`, `UPDATE`, `DELETE` and
+/// `MERGE` statement before executing it, see the [module documentation](self).
+///
+/// Other statements, and anything run with `batch_execute`, are executed without
+/// being explained. Capturing never changes the outcome of a statement: plans
+/// which cannot be obtained are skipped, and inside a transaction each `EXPLAIN`
+/// runs in its own savepoint so that a failing one doesn't abort the transaction.
+pub struct AutoExplainConnection<C> {
+    inner: C,
+    sink: Box<dyn PlanSink>,
+    sampler: Sampler,
+    thresholds: Thresholds,
+}
+
+impl<C> AutoExplainConnection<C>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    /// Wraps `inner`, passing the plan of every explainable statement to `sink`.
+    ///
+    /// [`CaptureContext::duration`] is set with [`AutoExplainConnection::min_duration()`]
+    /// to how long the earlier execution took which caused this one to be explained.
+    ///
+    /// The sampling rate and thresholds default to those of the installed
+    /// [`ExplainConfig`], and plans are captured with its options, except that
+    /// `ANALYZE` and the options requiring it are always off.
+    pub fn new(inner: C, sink: impl PlanSink + 'static) -> Self {
+        let config = ExplainConfig::global();
+        Self {
+            inner,
+            sink: Box::new(sink),
+            sampler: Sampler {
+                rate: config.sample_rate,
+                ..Sampler::new()
+            },
+            thresholds: Thresholds {
+                min_duration: config.min_duration,
+                min_cost: config.min_cost,
+                ..Thresholds::default()
+            },
+        }
+    }
+
+    /// Explains each statement with probability `rate`, between 0.0 and 1.0,
+    /// e.g. `0.01` to capture 1% of executions. Defaults to 1.0.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sampler.rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Captures at most `plans` plans per minute for each distinct statement.
+    /// Statements are told apart by their SQL, so executions differing only in
+    /// bind values count as the same statement. Unlimited by default.
+    pub fn max_per_minute(mut self, plans: u32) -> Self {
+        self.sampler.max_per_minute = Some(plans);
+        self
+    }
+
+    /// Only captures statements whose execution took at least `duration`.
+    ///
+    /// A statement can't be explained after it ran, since Diesel consumes it, so
+    /// the plan is captured the next time the same statement is executed, with
+    /// the bind values of that execution. Execution time is measured until Diesel
+    /// returns the result, or the first row when loading row by row.
+    pub fn min_duration(mut self, duration: Duration) -> Self {
+        self.thresholds.min_duration = Some(duration);
+        self
+    }
+
+    /// Only captures plans whose estimated total cost is at least `cost`. Statements
+    /// are still explained to learn their cost, but cheap plans are not reported.
+    pub fn min_cost(mut self, cost: f64) -> Self {
+        self.thresholds.min_cost = Some(cost);
+        self
+    }
+
+    /// Returns the wrapped connection, e.g. to run statements without capturing them.
+    pub fn inner(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Unwraps the connection.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    /// Explains `source` if it is selected for capture, and returns the fingerprint
+    /// of explainable statements for [`Thresholds::record()`].
+    fn capture<T: QueryFragment<Pg>>(&mut self, source: &T) -> Option<u64> {
+        let mut query_builder = PgQueryBuilder::default();
+        source.to_sql(&mut query_builder, &Pg).ok()?;
+        let sql = query_builder.finish();
+        if !is_explainable(&sql) {
+            return None;
+        }
+        let fingerprint = fingerprint(&sql);
+        let Some(duration) = self.thresholds.due(fingerprint) else {
+            return Some(fingerprint);
+        };
+        if !self.sampler.admit(fingerprint, Instant::now()) {
+            return Some(fingerprint);
+        }
+        if let Ok(item) = self.explain(source) {
+            if self.thresholds.is_expensive(&item.plan) {
+                let ctx = CaptureContext {
+                    sql,
+                    options: capture_options(ExplainConfig::global().options),
+                    duration,
+                    captured_at: SystemTime::now(),
+                    query_identifier: item.query_identifier,
+                };
+                self.sink.on_plan(&ctx, &item.plan);
+            }
+        }
+        Some(fingerprint)
+    }
+
+    fn explain<T: QueryFragment<Pg>>(&mut self, source: &T) -> Result<ExplainItem, ExplainError> {
+        let options = capture_options(ExplainConfig::global().options);
+        let status = C::TransactionManager::transaction_manager_status_mut(&mut self.inner);
+        if !matches!(status.transaction_depth(), Ok(Some(_))) {
+            return Explain::new(source)
+                .options(options)
+                .explain_item(&mut self.inner);
+        }
+        C::TransactionManager::begin_transaction(&mut self.inner)?;
+        let item = Explain::new(source)
+            .options(options)
+            .explain_item(&mut self.inner);
+        C::TransactionManager::rollback_transaction(&mut self.inner)?;
+        item
+    }
+}
+
+/// Turns off `ANALYZE`, which would execute the statement a second time, and
+/// the options PostgreSQL rejects without it: `WAL`, `TIMING` and, before
+/// PostgreSQL 13, `BUFFERS`.
+fn capture_options(options: ExplainOptions) -> ExplainOptions {
+    options
+        .analyze(false)
+        .buffers(false)
+        .wal(false)
+        .timing(true)
+}
+
+/// Identifies a statement by its SQL text.
+fn fingerprint(sql: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    sql.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// See [`AutoExplainConnection::min_duration()`] and [`AutoExplainConnection::min_cost()`].
+#[derive(Default)]
+struct Thresholds {
+    min_duration: Option<Duration>,
+    min_cost: Option<f64>,
+    /// Statements which took at least `min_duration`, by fingerprint, with the
+    /// time they took. Removed once the next execution is explained.
+    slow: HashMap<u64, Duration>,
+}
+
+impl Thresholds {
+    /// Remembers the statement with `fingerprint` if its execution took at least
+    /// `min_duration`.
+    fn record(&mut self, fingerprint: Option<u64>, duration: Duration) {
+        let (Some(fingerprint), Some(min_duration)) = (fingerprint, self.min_duration) else {
+            return;
+        };
+        if duration >= min_duration {
+            self.slow.insert(fingerprint, duration);
+        }
+    }
+
+    /// Decides whether the statement with `fingerprint` is explained before it
+    /// runs: always without `min_duration`, otherwise once after each execution
+    /// [recorded](Self::record) as slow, returning how long that took. `None` if
+    /// the statement is not explained.
+    fn due(&mut self, fingerprint: u64) -> Option<Option<Duration>> {
+        match self.min_duration {
+            Some(_) => self.slow.remove(&fingerprint).map(Some),
+            None => Some(None),
+        }
+    }
+
+    /// Returns `true` if `plan` costs at least `min_cost`, or if it isn't set.
+    fn is_expensive(&self, plan: &ExplainPlan) -> bool {
+        self.min_cost.is_none_or(|cost| plan.total_cost >= cost)
+    }
+}
+
+/// Decides which statements are explained, see [`AutoExplainConnection::sample_rate()`]
+/// and [`AutoExplainConnection::max_per_minute()`].
+struct Sampler {
+    rate: f64,
+    max_per_minute: Option<u32>,
+    /// State of a xorshift generator, never zero.
+    random: u64,
+    /// Start of the current one-minute window and number of plans captured in
+    /// it, by hash of the statement.
+    windows: HashMap<u64, (Instant, u32)>,
+}
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+impl Sampler {
+    fn new() -> Self {
+        Self {
+            rate: 1.0,
+            max_per_minute: None,
+            random: RandomState::new().build_hasher().finish() | 1,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Decides whether the statement with `fingerprint`, executed at `now`, is
+    /// explained.
+    fn admit(&mut self, fingerprint: u64, now: Instant) -> bool {
+        if self.rate < 1.0 && self.next_random() >= self.rate {
+            return false;
+        }
+        let Some(max) = self.max_per_minute else {
+            return true;
+        };
+
+        let (start, count) = self.windows.entry(fingerprint).or_insert((now, 0));
+        if now.duration_since(*start) >= WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= max {
+            return false;
+        }
+        *count += 1;
+
+        // Forget windows which have ended, so the map only grows with the number
+        // of statements captured within a minute.
+        if self.windows.len() > 1024 {
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+        }
+        true
+    }
+
+    /// Returns a pseudo-random number in `[0, 1)`.
+    fn next_random(&mut self) -> f64 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        (self.random >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Whether `sql` starts with a statement keyword `EXPLAIN` accepts.
+fn is_explainable(sql: &str) -> bool {
+    let sql = sql.trim_start_matches(|c: char| c.is_whitespace() || c == '(');
+    EXPLAINABLE.iter().any(|keyword| {
+        sql.get(..keyword.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(keyword))
+    })
+}
+
+impl<C> SimpleConnection for AutoExplainConnection<C>
+where
+    C: SimpleConnection,
+{
+    fn batch_execute(&mut self, query: &str) -> QueryResult<()> {
+        self.inner.batch_execute(query)
+    }
+}
+
+impl<C> ConnectionSealed for AutoExplainConnection<C> {}
+
+impl<C> Connection for AutoExplainConnection<C>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    type Backend = Pg;
+    type TransactionManager = AutoExplainTransactionManager<C::TransactionManager>;
+
+    fn establish(_: &str) -> ConnectionResult<Self> {
+        Err(ConnectionError::BadConnection(String::from(
+            "Cannot directly establish an auto-explain connection, use AutoExplainConnection::new",
+        )))
+    }
+
+    fn execute_returning_count<T>(&mut self, source: &T) -> QueryResult<usize>
+    where
+        T: QueryFragment<Pg> + QueryId,
+    {
+        let fingerprint = self.capture(source);
+        let started = Instant::now();
+        let count = self.inner.execute_returning_count(source);
+        self.thresholds.record(fingerprint, started.elapsed());
+        count
+    }
+
+    fn transaction_state(
+        &mut self,
+    ) -> &mut <Self::TransactionManager as TransactionManager<Self>>::TransactionStateData {
+        self.inner.transaction_state()
+    }
+
+    fn instrumentation(&mut self) -> &mut dyn Instrumentation {
+        self.inner.instrumentation()
+    }
+
+    fn set_instrumentation(&mut self, instrumentation: impl Instrumentation) {
+        self.inner.set_instrumentation(instrumentation)
+    }
+
+    fn set_prepared_statement_cache_size(&mut self, size: CacheSize) {
+        self.inner.set_prepared_statement_cache_size(size)
+    }
+}
+
+impl<B, C> LoadConnection<B> for AutoExplainConnection<C>
+where
+    C: Connection<Backend = Pg> + LoadConnection + LoadConnection<B>,
+{
+    type Cursor<'conn, 'query>
+        = <C as LoadConnection<B>>::Cursor<'conn, 'query>
+    where
+        Self: 'conn;
+    type Row<'conn, 'query>
+        = <C as LoadConnection<B>>::Row<'conn, 'query>
+    where
+        Self: 'conn;
+
+    fn load<'conn, 'query, T>(
+        &'conn mut self,
+        source: T,
+    ) -> QueryResult<Self::Cursor<'conn, 'query>>
+    where
+        T: Query + QueryFragment<Pg> + QueryId + 'query,
+        Pg: QueryMetadata<T::SqlType>,
+    {
+        let fingerprint = self.capture(&source);
+        let started = Instant::now();
+        let cursor = <C as LoadConnection<B>>::load(&mut self.inner, source);
+        self.thresholds.record(fingerprint, started.elapsed());
+        cursor
+    }
+}
+
+/// Transaction manager of [`AutoExplainConnection`], forwarding to the one of
+/// the wrapped connection.
+#[doc(hidden)]
+#[allow(missing_debug_implementations)]
+pub struct AutoExplainTransactionManager<T>(PhantomData<T>);
+
+impl<C, T> TransactionManager<AutoExplainConnection<C>> for AutoExplainTransactionManager<T>
+where
+    C: Connection<Backend = Pg, TransactionManager = T> + LoadConnection,
+    T: TransactionManager<C>,
+{
+    type TransactionStateData = T::TransactionStateData;
+
+    fn begin_transaction(conn: &mut AutoExplainConnection<C>) -> QueryResult<()> {
+        T::begin_transaction(&mut conn.inner)
+    }
+
+    fn rollback_transaction(conn: &mut AutoExplainConnection<C>) -> QueryResult<()> {
+        T::rollback_transaction(&mut conn.inner)
+    }
+
+    fn commit_transaction(conn: &mut AutoExplainConnection<C>) -> QueryResult<()> {
+        T::commit_transaction(&mut conn.inner)
+    }
+
+    fn transaction_manager_status_mut(
+        conn: &mut AutoExplainConnection<C>,
+    ) -> &mut TransactionManagerStatus {
+        T::transaction_manager_status_mut(&mut conn.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_statements_are_explained_on_their_next_execution() {
+        let mut unlimited = Thresholds::default();
+        assert_eq!(unlimited.due(1), Some(None));
+        unlimited.record(Some(1), Duration::from_secs(5));
+        assert!(unlimited.slow.is_empty());
+
+        let mut thresholds = Thresholds {
+            min_duration: Some(Duration::from_millis(100)),
+            ..Thresholds::default()
+        };
+        assert_eq!(thresholds.due(1), None);
+        thresholds.record(Some(1), Duration::from_millis(50));
+        assert_eq!(thresholds.due(1), None);
+        thresholds.record(Some(1), Duration::from_millis(150));
+        thresholds.record(None, Duration::from_millis(150));
+        assert_eq!(thresholds.due(2), None);
+        assert_eq!(thresholds.due(1), Some(Some(Duration::from_millis(150))));
+        assert_eq!(thresholds.due(1), None);
+    }
+
+    #[test]
+    fn cheap_plans_are_not_captured() {
+        let plan = |total_cost| ExplainPlan {
+            total_cost,
+            ..ExplainPlan::scan("Seq Scan", "users")
+        };
+        assert!(Thresholds::default().is_expensive(&plan(0.0)));
+        let thresholds = Thresholds {
+            min_cost: Some(1000.0),
+            ..Thresholds::default()
+        };
+        assert!(!thresholds.is_expensive(&plan(999.9)));
+        assert!(thresholds.is_expensive(&plan(1000.0)));
+        assert!(thresholds.is_expensive(&plan(25_000.0)));
+    }
+
+    #[test]
+    fn sampler_admits_rate_and_limits_per_minute() {
+        let now = Instant::now();
+        let mut never = Sampler {
+            rate: 0.0,
+            ..Sampler::new()
+        };
+        assert!((0..100).all(|fingerprint| !never.admit(fingerprint, now)));
+        let mut always = Sampler::new();
+        assert!((0..100).all(|fingerprint| always.admit(fingerprint, now)));
+
+        let mut limited = Sampler {
+            max_per_minute: Some(1),
+            ..Sampler::new()
+        };
+        assert!(limited.admit(1, now));
+        assert!(!limited.admit(1, now + Duration::from_secs(30)));
+        assert!(limited.admit(2, now + Duration::from_secs(30)));
+        assert!(limited.admit(1, now + WINDOW));
+        assert!(!limited.admit(1, now + WINDOW + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn capture_never_analyzes() {
+        let options = ExplainOptions::default()
+            .analyze(true)
+            .wal(true)
+            .timing(false)
+            .buffers(true);
+        assert_eq!(capture_options(options), ExplainOptions::default());
+    }
+}
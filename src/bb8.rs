@@ -0,0 +1,71 @@
+//! Support for `bb8` pools of [`AsyncPgConnection`]s, as provided by
+//! [`diesel_async::pooled_connection::bb8`].
+
+use std::time::Duration;
+
+use diesel_async::methods::LoadQuery;
+use diesel_async::pooled_connection::bb8::{Pool, RunError};
+use diesel_async::pooled_connection::PoolError;
+use diesel_async::AsyncPgConnection;
+
+use crate::{Explain, ExplainError, ExplainPlan};
+
+/// Errors returned by [`Explain::explain_bb8()`].
+#[derive(Debug, thiserror::Error)]
+pub enum Bb8ExplainError {
+    /// The pool was exhausted and no connection became available in time.
+    #[error("timed out waiting for a pooled connection")]
+    PoolTimeout,
+    /// The pool failed to establish or validate a connection.
+    #[error("failed to check out a connection: {0}")]
+    Pool(#[source] PoolError),
+    /// Explaining the query failed.
+    #[error(transparent)]
+    Query(#[from] ExplainError),
+}
+
+impl From<RunError> for Bb8ExplainError {
+    fn from(e: RunError) -> Self {
+        match e {
+            RunError::User(e) => Self::Pool(e),
+            RunError::TimedOut => Self::PoolTimeout,
+        }
+    }
+}
+
+impl<Q> Explain<Q> {
+    /// Checks a connection out of a `bb8` pool and runs
+    /// [`Explain::explain_async()`] on it.
+    ///
+    /// Waiting for a connection is bounded by `checkout_timeout` as well as by
+    /// the pool's own `connection_timeout`, whichever expires first, so an
+    /// exhausted pool makes plan capture fail fast instead of stalling the caller.
+    ///
+    /// Example:
+    /// ```rust
+    /// let plan = my_query
+    ///     .wrap_explain()
+    ///     .explain_bb8(&pool, Duration::from_millis(100))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`Bb8ExplainError::PoolTimeout`] if no connection became available
+    /// in time, [`Bb8ExplainError::Pool`] if the pool failed to provide one, and
+    /// [`Bb8ExplainError::Query`] if the query fails as described in
+    /// [`Explain::explain_async()`].
+    pub async fn explain_bb8<'a>(
+        self,
+        pool: &Pool<AsyncPgConnection>,
+        checkout_timeout: Duration,
+    ) -> Result<ExplainPlan, Bb8ExplainError>
+    where
+        Self: LoadQuery<'a, AsyncPgConnection, String> + 'a,
+    {
+        let mut conn = tokio::time::timeout(checkout_timeout, pool.get())
+            .await
+            .map_err(|_| Bb8ExplainError::PoolTimeout)??;
+        let plan = self.explain_async(&mut conn).await?;
+        Ok(plan)
+    }
+}
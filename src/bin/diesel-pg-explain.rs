@@ -0,0 +1,209 @@
+//! Explains SQL statements given on the command line or read from files and
+//! prints their plan trees, or renders `EXPLAIN` output read from stdin.
+//! Built with the `cli` feature.
+
+use std::io::{self, Read};
+use std::process::ExitCode;
+use std::{env, fs};
+
+use diesel::{Connection, PgConnection};
+use diesel_pg_explain::render::{self, Format};
+use diesel_pg_explain::{explain_raw, ExplainConfig, ExplainOptions};
+
+const USAGE: &str = "\
+Usage: diesel-pg-explain [OPTIONS] [SQL]...
+       diesel-pg-explain --stdin [--format <FORMAT>]
+
+Explains each SQL statement, given as an argument or read from files, and
+prints its plan tree. With --stdin, renders EXPLAIN (FORMAT JSON) output, e.g.
+copied from psql, without connecting to a database.
+
+Options:
+  -f, --file <PATH>          Explain the statements in PATH, separated by `;`
+  -d, --database-url <URL>   Database to connect to [default: $DATABASE_URL]
+      --analyze              Run the statements, inside rolled back transactions
+      --buffers              Report buffer usage
+      --verbose              Report output columns and other details
+      --settings             Report modified planner settings
+      --json                 Print plans as JSON instead of trees
+      --format <FORMAT>      Render plans as `tree`, `markdown` or `dot` [default: tree]
+      --stdin                Render the EXPLAIN output read from stdin
+  -h, --help                 Print this help
+
+Default options are read from DIESEL_PG_EXPLAIN_* environment variables.";
+
+struct Args {
+    database_url: Option<String>,
+    options: ExplainOptions,
+    json: bool,
+    format: Format,
+    stdin: bool,
+    statements: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let config = ExplainConfig::from_env().map_err(|e| e.to_string())?;
+    let mut parsed = Args {
+        database_url: env::var("DATABASE_URL").ok(),
+        options: config.options,
+        json: false,
+        format: Format::Tree,
+        stdin: false,
+        statements: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{name} requires a value"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-f" | "--file" => {
+                let path = value(&arg)?;
+                let sql = fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+                parsed.statements.extend(split_statements(&sql));
+            }
+            "-d" | "--database-url" => parsed.database_url = Some(value(&arg)?),
+            "--analyze" => parsed.options = parsed.options.analyze(true),
+            "--buffers" => parsed.options = parsed.options.buffers(true),
+            "--verbose" => parsed.options = parsed.options.verbose(true),
+            "--settings" => parsed.options = parsed.options.settings(true),
+            "--json" => parsed.json = true,
+            "--format" => {
+                parsed.format = match value(&arg)?.as_str() {
+                    "tree" => Format::Tree,
+                    "markdown" => Format::Markdown,
+                    "dot" => Format::Dot,
+                    format => return Err(format!("unknown format {format}")),
+                }
+            }
+            "--stdin" => parsed.stdin = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ => parsed.statements.push(arg),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+/// Splits a script into statements at semicolons outside of quotes, quoted
+/// identifiers, dollar-quoted strings and comments. Empty statements are skipped.
+fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut rest = sql.char_indices().peekable();
+    while let Some((i, c)) = rest.next() {
+        let skip_to = match c {
+            ';' => {
+                statements.push(sql[start..i].to_owned());
+                start = i + 1;
+                continue;
+            }
+            '\'' | '"' => sql[i + 1..].find(c).map(|end| i + 1 + end + 1),
+            '-' if sql[i..].starts_with("--") => sql[i..].find('\n').map(|end| i + end),
+            '/' if sql[i..].starts_with("/*") => sql[i..].find("*/").map(|end| i + end + 2),
+            '$' => {
+                // `$1` is a parameter rather than the start of a `$tag$`.
+                let Some(tag) = sql[i + 1..]
+                    .find('$')
+                    .map(|end| &sql[i..i + 1 + end + 1])
+                    .filter(|tag| {
+                        !tag[1..].starts_with(|c: char| c.is_ascii_digit())
+                            && tag[1..tag.len() - 1]
+                                .chars()
+                                .all(|c| c.is_alphanumeric() || c == '_')
+                    })
+                else {
+                    continue;
+                };
+                sql[i + tag.len()..]
+                    .find(tag)
+                    .map(|end| i + 2 * tag.len() + end)
+            }
+            _ => continue,
+        };
+        let skip_to = skip_to.unwrap_or(sql.len());
+        while rest.next_if(|&(j, _)| j < skip_to).is_some() {}
+    }
+    statements.push(sql[start..].to_owned());
+    statements.retain(|statement| !statement.trim().is_empty());
+    statements
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    if args.stdin {
+        let mut input = String::new();
+        let rendered = io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| e.to_string())
+            .and_then(|_| render::render_json(&input, args.format).map_err(|e| e.to_string()));
+        return match rendered {
+            Ok(rendered) => {
+                print!("{rendered}");
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let Some(database_url) = args.database_url else {
+        eprintln!("error: no database given, set DATABASE_URL or pass --database-url");
+        return ExitCode::from(2);
+    };
+    let mut conn = match PgConnection::establish(&database_url) {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut status = ExitCode::SUCCESS;
+    for (i, sql) in args.statements.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("-- {}", sql.trim());
+        match explain_raw(&mut conn, sql, args.options) {
+            Ok(plan) if args.json => println!(
+                "{}",
+                serde_json::to_string_pretty(&plan).expect("plans serialize to JSON")
+            ),
+            Ok(plan) => print!("{}", render::render(&plan, args.format)),
+            Err(e) => {
+                eprintln!("error: {e}");
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_are_split_outside_quotes_and_comments() {
+        let sql = "SELECT ';' AS \"a;b\"; -- not ; a statement\n\
+                   SELECT $f$ ; $f$ /* ; */ ;\n\
+                   SELECT $1 ;\n ;";
+        assert_eq!(
+            split_statements(sql),
+            [
+                "SELECT ';' AS \"a;b\"",
+                " -- not ; a statement\nSELECT $f$ ; $f$ /* ; */ ",
+                "\nSELECT $1 ",
+            ]
+        );
+    }
+}
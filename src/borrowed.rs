@@ -0,0 +1,160 @@
+//! A plan tree borrowing its strings from the `EXPLAIN (FORMAT JSON)` output.
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{deserialize, single_item, ExplainError};
+
+/// A lightweight variant of [`ExplainPlan`](crate::ExplainPlan) borrowing its
+/// strings from the JSON document it was parsed from.
+///
+/// Only the most commonly used fields are kept and everything else is skipped, so
+/// parsing large plans allocates little more than the `plans` vectors. Strings
+/// are only copied when they contain JSON escapes.
+///
+/// Example:
+/// ```rust
+/// let json = query.wrap_explain().explain_json(&mut conn)?;
+/// let plan = ExplainPlanRef::parse(&json)?;
+/// let scans = plan.nodes().filter(|node| node.node_type == "Seq Scan").count();
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExplainPlanRef<'a> {
+    /// The type of the plan node, e.g. "Seq Scan".
+    #[serde(rename = "Node Type", borrow)]
+    pub node_type: Cow<'a, str>,
+
+    /// The relationship of this node to its parent, e.g. "Outer" or "Inner".
+    #[serde(
+        rename = "Parent Relationship",
+        borrow,
+        default,
+        deserialize_with = "borrow_str"
+    )]
+    pub parent_relationship: Option<Cow<'a, str>>,
+
+    /// Type of a join node, e.g. "Inner" or "Left".
+    #[serde(rename = "Join Type", borrow, default, deserialize_with = "borrow_str")]
+    pub join_type: Option<Cow<'a, str>>,
+
+    /// Name of the table read or modified by this node.
+    #[serde(
+        rename = "Relation Name",
+        borrow,
+        default,
+        deserialize_with = "borrow_str"
+    )]
+    pub relation_name: Option<Cow<'a, str>>,
+
+    /// Alias under which the relation appears in the query.
+    #[serde(rename = "Alias", borrow, default, deserialize_with = "borrow_str")]
+    pub alias: Option<Cow<'a, str>>,
+
+    /// Name of the index used by an index scan.
+    #[serde(
+        rename = "Index Name",
+        borrow,
+        default,
+        deserialize_with = "borrow_str"
+    )]
+    pub index_name: Option<Cow<'a, str>>,
+
+    /// Condition used to filter rows after reading them.
+    #[serde(rename = "Filter", borrow, default, deserialize_with = "borrow_str")]
+    pub filter: Option<Cow<'a, str>>,
+
+    /// The estimated cost of starting this plan node.
+    #[serde(rename = "Startup Cost")]
+    pub startup_cost: f64,
+
+    /// The estimated total cost of this plan node.
+    #[serde(rename = "Total Cost")]
+    pub total_cost: f64,
+
+    /// The estimated number of rows this plan node will output.
+    #[serde(rename = "Plan Rows")]
+    pub plan_rows: u64,
+
+    /// Time in milliseconds spent in this node and its children, averaged per loop.
+    /// Reported with `ANALYZE`.
+    #[serde(rename = "Actual Total Time", default)]
+    pub actual_total_time: Option<f64>,
+
+    /// Number of rows returned by this node, averaged per loop. Reported with `ANALYZE`.
+    #[serde(rename = "Actual Rows", default)]
+    pub actual_rows: Option<f64>,
+
+    /// Number of times this node was executed. Reported with `ANALYZE`.
+    #[serde(rename = "Actual Loops", default)]
+    pub actual_loops: Option<u64>,
+
+    /// Child plan nodes.
+    #[serde(rename = "Plans", borrow, default)]
+    pub plans: Vec<ExplainPlanRef<'a>>,
+}
+
+/// A string borrowed from the input where possible. `Option<Cow<str>>` fields
+/// are always copied by serde, so optional strings are deserialized through this.
+#[derive(Deserialize)]
+struct BorrowedStr<'a>(#[serde(borrow)] Cow<'a, str>);
+
+fn borrow_str<'de: 'a, 'a, D>(deserializer: D) -> Result<Option<Cow<'a, str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<BorrowedStr<'a>>::deserialize(deserializer)?;
+    Ok(value.map(|value| value.0))
+}
+
+#[derive(Deserialize)]
+struct ExplainItemRef<'a> {
+    #[serde(rename = "Plan", borrow)]
+    plan: ExplainPlanRef<'a>,
+}
+
+impl<'a> ExplainPlanRef<'a> {
+    /// Parses an `EXPLAIN (FORMAT JSON)` document, which must describe exactly one
+    /// plan, e.g. as returned by [`Explain::explain_json()`](crate::Explain::explain_json).
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Parse`] for malformed documents, and
+    /// [`ExplainError::EmptyResult`] or [`ExplainError::MultipleResults`] if the
+    /// document does not contain exactly one plan.
+    pub fn parse(json: &'a str) -> Result<Self, ExplainError> {
+        let items: Vec<ExplainItemRef<'a>> = deserialize(json)?;
+        Ok(single_item(items)?.plan)
+    }
+
+    /// Iterates over this node and all of its descendants in depth-first pre-order.
+    pub fn nodes(&self) -> impl Iterator<Item = &ExplainPlanRef<'a>> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.plans.iter().rev());
+            Some(node)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrowed_plans_copy_only_escaped_strings() {
+        let json = r#"[{"Plan": {"Node Type": "Seq Scan", "Relation Name": "users",
+            "Filter": "(name = 'a\"b'::text)", "Startup Cost": 0.0, "Total Cost": 1.5,
+            "Plan Rows": 3, "Plan Width": 4, "Parallel Aware": false}}]"#;
+
+        let plan = ExplainPlanRef::parse(json).unwrap();
+        assert!(matches!(plan.node_type, Cow::Borrowed("Seq Scan")));
+        assert!(matches!(plan.relation_name, Some(Cow::Borrowed("users"))));
+        assert!(matches!(plan.filter, Some(Cow::Owned(_))));
+        assert_eq!(plan.filter.as_deref(), Some("(name = 'a\"b'::text)"));
+        assert!(matches!(
+            ExplainPlanRef::parse("[]"),
+            Err(ExplainError::EmptyResult)
+        ));
+    }
+}
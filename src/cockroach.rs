@@ -0,0 +1,529 @@
+//! Best-effort support for CockroachDB.
+//!
+//! CockroachDB speaks the PostgreSQL wire protocol, so Diesel connects to it
+//! like to any other PostgreSQL server, but it doesn't support
+//! `EXPLAIN (FORMAT JSON)`: [`Explain`](crate::Explain) fails with a database
+//! error. Its own `EXPLAIN` output is a tree of `•` nodes with indented
+//! properties instead, which [`parse()`] turns into the same types as a
+//! PostgreSQL plan:
+//!
+//! ```text
+//! distribution: local
+//! vectorized: true
+//!
+//! • hash join
+//! │ estimated row count: 3
+//! │ equality: (id) = (user_id)
+//! │
+//! ├── • scan
+//! │     estimated row count: 3 (100% of the table; stats collected 2 minutes ago)
+//! │     table: users@users_pkey
+//! │     spans: FULL SCAN
+//! │
+//! └── • scan
+//!       estimated row count: 2
+//!       table: orders@orders_user_id_idx
+//!       spans: [/1 - /1]
+//! ```
+//!
+//! Node names are mapped to their closest PostgreSQL counterparts, e.g. a `scan`
+//! with `spans: FULL SCAN` becomes a `Seq Scan` and a `lookup join` a
+//! `Nested Loop`, so the [`analysis`](crate::analysis) and [`lint`](crate::lint)
+//! modules work on the result. CockroachDB doesn't report costs, so these are
+//! zero, which makes cost-based checks meaningless. The original node name and
+//! all properties without a PostgreSQL equivalent are kept as strings in
+//! [`ExplainPlan::extra`](crate::ExplainPlan::extra).
+
+#[cfg(feature = "diesel-2")]
+use diesel::sql_types::Text;
+#[cfg(feature = "diesel-2")]
+use diesel::{connection::TransactionManager, QueryableByName, RunQueryDsl};
+use serde_json::{Map, Value};
+
+#[cfg(feature = "diesel-2")]
+use crate::ExplainConnection;
+use crate::{deserialize, ExplainError, ExplainItem};
+
+/// The key in [`ExplainPlan::extra`](crate::ExplainPlan::extra) holding the
+/// CockroachDB name of a node, e.g. `lookup join (semi)`.
+pub const NODE_NAME_KEY: &str = "CockroachDB Node";
+
+/// Parses the output of CockroachDB's `EXPLAIN` or `EXPLAIN ANALYZE`, with or
+/// without the `info` header and row count footer printed by `cockroach sql`.
+///
+/// The `planning time` and `execution time` of `EXPLAIN ANALYZE` are returned as
+/// [`ExplainItem::planning_time`] and [`ExplainItem::execution_time`]; the other
+/// lines preceding the tree, e.g. `distribution: local`, are kept in
+/// [`ExplainItem::extra`].
+///
+/// Example:
+/// ```rust
+/// let item = diesel_pg_explain::cockroach::parse(
+///     "• scan\n  estimated row count: 10\n  table: users@users_pkey\n  spans: FULL SCAN",
+/// )?;
+/// assert_eq!(item.plan.node_type, "Seq Scan");
+/// ```
+///
+/// # Errors
+/// Returns [`ExplainError::ParseText`] if a node line is malformed or `text`
+/// contains more than one tree, and [`ExplainError::EmptyResult`] if it contains
+/// none.
+pub fn parse(text: &str) -> Result<ExplainItem, ExplainError> {
+    let mut item = Map::new();
+    // Open nodes with the column of their bullet, innermost last.
+    let mut stack: Vec<(usize, Map<String, Value>)> = Vec::new();
+    let mut root = None;
+
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        if is_decoration(line) {
+            continue;
+        }
+        let content = line.trim_start_matches([' ', '│', '├', '└', '─']);
+        let column = line.chars().count() - content.chars().count();
+        let content = content.trim_end();
+        if content.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = content.strip_prefix('•') {
+            close(&mut stack, &mut root, column).map_err(|message| ExplainError::ParseText {
+                line: number,
+                message,
+            })?;
+            if stack.is_empty() && root.is_some() {
+                return Err(ExplainError::ParseText {
+                    line: number,
+                    message: "found more than one plan".to_owned(),
+                });
+            }
+            stack.push((column, node(name.trim())));
+            continue;
+        }
+
+        close(&mut stack, &mut root, column).map_err(|message| ExplainError::ParseText {
+            line: number,
+            message,
+        })?;
+        // Lines without a value, e.g. `missing stats`, are notes on the node.
+        let (key, value) = content.split_once(": ").unwrap_or((content, ""));
+        match stack.last_mut() {
+            Some((_, object)) => add_property(object, key, value.trim()),
+            None => add_summary(&mut item, key, value.trim()),
+        }
+    }
+    close(&mut stack, &mut root, 0)
+        .map_err(|message| ExplainError::ParseText { line: 0, message })?;
+
+    let Some(root) = root else {
+        return Err(ExplainError::EmptyResult);
+    };
+    item.insert("Plan".to_owned(), Value::Object(root));
+    deserialize(&Value::Object(item).to_string())
+}
+
+/// Returns `true` for the `info` header, the dashed line and the `(N rows)`
+/// footer printed by `cockroach sql`.
+fn is_decoration(line: &str) -> bool {
+    let trimmed = line.trim();
+    let is_footer = trimmed.starts_with('(')
+        && (trimmed.ends_with(" rows)") || trimmed.ends_with(" row)"))
+        && trimmed[1..].starts_with(|c: char| c.is_ascii_digit());
+    trimmed == "info"
+        || is_footer
+        || (trimmed.starts_with('-') && trimmed.chars().all(|c| c == '-' || c == '+'))
+}
+
+/// Closes the nodes whose bullet is at or right of `column`, attaching each to
+/// its parent, or making it the root.
+fn close(
+    stack: &mut Vec<(usize, Map<String, Value>)>,
+    root: &mut Option<Map<String, Value>>,
+    column: usize,
+) -> Result<(), String> {
+    while stack.last().is_some_and(|(open, _)| *open >= column) {
+        let Some((_, mut object)) = stack.pop() else {
+            break;
+        };
+        finish(&mut object);
+        match stack.last_mut() {
+            Some((_, parent)) => {
+                let relationship = match parent.get("Plans").and_then(Value::as_array) {
+                    Some(children) if !children.is_empty() => "Inner",
+                    _ => "Outer",
+                };
+                object.insert("Parent Relationship".to_owned(), relationship.into());
+                if let Value::Array(children) = parent
+                    .entry("Plans")
+                    .or_insert_with(|| Value::Array(Vec::new()))
+                {
+                    children.push(Value::Object(object));
+                }
+            }
+            None if root.is_some() => return Err("found more than one plan".to_owned()),
+            None => *root = Some(object),
+        }
+    }
+    Ok(())
+}
+
+/// Starts the object of a node named e.g. `hash join (left outer)`.
+fn node(name: &str) -> Map<String, Value> {
+    let mut object = Map::new();
+    object.insert(NODE_NAME_KEY.to_owned(), name.into());
+    object.insert("Parallel Aware".to_owned(), false.into());
+    object.insert("Startup Cost".to_owned(), 0.into());
+    object.insert("Total Cost".to_owned(), 0.into());
+    object.insert("Plan Rows".to_owned(), 0.into());
+    object.insert("Plan Width".to_owned(), 0.into());
+    object
+}
+
+/// Sets the PostgreSQL node type once all properties of a node are known.
+fn finish(object: &mut Map<String, Value>) {
+    let name = object
+        .get(NODE_NAME_KEY)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_owned();
+    let (head, detail) = match name.split_once(" (") {
+        Some((head, detail)) => (head, detail.strip_suffix(')')),
+        None => (name.as_str(), None),
+    };
+    let mut fields = Vec::new();
+    let node_type = match head {
+        "scan" => {
+            let full = object
+                .get("spans")
+                .and_then(Value::as_str)
+                .is_some_and(|spans| spans.starts_with("FULL SCAN"));
+            if full {
+                object.remove("Index Name");
+                "Seq Scan"
+            } else {
+                if let Some(spans) = object.remove("spans") {
+                    object.insert("Index Cond".to_owned(), spans);
+                }
+                object.insert("Scan Direction".to_owned(), "Forward".into());
+                "Index Scan"
+            }
+        }
+        "hash join" | "merge join" | "lookup join" | "inverted join" | "cross join"
+        | "apply join" | "zigzag join" => {
+            fields.push(("Join Type", join_type(detail)));
+            match head {
+                "hash join" => "Hash Join",
+                "merge join" => {
+                    if let Some(equality) = object.remove("Hash Cond") {
+                        object.insert("Merge Cond".to_owned(), equality);
+                    }
+                    "Merge Join"
+                }
+                _ => "Nested Loop",
+            }
+        }
+        "index join" => {
+            fields.push(("Join Type", "Inner"));
+            "Nested Loop"
+        }
+        "group" | "distinct" if detail == Some("scalar") => {
+            fields.push(("Strategy", "Plain"));
+            "Aggregate"
+        }
+        "group" => {
+            let strategy = if detail == Some("hash") {
+                "Hashed"
+            } else {
+                "Sorted"
+            };
+            fields.push(("Strategy", strategy));
+            "Aggregate"
+        }
+        "distinct" => "Unique",
+        "sort" | "top-k" => "Sort",
+        "limit" => "Limit",
+        "window" => "WindowAgg",
+        "values" => "Values Scan",
+        "union all" => "Append",
+        "insert" | "insert fast path" | "upsert" => {
+            fields.push(("Operation", "Insert"));
+            "ModifyTable"
+        }
+        "update" => {
+            fields.push(("Operation", "Update"));
+            "ModifyTable"
+        }
+        "delete" | "delete range" => {
+            fields.push(("Operation", "Delete"));
+            "ModifyTable"
+        }
+        _ => {
+            object.insert("Node Type".to_owned(), title_case(head).into());
+            return;
+        }
+    };
+    object.insert("Node Type".to_owned(), node_type.into());
+    for (key, value) in fields {
+        object.insert(key.to_owned(), value.into());
+    }
+}
+
+/// Maps the join kind in parentheses, e.g. `left outer`, to a `Join Type`.
+fn join_type(detail: Option<&str>) -> &'static str {
+    match detail {
+        Some("left outer") => "Left",
+        Some("right outer") => "Right",
+        Some("full outer") => "Full",
+        Some("semi" | "left semi") => "Semi",
+        Some("anti" | "left anti") => "Anti",
+        Some("right semi") => "Right Semi",
+        Some("right anti") => "Right Anti",
+        _ => "Inner",
+    }
+}
+
+fn title_case(name: &str) -> String {
+    name.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Adds a property line of a node, e.g. `table: users@users_pkey`.
+fn add_property(object: &mut Map<String, Value>, key: &str, value: &str) {
+    let mut set = |key: &str, value: Value| {
+        object.insert(key.to_owned(), value);
+    };
+    match key {
+        "estimated row count" => set("Plan Rows", count(value).map_or(0.into(), Value::from)),
+        "actual row count" => {
+            if let Some(rows) = count(value) {
+                set("Actual Rows", rows.into());
+                set("Actual Loops", 1.into());
+            }
+        }
+        "execution time" => match duration_ms(value) {
+            Some(ms) => {
+                set("Actual Startup Time", 0.into());
+                set("Actual Total Time", ms.into());
+            }
+            None => set(key, value.into()),
+        },
+        "table" => {
+            let (relation, index) = value.split_once('@').unwrap_or((value, ""));
+            set("Relation Name", relation.into());
+            set("Alias", relation.into());
+            if !index.is_empty() {
+                set("Index Name", index.into());
+            }
+        }
+        "filter" => set("Filter", value.into()),
+        "equality" => set("Hash Cond", value.into()),
+        "order" | "ordering" => set("Sort Key", sort_keys(value)),
+        "group by" => set(
+            "Group Key",
+            value
+                .split(", ")
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        _ => set(key, value.into()),
+    }
+}
+
+/// Adds a line preceding the tree, e.g. `planning time: 10ms`.
+fn add_summary(item: &mut Map<String, Value>, key: &str, value: &str) {
+    let name = match key {
+        "planning time" => "Planning Time",
+        "execution time" => "Execution Time",
+        _ => {
+            item.insert(key.to_owned(), value.into());
+            return;
+        }
+    };
+    match duration_ms(value) {
+        Some(ms) => item.insert(name.to_owned(), ms.into()),
+        None => item.insert(key.to_owned(), value.into()),
+    };
+}
+
+/// Parses the leading number of e.g. `1,000 (10% of the table; ...)`.
+fn count(value: &str) -> Option<u64> {
+    value
+        .split_whitespace()
+        .next()?
+        .replace(',', "")
+        .parse()
+        .ok()
+}
+
+/// Turns an ordering like `+name,-id` into PostgreSQL sort keys.
+fn sort_keys(value: &str) -> Value {
+    value
+        .split(',')
+        .map(|key| {
+            let key = key.trim();
+            match key.strip_prefix('-') {
+                Some(column) => format!("{column} DESC"),
+                None => key.trim_start_matches('+').to_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Parses a duration like `345µs`, `12ms` or `1.5s` into milliseconds.
+fn duration_ms(value: &str) -> Option<f64> {
+    let split = value.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let scale = match unit {
+        "ns" => 1e-6,
+        "µs" | "us" => 1e-3,
+        "ms" => 1.0,
+        "s" => 1e3,
+        _ => return None,
+    };
+    Some(number * scale)
+}
+
+#[cfg(feature = "diesel-2")]
+#[derive(QueryableByName)]
+struct Version {
+    #[diesel(sql_type = Text)]
+    version: String,
+}
+
+#[cfg(feature = "diesel-2")]
+#[derive(QueryableByName)]
+struct InfoRow {
+    #[diesel(sql_type = Text)]
+    info: String,
+}
+
+/// Returns `true` if `conn` is connected to CockroachDB rather than PostgreSQL.
+///
+/// # Errors
+/// Returns [`ExplainError::Diesel`] if the version can't be queried.
+#[cfg(feature = "diesel-2")]
+pub fn is_cockroach<C: ExplainConnection>(conn: &mut C) -> Result<bool, ExplainError> {
+    let version = diesel::sql_query("SELECT version() AS version").get_result::<Version>(conn)?;
+    Ok(version.version.contains("CockroachDB"))
+}
+
+/// Explains the raw SQL statement `sql` on a CockroachDB connection and parses
+/// the output with [`parse()`]. With `analyze`, the statement is executed in a
+/// transaction which is rolled back afterwards.
+///
+/// # Errors
+/// Returns [`ExplainError::Diesel`] if the `EXPLAIN` fails, e.g. because `conn` is
+/// connected to PostgreSQL, or any error of [`parse()`].
+#[cfg(feature = "diesel-2")]
+pub fn explain<C: ExplainConnection>(
+    conn: &mut C,
+    sql: &str,
+    analyze: bool,
+) -> Result<ExplainItem, ExplainError> {
+    let sql = sql.trim_end().trim_end_matches(';');
+    let query = if analyze {
+        format!("EXPLAIN ANALYZE {sql}")
+    } else {
+        format!("EXPLAIN {sql}")
+    };
+    let rows = if analyze {
+        C::TransactionManager::begin_transaction(conn)?;
+        let rows = diesel::sql_query(&query).load::<InfoRow>(conn);
+        C::TransactionManager::rollback_transaction(conn)?;
+        rows?
+    } else {
+        diesel::sql_query(&query).load::<InfoRow>(conn)?
+    };
+    parse(
+        &rows
+            .into_iter()
+            .map(|row| row.info)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cockroach_plans_are_parsed() {
+        let output = [
+            "info",
+            "----------",
+            "planning time: 10ms",
+            "execution time: 4ms",
+            "distribution: local",
+            "",
+            "• hash join (left outer)",
+            "│ actual row count: 2",
+            "│ estimated row count: 3",
+            "│ equality: (id) = (user_id)",
+            "│",
+            "├── • scan",
+            "│     actual row count: 3",
+            "│     estimated row count: 1,000 (100% of the table; stats collected 2 minutes ago)",
+            "│     table: users@users_pkey",
+            "│     spans: FULL SCAN",
+            "│",
+            "└── • sort",
+            "      order: +user_id,-id",
+            "      │",
+            "      └── • scan",
+            "            missing stats",
+            "            table: orders@orders_user_id_idx",
+            "            spans: [/1 - /1]",
+            "(19 rows)",
+        ]
+        .join("\n");
+        let item = parse(&output).unwrap();
+        assert_eq!(item.planning_time, Some(10.0));
+        assert_eq!(item.execution_time, Some(4.0));
+        assert_eq!(item.extra["distribution"], "local");
+
+        let join = &item.plan;
+        assert_eq!(join.node_type, "Hash Join");
+        assert_eq!(join.join_type.as_deref(), Some("Left"));
+        assert_eq!(join.hash_cond.as_deref(), Some("(id) = (user_id)"));
+        assert_eq!(join.actual_rows, Some(2.0));
+        assert_eq!(join.extra[NODE_NAME_KEY], "hash join (left outer)");
+
+        let [users, sort] = &join.plans[..] else {
+            panic!("expected two children, got {:?}", join.plans);
+        };
+        assert_eq!(users.node_type, "Seq Scan");
+        assert_eq!(users.relation_name.as_deref(), Some("users"));
+        assert_eq!(users.plan_rows, 1000);
+        assert_eq!(sort.node_type, "Sort");
+        assert_eq!(
+            sort.sort_key.as_deref(),
+            Some(&["user_id".to_owned(), "id DESC".to_owned()][..])
+        );
+        let orders = &sort.plans[0];
+        assert_eq!(orders.node_type, "Index Scan");
+        assert_eq!(orders.index_name.as_deref(), Some("orders_user_id_idx"));
+        assert_eq!(orders.index_cond.as_deref(), Some("[/1 - /1]"));
+        assert_eq!(orders.extra["missing stats"], "");
+
+        assert!(matches!(
+            parse("• scan\n• scan"),
+            Err(ExplainError::ParseText { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse("distribution: local"),
+            Err(ExplainError::EmptyResult)
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+//! Processing captured plans off the hot path.
+//!
+//! [`PlanCollector`] is a [`PlanSink`] which only puts plans on a bounded queue.
+//! A background thread takes them from the queue and passes them to the sink
+//! doing the actual work, such as storage, aggregation or lints. When the queue
+//! is full, new plans are dropped and counted instead of blocking the query
+//! which produced them.
+//!
+//! Example:
+//! ```rust
+//! use std::sync::Arc;
+//! use diesel_pg_explain::auto::AutoExplainConnection;
+//! use diesel_pg_explain::collector::PlanCollector;
+//!
+//! let collector = Arc::new(PlanCollector::spawn(1024, store_plan));
+//! let mut conn = AutoExplainConnection::new(conn, Arc::clone(&collector));
+//! // ...
+//! metrics::counter!("plans_dropped").absolute(collector.dropped());
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::sink::{CaptureContext, PlanSink};
+use crate::ExplainPlan;
+
+/// A [`PlanSink`] handing plans to another sink on a background thread,
+/// see the [module documentation](self).
+pub struct PlanCollector {
+    sender: Option<SyncSender<(CaptureContext, ExplainPlan)>>,
+    dropped: Arc<AtomicU64>,
+    lost: AtomicU64,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl PlanCollector {
+    /// Starts a background thread passing plans to `sink`, with room for
+    /// `capacity` plans waiting to be processed.
+    ///
+    /// # Panics
+    /// Panics if the thread cannot be spawned.
+    pub fn spawn(capacity: usize, sink: impl PlanSink + 'static) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<(CaptureContext, ExplainPlan)>(capacity);
+        let worker = thread::Builder::new()
+            .name("diesel-pg-explain-collector".to_owned())
+            .spawn(move || {
+                for (ctx, plan) in receiver {
+                    sink.on_plan(&ctx, &plan);
+                }
+            })
+            .expect("failed to spawn the plan collector thread");
+        Self {
+            sender: Some(sender),
+            dropped: Arc::new(AtomicU64::new(0)),
+            lost: AtomicU64::new(0),
+            worker: Some(worker),
+        }
+    }
+
+    /// Queues `plan` for processing. Returns `false` and drops the plan if the
+    /// queue is full, or if the background thread has stopped because the sink
+    /// panicked.
+    pub fn submit(&self, ctx: CaptureContext, plan: ExplainPlan) -> bool {
+        let Some(sender) = &self.sender else {
+            return false;
+        };
+        match sender.try_send((ctx, plan)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.lost.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    /// Returns the number of plans dropped so far because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of plans dropped so far because the background thread
+    /// had stopped after the sink panicked.
+    pub fn lost(&self) -> u64 {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// Stops accepting plans and waits until the queued ones are processed.
+    pub fn shutdown(mut self) {
+        self.sender = None;
+        if let Some(worker) = self.worker.take() {
+            // A panicking sink has already reported its panic; the remaining
+            // plans are lost either way.
+            let _ = worker.join();
+        }
+    }
+}
+
+impl PlanSink for PlanCollector {
+    fn on_plan(&self, ctx: &CaptureContext, plan: &ExplainPlan) {
+        self.submit(ctx.clone(), plan.clone());
+    }
+}
+
+impl std::fmt::Debug for PlanCollector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlanCollector")
+            .field("dropped", &self.dropped())
+            .field("lost", &self.lost())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn collector_drops_plans_when_queue_is_full() {
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let (started_tx, release_rx) = (Mutex::new(started_tx), Mutex::new(release_rx));
+        let processed = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&processed);
+        let collector = PlanCollector::spawn(1, move |ctx: &CaptureContext, _: &ExplainPlan| {
+            started_tx.lock().unwrap().send(()).unwrap();
+            release_rx.lock().unwrap().recv().unwrap();
+            seen.lock().unwrap().push(ctx.sql.clone());
+        });
+
+        let plan = ExplainPlan::default();
+        collector.on_plan(&CaptureContext::new("first"), &plan);
+        started.recv().unwrap();
+        assert!(collector.submit(CaptureContext::new("second"), plan.clone()));
+        assert!(!collector.submit(CaptureContext::new("third"), plan));
+        assert_eq!((collector.dropped(), collector.lost()), (1, 0));
+
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+        collector.shutdown();
+        assert_eq!(*processed.lock().unwrap(), ["first", "second"]);
+    }
+
+    #[test]
+    fn plans_submitted_after_a_sink_panic_are_lost() {
+        let mut collector = PlanCollector::spawn(1, |_: &CaptureContext, _: &ExplainPlan| {
+            panic!("sink failed");
+        });
+        assert!(collector.submit(CaptureContext::new("first"), ExplainPlan::default()));
+        assert!(collector.worker.take().unwrap().join().is_err());
+
+        assert!(!collector.submit(CaptureContext::new("second"), ExplainPlan::default()));
+        assert_eq!((collector.dropped(), collector.lost()), (0, 1));
+    }
+}
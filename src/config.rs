@@ -0,0 +1,313 @@
+//! Process-wide defaults for the convenience APIs.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::render::RenderOptions;
+use crate::{ExplainError, ExplainOptions, ParseMode};
+
+static INSTALLED: OnceLock<ExplainConfig> = OnceLock::new();
+static DEFAULT: OnceLock<ExplainConfig> = OnceLock::new();
+
+/// Defaults used wherever no value is given explicitly, so large codebases don't
+/// pass options through every call site.
+///
+/// The [installed](ExplainConfig::install) configuration is picked up by:
+/// - [`Explain::new()`](crate::Explain::new) and `wrap_explain()`, for the
+///   `EXPLAIN` options and the parse mode;
+/// - [`ExplainSession::new()`](crate::ExplainSession::new), likewise;
+/// - `AutoExplainConnection::new()`, for the sampling rate and the thresholds;
+/// - [`render::tree()`](crate::render::tree), for the render options.
+///
+/// Values set explicitly, e.g. with [`Explain::options()`](crate::Explain::options),
+/// take precedence.
+///
+/// Example:
+/// ```rust
+/// ExplainConfig::default()
+///     .options(ExplainOptions::default().buffers(true))
+///     .min_cost(10_000.0)
+///     .install()
+///     .expect("explain configuration is installed once");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainConfig {
+    /// Options passed to `EXPLAIN`.
+    pub options: ExplainOptions,
+
+    /// How fields unknown to [`ExplainPlan`](crate::ExplainPlan) are handled.
+    pub parse_mode: ParseMode,
+
+    /// Probability, between 0.0 and 1.0, of capturing a statement automatically.
+    pub sample_rate: f64,
+
+    /// Minimum execution time of automatically captured statements.
+    pub min_duration: Option<Duration>,
+
+    /// Minimum estimated total cost of automatically captured plans.
+    pub min_cost: Option<f64>,
+
+    /// How long `EXPLAIN ANALYZE` may wait for a lock before it is cancelled,
+    /// see [`Explain::lock_timeout()`](crate::Explain::lock_timeout). One second
+    /// by default; `None` disables the guard.
+    pub lock_timeout: Option<Duration>,
+
+    /// Options of the text renderer.
+    pub render: RenderOptions,
+}
+
+impl Default for ExplainConfig {
+    fn default() -> Self {
+        Self {
+            options: ExplainOptions::default(),
+            parse_mode: ParseMode::default(),
+            sample_rate: 1.0,
+            min_duration: None,
+            min_cost: None,
+            lock_timeout: Some(Duration::from_secs(1)),
+            render: RenderOptions::default(),
+        }
+    }
+}
+
+impl ExplainConfig {
+    /// Sets the options passed to `EXPLAIN`.
+    pub fn options(mut self, options: ExplainOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets how unknown fields are handled.
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    /// Sets the probability of capturing a statement automatically, clamped to
+    /// the range from 0.0 to 1.0.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the minimum execution time of automatically captured statements.
+    pub fn min_duration(mut self, duration: Duration) -> Self {
+        self.min_duration = Some(duration);
+        self
+    }
+
+    /// Sets the minimum estimated total cost of automatically captured plans.
+    pub fn min_cost(mut self, cost: f64) -> Self {
+        self.min_cost = Some(cost);
+        self
+    }
+
+    /// Sets how long `EXPLAIN ANALYZE` may wait for a lock, or disables the guard
+    /// with `None`.
+    pub fn lock_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Sets the options of the text renderer.
+    pub fn render(mut self, render: RenderOptions) -> Self {
+        self.render = render;
+        self
+    }
+
+    /// Returns the default configuration with the values of the `DIESEL_PG_EXPLAIN_*`
+    /// environment variables applied, see [`ExplainConfig::merge_env()`].
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::InvalidEnvVar`] if a variable has an invalid value.
+    pub fn from_env() -> Result<Self, ExplainError> {
+        Self::default().merge_env()
+    }
+
+    /// Overrides values of this configuration with those of environment variables,
+    /// so operators can tune capture without redeploying code:
+    ///
+    /// | Variable | Value | Sets |
+    /// |----------|-------|------|
+    /// | `DIESEL_PG_EXPLAIN_CAPTURE` | boolean | `sample_rate` to 0.0 when false |
+    /// | `DIESEL_PG_EXPLAIN_SAMPLE_RATE` | number from 0.0 to 1.0 | `sample_rate` |
+    /// | `DIESEL_PG_EXPLAIN_MIN_COST` | number | `min_cost` |
+    /// | `DIESEL_PG_EXPLAIN_MIN_DURATION_MS` | milliseconds | `min_duration` |
+    /// | `DIESEL_PG_EXPLAIN_LOCK_TIMEOUT_MS` | milliseconds, 0 to disable | `lock_timeout` |
+    /// | `DIESEL_PG_EXPLAIN_STRICT` | boolean | `parse_mode` |
+    /// | `DIESEL_PG_EXPLAIN_ANALYZE`, `_VERBOSE`, `_COSTS`, `_SETTINGS`, `_BUFFERS`, `_WAL`, `_TIMING`, `_SUMMARY` | boolean | the corresponding `options` |
+    ///
+    /// Booleans are `1`, `true`, `on` or `yes` and `0`, `false`, `off` or `no`,
+    /// in any case. Unset and empty variables leave the value unchanged.
+    ///
+    /// Example:
+    /// ```rust
+    /// ExplainConfig::default()
+    ///     .min_cost(10_000.0)
+    ///     .merge_env()?
+    ///     .install()
+    ///     .expect("explain configuration is installed once");
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::InvalidEnvVar`] if a variable has an invalid value.
+    pub fn merge_env(self) -> Result<Self, ExplainError> {
+        self.merge_vars(|name| std::env::var(name).ok())
+    }
+
+    pub(crate) fn merge_vars(
+        mut self,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ExplainError> {
+        let var = |suffix: &str| {
+            let name = format!("DIESEL_PG_EXPLAIN_{suffix}");
+            var(&name)
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| EnvVar { name, value })
+        };
+
+        if let Some(capture) = var("CAPTURE") {
+            if !capture.parse_bool()? {
+                self.sample_rate = 0.0;
+            }
+        }
+        if let Some(rate) = var("SAMPLE_RATE") {
+            let expected = "a number from 0.0 to 1.0";
+            self.sample_rate = rate.parse_number(expected)?;
+            if !(0.0..=1.0).contains(&self.sample_rate) {
+                return Err(rate.invalid(expected));
+            }
+        }
+        if let Some(cost) = var("MIN_COST") {
+            self.min_cost = Some(cost.parse_number("a number")?);
+        }
+        if let Some(millis) = var("MIN_DURATION_MS") {
+            let millis = millis.parse_number("a whole number of milliseconds")?;
+            self.min_duration = Some(Duration::from_millis(millis));
+        }
+        if let Some(millis) = var("LOCK_TIMEOUT_MS") {
+            let millis = millis.parse_number("a whole number of milliseconds")?;
+            self.lock_timeout = (millis > 0).then(|| Duration::from_millis(millis));
+        }
+        if let Some(strict) = var("STRICT") {
+            self.parse_mode = match strict.parse_bool()? {
+                true => ParseMode::Strict,
+                false => ParseMode::Lenient,
+            };
+        }
+
+        let options = &mut self.options;
+        for (suffix, option) in [
+            ("ANALYZE", &mut options.analyze),
+            ("VERBOSE", &mut options.verbose),
+            ("COSTS", &mut options.costs),
+            ("SETTINGS", &mut options.settings),
+            ("BUFFERS", &mut options.buffers),
+            ("WAL", &mut options.wal),
+            ("TIMING", &mut options.timing),
+        ] {
+            if let Some(value) = var(suffix) {
+                *option = value.parse_bool()?;
+            }
+        }
+        if let Some(summary) = var("SUMMARY") {
+            options.summary = Some(summary.parse_bool()?);
+        }
+        Ok(self)
+    }
+
+    /// Makes this configuration the process-wide default.
+    ///
+    /// Should be called once at startup, before anything is explained.
+    ///
+    /// # Errors
+    /// Returns the configuration back if one was installed already.
+    pub fn install(self) -> Result<(), Self> {
+        INSTALLED.set(self)
+    }
+
+    /// Returns the installed configuration, or the default one if none was installed.
+    pub fn global() -> &'static Self {
+        INSTALLED
+            .get()
+            .unwrap_or_else(|| DEFAULT.get_or_init(Self::default))
+    }
+}
+
+/// A set environment variable, see [`ExplainConfig::merge_env()`].
+struct EnvVar {
+    name: String,
+    value: String,
+}
+
+impl EnvVar {
+    fn parse_bool(&self) -> Result<bool, ExplainError> {
+        match self.value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "on" | "yes" => Ok(true),
+            "0" | "false" | "off" | "no" => Ok(false),
+            _ => Err(self.invalid("a boolean such as `1` or `0`")),
+        }
+    }
+
+    fn parse_number<T: std::str::FromStr>(
+        &self,
+        expected: &'static str,
+    ) -> Result<T, ExplainError> {
+        match self.value.trim().parse() {
+            Ok(number) => Ok(number),
+            Err(_) => Err(self.invalid(expected)),
+        }
+    }
+
+    fn invalid(&self, expected: &'static str) -> ExplainError {
+        ExplainError::InvalidEnvVar {
+            name: self.name.clone(),
+            value: self.value.clone(),
+            expected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_is_read_from_environment_variables() {
+        let vars = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| (*value).to_owned())
+            }
+        };
+
+        let config = ExplainConfig::default()
+            .merge_vars(vars(&[
+                ("DIESEL_PG_EXPLAIN_ANALYZE", "1"),
+                ("DIESEL_PG_EXPLAIN_BUFFERS", "On"),
+                ("DIESEL_PG_EXPLAIN_COSTS", ""),
+                ("DIESEL_PG_EXPLAIN_MIN_COST", "10000"),
+                ("DIESEL_PG_EXPLAIN_MIN_DURATION_MS", "250"),
+                ("DIESEL_PG_EXPLAIN_LOCK_TIMEOUT_MS", "0"),
+                ("DIESEL_PG_EXPLAIN_CAPTURE", "no"),
+            ]))
+            .unwrap();
+        assert_eq!(
+            config.options,
+            ExplainOptions::default().analyze(true).buffers(true)
+        );
+        assert_eq!(config.min_cost, Some(10_000.0));
+        assert_eq!(config.min_duration, Some(Duration::from_millis(250)));
+        assert_eq!(config.lock_timeout, None);
+        assert_eq!(config.sample_rate, 0.0);
+
+        let err = ExplainConfig::default()
+            .merge_vars(vars(&[("DIESEL_PG_EXPLAIN_SAMPLE_RATE", "2")]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value `2` of DIESEL_PG_EXPLAIN_SAMPLE_RATE, expected a number from 0.0 to 1.0"
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! Aggregates over many plans, e.g. all plans captured in production during a
+//! week, for periodic performance reports.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::analysis::work_mem_needed;
+use crate::ExplainPlan;
+
+/// Statistics over a corpus of plans, see [`Stats::from_plans()`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// Number of plans in the corpus.
+    pub plans: usize,
+
+    /// Number of nodes of each node type, across all plans.
+    pub node_types: BTreeMap<String, usize>,
+
+    /// Number of plans sequentially scanning each relation.
+    pub seq_scans: BTreeMap<String, usize>,
+
+    /// Number of plans with a node which exceeded `work_mem`, e.g. a sort spilling
+    /// to disk or a hash join using several batches. Only known for plans captured
+    /// with `ANALYZE`.
+    pub spilling_plans: usize,
+
+    /// Total costs of the root nodes, in ascending order.
+    total_costs: Vec<f64>,
+}
+
+impl Stats {
+    /// Computes the statistics of `plans`.
+    ///
+    /// Example:
+    /// ```rust
+    /// let stats = corpus::Stats::from_plans(captured.iter().map(|item| &item.plan));
+    /// println!("p95 cost: {:?}", stats.cost_percentile(95.0));
+    /// for (relation, count) in &stats.seq_scans {
+    ///     println!("{relation}: {:.0}%", 100.0 * stats.seq_scan_frequency(relation));
+    /// }
+    /// ```
+    pub fn from_plans<'a>(plans: impl IntoIterator<Item = &'a ExplainPlan>) -> Self {
+        let mut stats = Self::default();
+        for plan in plans {
+            stats.plans += 1;
+            stats.total_costs.push(plan.total_cost);
+            let mut seq_scanned = Vec::new();
+            let mut spilled = false;
+            for node in plan.nodes() {
+                *stats.node_types.entry(node.node_type.clone()).or_default() += 1;
+                if node.node_type.ends_with("Seq Scan") {
+                    if let Some(relation) = &node.relation_name {
+                        if !seq_scanned.contains(&relation) {
+                            seq_scanned.push(relation);
+                        }
+                    }
+                }
+                spilled |= work_mem_needed(node, 1.0).is_some();
+            }
+            for relation in seq_scanned {
+                *stats.seq_scans.entry(relation.clone()).or_default() += 1;
+            }
+            stats.spilling_plans += usize::from(spilled);
+        }
+        stats.total_costs.sort_by(f64::total_cmp);
+        stats
+    }
+
+    /// Returns the total cost below which `percentile` percent of the plans lie,
+    /// e.g. the median for 50.0, using the nearest-rank method. Returns `None` for
+    /// an empty corpus.
+    pub fn cost_percentile(&self, percentile: f64) -> Option<f64> {
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * self.total_costs.len() as f64).ceil();
+        let index = (rank as usize).saturating_sub(1);
+        self.total_costs.get(index).copied()
+    }
+
+    /// Returns the share of plans sequentially scanning `relation`, between 0.0
+    /// and 1.0.
+    pub fn seq_scan_frequency(&self, relation: &str) -> f64 {
+        self.share(self.seq_scans.get(relation).copied().unwrap_or_default())
+    }
+
+    /// Returns the share of plans which exceeded `work_mem`, between 0.0 and 1.0.
+    pub fn spill_frequency(&self) -> f64 {
+        self.share(self.spilling_plans)
+    }
+
+    fn share(&self, count: usize) -> f64 {
+        if self.plans == 0 {
+            0.0
+        } else {
+            count as f64 / self.plans as f64
+        }
+    }
+}
+
+/// Returns a hash of the shape of `plan`, so plans which are
+/// [structurally equal](ExplainPlan::structurally_eq) have the same fingerprint.
+/// The plans of the same query differ only if the planner chose a different plan.
+///
+/// Fingerprints are stable within a build of the crate but may change between
+/// Rust versions, so don't persist them.
+pub fn fingerprint(plan: &ExplainPlan) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_shape(plan, &mut hasher);
+    hasher.finish()
+}
+
+fn hash_shape(node: &ExplainPlan, hasher: &mut DefaultHasher) {
+    node.shape().hash(hasher);
+    node.plans.len().hash(hasher);
+    for child in &node.plans {
+        hash_shape(child, hasher);
+    }
+}
+
+/// Plans of the same shape, see [`clusters()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster<'a> {
+    /// The [`fingerprint()`] shared by the plans.
+    pub fingerprint: u64,
+
+    /// Positions of the plans in the corpus, in ascending order.
+    pub members: Vec<usize>,
+
+    /// The first plan of this shape, as representative of all of them.
+    pub example: &'a ExplainPlan,
+}
+
+/// Groups `plans` by their [`fingerprint()`], most frequent shape first. Shapes
+/// equally frequent are ordered by their first occurrence.
+///
+/// Given the plans captured for one statement, more than one cluster means the
+/// planner switched between plans, e.g. because of changing statistics or
+/// parameter values; comparing the examples with [`diff::compare()`](crate::diff::compare)
+/// shows how.
+///
+/// Example:
+/// ```rust
+/// let plans: Vec<ExplainPlan> = captured.into_iter().map(|item| item.plan).collect();
+/// for cluster in corpus::clusters(&plans) {
+///     println!("{} plans:\n{}", cluster.members.len(), render::tree(cluster.example));
+/// }
+/// ```
+pub fn clusters<'a>(plans: impl IntoIterator<Item = &'a ExplainPlan>) -> Vec<Cluster<'a>> {
+    let mut clusters: Vec<Cluster<'a>> = Vec::new();
+    let mut positions = HashMap::new();
+    for (index, plan) in plans.into_iter().enumerate() {
+        let fingerprint = fingerprint(plan);
+        let position = *positions.entry(fingerprint).or_insert_with(|| {
+            clusters.push(Cluster {
+                fingerprint,
+                members: Vec::new(),
+                example: plan,
+            });
+            clusters.len() - 1
+        });
+        clusters[position].members.push(index);
+    }
+    // Stable, so ties keep their order of first occurrence.
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.members.len()));
+    clusters
+}
+
+/// What a [`Hotspot`] is attributed to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Target {
+    /// A table or other relation, scanned without an index or through a bitmap.
+    Relation(String),
+
+    /// An index, scanned by an `Index Scan`, `Index Only Scan` or
+    /// `Bitmap Index Scan`.
+    Index(String),
+
+    /// Nodes of a type not accessing a relation, e.g. `Sort` or `Hash Join`.
+    Node(String),
+}
+
+impl Target {
+    fn of(node: &ExplainPlan) -> Self {
+        match (&node.index_name, &node.relation_name) {
+            (Some(index), _) => Target::Index(index.clone()),
+            (None, Some(relation)) => Target::Relation(relation.clone()),
+            (None, None) => Target::Node(node.node_type.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Relation(name) => write!(f, "relation {name}"),
+            Target::Index(name) => write!(f, "index {name}"),
+            Target::Node(node_type) => write!(f, "{node_type} nodes"),
+        }
+    }
+}
+
+/// What [`hotspots()`] ranks by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Metric {
+    /// Time spent in the nodes themselves, excluding their children.
+    #[default]
+    Time,
+
+    /// Shared blocks read from disk or the OS cache by the nodes themselves.
+    Reads,
+}
+
+/// The work of all nodes attributed to one [`Target`] across a corpus, see
+/// [`hotspots()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotspot {
+    /// What the work is attributed to.
+    pub target: Target,
+
+    /// Milliseconds spent in the nodes, excluding their children and summed over
+    /// all loops. Zero for plans captured without `ANALYZE`.
+    pub time_ms: f64,
+
+    /// Shared blocks read by the nodes, excluding their children. Zero for plans
+    /// captured without `BUFFERS`.
+    pub read_blocks: u64,
+
+    /// Number of nodes attributed to the target.
+    pub nodes: usize,
+}
+
+/// Ranks the relations, indexes and other nodes of `plans` by the work done on
+/// them, most expensive first, and returns the first `limit` of them, e.g. to
+/// decide which tables to index first.
+///
+/// Times of parallel nodes are those of the leader process, so the work of
+/// parallel workers is underrepresented.
+///
+/// Example:
+/// ```rust
+/// let plans = captured.iter().map(|item| &item.plan);
+/// for hotspot in corpus::hotspots(plans, corpus::Metric::Reads, 10) {
+///     println!("{}: {} blocks read", hotspot.target, hotspot.read_blocks);
+/// }
+/// ```
+pub fn hotspots<'a>(
+    plans: impl IntoIterator<Item = &'a ExplainPlan>,
+    metric: Metric,
+    limit: usize,
+) -> Vec<Hotspot> {
+    let mut hotspots = BTreeMap::<Target, Hotspot>::new();
+    for node in plans.into_iter().flat_map(ExplainPlan::nodes) {
+        let target = Target::of(node);
+        let hotspot = hotspots.entry(target.clone()).or_insert(Hotspot {
+            target,
+            time_ms: 0.0,
+            read_blocks: 0,
+            nodes: 0,
+        });
+        hotspot.time_ms += own_time_ms(node);
+        hotspot.read_blocks += node.exclusive_buffer_hits().unwrap_or_default().read;
+        hotspot.nodes += 1;
+    }
+    let mut hotspots: Vec<_> = hotspots.into_values().collect();
+    match metric {
+        Metric::Time => hotspots.sort_by(|a, b| b.time_ms.total_cmp(&a.time_ms)),
+        Metric::Reads => hotspots.sort_by_key(|hotspot| std::cmp::Reverse(hotspot.read_blocks)),
+    }
+    hotspots.truncate(limit);
+    hotspots
+}
+
+/// Returns the milliseconds spent in `node` over all loops, minus those spent in
+/// its children.
+pub(crate) fn own_time_ms(node: &ExplainPlan) -> f64 {
+    let total = |node: &ExplainPlan| node.total_actual_time().unwrap_or_default();
+    let children: f64 = node.plans.iter().map(total).sum();
+    (total(node) - children).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corpus_stats_are_aggregated() {
+        let scan = |relation: &str| ExplainPlan {
+            node_type: "Seq Scan".to_owned(),
+            relation_name: Some(relation.to_owned()),
+            ..ExplainPlan::default()
+        };
+        let plans = [
+            ExplainPlan {
+                node_type: "Sort".to_owned(),
+                total_cost: 30.0,
+                sort_space_type: Some("Disk".to_owned()),
+                sort_space_used: Some(100),
+                plans: vec![scan("users")],
+                ..ExplainPlan::default()
+            },
+            ExplainPlan {
+                node_type: "Append".to_owned(),
+                total_cost: 10.0,
+                plans: vec![scan("users"), scan("users")],
+                ..ExplainPlan::default()
+            },
+            ExplainPlan {
+                total_cost: 20.0,
+                ..scan("orders")
+            },
+        ];
+
+        let stats = Stats::from_plans(&plans);
+        assert_eq!(stats.plans, 3);
+        assert_eq!(stats.node_types["Seq Scan"], 4);
+        assert_eq!(stats.seq_scans["users"], 2);
+        assert!((stats.seq_scan_frequency("users") - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.seq_scan_frequency("products"), 0.0);
+        assert_eq!(stats.spilling_plans, 1);
+        assert_eq!(stats.cost_percentile(50.0), Some(20.0));
+        assert_eq!(stats.cost_percentile(100.0), Some(30.0));
+        assert_eq!(stats.cost_percentile(0.0), Some(10.0));
+        assert_eq!(Stats::from_plans([]).cost_percentile(50.0), None);
+    }
+
+    #[test]
+    fn plans_are_clustered_by_shape() {
+        let scan = |node_type: &str, total_cost| ExplainPlan {
+            node_type: node_type.to_owned(),
+            relation_name: Some("users".to_owned()),
+            total_cost,
+            ..ExplainPlan::default()
+        };
+        let plans = [
+            scan("Index Scan", 1.0),
+            scan("Seq Scan", 10.0),
+            scan("Seq Scan", 20.0),
+            ExplainPlan {
+                alias: Some("u".to_owned()),
+                ..scan("Index Scan", 2.0)
+            },
+            scan("Bitmap Heap Scan", 5.0),
+        ];
+
+        let clusters = clusters(&plans);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(clusters[0].members, [0, 3]);
+        assert_eq!(clusters[0].example.total_cost, 1.0);
+        assert_eq!(clusters[1].members, [1, 2]);
+        assert_eq!(clusters[2].members, [4]);
+        assert_eq!(clusters[1].fingerprint, fingerprint(&plans[2]));
+        assert_ne!(clusters[0].fingerprint, clusters[1].fingerprint);
+    }
+
+    #[test]
+    fn hotspots_are_ranked_across_plans() {
+        let join = |scan_time, scan_reads| ExplainPlan {
+            node_type: "Nested Loop".to_owned(),
+            actual_total_time: Some(12.0),
+            actual_loops: Some(1),
+            shared_read_blocks: Some(scan_reads + 5),
+            plans: vec![
+                ExplainPlan {
+                    node_type: "Seq Scan".to_owned(),
+                    relation_name: Some("users".to_owned()),
+                    actual_total_time: Some(scan_time),
+                    actual_loops: Some(1),
+                    shared_read_blocks: Some(scan_reads),
+                    ..ExplainPlan::default()
+                },
+                ExplainPlan {
+                    node_type: "Index Scan".to_owned(),
+                    relation_name: Some("orders".to_owned()),
+                    index_name: Some("orders_user_id_idx".to_owned()),
+                    actual_total_time: Some(0.5),
+                    actual_loops: Some(4),
+                    shared_read_blocks: Some(5),
+                    ..ExplainPlan::default()
+                },
+            ],
+            ..ExplainPlan::default()
+        };
+        let plans = [join(3.0, 100), join(5.0, 50)];
+
+        let by_time = hotspots(&plans, Metric::Time, 2);
+        assert_eq!(by_time.len(), 2);
+        assert_eq!(by_time[0].target.to_string(), "Nested Loop nodes");
+        assert_eq!(by_time[0].time_ms, 12.0);
+        assert_eq!(by_time[1].target, Target::Relation("users".to_owned()));
+        assert_eq!(by_time[1].time_ms, 8.0);
+        assert_eq!(by_time[1].nodes, 2);
+
+        let by_reads = hotspots(&plans, Metric::Reads, 10);
+        assert_eq!(by_reads.len(), 3);
+        assert_eq!(by_reads[0].read_blocks, 150);
+        assert_eq!(
+            by_reads[1].target,
+            Target::Index("orders_user_id_idx".to_owned())
+        );
+        assert_eq!(by_reads[1].read_blocks, 10);
+        assert_eq!(by_reads[2].read_blocks, 0);
+    }
+}
@@ -0,0 +1,52 @@
+//! Support for [`deadpool_diesel`]: explaining queries on a connection checked
+//! out of a `deadpool_diesel::postgres::Pool`.
+
+use deadpool_diesel::postgres::{InteractError, Pool, PoolError};
+use diesel::pg::PgConnection;
+use diesel::query_dsl::methods::LoadQuery;
+
+use crate::{Explain, ExplainError, ExplainPlan};
+
+/// Errors returned by [`Explain::explain_pooled()`].
+#[derive(Debug, thiserror::Error)]
+pub enum PooledExplainError {
+    /// No connection could be checked out of the pool.
+    #[error("failed to check out a connection: {0}")]
+    Pool(#[from] PoolError),
+    /// The blocking task running the query panicked or was aborted.
+    #[error("failed to run explain on the pooled connection: {0}")]
+    Interact(#[from] InteractError),
+    /// Explaining the query failed.
+    #[error(transparent)]
+    Query(#[from] ExplainError),
+}
+
+impl<Q> Explain<Q>
+where
+    Q: Send + 'static,
+{
+    /// Checks a connection out of `pool`, runs [`Explain::explain()`] on it and
+    /// returns the connection to the pool.
+    ///
+    /// The query is moved onto deadpool's blocking thread, so it must be owned:
+    /// wrap it with [`Explain`] directly rather than borrowing it via
+    /// [`ExplainWrapped::wrap_explain()`](crate::ExplainWrapped::wrap_explain).
+    ///
+    /// Example:
+    /// ```rust
+    /// let query = users::table.filter(users::age.gt(30)).select(users::id);
+    /// let plan = Explain::new(query).explain_pooled(&pool).await?;
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`PooledExplainError`] if no connection can be checked out, the
+    /// blocking task fails, or the query fails as described in [`Explain::explain()`].
+    pub async fn explain_pooled(self, pool: &Pool) -> Result<ExplainPlan, PooledExplainError>
+    where
+        Self: LoadQuery<'static, PgConnection, String>,
+    {
+        let conn = pool.get().await?;
+        let plan = conn.interact(move |conn| self.explain(conn)).await??;
+        Ok(plan)
+    }
+}
@@ -0,0 +1,138 @@
+//! Default `EXPLAIN` options stored with a connection.
+
+use std::collections::BTreeSet;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use diesel::sql_types::{Nullable, Text};
+use diesel::{QueryableByName, RunQueryDsl};
+
+use crate::{deserialize, Explain, ExplainConnection, ExplainError, ExplainOptions};
+
+/// The session variable holding the defaults, as JSON.
+const VARIABLE: &str = "diesel_pg_explain.default_options";
+
+/// Addresses of the connections defaults were stored with, so that explains on
+/// other connections don't pay for looking them up.
+static CONNECTIONS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+/// Sets default options for every query explained on a connection, without
+/// passing them to each call.
+///
+/// Implemented for every [`ExplainConnection`], including pooled connections.
+/// The defaults are kept by PostgreSQL in a session variable, so they live as
+/// long as the database session and are undone, like a `SET`, if the
+/// transaction setting them is rolled back. They are looked up only when
+/// explaining on the connection value they were set on, recognized by its
+/// address, so that other connections don't pay for a round trip: set them
+/// again after moving a connection, e.g. on every checkout from a pool.
+///
+/// Options passed with [`Explain::options()`] take precedence over the
+/// connection's defaults, which take precedence over those of the installed
+/// [`ExplainConfig`](crate::ExplainConfig).
+///
+/// Example:
+/// ```rust
+/// use diesel_pg_explain::PgConnectionExplainExt;
+///
+/// conn.set_default_explain_options(ExplainOptions::default().analyze(true).buffers(true))?;
+/// let plan = query.wrap_explain().explain(&mut conn)?; // EXPLAIN (ANALYZE, BUFFERS)
+/// ```
+pub trait PgConnectionExplainExt {
+    /// Uses `options` for queries subsequently explained on this connection.
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Diesel`] if the options cannot be stored.
+    fn set_default_explain_options(&mut self, options: ExplainOptions) -> Result<(), ExplainError>;
+
+    /// Removes the defaults set with [`set_default_explain_options()`](Self::set_default_explain_options).
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Diesel`] if the options cannot be removed.
+    fn reset_default_explain_options(&mut self) -> Result<(), ExplainError>;
+
+    /// Returns the defaults of this connection, if any.
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Diesel`] if the options cannot be read.
+    fn default_explain_options(&mut self) -> Result<Option<ExplainOptions>, ExplainError>;
+}
+
+impl<C: ExplainConnection> PgConnectionExplainExt for C {
+    fn set_default_explain_options(&mut self, options: ExplainOptions) -> Result<(), ExplainError> {
+        let json = serde_json::to_string(&options).expect("options serialize to JSON");
+        set(self, &json)?;
+        connections().insert(address(self));
+        Ok(())
+    }
+
+    fn reset_default_explain_options(&mut self) -> Result<(), ExplainError> {
+        set(self, "")?;
+        connections().remove(&address(self));
+        Ok(())
+    }
+
+    fn default_explain_options(&mut self) -> Result<Option<ExplainOptions>, ExplainError> {
+        let options = diesel::sql_query("SELECT current_setting($1, true) AS json")
+            .bind::<Text, _>(VARIABLE)
+            .get_result::<StoredOptions>(self)?;
+        match options.json.as_deref() {
+            None | Some("") => Ok(None),
+            Some(json) => deserialize(json).map(Some),
+        }
+    }
+}
+
+fn set<C: ExplainConnection>(conn: &mut C, json: &str) -> Result<(), ExplainError> {
+    diesel::sql_query("SELECT set_config($1, $2, false)")
+        .bind::<Text, _>(VARIABLE)
+        .bind::<Text, _>(json)
+        .execute(conn)?;
+    Ok(())
+}
+
+fn connections() -> MutexGuard<'static, BTreeSet<usize>> {
+    CONNECTIONS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn address<C>(conn: &C) -> usize {
+    std::ptr::from_ref(conn) as usize
+}
+
+/// Returns the defaults stored with `conn`, without a round trip if none were
+/// set on it.
+pub(crate) fn connection_options<C: ExplainConnection>(
+    conn: &mut C,
+) -> Result<Option<ExplainOptions>, ExplainError> {
+    if !connections().contains(&address(conn)) {
+        return Ok(None);
+    }
+    let options = conn.default_explain_options()?;
+    if options.is_none() {
+        // Rolled back or reset through another value of the same connection.
+        connections().remove(&address(conn));
+    }
+    Ok(options)
+}
+
+#[derive(QueryableByName)]
+struct StoredOptions {
+    #[diesel(sql_type = Nullable<Text>)]
+    json: Option<String>,
+}
+
+impl<Q> Explain<Q> {
+    /// Replaces the options with the defaults of `conn`, unless options were set
+    /// explicitly.
+    pub(crate) fn with_connection_defaults<C: ExplainConnection>(
+        mut self,
+        conn: &mut C,
+    ) -> Result<Self, ExplainError> {
+        if !self.explicit_options {
+            if let Some(options) = connection_options(conn)? {
+                self.options = options;
+            }
+        }
+        self.explicit_options = true;
+        Ok(self)
+    }
+}
@@ -0,0 +1,207 @@
+//! Structural comparison of two plans of the same query.
+//!
+//! Plans are compared node by node, matching children by position. Where the
+//! node types differ the subtrees are not comparable, so only that change is
+//! reported for them.
+//!
+//! Example:
+//! ```rust
+//! for change in diff::compare(&before, &after) {
+//!     println!("{change}");
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::ExplainPlan;
+
+/// Position of a node in a plan tree: the indices of the children leading to it
+/// from the root, e.g. `[0, 1]` for the second child of the first child.
+pub type NodePath = Vec<usize>;
+
+/// A difference between two plans, found by [`compare()`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanChange {
+    /// Different node types, e.g. `Seq Scan` replaced by `Index Scan`.
+    NodeType {
+        /// Position of the node.
+        path: NodePath,
+        /// Node type in the first plan.
+        before: String,
+        /// Node type in the second plan.
+        after: String,
+    },
+
+    /// The same node type uses a different index.
+    Index {
+        /// Position of the node.
+        path: NodePath,
+        /// Index used in the first plan.
+        before: Option<String>,
+        /// Index used in the second plan.
+        after: Option<String>,
+    },
+
+    /// Different estimated number of rows.
+    Rows {
+        /// Position of the node.
+        path: NodePath,
+        /// Estimate in the first plan.
+        before: u64,
+        /// Estimate in the second plan.
+        after: u64,
+    },
+
+    /// Different estimated total cost.
+    TotalCost {
+        /// Position of the node.
+        path: NodePath,
+        /// Estimate in the first plan.
+        before: f64,
+        /// Estimate in the second plan.
+        after: f64,
+    },
+
+    /// A node which only exists in the second plan.
+    Added {
+        /// Position of the node in the second plan.
+        path: NodePath,
+        /// Type of the node.
+        node_type: String,
+    },
+
+    /// A node which only exists in the first plan.
+    Removed {
+        /// Position of the node in the first plan.
+        path: NodePath,
+        /// Type of the node.
+        node_type: String,
+    },
+}
+
+impl fmt::Display for PlanChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanChange::NodeType {
+                path,
+                before,
+                after,
+            } => write!(f, "{path:?}: {before} -> {after}"),
+            PlanChange::Index {
+                path,
+                before,
+                after,
+            } => write!(
+                f,
+                "{path:?}: index {} -> {}",
+                before.as_deref().unwrap_or("none"),
+                after.as_deref().unwrap_or("none")
+            ),
+            PlanChange::Rows {
+                path,
+                before,
+                after,
+            } => write!(f, "{path:?}: rows {before} -> {after}"),
+            PlanChange::TotalCost {
+                path,
+                before,
+                after,
+            } => write!(f, "{path:?}: total cost {before:.2} -> {after:.2}"),
+            PlanChange::Added { path, node_type } => write!(f, "{path:?}: added {node_type}"),
+            PlanChange::Removed { path, node_type } => write!(f, "{path:?}: removed {node_type}"),
+        }
+    }
+}
+
+/// Lists the differences between `before` and `after`, in pre-order.
+/// Returns an empty vector if the plans have the same shape and estimates.
+pub fn compare(before: &ExplainPlan, after: &ExplainPlan) -> Vec<PlanChange> {
+    let mut changes = Vec::new();
+    compare_nodes(before, after, &mut Vec::new(), &mut changes);
+    changes
+}
+
+fn compare_nodes(
+    before: &ExplainPlan,
+    after: &ExplainPlan,
+    path: &mut NodePath,
+    changes: &mut Vec<PlanChange>,
+) {
+    if before.node_type != after.node_type {
+        changes.push(PlanChange::NodeType {
+            path: path.clone(),
+            before: before.node_type.clone(),
+            after: after.node_type.clone(),
+        });
+        return;
+    }
+    if before.index_name != after.index_name {
+        changes.push(PlanChange::Index {
+            path: path.clone(),
+            before: before.index_name.clone(),
+            after: after.index_name.clone(),
+        });
+    }
+    if before.plan_rows != after.plan_rows {
+        changes.push(PlanChange::Rows {
+            path: path.clone(),
+            before: before.plan_rows,
+            after: after.plan_rows,
+        });
+    }
+    if before.total_cost != after.total_cost {
+        changes.push(PlanChange::TotalCost {
+            path: path.clone(),
+            before: before.total_cost,
+            after: after.total_cost,
+        });
+    }
+
+    let children = before.plans.len().max(after.plans.len());
+    for index in 0..children {
+        path.push(index);
+        match (before.plans.get(index), after.plans.get(index)) {
+            (Some(before), Some(after)) => compare_nodes(before, after, path, changes),
+            (Some(before), None) => changes.push(PlanChange::Removed {
+                path: path.clone(),
+                node_type: before.node_type.clone(),
+            }),
+            (None, Some(after)) => changes.push(PlanChange::Added {
+                path: path.clone(),
+                node_type: after.node_type.clone(),
+            }),
+            (None, None) => unreachable!("index is below the larger number of children"),
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::node;
+
+    #[test]
+    fn diff_reports_changed_nodes() {
+        let before = node(
+            "Hash Join",
+            vec![node("Seq Scan", vec![]), node("Hash", vec![])],
+        );
+        let after = node("Hash Join", vec![node("Index Scan", vec![])]);
+
+        assert_eq!(
+            compare(&before, &after),
+            [
+                PlanChange::NodeType {
+                    path: vec![0],
+                    before: "Seq Scan".to_owned(),
+                    after: "Index Scan".to_owned(),
+                },
+                PlanChange::Removed {
+                    path: vec![1],
+                    node_type: "Hash".to_owned(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,490 @@
+//! Enrichment of plans with facts from the system catalogs.
+//!
+//! Plans only carry the planner's estimates, which may be far off for tables
+//! whose statistics are stale. [`with_catalog()`] looks up the actual sizes of
+//! the relations and indexes a plan accesses and attaches them to its nodes,
+//! where [`catalog()`] reads them back. [`explain_misestimates()`] looks for the
+//! causes of bad row estimates in the column statistics.
+//!
+//! Example:
+//! ```rust
+//! let mut plan = query.wrap_explain().explain(&mut conn)?;
+//! enrich::with_catalog(&mut conn, &mut plan)?;
+//! for node in plan.nodes().filter(|node| node.node_type == "Seq Scan") {
+//!     if let Some(size) = enrich::catalog(node).and_then(|info| info.relation_size) {
+//!         println!("{:?}: {} MB", node.relation_name, size / 1024 / 1024);
+//!     }
+//! }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use diesel::sql_types::{Array, BigInt, Double, Nullable, Text};
+use diesel::{QueryableByName, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use crate::expr::{self, Expr};
+use crate::lint::Finding;
+use crate::{ExplainConnection, ExplainError, ExplainPlan};
+
+/// The key in [`ExplainPlan::extra`] holding the [`CatalogInfo`] attached by
+/// [`with_catalog()`].
+pub const CATALOG_KEY: &str = "Catalog";
+
+/// Sizes of the relation and index accessed by a node, as recorded in the
+/// catalogs when [`with_catalog()`] ran.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CatalogInfo {
+    /// Number of pages of the relation according to `pg_class.relpages`, as of
+    /// the last `VACUUM` or `ANALYZE`.
+    #[serde(
+        rename = "Relation Pages",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relation_pages: Option<i64>,
+
+    /// Number of rows of the relation according to `pg_class.reltuples`, as of
+    /// the last `VACUUM` or `ANALYZE`. `None` if the relation was never analyzed.
+    #[serde(
+        rename = "Relation Tuples",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relation_tuples: Option<f64>,
+
+    /// Current size of the relation's main fork in bytes, see `pg_relation_size()`.
+    #[serde(
+        rename = "Relation Size",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relation_size: Option<i64>,
+
+    /// Current size of the relation in bytes including its indexes and TOAST
+    /// data, see `pg_total_relation_size()`.
+    #[serde(
+        rename = "Total Relation Size",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub total_relation_size: Option<i64>,
+
+    /// Number of entries of the index according to `pg_class.reltuples`.
+    #[serde(
+        rename = "Index Tuples",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub index_tuples: Option<f64>,
+
+    /// Current size of the index in bytes.
+    #[serde(
+        rename = "Index Size",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub index_size: Option<i64>,
+}
+
+/// Returns the catalog facts [`with_catalog()`] attached to `node`, or `None` if
+/// it attached none, e.g. because the node doesn't access a relation.
+pub fn catalog(node: &ExplainPlan) -> Option<CatalogInfo> {
+    serde_json::from_value(node.extra.get(CATALOG_KEY)?.clone()).ok()
+}
+
+#[derive(QueryableByName)]
+struct RelationSize {
+    #[diesel(sql_type = BigInt)]
+    pages: i64,
+    #[diesel(sql_type = Nullable<Double>)]
+    tuples: Option<f64>,
+    #[diesel(sql_type = BigInt)]
+    size: i64,
+    #[diesel(sql_type = BigInt)]
+    total_size: i64,
+}
+
+/// Looks up a relation or index by its quoted, possibly schema-qualified name.
+/// `reltuples` is -1 for tables which were never analyzed since PostgreSQL 14.
+const RELATION_SIZE_SQL: &str = "SELECT c.relpages::int8 AS pages, \
+     CASE WHEN c.reltuples >= 0 THEN c.reltuples::float8 END AS tuples, \
+     pg_relation_size(c.oid) AS size, pg_total_relation_size(c.oid) AS total_size \
+     FROM pg_class c WHERE c.oid = to_regclass($1)";
+
+/// Attaches the sizes of the relations and indexes accessed by the nodes of
+/// `plan` to them, see [`catalog()`], so checks can rely on current sizes
+/// rather than estimates. Each relation and index is looked up once.
+///
+/// Relations are resolved through the `search_path` unless the plan was
+/// captured with `VERBOSE`, which reports their schemas. Nodes whose relation
+/// can't be found, e.g. because it was dropped, are left alone.
+///
+/// # Errors
+/// Returns [`ExplainError::Diesel`] if a lookup fails.
+pub fn with_catalog<C: ExplainConnection>(
+    conn: &mut C,
+    plan: &mut ExplainPlan,
+) -> Result<(), ExplainError> {
+    let mut cache = BTreeMap::new();
+    enrich_node(conn, plan, &mut cache)
+}
+
+type Cache = BTreeMap<String, Option<RelationSize>>;
+
+fn enrich_node<C: ExplainConnection>(
+    conn: &mut C,
+    node: &mut ExplainPlan,
+    cache: &mut Cache,
+) -> Result<(), ExplainError> {
+    let schema = node.schema.as_deref();
+    let mut info = CatalogInfo::default();
+    if let Some(relation) = &node.relation_name {
+        if let Some(size) = lookup(conn, cache, schema, relation)? {
+            info.relation_pages = Some(size.pages);
+            info.relation_tuples = size.tuples;
+            info.relation_size = Some(size.size);
+            info.total_relation_size = Some(size.total_size);
+        }
+    }
+    if let Some(index) = &node.index_name {
+        if let Some(size) = lookup(conn, cache, schema, index)? {
+            info.index_tuples = size.tuples;
+            info.index_size = Some(size.size);
+        }
+    }
+    if info != CatalogInfo::default() {
+        if let Ok(value) = serde_json::to_value(info) {
+            node.extra.insert(CATALOG_KEY.to_owned(), value);
+        }
+    }
+    for child in &mut node.plans {
+        enrich_node(conn, child, cache)?;
+    }
+    Ok(())
+}
+
+fn lookup<'a, C: ExplainConnection>(
+    conn: &mut C,
+    cache: &'a mut Cache,
+    schema: Option<&str>,
+    name: &str,
+) -> Result<Option<&'a RelationSize>, ExplainError> {
+    let qualified = match schema {
+        Some(schema) => format!("{}.{}", quote_ident(schema), quote_ident(name)),
+        None => quote_ident(name),
+    };
+    if !cache.contains_key(&qualified) {
+        let size = diesel::sql_query(RELATION_SIZE_SQL)
+            .bind::<Text, _>(&qualified)
+            .load::<RelationSize>(conn)?
+            .pop();
+        cache.insert(qualified.clone(), size);
+    }
+    Ok(cache.get(&qualified).and_then(Option::as_ref))
+}
+
+/// Quotes an identifier like `quote_ident()`, except that it always quotes.
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Statistics of a relation relevant to its row estimates.
+#[derive(Debug, Clone, Default, QueryableByName)]
+pub(crate) struct TableStats {
+    /// `pg_class.reltuples`, or `None` if never analyzed.
+    #[diesel(sql_type = Nullable<Double>)]
+    pub(crate) tuples: Option<f64>,
+    /// Rows inserted, updated or deleted since the last `ANALYZE`.
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub(crate) modified: Option<i64>,
+}
+
+/// Statistics of a column from `pg_stats`.
+#[derive(Debug, Clone, Default, QueryableByName)]
+pub(crate) struct ColumnStats {
+    #[diesel(sql_type = Text)]
+    pub(crate) column_name: String,
+    /// Number of distinct values, or its negated ratio to the row count.
+    #[diesel(sql_type = Nullable<Double>)]
+    pub(crate) n_distinct: Option<f64>,
+    /// Frequency of the most common value.
+    #[diesel(sql_type = Nullable<Double>)]
+    pub(crate) top_frequency: Option<f64>,
+}
+
+const TABLE_STATS_SQL: &str =
+    "SELECT CASE WHEN c.reltuples >= 0 THEN c.reltuples::float8 END AS tuples, \
+     s.n_mod_since_analyze AS modified \
+     FROM pg_class c LEFT JOIN pg_stat_all_tables s ON s.relid = c.oid \
+     WHERE c.oid = to_regclass($1)";
+
+const COLUMN_STATS_SQL: &str = "SELECT DISTINCT ON (s.attname) s.attname::text AS column_name, \
+     s.n_distinct::float8 AS n_distinct, \
+     (SELECT max(f) FROM unnest(s.most_common_freqs) f)::float8 AS top_frequency \
+     FROM pg_stats s \
+     JOIN pg_namespace n ON n.nspname = s.schemaname \
+     JOIN pg_class c ON c.relnamespace = n.oid AND c.relname = s.tablename \
+     WHERE c.oid = to_regclass($1) AND s.attname = ANY ($2) \
+     ORDER BY s.attname, s.inherited";
+
+/// Share of rows modified since the last `ANALYZE` above which statistics are
+/// considered stale, the default of `autovacuum_analyze_scale_factor`.
+const STALE_FRACTION: f64 = 0.1;
+
+/// Frequency of the most common value above which a column counts as skewed.
+const SKEWED_FREQUENCY: f64 = 0.2;
+
+/// Explains the row count misestimates of the scans in `plan` by at least
+/// `min_factor` times in either direction, see
+/// [`ExplainPlan::worst_misestimates()`], with likely causes found in the
+/// statistics of the scanned relations and the columns their conditions use:
+///
+/// - stale statistics, when more than 10% of the rows were modified since the
+///   last `ANALYZE`, or the relation or a column was never analyzed;
+/// - correlated columns, when rows are underestimated for conditions on several
+///   columns, which the planner assumes to be independent unless extended
+///   statistics exist;
+/// - skew, when the most common value of a column covers more than 20% of the
+///   rows, so values missing from the most common values list are estimated
+///   poorly.
+///
+/// Requires a plan captured with `ANALYZE`. Joins and other nodes not scanning a
+/// relation are skipped, as are relations which no longer exist.
+///
+/// Example:
+/// ```rust
+/// let plan = query.wrap_explain().explain_analyze(&mut conn)?;
+/// for finding in enrich::explain_misestimates(&mut conn, &plan, 10.0)? {
+///     println!("{finding}");
+/// }
+/// ```
+///
+/// # Errors
+/// Returns [`ExplainError::Diesel`] if querying the statistics fails.
+pub fn explain_misestimates<C: ExplainConnection>(
+    conn: &mut C,
+    plan: &ExplainPlan,
+    min_factor: f64,
+) -> Result<Vec<Finding>, ExplainError> {
+    let mut findings = Vec::new();
+    for node in plan.nodes() {
+        let Some(relation) = &node.relation_name else {
+            continue;
+        };
+        if node
+            .misestimate_factor()
+            .is_none_or(|factor| factor < min_factor)
+        {
+            continue;
+        }
+        let qualified = match &node.schema {
+            Some(schema) => format!("{}.{}", quote_ident(schema), quote_ident(relation)),
+            None => quote_ident(relation),
+        };
+        let columns = node_columns(node);
+        let Some(table) = diesel::sql_query(TABLE_STATS_SQL)
+            .bind::<Text, _>(&qualified)
+            .load::<TableStats>(conn)?
+            .pop()
+        else {
+            continue;
+        };
+        let stats = diesel::sql_query(COLUMN_STATS_SQL)
+            .bind::<Text, _>(&qualified)
+            .bind::<Array<Text>, _>(&columns)
+            .load::<ColumnStats>(conn)?;
+        findings.extend(misestimate_finding(node, &columns, &table, &stats));
+    }
+    Ok(findings)
+}
+
+/// Returns the columns of the scanned relation used by the conditions of `node`.
+fn node_columns(node: &ExplainPlan) -> Vec<String> {
+    let mut columns = BTreeSet::new();
+    let conditions = node
+        .nodes()
+        .take_while(|child| std::ptr::eq(*child, node) || child.node_type == "Bitmap Index Scan")
+        .flat_map(expr::conditions)
+        .filter_map(|(_, condition)| expr::parse(condition).ok())
+        .collect::<Vec<_>>();
+    for condition in &conditions {
+        for expr in condition.nodes() {
+            if let Expr::Column(column) = expr {
+                let own = column.relation.is_none() || column.relation == node.alias;
+                if own && column.name != "*" {
+                    columns.insert(column.name.clone());
+                }
+            }
+        }
+    }
+    columns.into_iter().collect()
+}
+
+/// Builds the finding for a misestimated scan from the statistics of its
+/// relation and `columns`.
+pub(crate) fn misestimate_finding(
+    node: &ExplainPlan,
+    columns: &[String],
+    table: &TableStats,
+    stats: &[ColumnStats],
+) -> Option<Finding> {
+    let ratio = node.estimate_ratio()?;
+    let relation = node.relation_name.as_deref()?;
+    let mut causes = Vec::new();
+
+    match table.tuples {
+        None => causes.push(format!(
+            "{relation} was never analyzed, run ANALYZE {relation}"
+        )),
+        Some(tuples) => {
+            let modified = table.modified.unwrap_or_default();
+            if modified as f64 > tuples.max(1.0) * STALE_FRACTION {
+                causes.push(format!(
+                    "statistics are stale, {modified} rows of {relation} were modified since \
+                     the last ANALYZE, run ANALYZE {relation}"
+                ));
+            }
+        }
+    }
+    let missing: Vec<_> = columns
+        .iter()
+        .filter(|column| !stats.iter().any(|stats| &stats.column_name == *column))
+        .map(String::as_str)
+        .collect();
+    if table.tuples.is_some() && !missing.is_empty() {
+        causes.push(format!(
+            "{} {} no statistics, run ANALYZE {relation}",
+            missing.join(", "),
+            if missing.len() == 1 { "has" } else { "have" },
+        ));
+    }
+    if ratio > 1.0 && columns.len() > 1 {
+        causes.push(format!(
+            "{} are filtered together and may be correlated, consider \
+             CREATE STATISTICS ON {} FROM {relation}",
+            columns.join(", "),
+            columns.join(", "),
+        ));
+    }
+    for column in stats {
+        if let Some(frequency) = column.top_frequency.filter(|f| *f > SKEWED_FREQUENCY) {
+            let distinct = match column.n_distinct {
+                Some(n) if n < 0.0 => format!("{:.0}% distinct values", -n * 100.0),
+                Some(n) => format!("{n:.0} distinct values"),
+                None => "unknown distinct values".to_owned(),
+            };
+            causes.push(format!(
+                "{} is skewed, its most common value covers {:.0}% of the rows ({distinct}), \
+                 consider ALTER TABLE {relation} ALTER COLUMN {} SET STATISTICS 1000",
+                column.column_name,
+                frequency * 100.0,
+                column.column_name,
+            ));
+        }
+    }
+
+    let actual = node.actual_rows.unwrap_or_default();
+    let summary = format!(
+        "{} on {relation} returned {actual:.0} rows per loop where {} were estimated \
+         ({})",
+        node.node_type,
+        node.plan_rows,
+        if ratio >= 1.0 {
+            format!("{ratio:.0}x more")
+        } else {
+            format!("{:.0}x fewer", 1.0 / ratio.max(f64::MIN_POSITIVE))
+        },
+    );
+    let message = if causes.is_empty() {
+        format!("{summary}; the statistics show no obvious cause")
+    } else {
+        format!("{summary}; {}", causes.join("; "))
+    };
+    Some(Finding::new("misestimate", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "diesel-2")]
+    #[test]
+    fn catalog_info_is_read_from_nodes() {
+        let node = ExplainPlan {
+            extra: [(
+                CATALOG_KEY.to_owned(),
+                serde_json::json!({"Relation Pages": 45, "Relation Size": 368640, "Index Size": 245760}),
+            )]
+            .into(),
+            ..ExplainPlan::scan("Index Scan", "users")
+        };
+        let info = catalog(&node).unwrap();
+        assert_eq!(info.relation_pages, Some(45));
+        assert_eq!(info.relation_size, Some(368640));
+        assert_eq!(info.index_size, Some(245760));
+        assert_eq!(info.relation_tuples, None);
+        assert_eq!(
+            serde_json::to_value(&info).unwrap(),
+            node.extra[CATALOG_KEY]
+        );
+        assert_eq!(catalog(&ExplainPlan::new("Result")), None);
+    }
+
+    #[cfg(feature = "diesel-2")]
+    #[test]
+    fn misestimates_are_explained_by_statistics() {
+        use {misestimate_finding, ColumnStats, TableStats};
+
+        let scan = ExplainPlan {
+            plan_rows: 10,
+            actual_rows: Some(1000.0),
+            actual_loops: Some(1),
+            filter: Some("((a = 1) AND (b = 1))".to_owned()),
+            ..ExplainPlan::scan("Seq Scan", "t")
+        };
+        let columns = ["a".to_owned(), "b".to_owned()];
+        let column = |name: &str, top_frequency| ColumnStats {
+            column_name: name.to_owned(),
+            n_distinct: Some(100.0),
+            top_frequency,
+        };
+        let table = TableStats {
+            tuples: Some(100_000.0),
+            modified: Some(50_000),
+        };
+        let finding = misestimate_finding(
+            &scan,
+            &columns,
+            &table,
+            &[column("a", Some(0.5)), column("b", Some(0.01))],
+        )
+        .unwrap();
+        assert_eq!(finding.lint, "misestimate");
+        assert!(finding.message.starts_with(
+            "Seq Scan on t returned 1000 rows per loop where 10 were estimated (100x more); "
+        ));
+        assert!(finding.message.contains("50000 rows of t were modified"));
+        assert!(finding.message.contains("CREATE STATISTICS ON a, b FROM t"));
+        assert!(finding
+            .message
+            .contains("a is skewed, its most common value covers 50%"));
+        assert!(!finding.message.contains("b is skewed"));
+
+        let never_analyzed = TableStats::default();
+        let finding = misestimate_finding(&scan, &columns[..1], &never_analyzed, &[]).unwrap();
+        assert!(finding
+            .message
+            .ends_with("t was never analyzed, run ANALYZE t"));
+
+        let fresh = TableStats {
+            tuples: Some(100_000.0),
+            modified: Some(0),
+        };
+        let finding =
+            misestimate_finding(&scan, &columns[..1], &fresh, &[column("a", None)]).unwrap();
+        assert!(finding
+            .message
+            .ends_with("the statistics show no obvious cause"));
+    }
+}
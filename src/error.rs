@@ -0,0 +1,174 @@
+//! The error type returned by explain operations.
+
+#[cfg(feature = "diesel-2")]
+use diesel::result::DatabaseErrorKind;
+
+/// Errors which can occur while explaining a query.
+///
+/// With the `diesel-2` feature, converts into `diesel::result::Error`, so `?` keeps
+/// working in functions returning `diesel::QueryResult`: database errors are passed
+/// through unchanged and all other variants become `DeserializationError`.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ExplainError {
+    /// PostgreSQL returned no plan: either no rows or an empty JSON array.
+    #[error("EXPLAIN returned no plan")]
+    EmptyResult,
+
+    /// PostgreSQL returned several plans where exactly one was expected.
+    #[error("EXPLAIN returned {count} plans where one was expected")]
+    MultipleResults {
+        /// Number of plans returned.
+        count: usize,
+    },
+
+    /// The JSON returned by PostgreSQL could not be parsed into an [`ExplainPlan`](crate::ExplainPlan).
+    #[error("failed to parse EXPLAIN output at `{path}`: {source} (near `{snippet}`)")]
+    Parse {
+        /// The underlying deserialization error.
+        source: serde_json::Error,
+        /// Path to the offending value, e.g. `[0].Plan.Plans[1].Plan Rows`.
+        path: String,
+        /// A short excerpt of the JSON around the error position.
+        snippet: String,
+        /// The JSON document which failed to parse.
+        raw_json: String,
+    },
+
+    /// The output contained fields [`ExplainPlan`](crate::ExplainPlan) does not model.
+    /// Only returned in [`ParseMode::Strict`](crate::ParseMode::Strict).
+    #[error("EXPLAIN output contains unknown fields: {}", fields.join(", "))]
+    UnknownFields {
+        /// Paths of the unknown fields, e.g. `[0].Plan.Plans[1].Memory Used`.
+        fields: Vec<String>,
+    },
+
+    /// PostgreSQL refused to plan the query because the current role lacks a privilege
+    /// on one of the objects it references.
+    #[cfg(feature = "diesel-2")]
+    #[error("insufficient privilege to explain the query: {source}")]
+    InsufficientPrivilege {
+        /// The error reported by Diesel.
+        source: diesel::result::Error,
+    },
+
+    /// The query references a table, view or sequence which does not exist.
+    #[cfg(feature = "diesel-2")]
+    #[error("the query references an unknown relation: {source}")]
+    UndefinedRelation {
+        /// The error reported by Diesel.
+        source: diesel::result::Error,
+    },
+
+    /// The `EXPLAIN` was cancelled, e.g. by `statement_timeout`, `lock_timeout` or
+    /// `pg_cancel_backend()`. Retrying may succeed.
+    #[cfg(feature = "diesel-2")]
+    #[error("EXPLAIN was cancelled: {source}")]
+    Cancelled {
+        /// The error reported by Diesel.
+        source: diesel::result::Error,
+    },
+
+    /// An environment variable read by
+    /// [`ExplainConfig::from_env()`](crate::ExplainConfig::from_env) has an invalid value.
+    #[error("invalid value `{value}` of {name}, expected {expected}")]
+    InvalidEnvVar {
+        /// Name of the variable.
+        name: String,
+        /// The value which could not be parsed.
+        value: String,
+        /// Description of the accepted values.
+        expected: &'static str,
+    },
+
+    /// A plan in the text format could not be parsed, see
+    /// [`ExplainItem::from_text()`](crate::ExplainItem::from_text).
+    #[error("failed to parse text plan at line {line}: {message}")]
+    ParseText {
+        /// The 1-based number of the offending line, or 0 if the plan as a whole
+        /// is malformed.
+        line: usize,
+        /// What is wrong with the line.
+        message: String,
+    },
+
+    /// A condition could not be parsed by [`expr::parse()`](crate::expr::parse).
+    #[error("failed to parse expression at offset {offset}: {message} in `{expression}`")]
+    ParseExpr {
+        /// The expression which failed to parse.
+        expression: String,
+        /// Byte offset of the error in `expression`.
+        offset: usize,
+        /// What is wrong at that position.
+        message: String,
+    },
+
+    /// Reading plans from a file failed, see the [`logscan`](crate::logscan) module.
+    #[error("failed to read log: {source}")]
+    Io {
+        /// The error reported by the reader.
+        source: std::io::Error,
+    },
+
+    /// Running the `EXPLAIN` query failed for any other reason.
+    #[cfg(feature = "diesel-2")]
+    #[error(transparent)]
+    Diesel(diesel::result::Error),
+}
+
+impl ExplainError {
+    /// Returns `true` for failures which do not indicate a problem with the query
+    /// itself, such as cancellations, serialization failures or lost connections,
+    /// so the same `EXPLAIN` may succeed when retried.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            #[cfg(feature = "diesel-2")]
+            ExplainError::Cancelled { .. } => true,
+            #[cfg(feature = "diesel-2")]
+            ExplainError::Diesel(diesel::result::Error::DatabaseError(kind, _)) => matches!(
+                kind,
+                DatabaseErrorKind::SerializationFailure
+                    | DatabaseErrorKind::ClosedConnection
+                    | DatabaseErrorKind::UnableToSendCommand
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Classifies database errors raised while planning a query.
+///
+/// Diesel does not expose the SQLSTATE of PostgreSQL errors, so the
+/// classification relies on the (English) server messages; errors reported in
+/// other languages stay [`ExplainError::Diesel`].
+#[cfg(feature = "diesel-2")]
+impl From<diesel::result::Error> for ExplainError {
+    fn from(source: diesel::result::Error) -> Self {
+        let diesel::result::Error::DatabaseError(DatabaseErrorKind::Unknown, info) = &source else {
+            return ExplainError::Diesel(source);
+        };
+        let message = info.message();
+        if message.starts_with("permission denied") {
+            ExplainError::InsufficientPrivilege { source }
+        } else if message.starts_with("relation ") && message.ends_with(" does not exist") {
+            ExplainError::UndefinedRelation { source }
+        } else if message.starts_with("canceling statement due to") {
+            ExplainError::Cancelled { source }
+        } else {
+            ExplainError::Diesel(source)
+        }
+    }
+}
+
+#[cfg(feature = "diesel-2")]
+impl From<ExplainError> for diesel::result::Error {
+    fn from(e: ExplainError) -> Self {
+        match e {
+            ExplainError::Diesel(e)
+            | ExplainError::InsufficientPrivilege { source: e }
+            | ExplainError::UndefinedRelation { source: e }
+            | ExplainError::Cancelled { source: e } => e,
+            e => diesel::result::Error::DeserializationError(Box::new(e)),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Rough wall-clock estimates for planner costs.
+//!
+//! Planner costs are in arbitrary units, by default the cost of reading one page
+//! sequentially, which makes cost gates hard to reason about. A [`CostModel`]
+//! measures how long a cost unit takes on a particular server by running
+//! calibration queries with `EXPLAIN ANALYZE`, and converts costs into times and
+//! back.
+//!
+//! Estimates are coarse: how long a query takes depends on caching, concurrency
+//! and how well the planner's row estimates match reality. They are meant to
+//! tell a 1 ms query from a 1 s query, not a 1 ms query from a 2 ms one.
+//!
+//! Example:
+//! ```rust
+//! let model = CostModel::calibrate(&mut conn)?;
+//! let plan = query.wrap_explain().explain(&mut conn)?;
+//! println!("cost {:.0}, about {:?}", plan.total_cost, model.estimate(&plan));
+//! registry.max_cost(model.cost_for(Duration::from_millis(50)));
+//! ```
+
+use std::time::Duration;
+
+#[cfg(feature = "diesel-2")]
+use diesel::sql_types::Double;
+#[cfg(feature = "diesel-2")]
+use diesel::{QueryableByName, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use crate::ExplainPlan;
+#[cfg(feature = "diesel-2")]
+use crate::{ExplainConnection, ExplainError, ExplainOptions, RawSql};
+
+/// Queries used by [`CostModel::calibrate()`]: a sequential scan of a catalog,
+/// an aggregate over generated rows and a sort, covering I/O and CPU costs.
+pub const CALIBRATION_QUERIES: &[&str] = &[
+    "SELECT * FROM pg_catalog.pg_attribute",
+    "SELECT count(*) FROM generate_series(1, 100000)",
+    "SELECT g FROM generate_series(1, 50000) AS g ORDER BY g DESC",
+];
+
+/// Each calibration query runs this many times, and the fastest run counts, so
+/// that a cold cache or a concurrent load spike doesn't skew the model.
+#[cfg(feature = "diesel-2")]
+const RUNS: usize = 3;
+
+/// Converts planner costs into wall-clock time for one server, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CostModel {
+    /// Milliseconds per cost unit.
+    pub ms_per_cost: f64,
+
+    /// The `seq_page_cost` setting the model was calibrated with.
+    pub seq_page_cost: f64,
+
+    /// The `random_page_cost` setting the model was calibrated with.
+    pub random_page_cost: f64,
+}
+
+#[cfg(feature = "diesel-2")]
+#[derive(QueryableByName)]
+struct PageCosts {
+    #[diesel(sql_type = Double)]
+    seq_page_cost: f64,
+    #[diesel(sql_type = Double)]
+    random_page_cost: f64,
+}
+
+impl CostModel {
+    /// Creates a model from a known number of milliseconds per cost unit, e.g. one
+    /// measured earlier, assuming PostgreSQL's default page costs.
+    pub fn from_ms_per_cost(ms_per_cost: f64) -> Self {
+        Self {
+            ms_per_cost,
+            seq_page_cost: 1.0,
+            random_page_cost: 4.0,
+        }
+    }
+
+    /// Calibrates a model on `conn` with [`CALIBRATION_QUERIES`].
+    ///
+    /// # Errors
+    /// Same as [`CostModel::calibrate_with()`].
+    #[cfg(feature = "diesel-2")]
+    pub fn calibrate<C: ExplainConnection>(conn: &mut C) -> Result<Self, ExplainError> {
+        Self::calibrate_with(conn, CALIBRATION_QUERIES)
+    }
+
+    /// Calibrates a model on `conn` by running each of `queries` with
+    /// `EXPLAIN ANALYZE` a few times, comparing the execution time of the fastest
+    /// run to the estimated cost, and taking the median over all queries.
+    ///
+    /// Queries representative of the application, e.g. taken from
+    /// `pg_stat_statements`, give the best estimates. They are executed inside a
+    /// transaction which is rolled back, and should run for at least a few
+    /// milliseconds for the measurement to be meaningful.
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::EmptyResult`] if `queries` is empty, or an error
+    /// described in [`Explain::explain()`](crate::Explain::explain) if a query fails.
+    #[cfg(feature = "diesel-2")]
+    pub fn calibrate_with<C: ExplainConnection>(
+        conn: &mut C,
+        queries: &[&str],
+    ) -> Result<Self, ExplainError> {
+        let costs = diesel::sql_query(
+            "SELECT current_setting('seq_page_cost')::float8 AS seq_page_cost, \
+             current_setting('random_page_cost')::float8 AS random_page_cost",
+        )
+        .get_result::<PageCosts>(conn)?;
+
+        // Per-node timing is turned off, as its overhead would inflate the times.
+        let options = ExplainOptions::default()
+            .analyze(true)
+            .timing(false)
+            .summary(true);
+        let mut ratios = Vec::with_capacity(queries.len());
+        for sql in queries {
+            let mut fastest = f64::INFINITY;
+            let mut cost = 0.0;
+            for _ in 0..RUNS {
+                let item = crate::Explain::new(RawSql::statement(sql))
+                    .options(options)
+                    .explain_item(conn)?;
+                fastest = fastest.min(item.execution_time.unwrap_or_default());
+                cost = item.plan.total_cost;
+            }
+            if cost > 0.0 {
+                ratios.push(fastest / cost);
+            }
+        }
+        if ratios.is_empty() {
+            return Err(ExplainError::EmptyResult);
+        }
+        ratios.sort_by(f64::total_cmp);
+        Ok(Self {
+            ms_per_cost: ratios[ratios.len() / 2],
+            seq_page_cost: costs.seq_page_cost,
+            random_page_cost: costs.random_page_cost,
+        })
+    }
+
+    /// Estimates how long executing `plan` takes.
+    pub fn estimate(&self, plan: &ExplainPlan) -> Duration {
+        self.time_for(plan.total_cost)
+    }
+
+    /// Converts `cost` into time.
+    pub fn time_for(&self, cost: f64) -> Duration {
+        Duration::from_secs_f64((cost * self.ms_per_cost / 1000.0).max(0.0))
+    }
+
+    /// Converts `time` into the cost of a plan expected to take that long, e.g. to
+    /// express a cost gate as a time budget.
+    pub fn cost_for(&self, time: Duration) -> f64 {
+        time.as_secs_f64() * 1000.0 / self.ms_per_cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cost_model_converts_both_ways() {
+        let model = CostModel::from_ms_per_cost(0.002);
+        let plan = ExplainPlan {
+            total_cost: 25_000.0,
+            ..ExplainPlan::default()
+        };
+
+        assert_eq!(model.estimate(&plan), Duration::from_millis(50));
+        assert_eq!(model.cost_for(Duration::from_millis(50)), 25_000.0);
+        assert_eq!(model.time_for(-1.0), Duration::ZERO);
+    }
+}
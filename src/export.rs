@@ -0,0 +1,341 @@
+//! Exporting plans as tables with one row per node, for analysis in
+//! spreadsheets and BI tools.
+//!
+//! Example:
+//! ```rust
+//! let plan = query.wrap_explain().explain_analyze(&mut conn)?;
+//! export::to_csv(&plan, std::fs::File::create("plan.csv")?)?;
+//! ```
+//!
+//! With the `arrow` feature, the nodes of many plans can be converted into an
+//! Arrow record batch with [`to_record_batch()`], and with the `parquet` feature
+//! written to a Parquet file with [`to_parquet()`], e.g. to query a corpus of
+//! captured plans with DataFusion or DuckDB:
+//! ```rust
+//! let plans: Vec<ExplainPlan> = captured.into_iter().map(|item| item.plan).collect();
+//! export::to_parquet(&plans, std::fs::File::create("plans.parquet")?)?;
+//! ```
+//! ```sql
+//! SELECT relation_name, sum(actual_total_time * actual_loops)
+//! FROM 'plans.parquet' WHERE node_type = 'Seq Scan' GROUP BY 1 ORDER BY 2 DESC;
+//! ```
+
+use std::io::{self, Write};
+#[cfg(feature = "arrow")]
+use std::sync::Arc;
+
+#[cfg(feature = "arrow")]
+use arrow::array::{ArrayRef, Float64Array, StringArray, UInt64Array};
+#[cfg(feature = "arrow")]
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+#[cfg(feature = "arrow")]
+use arrow::error::ArrowError;
+#[cfg(feature = "arrow")]
+use arrow::record_batch::RecordBatch;
+
+use crate::ExplainPlan;
+
+/// A plan node without its children, as exported by [`to_csv()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlatNode {
+    /// Position of the node in a depth-first walk of the plan, starting at 0 for
+    /// the root.
+    pub id: usize,
+
+    /// `id` of the parent node; `None` for the root.
+    pub parent_id: Option<usize>,
+
+    /// Distance from the root, 0 for the root itself.
+    pub depth: usize,
+
+    /// See [`ExplainPlan::node_type`].
+    pub node_type: String,
+
+    /// See [`ExplainPlan::relation_name`].
+    pub relation_name: Option<String>,
+
+    /// See [`ExplainPlan::alias`].
+    pub alias: Option<String>,
+
+    /// See [`ExplainPlan::index_name`].
+    pub index_name: Option<String>,
+
+    /// See [`ExplainPlan::startup_cost`].
+    pub startup_cost: f64,
+
+    /// See [`ExplainPlan::total_cost`].
+    pub total_cost: f64,
+
+    /// See [`ExplainPlan::plan_rows`].
+    pub plan_rows: u64,
+
+    /// See [`ExplainPlan::plan_width`].
+    pub plan_width: u64,
+
+    /// See [`ExplainPlan::actual_startup_time`].
+    pub actual_startup_time: Option<f64>,
+
+    /// See [`ExplainPlan::actual_total_time`].
+    pub actual_total_time: Option<f64>,
+
+    /// See [`ExplainPlan::actual_rows`].
+    pub actual_rows: Option<f64>,
+
+    /// See [`ExplainPlan::actual_loops`].
+    pub actual_loops: Option<u64>,
+
+    /// See [`ExplainPlan::shared_hit_blocks`].
+    pub shared_hit_blocks: Option<u64>,
+
+    /// See [`ExplainPlan::shared_read_blocks`].
+    pub shared_read_blocks: Option<u64>,
+
+    /// See [`ExplainPlan::shared_dirtied_blocks`].
+    pub shared_dirtied_blocks: Option<u64>,
+
+    /// See [`ExplainPlan::shared_written_blocks`].
+    pub shared_written_blocks: Option<u64>,
+
+    /// See [`ExplainPlan::temp_read_blocks`].
+    pub temp_read_blocks: Option<u64>,
+
+    /// See [`ExplainPlan::temp_written_blocks`].
+    pub temp_written_blocks: Option<u64>,
+}
+
+/// Flattens `plan` into one [`FlatNode`] per node, in depth-first order.
+pub fn flatten(plan: &ExplainPlan) -> Vec<FlatNode> {
+    let mut nodes = Vec::new();
+    flatten_into(&mut nodes, plan, None, 0);
+    nodes
+}
+
+fn flatten_into(
+    nodes: &mut Vec<FlatNode>,
+    plan: &ExplainPlan,
+    parent_id: Option<usize>,
+    depth: usize,
+) {
+    let id = nodes.len();
+    nodes.push(FlatNode {
+        id,
+        parent_id,
+        depth,
+        node_type: plan.node_type.clone(),
+        relation_name: plan.relation_name.clone(),
+        alias: plan.alias.clone(),
+        index_name: plan.index_name.clone(),
+        startup_cost: plan.startup_cost,
+        total_cost: plan.total_cost,
+        plan_rows: plan.plan_rows,
+        plan_width: plan.plan_width,
+        actual_startup_time: plan.actual_startup_time,
+        actual_total_time: plan.actual_total_time,
+        actual_rows: plan.actual_rows,
+        actual_loops: plan.actual_loops,
+        shared_hit_blocks: plan.shared_hit_blocks,
+        shared_read_blocks: plan.shared_read_blocks,
+        shared_dirtied_blocks: plan.shared_dirtied_blocks,
+        shared_written_blocks: plan.shared_written_blocks,
+        temp_read_blocks: plan.temp_read_blocks,
+        temp_written_blocks: plan.temp_written_blocks,
+    });
+    for child in &plan.plans {
+        flatten_into(nodes, child, Some(id), depth + 1);
+    }
+}
+
+/// The header row written by [`to_csv()`].
+pub const CSV_HEADER: &str = "id,parent_id,depth,node_type,relation_name,alias,index_name,\
+startup_cost,total_cost,plan_rows,plan_width,actual_startup_time,actual_total_time,\
+actual_rows,actual_loops,shared_hit_blocks,shared_read_blocks,shared_dirtied_blocks,\
+shared_written_blocks,temp_read_blocks,temp_written_blocks";
+
+/// Writes `plan` to `writer` as CSV, with a header row and one row per node in
+/// depth-first order, see [`FlatNode`]. Values missing from the plan, e.g. actual
+/// statistics without `ANALYZE`, are left empty.
+///
+/// # Errors
+/// Returns any error of `writer`.
+pub fn to_csv(plan: &ExplainPlan, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "{CSV_HEADER}")?;
+    for node in flatten(plan) {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        let text = |value: &Option<String>| value.as_deref().map(csv_escape).unwrap_or_default();
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            node.id,
+            optional(node.parent_id.map(|id| id.to_string())),
+            node.depth,
+            csv_escape(&node.node_type),
+            text(&node.relation_name),
+            text(&node.alias),
+            text(&node.index_name),
+            node.startup_cost,
+            node.total_cost,
+            node.plan_rows,
+            node.plan_width,
+            optional(node.actual_startup_time.map(|time| time.to_string())),
+            optional(node.actual_total_time.map(|time| time.to_string())),
+            optional(node.actual_rows.map(|rows| rows.to_string())),
+            optional(node.actual_loops.map(|loops| loops.to_string())),
+            optional(node.shared_hit_blocks.map(|blocks| blocks.to_string())),
+            optional(node.shared_read_blocks.map(|blocks| blocks.to_string())),
+            optional(node.shared_dirtied_blocks.map(|blocks| blocks.to_string())),
+            optional(node.shared_written_blocks.map(|blocks| blocks.to_string())),
+            optional(node.temp_read_blocks.map(|blocks| blocks.to_string())),
+            optional(node.temp_written_blocks.map(|blocks| blocks.to_string())),
+        )?;
+    }
+    Ok(())
+}
+
+/// Quotes `value` if it contains a comma, a quote or a line break.
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// The schema of [`to_record_batch()`]: a `plan_id` column holding the position
+/// of the plan in the exported sequence, followed by the fields of [`FlatNode`].
+#[cfg(feature = "arrow")]
+pub fn schema() -> SchemaRef {
+    let text = |name| Field::new(name, DataType::Utf8, true);
+    let float = |name, nullable| Field::new(name, DataType::Float64, nullable);
+    let count = |name, nullable| Field::new(name, DataType::UInt64, nullable);
+    Arc::new(Schema::new(vec![
+        count("plan_id", false),
+        count("id", false),
+        count("parent_id", true),
+        count("depth", false),
+        Field::new("node_type", DataType::Utf8, false),
+        text("relation_name"),
+        text("alias"),
+        text("index_name"),
+        float("startup_cost", false),
+        float("total_cost", false),
+        count("plan_rows", false),
+        count("plan_width", false),
+        float("actual_startup_time", true),
+        float("actual_total_time", true),
+        float("actual_rows", true),
+        count("actual_loops", true),
+        count("shared_hit_blocks", true),
+        count("shared_read_blocks", true),
+        count("shared_dirtied_blocks", true),
+        count("shared_written_blocks", true),
+        count("temp_read_blocks", true),
+        count("temp_written_blocks", true),
+    ]))
+}
+
+/// Converts the nodes of `plans` into a single record batch with one row per
+/// node, see [`schema()`].
+///
+/// # Errors
+/// Returns any error of [`RecordBatch::try_new()`], which is not expected for
+/// well-formed plans.
+#[cfg(feature = "arrow")]
+pub fn to_record_batch<'a>(
+    plans: impl IntoIterator<Item = &'a ExplainPlan>,
+) -> Result<RecordBatch, ArrowError> {
+    let mut plan_ids = Vec::new();
+    let mut nodes = Vec::new();
+    for (plan_id, plan) in plans.into_iter().enumerate() {
+        let flat = flatten(plan);
+        plan_ids.extend(std::iter::repeat_n(plan_id as u64, flat.len()));
+        nodes.extend(flat);
+    }
+
+    let count = |field: fn(&FlatNode) -> Option<u64>| -> ArrayRef {
+        Arc::new(nodes.iter().map(field).collect::<UInt64Array>())
+    };
+    let float = |field: fn(&FlatNode) -> Option<f64>| -> ArrayRef {
+        Arc::new(nodes.iter().map(field).collect::<Float64Array>())
+    };
+    let text = |field: fn(&FlatNode) -> Option<&str>| -> ArrayRef {
+        Arc::new(nodes.iter().map(field).collect::<StringArray>())
+    };
+    RecordBatch::try_new(
+        schema(),
+        vec![
+            Arc::new(UInt64Array::from(plan_ids)),
+            count(|node| Some(node.id as u64)),
+            count(|node| node.parent_id.map(|id| id as u64)),
+            count(|node| Some(node.depth as u64)),
+            text(|node| Some(&node.node_type)),
+            text(|node| node.relation_name.as_deref()),
+            text(|node| node.alias.as_deref()),
+            text(|node| node.index_name.as_deref()),
+            float(|node| Some(node.startup_cost)),
+            float(|node| Some(node.total_cost)),
+            count(|node| Some(node.plan_rows)),
+            count(|node| Some(node.plan_width)),
+            float(|node| node.actual_startup_time),
+            float(|node| node.actual_total_time),
+            float(|node| node.actual_rows),
+            count(|node| node.actual_loops),
+            count(|node| node.shared_hit_blocks),
+            count(|node| node.shared_read_blocks),
+            count(|node| node.shared_dirtied_blocks),
+            count(|node| node.shared_written_blocks),
+            count(|node| node.temp_read_blocks),
+            count(|node| node.temp_written_blocks),
+        ],
+    )
+}
+
+/// Writes the nodes of `plans` to `writer` as a Parquet file, see
+/// [`to_record_batch()`].
+///
+/// # Errors
+/// Returns any error of `writer` or the Parquet encoder.
+#[cfg(feature = "parquet")]
+pub fn to_parquet<'a>(
+    plans: impl IntoIterator<Item = &'a ExplainPlan>,
+    writer: impl Write + Send,
+) -> Result<(), parquet::errors::ParquetError> {
+    let batch = to_record_batch(plans)?;
+    let mut writer = parquet::arrow::ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_are_exported_as_csv() {
+        let plan = ExplainPlan {
+            node_type: "Hash Join".to_owned(),
+            total_cost: 2.5,
+            actual_loops: Some(1),
+            plans: vec![ExplainPlan {
+                node_type: "Seq Scan".to_owned(),
+                relation_name: Some("users".to_owned()),
+                alias: Some("u, \"v\"".to_owned()),
+                shared_hit_blocks: Some(3),
+                ..ExplainPlan::default()
+            }],
+            ..ExplainPlan::default()
+        };
+
+        let mut csv = Vec::new();
+        to_csv(&plan, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], "0,,0,Hash Join,,,,0,2.5,0,0,,,,1,,,,,,");
+        assert_eq!(
+            lines[2],
+            "1,0,1,Seq Scan,users,\"u, \"\"v\"\"\",,0,0,0,0,,,,,3,,,,,"
+        );
+        assert_eq!(lines.len(), 3);
+    }
+}
@@ -0,0 +1,989 @@
+//! Parsing of condition expressions such as `Filter` or `Index Cond`.
+//!
+//! PostgreSQL reports conditions as SQL text deparsed from the planner's
+//! expression trees, e.g. `((users.age > 21) AND (lower(name) = 'x'::text))`.
+//! [`parse()`] turns such strings back into a small [`Expr`] tree of columns,
+//! constants, operators and function calls, for analyses which can't work on
+//! raw strings, e.g. which columns a query filters on or whether a predicate
+//! can use an index.
+//!
+//! The parser covers what PostgreSQL's deparser produces for conditions, not
+//! arbitrary SQL: operators keep their PostgreSQL names, e.g. `LIKE` is `~~`,
+//! and `BETWEEN` or `IN` lists arrive already rewritten by the planner.
+
+use crate::{ExplainError, ExplainPlan};
+
+/// A column reference, e.g. `users.age` or `age`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Column {
+    /// The relation qualifying the column as printed, usually the alias of the
+    /// scanned table. Conditions of scans often leave columns unqualified; plans
+    /// captured with `VERBOSE` qualify all of them.
+    pub relation: Option<String>,
+
+    /// The column name, without quotes.
+    pub name: String,
+}
+
+/// Whether a comparison with an array must hold for any or all of its elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantifier {
+    /// `= ANY (...)`, what `IN` lists are rewritten to.
+    Any,
+
+    /// `<> ALL (...)`, what `NOT IN` lists are rewritten to.
+    All,
+}
+
+/// A node of a parsed condition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A column reference.
+    Column(Column),
+
+    /// A literal, without quotes, e.g. `21` for `21` or `x` for `'x'::text`.
+    /// `NULL`, `true` and `false` are constants without a type.
+    Const {
+        /// The literal as written, with quotes and escapes removed.
+        value: String,
+        /// The type the literal is cast to, e.g. `text` for `'x'::text`.
+        type_name: Option<String>,
+    },
+
+    /// A query parameter such as `$1`, or the output of an `InitPlan` up to
+    /// PostgreSQL 16.
+    Param(String),
+
+    /// The result of a subquery, e.g. `SubPlan 1`, `hashed SubPlan 2` or
+    /// `InitPlan 1`.
+    SubPlan(String),
+
+    /// A binary operator, e.g. `>`, `~~` for `LIKE` or `IS DISTINCT FROM`.
+    BinaryOp {
+        /// The operator.
+        op: String,
+        /// The left operand.
+        left: Box<Expr>,
+        /// The right operand.
+        right: Box<Expr>,
+    },
+
+    /// A prefix operator such as `-`, or a postfix test such as `IS TRUE`.
+    UnaryOp {
+        /// The operator.
+        op: String,
+        /// The operand.
+        operand: Box<Expr>,
+    },
+
+    /// Conditions joined by `AND`.
+    And(Vec<Expr>),
+
+    /// Conditions joined by `OR`.
+    Or(Vec<Expr>),
+
+    /// A negated condition.
+    Not(Box<Expr>),
+
+    /// A comparison with the elements of an array, e.g. `id = ANY ($1)`.
+    Quantified {
+        /// The comparison operator.
+        op: String,
+        /// `ANY` or `ALL`.
+        quantifier: Quantifier,
+        /// The value compared with each element.
+        left: Box<Expr>,
+        /// The array.
+        right: Box<Expr>,
+    },
+
+    /// `IS NULL`, or `IS NOT NULL` if `negated`.
+    IsNull {
+        /// The tested value.
+        operand: Box<Expr>,
+        /// Whether the test is `IS NOT NULL`.
+        negated: bool,
+    },
+
+    /// A function call, e.g. `lower(name)`.
+    Function {
+        /// The function name as printed, possibly schema-qualified.
+        name: String,
+        /// The arguments.
+        args: Vec<Expr>,
+    },
+
+    /// A cast of anything but a literal, e.g. `(name)::text`.
+    Cast {
+        /// The cast value.
+        operand: Box<Expr>,
+        /// The target type.
+        type_name: String,
+    },
+
+    /// An array constructor, `ARRAY[...]`.
+    Array(Vec<Expr>),
+
+    /// A row constructor, `ROW(...)` or `(a, b)`.
+    Row(Vec<Expr>),
+
+    /// A `CASE` expression.
+    Case {
+        /// The value compared with each `WHEN` value, for `CASE x WHEN ...`.
+        operand: Option<Box<Expr>>,
+        /// The `WHEN` conditions or values with their results.
+        whens: Vec<(Expr, Expr)>,
+        /// The `ELSE` result.
+        default: Option<Box<Expr>>,
+    },
+
+    /// An array subscript or slice, e.g. `tags[1]`.
+    Subscript {
+        /// The array.
+        operand: Box<Expr>,
+        /// The index, or the lower bound of a slice.
+        index: Box<Expr>,
+    },
+
+    /// A field of a composite value, e.g. `(InitPlan 1).col1`.
+    Field {
+        /// The composite value.
+        operand: Box<Expr>,
+        /// The field name.
+        name: String,
+    },
+
+    /// An explicit collation, e.g. `name COLLATE "C"`.
+    Collate {
+        /// The collated value.
+        operand: Box<Expr>,
+        /// The collation name.
+        collation: String,
+    },
+}
+
+impl Expr {
+    /// Iterates over this expression and all of its subexpressions in
+    /// depth-first pre-order, like [`ExplainPlan::nodes()`].
+    pub fn nodes(&self) -> impl Iterator<Item = &Expr> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let expr = stack.pop()?;
+            let mut children = expr.children();
+            children.reverse();
+            stack.extend(children);
+            Some(expr)
+        })
+    }
+
+    fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Column(_) | Expr::Const { .. } | Expr::Param(_) | Expr::SubPlan(_) => Vec::new(),
+            Expr::BinaryOp { left, right, .. }
+            | Expr::Quantified { left, right, .. }
+            | Expr::Subscript {
+                operand: left,
+                index: right,
+            } => vec![left, right],
+            Expr::UnaryOp { operand, .. }
+            | Expr::Not(operand)
+            | Expr::IsNull { operand, .. }
+            | Expr::Cast { operand, .. }
+            | Expr::Field { operand, .. }
+            | Expr::Collate { operand, .. } => vec![operand],
+            Expr::And(args)
+            | Expr::Or(args)
+            | Expr::Function { args, .. }
+            | Expr::Array(args)
+            | Expr::Row(args) => args.iter().collect(),
+            Expr::Case {
+                operand,
+                whens,
+                default,
+            } => operand
+                .as_deref()
+                .into_iter()
+                .chain(whens.iter().flat_map(|(when, then)| [when, then]))
+                .chain(default.as_deref())
+                .collect(),
+        }
+    }
+}
+
+/// Parses a condition as printed by PostgreSQL, e.g. the
+/// [`filter`](ExplainPlan::filter) of a node.
+///
+/// Example:
+/// ```rust
+/// let expr = expr::parse("((users.age > 21) AND (lower(name) = 'x'::text))")?;
+/// let Expr::And(conditions) = expr else { unreachable!() };
+/// assert_eq!(conditions.len(), 2);
+/// ```
+///
+/// # Errors
+/// Returns [`ExplainError::ParseExpr`] if `text` isn't a complete expression
+/// or uses syntax the parser doesn't know.
+pub fn parse(text: &str) -> Result<Expr, ExplainError> {
+    let error = |(offset, message)| ExplainError::ParseExpr {
+        expression: text.to_owned(),
+        offset,
+        message,
+    };
+    let tokens = tokenize(text).map_err(error)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        end: text.len(),
+    };
+    let expr = parser.expr().map_err(error)?;
+    if let Some((offset, token)) = parser.tokens.get(parser.position) {
+        return Err(error((*offset, format!("unexpected {token}"))));
+    }
+    Ok(expr)
+}
+
+/// Returns the conditions of `node` with the names of the fields they come from,
+/// e.g. `("Index Cond", "(id = 1)")`.
+pub fn conditions(node: &ExplainPlan) -> impl Iterator<Item = (&'static str, &str)> {
+    [
+        ("Filter", &node.filter),
+        ("Index Cond", &node.index_cond),
+        ("Recheck Cond", &node.recheck_cond),
+        ("Hash Cond", &node.hash_cond),
+        ("Merge Cond", &node.merge_cond),
+        ("Join Filter", &node.join_filter),
+        ("TID Cond", &node.tid_cond),
+        ("One-Time Filter", &node.one_time_filter),
+    ]
+    .into_iter()
+    .filter_map(|(field, condition)| Some((field, condition.as_deref()?)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident { name: String, quoted: bool },
+    Number(String),
+    String(String),
+    Param(String),
+    Op(String),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Colon,
+    DoubleColon,
+    Dot,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident { name, .. } => write!(f, "`{name}`"),
+            Token::Number(number) => write!(f, "number `{number}`"),
+            Token::String(string) => write!(f, "string '{string}'"),
+            Token::Param(param) => write!(f, "`{param}`"),
+            Token::Op(op) => write!(f, "operator `{op}`"),
+            Token::LParen => f.write_str("`(`"),
+            Token::RParen => f.write_str("`)`"),
+            Token::LBracket => f.write_str("`[`"),
+            Token::RBracket => f.write_str("`]`"),
+            Token::Comma => f.write_str("`,`"),
+            Token::Colon => f.write_str("`:`"),
+            Token::DoubleColon => f.write_str("`::`"),
+            Token::Dot => f.write_str("`.`"),
+        }
+    }
+}
+
+/// Characters operators are made of, see `CREATE OPERATOR`.
+const OPERATOR_CHARS: &str = "+-*/<>=~!@#%^&|`?";
+
+type Error = (usize, String);
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' | ')' | '[' | ']' | ',' | '.' => {
+                chars.next();
+                match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    ',' => Token::Comma,
+                    _ => Token::Dot,
+                }
+            }
+            ':' => {
+                chars.next();
+                if chars.next_if(|&(_, c)| c == ':').is_some() {
+                    Token::DoubleColon
+                } else {
+                    Token::Colon
+                }
+            }
+            '\'' => Token::String(quoted(&mut chars, '\'', false)?),
+            '"' => Token::Ident {
+                name: quoted(&mut chars, '"', false)?,
+                quoted: true,
+            },
+            '$' => {
+                chars.next();
+                let mut param = String::from('$');
+                while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                    param.push(c);
+                }
+                if param.len() == 1 {
+                    return Err((start, "expected a parameter number after `$`".to_owned()));
+                }
+                Token::Param(param)
+            }
+            _ if c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '.')
+                {
+                    number.push(c);
+                }
+                Token::Number(number)
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '$')
+                {
+                    name.push(c);
+                }
+                if matches!(name.as_str(), "E" | "e")
+                    && chars.peek().is_some_and(|(_, c)| *c == '\'')
+                {
+                    Token::String(quoted(&mut chars, '\'', true)?)
+                } else {
+                    Token::Ident {
+                        name,
+                        quoted: false,
+                    }
+                }
+            }
+            _ if OPERATOR_CHARS.contains(c) => {
+                let mut op = String::new();
+                while let Some((_, c)) = chars.next_if(|(_, c)| OPERATOR_CHARS.contains(*c)) {
+                    op.push(c);
+                }
+                Token::Op(op)
+            }
+            _ => return Err((start, format!("unexpected character `{c}`"))),
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+/// Reads a literal or identifier enclosed in `quote`, which is doubled inside.
+fn quoted(
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+    quote: char,
+    backslash_escapes: bool,
+) -> Result<String, Error> {
+    let Some((start, _)) = chars.next() else {
+        return Err((0, "unexpected end".to_owned()));
+    };
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some((_, c)) if c == quote => {
+                if chars.next_if(|&(_, c)| c == quote).is_none() {
+                    return Ok(value);
+                }
+                value.push(quote);
+            }
+            Some((_, '\\')) if backslash_escapes => {
+                if let Some((_, c)) = chars.next() {
+                    value.push(c);
+                }
+            }
+            Some((_, c)) => value.push(c),
+            None => return Err((start, format!("unterminated {quote}"))),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    /// Length of the input, reported as the offset of errors at its end.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.position + ahead)
+            .map(|(_, token)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(offset, _)| *offset)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position)?.1.clone();
+        self.position += 1;
+        Some(token)
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, Error> {
+        let found = match self.peek() {
+            Some(token) => token.to_string(),
+            None => "end of expression".to_owned(),
+        };
+        Err((self.offset(), format!("expected {expected}, found {found}")))
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), Error> {
+        if self.peek() == Some(&token) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.unexpected(&token.to_string())
+        }
+    }
+
+    /// Whether the next token is the unquoted `keyword`, case-insensitively.
+    fn at_keyword(&self, keyword: &str) -> bool {
+        self.keyword_at(0, keyword)
+    }
+
+    fn keyword_at(&self, ahead: usize, keyword: &str) -> bool {
+        matches!(
+            self.peek_at(ahead),
+            Some(Token::Ident { name, quoted: false }) if name.eq_ignore_ascii_case(keyword)
+        )
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.at_keyword(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("`{keyword}`"))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, Error> {
+        let first = self.and()?;
+        if !self.at_keyword("OR") {
+            return Ok(first);
+        }
+        let mut args = vec![first];
+        while self.eat_keyword("OR") {
+            args.push(self.and()?);
+        }
+        Ok(Expr::Or(args))
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let first = self.not()?;
+        if !self.at_keyword("AND") {
+            return Ok(first);
+        }
+        let mut args = vec![first];
+        while self.eat_keyword("AND") {
+            args.push(self.not()?);
+        }
+        Ok(Expr::And(args))
+    }
+
+    fn not(&mut self) -> Result<Expr, Error> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.is()
+    }
+
+    fn is(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.binary()?;
+        while self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            let not = if negated { "NOT " } else { "" };
+            expr = if self.eat_keyword("NULL") {
+                Expr::IsNull {
+                    operand: Box::new(expr),
+                    negated,
+                }
+            } else if self.eat_keyword("DISTINCT") {
+                self.expect_keyword("FROM")?;
+                Expr::BinaryOp {
+                    op: format!("IS {not}DISTINCT FROM"),
+                    left: Box::new(expr),
+                    right: Box::new(self.binary()?),
+                }
+            } else {
+                let test = ["TRUE", "FALSE", "UNKNOWN"]
+                    .into_iter()
+                    .find(|test| self.eat_keyword(test));
+                match test {
+                    Some(test) => Expr::UnaryOp {
+                        op: format!("IS {not}{test}"),
+                        operand: Box::new(expr),
+                    },
+                    None => return self.unexpected("`NULL`, `TRUE`, `FALSE` or `DISTINCT`"),
+                }
+            };
+        }
+        Ok(expr)
+    }
+
+    fn binary(&mut self) -> Result<Expr, Error> {
+        let mut left = self.unary()?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let op = op.clone();
+            self.position += 1;
+            let quantifier = if self.eat_keyword("ANY") {
+                Some(Quantifier::Any)
+            } else if self.eat_keyword("ALL") {
+                Some(Quantifier::All)
+            } else {
+                None
+            };
+            left = match quantifier {
+                Some(quantifier) => {
+                    self.expect(Token::LParen)?;
+                    let right = self.expr()?;
+                    self.expect(Token::RParen)?;
+                    Expr::Quantified {
+                        op,
+                        quantifier,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    }
+                }
+                None => Expr::BinaryOp {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(self.unary()?),
+                },
+            };
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        if let Some(Token::Op(op)) = self.peek() {
+            let op = op.clone();
+            self.position += 1;
+            return Ok(Expr::UnaryOp {
+                op,
+                operand: Box::new(self.unary()?),
+            });
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.primary()?;
+        loop {
+            expr = match self.peek() {
+                Some(Token::DoubleColon) => {
+                    self.position += 1;
+                    let type_name = self.type_name()?;
+                    match expr {
+                        Expr::Const {
+                            value,
+                            type_name: None,
+                        } => Expr::Const {
+                            value,
+                            type_name: Some(type_name),
+                        },
+                        operand => Expr::Cast {
+                            operand: Box::new(operand),
+                            type_name,
+                        },
+                    }
+                }
+                Some(Token::LBracket) => {
+                    self.position += 1;
+                    let index = self.expr()?;
+                    // The upper bound of a slice is dropped.
+                    if self.peek() == Some(&Token::Colon) {
+                        self.position += 1;
+                        self.expr()?;
+                    }
+                    self.expect(Token::RBracket)?;
+                    Expr::Subscript {
+                        operand: Box::new(expr),
+                        index: Box::new(index),
+                    }
+                }
+                Some(Token::Dot) => {
+                    self.position += 1;
+                    Expr::Field {
+                        operand: Box::new(expr),
+                        name: self.ident()?,
+                    }
+                }
+                _ if self.at_keyword("COLLATE") => {
+                    self.position += 1;
+                    Expr::Collate {
+                        operand: Box::new(expr),
+                        collation: self.qualified_name()?,
+                    }
+                }
+                _ => return Ok(expr),
+            };
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        let offset = self.offset();
+        let Some(token) = self.next() else {
+            return self.unexpected("an expression");
+        };
+        match token {
+            Token::LParen => {
+                let first = self.expr()?;
+                if self.peek() != Some(&Token::Comma) {
+                    self.expect(Token::RParen)?;
+                    return Ok(first);
+                }
+                let mut items = vec![first];
+                while self.peek() == Some(&Token::Comma) {
+                    self.position += 1;
+                    items.push(self.expr()?);
+                }
+                self.expect(Token::RParen)?;
+                Ok(Expr::Row(items))
+            }
+            Token::Number(value) => Ok(Expr::Const {
+                value,
+                type_name: None,
+            }),
+            Token::String(value) => Ok(Expr::Const {
+                value,
+                type_name: None,
+            }),
+            Token::Param(param) => Ok(Expr::Param(param)),
+            Token::Ident { name, quoted: true } => self.name(name),
+            Token::Ident { name, .. } => match name.to_ascii_uppercase().as_str() {
+                "NULL" => Ok(Expr::Const {
+                    value: "NULL".to_owned(),
+                    type_name: None,
+                }),
+                "TRUE" | "FALSE" => Ok(Expr::Const {
+                    value: name.to_ascii_lowercase(),
+                    type_name: None,
+                }),
+                "ARRAY" => {
+                    self.expect(Token::LBracket)?;
+                    let items = self.list(Token::RBracket)?;
+                    Ok(Expr::Array(items))
+                }
+                "ROW" if self.peek() == Some(&Token::LParen) => {
+                    self.position += 1;
+                    Ok(Expr::Row(self.list(Token::RParen)?))
+                }
+                "CASE" => self.case(),
+                "SUBPLAN" | "INITPLAN" => self.subplan(name),
+                "HASHED" if self.keyword_at(0, "SubPlan") => {
+                    let Some(Token::Ident { name: subplan, .. }) = self.next() else {
+                        unreachable!("checked by keyword_at()");
+                    };
+                    let Expr::SubPlan(subplan) = self.subplan(subplan)? else {
+                        unreachable!("subplan() returns subplans");
+                    };
+                    Ok(Expr::SubPlan(format!("{name} {subplan}")))
+                }
+                _ => self.name(name),
+            },
+            token => Err((offset, format!("expected an expression, found {token}"))),
+        }
+    }
+
+    /// Parses `SubPlan 1` or `InitPlan 2`, after the first word.
+    fn subplan(&mut self, name: String) -> Result<Expr, Error> {
+        match self.peek() {
+            Some(Token::Number(number)) => {
+                let subplan = Expr::SubPlan(format!("{name} {number}"));
+                self.position += 1;
+                Ok(subplan)
+            }
+            _ => self.unexpected("a subplan number"),
+        }
+    }
+
+    /// Parses a column reference or function call starting with `first`.
+    fn name(&mut self, first: String) -> Result<Expr, Error> {
+        let mut parts = vec![first];
+        while self.peek() == Some(&Token::Dot)
+            && matches!(self.peek_at(1), Some(Token::Ident { .. }))
+        {
+            self.position += 1;
+            parts.push(self.ident()?);
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.position += 1;
+            // `count(*)` is the only call with a star argument.
+            let args = if self.peek() == Some(&Token::Op("*".to_owned()))
+                && self.peek_at(1) == Some(&Token::RParen)
+            {
+                self.position += 2;
+                vec![Expr::Column(Column {
+                    relation: None,
+                    name: "*".to_owned(),
+                })]
+            } else {
+                self.list(Token::RParen)?
+            };
+            return Ok(Expr::Function {
+                name: parts.join("."),
+                args,
+            });
+        }
+        let name = parts.pop().unwrap_or_default();
+        Ok(Expr::Column(Column {
+            relation: (!parts.is_empty()).then(|| parts.join(".")),
+            name,
+        }))
+    }
+
+    /// Parses comma-separated expressions up to the `close` token.
+    fn list(&mut self, close: Token) -> Result<Vec<Expr>, Error> {
+        let mut items = Vec::new();
+        if self.peek() == Some(&close) {
+            self.position += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(self.expr()?);
+            if self.peek() == Some(&Token::Comma) {
+                self.position += 1;
+                continue;
+            }
+            self.expect(close)?;
+            return Ok(items);
+        }
+    }
+
+    fn case(&mut self) -> Result<Expr, Error> {
+        let operand = if self.at_keyword("WHEN") {
+            None
+        } else {
+            Some(Box::new(self.expr()?))
+        };
+        let mut whens = Vec::new();
+        while self.eat_keyword("WHEN") {
+            let when = self.expr()?;
+            self.expect_keyword("THEN")?;
+            whens.push((when, self.expr()?));
+        }
+        if whens.is_empty() {
+            return self.unexpected("`WHEN`");
+        }
+        let default = if self.eat_keyword("ELSE") {
+            Some(Box::new(self.expr()?))
+        } else {
+            None
+        };
+        self.expect_keyword("END")?;
+        Ok(Expr::Case {
+            operand,
+            whens,
+            default,
+        })
+    }
+
+    fn ident(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some(Token::Ident { name, .. }) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok(name)
+            }
+            _ => self.unexpected("a name"),
+        }
+    }
+
+    fn qualified_name(&mut self) -> Result<String, Error> {
+        let mut name = self.ident()?;
+        while self.peek() == Some(&Token::Dot) {
+            self.position += 1;
+            name.push('.');
+            name.push_str(&self.ident()?);
+        }
+        Ok(name)
+    }
+
+    /// Parses a type name after `::`, e.g. `text`, `character varying(10)`,
+    /// `timestamp with time zone` or `integer[]`.
+    fn type_name(&mut self) -> Result<String, Error> {
+        let mut name = self.qualified_name()?;
+        while let Some(Token::Ident {
+            name: word,
+            quoted: false,
+        }) = self.peek()
+        {
+            let keyword = [
+                "AND", "OR", "IS", "NOT", "WHEN", "THEN", "ELSE", "END", "COLLATE",
+            ]
+            .iter()
+            .any(|keyword| word.eq_ignore_ascii_case(keyword));
+            if keyword {
+                break;
+            }
+            name.push(' ');
+            name.push_str(word);
+            self.position += 1;
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.position += 1;
+            let mut modifiers = Vec::new();
+            while let Some(Token::Number(number)) = self.peek() {
+                modifiers.push(number.clone());
+                self.position += 1;
+                if self.peek() == Some(&Token::Comma) {
+                    self.position += 1;
+                }
+            }
+            self.expect(Token::RParen)?;
+            name.push_str(&format!("({})", modifiers.join(",")));
+        }
+        while self.peek() == Some(&Token::LBracket) && self.peek_at(1) == Some(&Token::RBracket) {
+            self.position += 2;
+            name.push_str("[]");
+        }
+        Ok(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions_are_parsed_into_expressions() {
+        use {Column, Expr, Quantifier};
+
+        let column = |relation: Option<&str>, name: &str| {
+            Expr::Column(Column {
+                relation: relation.map(str::to_owned),
+                name: name.to_owned(),
+            })
+        };
+        let constant = |value: &str, type_name: Option<&str>| Expr::Const {
+            value: value.to_owned(),
+            type_name: type_name.map(str::to_owned),
+        };
+        let binary = |op: &str, left, right| Expr::BinaryOp {
+            op: op.to_owned(),
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+
+        assert_eq!(
+            parse("((users.age > 21) AND (lower((name)::text) = 'x'::text))").unwrap(),
+            Expr::And(vec![
+                binary(">", column(Some("users"), "age"), constant("21", None)),
+                binary(
+                    "=",
+                    Expr::Function {
+                        name: "lower".to_owned(),
+                        args: vec![Expr::Cast {
+                            operand: Box::new(column(None, "name")),
+                            type_name: "text".to_owned(),
+                        }],
+                    },
+                    constant("x", Some("text")),
+                ),
+            ])
+        );
+        assert_eq!(
+            parse("(id = ANY ('{1,2,3}'::integer[]))").unwrap(),
+            Expr::Quantified {
+                op: "=".to_owned(),
+                quantifier: Quantifier::Any,
+                left: Box::new(column(None, "id")),
+                right: Box::new(constant("{1,2,3}", Some("integer[]"))),
+            }
+        );
+        assert_eq!(
+            parse("((deleted_at IS NOT NULL) OR (NOT (hashed SubPlan 1)))").unwrap(),
+            Expr::Or(vec![
+                Expr::IsNull {
+                    operand: Box::new(column(None, "deleted_at")),
+                    negated: true,
+                },
+                Expr::Not(Box::new(Expr::SubPlan("hashed SubPlan 1".to_owned()))),
+            ])
+        );
+        assert_eq!(
+            parse(r#"("Weird Name" ~~ E'it\'s%'::text COLLATE "C")"#).unwrap(),
+            binary(
+                "~~",
+                column(None, "Weird Name"),
+                Expr::Collate {
+                    operand: Box::new(constant("it's%", Some("text"))),
+                    collation: "C".to_owned(),
+                },
+            )
+        );
+
+        let parsed = [
+            "(created_at > (now() - '1 day'::interval))",
+            "(ts < '2024-01-01 00:00:00'::timestamp without time zone)",
+            "((o.status)::text = ANY ((ARRAY[$1, $2])::text[]))",
+            "(CASE WHEN (a > 0) THEN 'pos'::text ELSE 'neg'::text END = 'pos'::text)",
+            "((code)::character varying(10) IS DISTINCT FROM (InitPlan 1).col1)",
+            "(tags[1] = 'a''b'::text)",
+            "(count(*) > 1)",
+        ];
+        for condition in parsed {
+            assert!(parse(condition).is_ok(), "{condition}");
+        }
+        let expr = parse("(CASE x WHEN 1 THEN y END = z)").unwrap();
+        let columns = expr
+            .nodes()
+            .filter_map(|node| match node {
+                Expr::Column(column) => Some(column.name.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(columns, ["x", "y", "z"]);
+
+        let Err(ExplainError::ParseExpr { offset, .. }) = parse("(a = )") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(offset, 5);
+        assert!(parse("(a = 'open)").is_err());
+        assert!(parse("(a = 1))").is_err());
+
+        let scan = ExplainPlan {
+            index_cond: Some("(id = 1)".to_owned()),
+            filter: Some("(age > 21)".to_owned()),
+            ..ExplainPlan::scan("Index Scan", "users")
+        };
+        assert_eq!(
+            conditions(&scan).collect::<Vec<_>>(),
+            [("Filter", "(age > 21)"), ("Index Cond", "(id = 1)")]
+        );
+    }
+}
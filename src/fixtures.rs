@@ -0,0 +1,103 @@
+//! A corpus of representative `EXPLAIN (FORMAT JSON)` outputs.
+//!
+//! The fixtures cover PostgreSQL 12 through 17 and a range of plan shapes:
+//! plain and `ANALYZE` plans, `BUFFERS` and `VERBOSE` output, parallel
+//! plans with per-worker details, partition pruning, DML with triggers and
+//! `SETTINGS`. They are intended for testing code that consumes
+//! [`ExplainPlan`] trees without access to a live database.
+//!
+//! Example:
+//! ```rust
+//! use diesel_pg_explain::fixtures;
+//!
+//! for fixture in fixtures::all() {
+//!     let plans = fixture.plans().expect("fixture must parse");
+//!     my_analyzer(&plans[0]);
+//! }
+//! ```
+
+use crate::{parse_items, ExplainError, ExplainPlan, ParseMode};
+
+/// A single captured `EXPLAIN (FORMAT JSON)` output.
+#[derive(Debug, Clone, Copy)]
+pub struct Fixture {
+    /// Short name describing the query and the options used to capture it,
+    /// e.g. `"hash_join_analyze_buffers"`.
+    pub name: &'static str,
+
+    /// Major version of the PostgreSQL server which produced the output.
+    pub server_version: u32,
+
+    /// The raw JSON document exactly as returned by `EXPLAIN`.
+    pub json: &'static str,
+}
+
+impl Fixture {
+    /// Parses the fixture into plan trees, one per item of the JSON array.
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Parse`] if the fixture cannot be deserialized
+    /// into [`ExplainPlan`].
+    pub fn plans(&self) -> Result<Vec<ExplainPlan>, ExplainError> {
+        self.plans_with_mode(ParseMode::Lenient)
+    }
+
+    /// Same as [`Fixture::plans()`], with an explicit [`ParseMode`].
+    ///
+    /// # Errors
+    /// Additionally returns [`ExplainError::UnknownFields`] in [`ParseMode::Strict`]
+    /// if the fixture contains fields [`ExplainPlan`] does not model.
+    pub fn plans_with_mode(&self, mode: ParseMode) -> Result<Vec<ExplainPlan>, ExplainError> {
+        let items = parse_items(self.json, mode)?;
+        Ok(items.into_iter().map(|item| item.plan).collect())
+    }
+}
+
+macro_rules! fixture {
+    ($version:literal, $name:literal) => {
+        Fixture {
+            name: $name,
+            server_version: $version,
+            json: include_str!(concat!(
+                "../fixtures/pg",
+                stringify!($version),
+                "/",
+                $name,
+                ".json"
+            )),
+        }
+    };
+}
+
+static FIXTURES: &[Fixture] = &[
+    fixture!(12, "seq_scan_filter"),
+    fixture!(12, "hash_join_analyze_buffers"),
+    fixture!(12, "subplan_analyze"),
+    fixture!(13, "index_scan"),
+    fixture!(13, "external_sort_analyze"),
+    fixture!(14, "nested_loop_verbose"),
+    fixture!(14, "partition_pruning_analyze"),
+    fixture!(15, "seq_scan_filter"),
+    fixture!(15, "hash_join_analyze_buffers"),
+    fixture!(15, "parallel_seq_scan_analyze"),
+    fixture!(15, "settings_work_mem"),
+    fixture!(16, "bitmap_or"),
+    fixture!(16, "insert_triggers_analyze"),
+    fixture!(17, "external_sort_analyze"),
+    fixture!(17, "parallel_sort_workers_verbose"),
+];
+
+/// Iterates over every bundled fixture, ordered by server version.
+pub fn all() -> impl Iterator<Item = &'static Fixture> {
+    FIXTURES.iter()
+}
+
+/// Iterates over fixtures captured from the given PostgreSQL major version.
+pub fn for_version(server_version: u32) -> impl Iterator<Item = &'static Fixture> {
+    all().filter(move |f| f.server_version == server_version)
+}
+
+/// Looks up a fixture by server version and name.
+pub fn get(server_version: u32, name: &str) -> Option<&'static Fixture> {
+    for_version(server_version).find(|f| f.name == name)
+}
@@ -0,0 +1,188 @@
+//! Comparison of the generic and custom plans of a parameterized query.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use diesel::connection::TransactionManager;
+use diesel::pg::{Pg, PgQueryBuilder};
+use diesel::query_builder::{QueryBuilder, QueryFragment};
+use diesel::query_dsl::methods::LoadQuery;
+use diesel::sql_types::{Integer, Text};
+use diesel::{QueryResult, QueryableByName, RunQueryDsl};
+
+use crate::diff::{self, PlanChange};
+use crate::{set_local, Explain, ExplainConnection, ExplainError, ExplainPlan, RawSql};
+
+/// Generic and custom plans of the same query,
+/// returned by [`Explain::explain_generic_and_custom()`].
+#[derive(Debug)]
+pub struct GenericPlanComparison {
+    /// The plan chosen without knowing the parameter values, as PostgreSQL may
+    /// cache it for a prepared statement.
+    pub generic: ExplainPlan,
+
+    /// The plan chosen for the actual parameter values.
+    pub custom: ExplainPlan,
+
+    /// Differences from the generic to the custom plan.
+    pub diff: Vec<PlanChange>,
+}
+
+/// Distinguishes the prepared statements of concurrent comparisons.
+static STATEMENT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(QueryableByName)]
+struct ParameterCount {
+    #[diesel(sql_type = Integer)]
+    count: i32,
+}
+
+impl<Q> Explain<Q> {
+    /// Explains a parameterized query twice: with the generic plan PostgreSQL may
+    /// cache for a prepared statement, and with a custom plan for the actual bind
+    /// values. A difference between the two means performance may depend on
+    /// `plan_cache_mode` and on how often the statement was executed before.
+    ///
+    /// The generic plan is obtained by preparing the SQL generated by Diesel and
+    /// explaining its execution with `plan_cache_mode = force_generic_plan`; the
+    /// prepared statement is deallocated afterwards. `ANALYZE` is disabled for it.
+    ///
+    /// Example:
+    /// ```rust
+    /// let comparison = query.wrap_explain().explain_generic_and_custom(&mut conn)?;
+    /// for change in &comparison.diff {
+    ///     println!("{change}");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn explain_generic_and_custom<'a, C>(
+        self,
+        conn: &mut C,
+    ) -> Result<GenericPlanComparison, ExplainError>
+    where
+        C: ExplainConnection,
+        Q: QueryFragment<Pg>,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let explain = self.with_connection_defaults(conn)?;
+        let generic = explain.explain_generic(conn)?;
+        let custom = explain.explain(conn)?;
+        let diff = diff::compare(&generic, &custom);
+        Ok(GenericPlanComparison {
+            generic,
+            custom,
+            diff,
+        })
+    }
+
+    fn explain_generic<C>(&self, conn: &mut C) -> Result<ExplainPlan, ExplainError>
+    where
+        C: ExplainConnection,
+        Q: QueryFragment<Pg>,
+    {
+        let mut query_builder = PgQueryBuilder::default();
+        self.query.to_sql(&mut query_builder, &Pg)?;
+        let name = format!(
+            "diesel_pg_explain_generic_{}",
+            STATEMENT_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        // Names are resolved when preparing, so the settings (e.g. `search_path`)
+        // must already be in effect.
+        C::TransactionManager::begin_transaction(conn)?;
+        let prepared = set_local(conn, &self.settings).and_then(|()| {
+            diesel::sql_query(prepare_sql(&name, &query_builder.finish())).execute(conn)
+        });
+        C::TransactionManager::rollback_transaction(conn)?;
+        prepared?;
+
+        let plan = explain_prepared(conn, &name, self);
+        let deallocated = diesel::sql_query(format!("DEALLOCATE {name}")).execute(conn);
+        deallocated_plan(plan, deallocated)
+    }
+}
+
+fn prepare_sql(name: &str, sql: &str) -> String {
+    format!("PREPARE {name} AS {sql}")
+}
+
+/// Executes the prepared statement `name` with `parameters` `NULL` arguments.
+fn execute_sql(name: &str, parameters: usize) -> String {
+    if parameters == 0 {
+        return format!("EXECUTE {name}");
+    }
+    format!("EXECUTE {name}({})", vec!["NULL"; parameters].join(", "))
+}
+
+/// Returns the plan of the prepared statement, or the error explaining it, which
+/// takes precedence over a failure to deallocate the statement afterwards.
+fn deallocated_plan(
+    plan: Result<ExplainPlan, ExplainError>,
+    deallocated: QueryResult<usize>,
+) -> Result<ExplainPlan, ExplainError> {
+    let plan = plan?;
+    deallocated?;
+    Ok(plan)
+}
+
+/// Explains the execution of the prepared statement `name` with a generic plan.
+/// The generic plan doesn't depend on the parameter values, so all are `NULL`.
+fn explain_prepared<C, Q>(
+    conn: &mut C,
+    name: &str,
+    explain: &Explain<Q>,
+) -> Result<ExplainPlan, ExplainError>
+where
+    C: ExplainConnection,
+{
+    let parameters = diesel::sql_query(
+        "SELECT cardinality(parameter_types) AS count FROM pg_prepared_statements WHERE name = $1",
+    )
+    .bind::<Text, _>(name)
+    .get_result::<ParameterCount>(conn)?;
+    let execute = execute_sql(name, parameters.count as usize);
+
+    Explain {
+        query: RawSql(&execute),
+        options: explain.options.analyze(false),
+        explicit_options: true,
+        mode: explain.mode,
+        settings: explain.settings.clone(),
+        lock_timeout: explain.lock_timeout,
+        hints: explain.hints.clone(),
+    }
+    .setting("plan_cache_mode", "force_generic_plan")
+    .explain(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepared_statements_are_executed_with_null_arguments() {
+        assert_eq!(
+            prepare_sql("generic_1", "SELECT $1 + $2"),
+            "PREPARE generic_1 AS SELECT $1 + $2"
+        );
+        assert_eq!(execute_sql("generic_1", 0), "EXECUTE generic_1");
+        assert_eq!(execute_sql("generic_1", 2), "EXECUTE generic_1(NULL, NULL)");
+    }
+
+    #[test]
+    fn explain_errors_take_precedence_over_deallocation() {
+        let plan = || Ok(ExplainPlan::new("Result"));
+        let failed = || Err(ExplainError::EmptyResult);
+        let not_deallocated = || Err(diesel::result::Error::NotFound);
+        assert!(deallocated_plan(plan(), Ok(0)).is_ok());
+        assert!(matches!(
+            deallocated_plan(failed(), not_deallocated()),
+            Err(ExplainError::EmptyResult)
+        ));
+        assert!(matches!(
+            deallocated_plan(plan(), not_deallocated()),
+            Err(ExplainError::Diesel(diesel::result::Error::NotFound))
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+//! Planner hints for the [pg_hint_plan](https://github.com/ossc-db/pg_hint_plan)
+//! extension.
+//!
+//! pg_hint_plan reads hints from a `/*+ ... */` comment at the start of the
+//! statement. [`Hints`] builds such a comment and [`Explain::hints()`] puts it in
+//! front of the `EXPLAIN`, so hinted and unhinted plans of the same query can be
+//! compared. Without the extension loaded, hints are ignored.
+//!
+//! Example:
+//! ```rust
+//! use diesel_pg_explain::hints::Hints;
+//!
+//! let hints = Hints::new()
+//!     .index_scan("users", ["users_age_idx"])
+//!     .hash_join(["users", "posts"]);
+//! let hinted = query.wrap_explain().hints(hints).explain(&mut conn)?;
+//! let unhinted = query.wrap_explain().explain(&mut conn)?;
+//! ```
+//!
+//! [`Explain::hints()`]: crate::Explain::hints
+
+/// A set of pg_hint_plan hints.
+///
+/// Table arguments are table names or aliases as they appear in the query.
+/// Identifiers are quoted where needed, but must not contain `*/`, which would
+/// end the hint comment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hints {
+    hints: Vec<String>,
+}
+
+impl Hints {
+    /// Creates an empty set of hints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forces a sequential scan of `table`.
+    pub fn seq_scan(self, table: &str) -> Self {
+        self.hint("SeqScan", [table])
+    }
+
+    /// Forbids sequential scans of `table`.
+    pub fn no_seq_scan(self, table: &str) -> Self {
+        self.hint("NoSeqScan", [table])
+    }
+
+    /// Forces an index scan of `table`, using one of `indexes` or, if empty, any index.
+    pub fn index_scan<'a>(
+        self,
+        table: &'a str,
+        indexes: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        self.hint("IndexScan", std::iter::once(table).chain(indexes))
+    }
+
+    /// Forbids index scans of `table`.
+    pub fn no_index_scan(self, table: &str) -> Self {
+        self.hint("NoIndexScan", [table])
+    }
+
+    /// Forces an index-only scan of `table`, using one of `indexes` or, if empty, any index.
+    pub fn index_only_scan<'a>(
+        self,
+        table: &'a str,
+        indexes: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        self.hint("IndexOnlyScan", std::iter::once(table).chain(indexes))
+    }
+
+    /// Forces a bitmap scan of `table`, using `indexes` or, if empty, any index.
+    pub fn bitmap_scan<'a>(
+        self,
+        table: &'a str,
+        indexes: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        self.hint("BitmapScan", std::iter::once(table).chain(indexes))
+    }
+
+    /// Forbids bitmap scans of `table`.
+    pub fn no_bitmap_scan(self, table: &str) -> Self {
+        self.hint("NoBitmapScan", [table])
+    }
+
+    /// Forces a nested loop for the join of `tables`.
+    pub fn nest_loop<'a>(self, tables: impl IntoIterator<Item = &'a str>) -> Self {
+        self.hint("NestLoop", tables)
+    }
+
+    /// Forbids a nested loop for the join of `tables`.
+    pub fn no_nest_loop<'a>(self, tables: impl IntoIterator<Item = &'a str>) -> Self {
+        self.hint("NoNestLoop", tables)
+    }
+
+    /// Forces a hash join for the join of `tables`.
+    pub fn hash_join<'a>(self, tables: impl IntoIterator<Item = &'a str>) -> Self {
+        self.hint("HashJoin", tables)
+    }
+
+    /// Forbids a hash join for the join of `tables`.
+    pub fn no_hash_join<'a>(self, tables: impl IntoIterator<Item = &'a str>) -> Self {
+        self.hint("NoHashJoin", tables)
+    }
+
+    /// Forces a merge join for the join of `tables`.
+    pub fn merge_join<'a>(self, tables: impl IntoIterator<Item = &'a str>) -> Self {
+        self.hint("MergeJoin", tables)
+    }
+
+    /// Forbids a merge join for the join of `tables`.
+    pub fn no_merge_join<'a>(self, tables: impl IntoIterator<Item = &'a str>) -> Self {
+        self.hint("NoMergeJoin", tables)
+    }
+
+    /// Forces `tables` to be joined in the given order.
+    pub fn leading<'a>(self, tables: impl IntoIterator<Item = &'a str>) -> Self {
+        self.hint("Leading", tables)
+    }
+
+    /// Overrides the estimated number of rows of the join of `tables` with `rows`.
+    pub fn rows<'a>(self, tables: impl IntoIterator<Item = &'a str>, rows: u64) -> Self {
+        self.hint_with("Rows", tables, &format!("#{rows}"))
+    }
+
+    /// Multiplies the estimated number of rows of the join of `tables` by `factor`.
+    pub fn rows_multiplied<'a>(
+        self,
+        tables: impl IntoIterator<Item = &'a str>,
+        factor: f64,
+    ) -> Self {
+        self.hint_with("Rows", tables, &format!("*{factor}"))
+    }
+
+    /// Sets the number of parallel workers for scanning `table`. With `hard`,
+    /// the planner is forced to use exactly that many.
+    pub fn parallel(self, table: &str, workers: u32, hard: bool) -> Self {
+        let strength = if hard { "hard" } else { "soft" };
+        self.hint_with("Parallel", [table], &format!("{workers} {strength}"))
+    }
+
+    /// Sets the run-time parameter `name` to `value` while planning.
+    pub fn set(self, name: &str, value: &str) -> Self {
+        self.hint("Set", [name, value])
+    }
+
+    /// Adds a hint written by hand, e.g. `"Memoize(a b)"`, for hints not covered
+    /// by the other methods. It is not checked or quoted.
+    pub fn raw(mut self, hint: impl Into<String>) -> Self {
+        self.hints.push(hint.into());
+        self
+    }
+
+    /// Returns `true` if no hints were added.
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    /// Returns the hint comment, e.g. `/*+ SeqScan(users) */`, or an empty string
+    /// if no hints were added.
+    pub fn to_comment(&self) -> String {
+        if self.hints.is_empty() {
+            return String::new();
+        }
+        format!("/*+ {} */", self.hints.join(" "))
+    }
+
+    fn hint<'a>(self, name: &str, args: impl IntoIterator<Item = &'a str>) -> Self {
+        self.hint_with(name, args, "")
+    }
+
+    fn hint_with<'a>(
+        mut self,
+        name: &str,
+        args: impl IntoIterator<Item = &'a str>,
+        suffix: &str,
+    ) -> Self {
+        let mut args: Vec<String> = args.into_iter().map(quote).collect();
+        if !suffix.is_empty() {
+            args.push(suffix.to_owned());
+        }
+        self.hints.push(format!("{name}({})", args.join(" ")));
+        self
+    }
+}
+
+/// Quotes `identifier` unless it consists of lowercase letters, digits and
+/// underscores only.
+fn quote(identifier: &str) -> String {
+    let plain = identifier.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && identifier
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if plain {
+        identifier.to_owned()
+    } else {
+        format!("\"{}\"", identifier.replace('"', "\"\""))
+    }
+}
@@ -8,9 +8,50 @@
 //! # Features
 //!
 //! - Wraps any Diesel query using `EXPLAIN (FORMAT JSON)`
-//! - Parses the JSON output into a typed `ExplainPlan` structure
+//! - Parses the JSON output into a typed `ExplainPlan` structure, either keeping
+//!   unknown fields or rejecting them, see [`ParseMode`]
+//! - Parses plans in the text format too, e.g. from `psql` or server logs, see
+//!   [`ExplainItem::from_text()`]
+//! - Extracts `auto_explain` plans from server log files in any log format, see the
+//!   [`logscan`] module, and imports plans shared through pev2, see the [`pev2`] module
 //! - Compatible with Diesel's `QueryDsl` and `RunQueryDsl`
-//! - Deserialization errors are reported as standard Diesel errors
+//! - Supports `EXPLAIN` options such as `ANALYZE` and `BUFFERS`, see [`ExplainOptions`]
+//! - Explains raw SQL strings with [`explain_raw()`], and many queries at once with
+//!   [`explain_batch()`]
+//! - Errors are reported as [`ExplainError`], which converts into standard Diesel errors
+//! - Compares plans with and without pg_hint_plan hints, see the [`hints`] module
+//! - Renders plan trees as indented text, Markdown tables or Graphviz graphs, also
+//!   straight from `EXPLAIN` output, see the [`render`] module
+//! - Passes captured plans to pluggable destinations, see the [`sink`] module, and
+//!   processes them on a background thread, see the [`collector`] module
+//! - Parses CockroachDB's `EXPLAIN` output into the same types on a best-effort
+//!   basis, see the [`cockroach`] module
+//! - Parses huge plans node by node with bounded memory, see the [`stream`] module
+//! - Stores plan trees in a flat, index-linked arena, see the [`arena`] module
+//! - Exports plan nodes as CSV rows for spreadsheets, see the [`export`] module
+//! - Aggregates statistics over many captured plans, groups them by shape and ranks
+//!   the most expensive relations and indexes, see the [`corpus`] module
+//! - Aggregates plan statistics for Prometheus, see the [`prometheus`] module
+//! - Links plans to `pg_stat_statements` statistics, see the [`statements`] module
+//! - Attaches current table and index sizes from the catalogs to plan nodes and
+//!   explains row misestimates with column statistics, see the [`enrich`] module
+//! - Repeats `EXPLAIN ANALYZE` and aggregates the timings of each node, see
+//!   [`Explain::explain_analyze_n()`]
+//! - Checks whether the planner keeps choosing the same plan for a query with
+//!   different bind values, see [`explain_stability()`] and the [`stability`] module
+//! - Converts planner costs into rough wall-clock estimates, see the [`estimate`] module
+//! - Checks partition pruning, buffer cache hit ratios, parallel efficiency, memory
+//!   usage, trigger overhead and other aspects of plans,
+//!   see the [`analysis`] module
+//! - Summarizes plans as flat JSON objects for log pipelines, see
+//!   [`ExplainPlan::to_log_record()`]
+//! - Parses conditions such as `Filter` or `Index Cond` into expression trees, see
+//!   the [`expr`] module
+//! - Flags common plan problems, such as work areas spilling to disk, with
+//!   concrete fixes, see the [`lint`] module
+//! - Takes process-wide defaults from an installed [`ExplainConfig`], optionally
+//!   read from environment variables, and per-connection defaults, see
+//!   [`PgConnectionExplainExt`]
 //!
 //! # Example
 //!
@@ -30,8 +71,8 @@
 //! This crate is best used in development tooling, diagnostics dashboards,
 //! or CLI utilities where understanding PostgreSQL query plans is helpful.
 //!
-//! Note: this does not run the actual query — it only asks PostgreSQL to
-//! generate and return the execution plan.
+//! Note: unless [`ExplainOptions::analyze`] is enabled, this does not run the actual
+//! query — it only asks PostgreSQL to generate and return the execution plan.
 //!
 //! # See also
 //!
@@ -39,131 +80,927 @@
 //!
 //! # Crate Features
 //!
-//! Currently no optional features. May add feature gates for serde or Diesel version in the future.
+//! The Diesel integration — [`Explain`], [`ExplainConnection`], [`ExplainSession`]
+//! and the features building on them — is enabled by the default `diesel-2`
+//! feature. Without default features, the crate only parses, analyzes and renders
+//! plans, e.g. ones read from files or obtained through another driver, and
+//! doesn't depend on Diesel. Future major versions of Diesel will be supported
+//! through their own features, such as `diesel-3`, sharing the same parsing layer.
+//!
+//! - `arrow`: converts the nodes of many plans into an Arrow record batch, see
+//!   [`export::to_record_batch()`].
+//! - `async`: adds [`Explain::explain_async()`] for `diesel_async::AsyncPgConnection`.
+//! - `auto-explain`: adds a connection wrapper capturing the plans of all queries
+//!   run through it, see the [`auto`] module. Enables Diesel's
+//!   `i-implement-a-third-party-backend-and-opt-into-breaking-changes` feature.
+//! - `bb8`: adds [`Explain::explain_bb8()`] for `bb8` pools of `AsyncPgConnection`s;
+//!   implies `async`.
+//! - `cli`: builds the `diesel-pg-explain` binary, which explains SQL statements
+//!   given as arguments or read from files, or renders `EXPLAIN` output read from
+//!   stdin, and adds
+//!   the [`audit`] module for project-specific `cargo pg-explain` tools.
+//! - `deadpool`: adds [`Explain::explain_pooled()`] for `deadpool_diesel` pools.
+//! - `diesel-2` (default): explains Diesel 2 queries, see [`Explain`]. Implied by
+//!   all features which explain queries, such as `async`, `log` or `tracing`.
+//! - `fixtures`: bundles a corpus of real `EXPLAIN` outputs from PostgreSQL 12–17,
+//!   see the [`fixtures`] module.
+//! - `hypopg`: explains queries with hypothetical indexes created by the HypoPG
+//!   extension, see the [`whatif`] module.
+//! - `log`: adds [`Explain::maybe_log_plan()`], which logs rendered plans exceeding a
+//!   cost or execution time threshold.
+//! - `metrics`: reports captured plans through the `metrics` crate, see the
+//!   [`metrics`] module.
+//! - `opentelemetry`: converts plans into OpenTelemetry span attributes, see the
+//!   [`opentelemetry`] module.
+//! - `parquet`: writes the nodes of many plans to Parquet files, see
+//!   [`export::to_parquet()`]; implies `arrow`.
+//! - `proptest`: implements `proptest::arbitrary::Arbitrary` for [`ExplainPlan`],
+//!   generating random but structurally valid plan trees.
+//! - `r2d2`: enables Diesel's `r2d2` support; connections checked out of an `r2d2`
+//!   pool can be passed to [`Explain::explain()`] like any other connection.
+//! - `schemars`: derives `schemars::JsonSchema` for [`ExplainPlan`], [`ExplainItem`],
+//!   [`ExplainReport`], [`ExplainOptions`], [`lint::Finding`], [`WorkerDetail`],
+//!   [`Jit`], [`JitTiming`] and [`Trigger`], describing the JSON they are
+//!   serialized to or parsed from.
+//! - `sentry`: attaches plan summaries and JSON to Sentry events, see the
+//!   [`sentry`] module.
+//! - `simd-json`: parses `EXPLAIN` output with `simd-json` instead of `serde_json`,
+//!   which is faster for multi-megabyte plans.
+//! - `slog`: logs plans as structured `slog` fields, see the [`slog`] module.
+//! - `tracing`: adds [`Explain::explain_traced()`], which records plan summaries on a
+//!   `tracing` span.
+
+#[cfg(feature = "diesel-2")]
+use std::time::Duration;
+use std::time::SystemTime;
 
-use diesel::pg::{Pg, PgConnection};
+#[cfg(feature = "diesel-2")]
+use diesel::connection::{LoadConnection, TransactionManager};
+#[cfg(feature = "diesel-2")]
+use diesel::pg::Pg;
+#[cfg(feature = "diesel-2")]
 use diesel::prelude::*;
+#[cfg(feature = "diesel-2")]
 use diesel::query_builder::*;
+#[cfg(feature = "diesel-2")]
 use diesel::query_dsl::methods::LoadQuery;
+#[cfg(feature = "diesel-2")]
+use diesel::sql_types::Text;
 use serde::{Deserialize, Serialize};
+mod borrowed;
+mod config;
+#[cfg(feature = "diesel-2")]
+mod defaults;
+mod error;
+mod logged;
+mod options;
+mod plan;
+#[cfg(feature = "diesel-2")]
+mod session;
 
-/// Recursive struct which describes the plan of a query
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ExplainPlan {
-    /// The type of the plan node (e.g., "Seq Scan", "Nested Loop", "Hash Join").
-    /// Indicates the operation performed at this step in the query execution plan.
-    #[serde(rename = "Node Type")]
-    pub node_type: String,
-
-    /// The relationship of this node to its parent in the plan tree.
-    /// Common values include:
-    /// - "Outer": This node is the outer input to a join (e.g., Nested Loop).
-    /// - "Inner": This node is the inner input to a join.
-    /// - "Subquery": This node is part of a subquery.
-    /// - "InitPlan", "SubPlan", "Member": Special plan node roles.
-    ///
-    /// May be `None` for root nodes or when not applicable.
-    #[serde(rename = "Parent Relationship", default)]
-    pub parent_relationship: Option<String>,
-
-    /// Indicates whether the plan node is aware of parallel query execution.
-    /// If true, the node may participate in or benefit from parallelism.
-    #[serde(rename = "Parallel Aware")]
-    pub parallel_aware: bool,
-
-    /// Indicates whether the node supports asynchronous execution.
-    /// Async-capable nodes can execute operations concurrently with others,
-    /// improving performance in some plans (especially with I/O or remote sources).
-    #[serde(rename = "Async Capable")]
-    pub async_capable: bool,
-
-    /// The estimated cost of starting this plan node.
-    /// This typically includes one-time setup costs, like initializing data structures.
-    #[serde(rename = "Startup Cost")]
-    pub startup_cost: f64,
-
-    /// The estimated total cost of fully executing this plan node,
-    /// including startup and all tuple processing.
-    #[serde(rename = "Total Cost")]
-    pub total_cost: f64,
-
-    /// The estimated number of rows this plan node will output.
-    /// This is a planner estimate, not an actual runtime value.
-    #[serde(rename = "Plan Rows")]
-    pub plan_rows: u64,
-
-    /// The estimated average width (in bytes) of each row produced by this node.
-    /// Useful for understanding memory and I/O implications.
-    #[serde(rename = "Plan Width")]
-    pub plan_width: u64,
-
-    /// Child plan nodes that this node depends on or drives.
-    /// For example, a join node will typically have two child plans (inner and outer).
-    #[serde(rename = "Plans", default)]
-    pub plans: Vec<ExplainPlan>,
-}
+pub use borrowed::ExplainPlanRef;
+pub use config::ExplainConfig;
+#[cfg(feature = "diesel-2")]
+pub use defaults::PgConnectionExplainExt;
+pub use error::ExplainError;
+#[cfg(feature = "diesel-2")]
+pub use generic::GenericPlanComparison;
+#[cfg(feature = "diesel-2")]
+use hints::Hints;
+pub use logged::LoggedPlan;
+pub use options::ExplainOptions;
+pub(crate) use plan::{deserialize, parse_items};
+pub use plan::{ExplainItem, ExplainPlan, Jit, JitTiming, ParseMode, Trigger, WorkerDetail};
+#[cfg(feature = "diesel-2")]
+pub use session::ExplainSession;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ExplainItem {
-    #[serde(rename = "Plan")]
-    pub plan: ExplainPlan,
-}
+pub mod analysis;
+#[cfg(feature = "proptest")]
+mod arbitrary;
+pub mod arena;
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "cli")]
+pub mod audit;
+#[cfg(feature = "auto-explain")]
+pub mod auto;
+#[cfg(feature = "bb8")]
+pub mod bb8;
+pub mod cockroach;
+pub mod collector;
+pub mod corpus;
+#[cfg(feature = "deadpool")]
+pub mod deadpool;
+pub mod diff;
+#[cfg(feature = "diesel-2")]
+pub mod enrich;
+pub mod estimate;
+pub mod export;
+pub mod expr;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+#[cfg(feature = "diesel-2")]
+mod generic;
+pub mod hints;
+pub mod lint;
+#[cfg(feature = "log")]
+pub mod log;
+pub mod logscan;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "opentelemetry")]
+pub mod opentelemetry;
+pub mod pev2;
+pub mod prometheus;
+pub mod render;
+pub mod repeat;
+#[cfg(feature = "sentry")]
+pub mod sentry;
+pub mod sink;
+#[cfg(feature = "slog")]
+pub mod slog;
+pub mod stability;
+pub mod statements;
+pub mod stream;
+#[cfg(test)]
+mod testing;
+mod text;
+#[cfg(feature = "tracing")]
+mod tracing;
+#[cfg(feature = "hypopg")]
+pub mod whatif;
 
 /// A wrapper around a Diesel query that transforms it into an
 /// `EXPLAIN (FORMAT JSON)` query.
 ///
-/// Use this type to inspect the query execution plan without running the query.
+/// Use this type to inspect the query execution plan without running the query,
+/// unless `ANALYZE` is requested through [`Explain::options()`].
 ///
 /// Example:
 /// ```rust
 /// let plan = my_query.wrap_explain().explain(&mut conn)?;
 /// println!("{:#?}", plan);
 /// ```
-#[derive(Clone, Copy, QueryId)]
-pub struct Explain<Q>(pub Q);
+#[cfg(feature = "diesel-2")]
+#[derive(Clone)]
+pub struct Explain<Q> {
+    query: Q,
+    options: ExplainOptions,
+    /// Whether `options` were chosen by the caller, rather than taken from the
+    /// [`ExplainConfig`], so that connection defaults don't replace them.
+    explicit_options: bool,
+    mode: ParseMode,
+    /// Run-time parameters applied with `SET LOCAL` while explaining.
+    settings: Vec<(String, String)>,
+    /// `lock_timeout` applied with `ANALYZE`, see [`Explain::lock_timeout()`].
+    lock_timeout: Option<Duration>,
+    hints: Hints,
+}
+
+// The SQL depends on the options chosen at runtime, so the query must not be
+// cached by its type alone.
+#[cfg(feature = "diesel-2")]
+impl<Q> QueryId for Explain<Q> {
+    type QueryId = ();
 
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(feature = "diesel-2")]
 impl<Q> QueryFragment<Pg> for Explain<Q>
 where
     Q: QueryFragment<Pg>,
 {
     fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> diesel::result::QueryResult<()> {
-        out.push_sql("EXPLAIN (FORMAT JSON) ");
-        self.0.walk_ast(out.reborrow())?;
+        if !self.hints.is_empty() {
+            out.push_sql(&self.hints.to_comment());
+            out.push_sql(" ");
+        }
+        out.push_sql(&self.options.to_sql());
+        self.query.walk_ast(out.reborrow())?;
         Ok(())
     }
 }
 
-impl<Q: Query> Query for Explain<Q> {
+// `EXPLAIN` returns text whatever the wrapped statement returns, so `Q` only
+// needs to be a `QueryFragment`. This lets `sql_query`, with or without binds,
+// be explained as well.
+#[cfg(feature = "diesel-2")]
+impl<Q> Query for Explain<Q> {
     type SqlType = diesel::sql_types::Text;
 }
 
-impl<Q> RunQueryDsl<PgConnection> for Explain<Q> {}
+#[cfg(feature = "diesel-2")]
+impl<Q, C> RunQueryDsl<C> for Explain<Q> {}
+
+/// Connection types which can run [`Explain`] queries: any Diesel connection to
+/// PostgreSQL, such as [`PgConnection`], a connection checked out of an `r2d2` pool,
+/// or a wrapper adding instrumentation.
+///
+/// Implemented automatically for every such connection.
+#[cfg(feature = "diesel-2")]
+pub trait ExplainConnection: Connection<Backend = Pg> + LoadConnection {}
+
+#[cfg(feature = "diesel-2")]
+impl<C> ExplainConnection for C where C: Connection<Backend = Pg> + LoadConnection {}
+
+/// Same as [`Explain::new()`], replacing the tuple constructor `Explain(query)`
+/// of 1.x.
+#[cfg(feature = "diesel-2")]
+impl<Q> From<Q> for Explain<Q> {
+    fn from(query: Q) -> Self {
+        Self::new(query)
+    }
+}
 
+#[cfg(feature = "diesel-2")]
 impl<Q> Explain<Q> {
+    /// Wraps `query`, with the options and parse mode of the installed
+    /// [`ExplainConfig`]: by default PostgreSQL's default options and
+    /// [`ParseMode::Lenient`]. Defaults set on the connection with
+    /// [`PgConnectionExplainExt`] replace the configured options.
+    pub fn new(query: Q) -> Self {
+        let config = ExplainConfig::global();
+        Explain {
+            query,
+            options: config.options,
+            explicit_options: false,
+            mode: config.parse_mode,
+            settings: Vec::new(),
+            lock_timeout: config.lock_timeout,
+            hints: Hints::new(),
+        }
+    }
+
+    /// Returns the wrapped query.
+    pub fn query(&self) -> &Q {
+        &self.query
+    }
+
+    /// Unwraps the query, replacing the public field of `Explain` up to 1.x.
+    pub fn into_query(self) -> Q {
+        self.query
+    }
+
+    /// Sets the options passed to `EXPLAIN`, instead of the defaults of the
+    /// connection or of the installed [`ExplainConfig`].
+    pub fn options(mut self, options: ExplainOptions) -> Self {
+        self.options = options;
+        self.explicit_options = true;
+        self
+    }
+
+    /// Sets how fields of the `EXPLAIN` output unknown to [`ExplainPlan`] are handled.
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Shorthand for `.parse_mode(ParseMode::Strict)`.
+    pub fn strict(self) -> Self {
+        self.parse_mode(ParseMode::Strict)
+    }
+
+    /// Prefixes the statement with pg_hint_plan hints, see the [`hints`] module.
+    pub fn hints(mut self, hints: Hints) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    /// Sets the run-time parameter `name` to `value` while explaining, e.g.
+    /// `.setting("enable_seqscan", "off")`.
+    ///
+    /// The parameter is applied with `SET LOCAL` inside a transaction (or savepoint)
+    /// which is rolled back afterwards, so the connection's settings are unaffected.
+    pub fn setting(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.settings.push((name.into(), value.into()));
+        self
+    }
+
+    /// Explains the query as `role`, so the plan reflects the row-level security
+    /// policies and privileges of that role rather than those of the connecting user.
+    ///
+    /// Same as `SET LOCAL ROLE`, undone after explaining; the connecting user must
+    /// be a member of `role`.
+    pub fn role(self, role: impl Into<String>) -> Self {
+        self.setting("role", role)
+    }
+
+    /// Resolves unqualified names in the query against `schemas`, in order, e.g. to
+    /// explain queries of a schema-per-tenant database for a particular tenant.
+    ///
+    /// Same as `SET LOCAL search_path`, undone after explaining. Schema names are
+    /// quoted, so they are matched exactly, including case.
+    pub fn search_path<I, S>(self, schemas: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let path = schemas
+            .into_iter()
+            .map(|schema| format!("\"{}\"", schema.as_ref().replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.setting("search_path", path)
+    }
+
+    /// Cancels the `EXPLAIN` if it runs longer than `timeout`, reporting
+    /// [`ExplainError::Cancelled`]. Meant for `ANALYZE`, which executes the statement.
+    ///
+    /// The timeout is applied with `SET LOCAL statement_timeout` inside the
+    /// transaction (or savepoint) the `EXPLAIN` runs in, so the previous value is
+    /// restored afterwards. Sub-millisecond timeouts are rounded up to one millisecond.
+    pub fn statement_timeout(self, timeout: Duration) -> Self {
+        let millis = timeout.as_millis().max(1);
+        self.setting("statement_timeout", format!("{millis}ms"))
+    }
+
+    /// Cancels an `ANALYZE` which waits longer than `timeout` for a lock, reporting
+    /// [`ExplainError::Cancelled`], instead of the [`ExplainConfig::lock_timeout`].
+    /// `None` lets it wait as long as the connection's `lock_timeout` allows.
+    ///
+    /// `UPDATE` and `DELETE` wait for the row locks of concurrent transactions, and
+    /// any statement queues behind a pending `ALTER TABLE`, so analyzing them in
+    /// production could block, and block others, for as long as the lock is held.
+    /// The timeout is applied with `SET LOCAL lock_timeout` inside the transaction
+    /// (or savepoint) which is rolled back after the `ANALYZE`, before the settings
+    /// of [`Explain::setting()`], which can override it. Sub-millisecond timeouts are
+    /// rounded up to one millisecond.
+    pub fn lock_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
     /// Executes the wrapped query using `EXPLAIN (FORMAT JSON)`, parses the result,
     /// and returns a structured `ExplainPlan` that represents the root of the query plan tree.
     ///
+    /// Any statement can be explained, including `INSERT`, `UPDATE` and `DELETE`.
+    /// With [`ExplainOptions::analyze`] the statement is executed inside a transaction
+    /// (or a savepoint, if one is already open) which is always rolled back.
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Diesel`] if the query fails, or a more specific variant
+    /// such as [`ExplainError::UndefinedRelation`] for common planning errors,
+    /// [`ExplainError::EmptyResult`]
+    /// if PostgreSQL returns no plan, [`ExplainError::MultipleResults`] if it returns
+    /// more than one plan (use [`Explain::explain_all()`] for such queries), and
+    /// [`ExplainError::Parse`] if the JSON returned by PostgreSQL cannot be parsed
+    /// into an `ExplainPlan`. In [`ParseMode::Strict`], [`ExplainError::UnknownFields`]
+    /// is returned if the output contains fields `ExplainPlan` does not model.
+    pub fn explain<'a, C>(self, conn: &mut C) -> Result<ExplainPlan, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let mode = self.mode;
+        let rows = self.load_rows(conn)?;
+        plan_from_rows(rows, mode)
+    }
+
+    /// Shorthand for explaining with [`ExplainOptions::analyze`] enabled on top of
+    /// the configured options: runs the statement and returns the plan with actual
+    /// row counts and times.
+    ///
+    /// The statement always runs in a transaction (or a savepoint) which is rolled
+    /// back afterwards, so analyzing DML never leaves changes behind.
+    ///
+    /// Example:
+    /// ```rust
+    /// let delete = diesel::delete(users::table.filter(users::age.lt(18)));
+    /// let plan = delete.wrap_explain().explain_analyze(&mut conn)?;
+    /// println!("{:?} rows affected", plan.plans[0].actual_rows);
+    /// ```
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn explain_analyze<'a, C>(mut self, conn: &mut C) -> Result<ExplainPlan, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        self = self.with_connection_defaults(conn)?;
+        self.options = self.options.analyze(true);
+        self.explain(conn)
+    }
+
+    /// Runs `EXPLAIN ANALYZE` `n` times, each in a transaction which is rolled
+    /// back, and returns the minimum, median and maximum actual time of each node,
+    /// smoothing out cache effects and noise, see [`repeat::summarize()`].
+    ///
+    /// Example:
+    /// ```rust
+    /// let runs = query.wrap_explain().explain_analyze_n(&mut conn, 5)?;
+    /// if let Some(total) = runs.total() {
+    ///     println!("{:.3} ms (min {:.3}, max {:.3})", total.median, total.min, total.max);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain_analyze()`], and [`ExplainError::EmptyResult`] if
+    /// `n` is zero.
+    pub fn explain_analyze_n<'a, C>(
+        self,
+        conn: &mut C,
+        n: usize,
+    ) -> Result<repeat::RepeatedAnalyze, ExplainError>
+    where
+        C: ExplainConnection,
+        Q: Clone,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let plans = (0..n)
+            .map(|_| self.clone().explain_analyze(conn))
+            .collect::<Result<Vec<_>, _>>()?;
+        repeat::summarize(plans).ok_or(ExplainError::EmptyResult)
+    }
+
+    /// Explains the query as the planner would plan it with the given run-time
+    /// parameters, without changing them for the connection. See [`Explain::setting()`].
+    ///
+    /// Example:
+    /// ```rust
+    /// let plan = query
+    ///     .wrap_explain()
+    ///     .explain_with_settings(&mut conn, &[("enable_seqscan", "off"), ("work_mem", "256MB")])?;
+    /// ```
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`]. Unknown parameters or invalid values are
+    /// reported as [`ExplainError::Diesel`].
+    pub fn explain_with_settings<'a, C>(
+        mut self,
+        conn: &mut C,
+        settings: &[(&str, &str)],
+    ) -> Result<ExplainPlan, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        for (name, value) in settings {
+            self = self.setting(*name, *value);
+        }
+        self.explain(conn)
+    }
+
+    /// Same as [`Explain::explain()`], but returns every plan PostgreSQL reports,
+    /// in order. Use this when the wrapped fragment may produce more than one
+    /// plan, e.g. for statements rewritten into several queries by rules.
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`], except that several plans are not an error.
+    pub fn explain_all<'a, C>(self, conn: &mut C) -> Result<Vec<ExplainPlan>, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let mode = self.mode;
+        let rows = self.load_rows(conn)?;
+        plans_from_rows(rows, mode)
+    }
+
+    /// Same as [`Explain::explain()`], but also returns the JSON document exactly as
+    /// PostgreSQL produced it, so it can be stored or re-parsed later, e.g. to read
+    /// fields [`ExplainPlan`] does not model.
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn explain_with_raw<'a, C>(self, conn: &mut C) -> Result<ExplainResult, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let mode = self.mode;
+        let raw_json = json_from_rows(self.load_rows(conn)?)?;
+        let item = single_item(parse_items(&raw_json, mode)?)?;
+        Ok(ExplainResult {
+            plan: item.plan,
+            query_identifier: item.query_identifier,
+            raw_json,
+        })
+    }
+
+    /// Same as [`Explain::explain()`], but returns the whole [`ExplainItem`], including
+    /// summary sections such as planning and execution times, triggers and JIT.
+    ///
+    /// Example:
+    /// ```rust
+    /// let item = query
+    ///     .wrap_explain()
+    ///     .options(ExplainOptions::default().analyze(true))
+    ///     .explain_item(&mut conn)?;
+    /// if let Some(jit) = item.jit()? {
+    ///     println!("{} functions compiled", jit.functions);
+    /// }
+    /// ```
+    ///
     /// # Errors
-    /// Returns a `diesel::result::Error::DeserializationError` if the JSON returned
-    /// by PostgreSQL cannot be parsed into an `ExplainPlan`.
-    pub fn explain<'a>(self, conn: &mut PgConnection) -> QueryResult<ExplainPlan>
+    /// Same as [`Explain::explain()`].
+    pub fn explain_item<'a, C>(self, conn: &mut C) -> Result<ExplainItem, ExplainError>
     where
-        Self: LoadQuery<'a, PgConnection, String>,
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
     {
-        let r = self.load::<String>(conn)?.into_iter().next().unwrap();
+        let mode = self.mode;
+        let json = json_from_rows(self.load_rows(conn)?)?;
+        single_item(parse_items(&json, mode)?)
+    }
+
+    /// Runs the `EXPLAIN` and returns the JSON document without parsing it, e.g. to
+    /// store it or to parse it with [`ExplainPlanRef::parse()`] without copying strings.
+    ///
+    /// Example:
+    /// ```rust
+    /// let json = query.wrap_explain().explain_json(&mut conn)?;
+    /// let plan = ExplainPlanRef::parse(&json)?;
+    /// println!("{} {}", plan.node_type, plan.total_cost);
+    /// ```
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`], except that the output is not parsed.
+    pub fn explain_json<'a, C>(self, conn: &mut C) -> Result<String, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        json_from_rows(self.load_rows(conn)?)
+    }
+
+    /// Same as [`Explain::explain()`], but also returns the SQL Diesel generated for
+    /// the wrapped query, together with its bind values, and what the plan was
+    /// captured with: the server version, the time and the `EXPLAIN` options.
+    ///
+    /// Example:
+    /// ```rust
+    /// let report = query.wrap_explain().explain_report(&mut conn)?;
+    /// println!("PostgreSQL {}: {}", report.server_version, report.sql);
+    /// println!("{}", render::tree(&report.plan));
+    /// ```
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn explain_report<'a, C>(self, conn: &mut C) -> Result<ExplainReport, ExplainError>
+    where
+        C: ExplainConnection,
+        Q: QueryFragment<Pg>,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let sql = diesel::debug_query::<Pg, _>(&self.query).to_string();
+        let server = diesel::sql_query(SERVER_VERSION_SQL).get_result::<ServerVersion>(conn)?;
+        let explain = self.with_connection_defaults(conn)?;
+        let options = explain.options;
+        let captured_at = SystemTime::now();
+        let item = explain.explain_item(conn)?;
+        Ok(ExplainReport {
+            sql,
+            server_version: server.version,
+            server_version_num: server.version_num,
+            captured_at,
+            options,
+            query_identifier: item.query_identifier,
+            statement_stats: None,
+            findings: lint::check(&item.plan),
+            plan: item.plan,
+        })
+    }
+
+    /// Runs the `EXPLAIN` query and returns its rows. With `ANALYZE` the statement
+    /// is actually executed, so it runs in a transaction (or a savepoint, inside an
+    /// existing one) which is always rolled back, discarding any changes made by DML.
+    pub(crate) fn load_rows<'a, C>(self, conn: &mut C) -> Result<Vec<String>, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let explain = self.with_connection_defaults(conn)?;
+        if !explain.needs_transaction() {
+            return Ok(explain.load::<String>(conn)?);
+        }
+        C::TransactionManager::begin_transaction(conn)?;
+        let rows =
+            set_local(conn, &explain.local_settings()).and_then(|()| explain.load::<String>(conn));
+        C::TransactionManager::rollback_transaction(conn)?;
+        Ok(rows?)
+    }
+
+    /// Whether the `EXPLAIN` must run in a transaction which is rolled back afterwards:
+    /// with `ANALYZE`, to discard the effects of the statement, and with settings,
+    /// to restore their previous values.
+    pub(crate) fn needs_transaction(&self) -> bool {
+        self.options.analyze || !self.settings.is_empty()
+    }
+
+    /// Returns the run-time parameters to apply with `SET LOCAL`: the
+    /// [`Explain::lock_timeout()`] guard with `ANALYZE`, then the
+    /// [`Explain::setting()`]s.
+    pub(crate) fn local_settings(&self) -> Vec<(String, String)> {
+        let guard = self
+            .lock_timeout
+            .filter(|_| self.options.analyze)
+            .map(|timeout| {
+                let millis = timeout.as_millis().max(1);
+                ("lock_timeout".to_owned(), format!("{millis}ms"))
+            });
+        guard.into_iter().chain(self.settings.clone()).collect()
+    }
+}
 
-        let r: Vec<ExplainItem> = serde_json::from_str(&r).map_err(|e: serde_json::Error| {
-            diesel::result::Error::DeserializationError(Box::new(e))
-        })?;
-        let r = r.into_iter().next().unwrap().plan;
-        Ok(r)
+/// SQL applying a run-time parameter until the end of the current transaction,
+/// same as `SET LOCAL`, but with the name and value passed as bind parameters.
+#[cfg(feature = "diesel-2")]
+pub(crate) const SET_LOCAL_SQL: &str = "SELECT set_config($1, $2, true)";
+
+/// Applies `settings` with `SET LOCAL` semantics.
+#[cfg(feature = "diesel-2")]
+fn set_local<C: ExplainConnection>(conn: &mut C, settings: &[(String, String)]) -> QueryResult<()> {
+    for (name, value) in settings {
+        diesel::sql_query(SET_LOCAL_SQL)
+            .bind::<Text, _>(name)
+            .bind::<Text, _>(value)
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Explains a raw SQL statement, e.g. one built with `sql_query` or read from a file.
+///
+/// `sql` must be a single statement without bind parameters; trailing semicolons
+/// and whitespace are stripped. It is sent as a prepared statement, so PostgreSQL rejects input
+/// containing several statements instead of executing them.
+///
+/// Example:
+/// ```rust
+/// let plan = explain_raw(&mut conn, "SELECT * FROM users WHERE age > 30", ExplainOptions::default())?;
+/// ```
+///
+/// # Errors
+/// Same as [`Explain::explain()`].
+#[cfg(feature = "diesel-2")]
+pub fn explain_raw<C>(
+    conn: &mut C,
+    sql: &str,
+    options: ExplainOptions,
+) -> Result<ExplainPlan, ExplainError>
+where
+    C: ExplainConnection,
+{
+    Explain::new(RawSql::statement(sql))
+        .options(options)
+        .explain(conn)
+}
+
+/// Explains many queries in a row over a single connection and transaction.
+///
+/// Each query is explained in its own savepoint, so a failing query doesn't
+/// prevent the remaining ones from being explained, and the transaction is
+/// rolled back at the end, discarding changes made with [`ExplainOptions::analyze`].
+/// Queries of different types can be passed as `Box<dyn QueryFragment<Pg>>`.
+///
+/// Without `options`, the queries are explained with the defaults of the
+/// connection, see [`PgConnectionExplainExt`], or of the installed
+/// [`ExplainConfig`].
+///
+/// Example:
+/// ```rust
+/// let queries: Vec<Box<dyn QueryFragment<Pg>>> = vec![
+///     Box::new(users::table.select(users::id)),
+///     Box::new(posts::table.filter(posts::user_id.eq(1)).select(posts::id)),
+/// ];
+/// for plan in explain_batch(&mut conn, queries, Some(ExplainOptions::default()))? {
+///     println!("{}", plan?.total_cost);
+/// }
+/// ```
+///
+/// # Errors
+/// The outer `Result` fails with [`ExplainError::Diesel`] if the transaction or a
+/// savepoint cannot be managed. Failures of individual queries are reported in
+/// the returned vector, in the same order as the queries, as described in
+/// [`Explain::explain()`].
+#[cfg(feature = "diesel-2")]
+pub fn explain_batch<C, I>(
+    conn: &mut C,
+    queries: I,
+    options: Option<ExplainOptions>,
+) -> Result<Vec<Result<ExplainPlan, ExplainError>>, ExplainError>
+where
+    C: ExplainConnection,
+    I: IntoIterator,
+    I::Item: QueryFragment<Pg>,
+{
+    let options = match options {
+        Some(options) => options,
+        None => defaults::connection_options(conn)?.unwrap_or(ExplainConfig::global().options),
+    };
+    C::TransactionManager::begin_transaction(conn)?;
+    let results = explain_each(conn, queries, options);
+    C::TransactionManager::rollback_transaction(conn)?;
+    results
+}
+
+/// Explains the same statement once per item of `queries`, e.g. with different
+/// bind values, and reports whether the planner chose the same plan shape every
+/// time, see [`stability::check()`]. The queries are explained as by
+/// [`explain_batch()`].
+///
+/// Example:
+/// ```rust
+/// let queries = ["new", "paid", "shipped"]
+///     .into_iter()
+///     .map(|status| orders::table.filter(orders::status.eq(status)).select(orders::id));
+/// let stability = explain_stability(&mut conn, queries, None)?;
+/// if !stability.is_stable() {
+///     println!("the plan depends on the status: {:?}", stability.shapes);
+/// }
+/// ```
+///
+/// To repeat a query without changing it, pass `std::iter::repeat_n(&query, k)`.
+///
+/// # Errors
+/// Same as [`explain_batch()`], and the first failure of an individual query.
+#[cfg(feature = "diesel-2")]
+pub fn explain_stability<C, I>(
+    conn: &mut C,
+    queries: I,
+    options: Option<ExplainOptions>,
+) -> Result<stability::Stability, ExplainError>
+where
+    C: ExplainConnection,
+    I: IntoIterator,
+    I::Item: QueryFragment<Pg>,
+{
+    let plans = explain_batch(conn, queries, options)?
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(stability::check(plans))
+}
+
+#[cfg(feature = "diesel-2")]
+fn explain_each<C, I>(
+    conn: &mut C,
+    queries: I,
+    options: ExplainOptions,
+) -> Result<Vec<Result<ExplainPlan, ExplainError>>, ExplainError>
+where
+    C: ExplainConnection,
+    I: IntoIterator,
+    I::Item: QueryFragment<Pg>,
+{
+    let mut results = Vec::new();
+    for query in queries {
+        let explain = Explain::new(query).options(options);
+        C::TransactionManager::begin_transaction(conn)?;
+        let rows =
+            set_local(conn, &explain.local_settings()).and_then(|()| explain.load::<String>(conn));
+        C::TransactionManager::rollback_transaction(conn)?;
+        results.push(
+            rows.map_err(ExplainError::from)
+                .and_then(|rows| plan_from_rows(rows, ExplainConfig::global().parse_mode)),
+        );
+    }
+    Ok(results)
+}
+
+/// A raw SQL statement embedded into an [`Explain`] query.
+#[cfg(feature = "diesel-2")]
+#[derive(Clone, Copy)]
+struct RawSql<'a>(&'a str);
+
+#[cfg(feature = "diesel-2")]
+impl<'a> RawSql<'a> {
+    /// Wraps a statement supplied by the user, stripping trailing semicolons and
+    /// whitespace, which are not allowed inside `EXPLAIN`.
+    fn statement(sql: &'a str) -> Self {
+        Self(sql.trim_end_matches(|c: char| c == ';' || c.is_whitespace()))
+    }
+}
+
+#[cfg(feature = "diesel-2")]
+impl QueryFragment<Pg> for RawSql<'_> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> diesel::result::QueryResult<()> {
+        out.push_sql(self.0);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "diesel-2")]
+impl QueryId for RawSql<'_> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A plan together with the SQL it was produced for and how it was captured,
+/// returned by [`Explain::explain_report()`].
+///
+/// Serializable, so plans can be stored and compared later with the context
+/// needed to interpret them.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ExplainReport {
+    /// The explained statement as generated by Diesel, followed by its bind values,
+    /// e.g. `SELECT "users"."id" FROM "users" WHERE ("users"."age" > $1) -- binds: [30]`.
+    pub sql: String,
+
+    /// The root node of the plan tree.
+    pub plan: ExplainPlan,
+
+    /// The `server_version` setting, e.g. `16.2 (Debian 16.2-1.pgdg120+2)`.
+    pub server_version: String,
+
+    /// The `server_version_num` setting, e.g. `160002`, for comparing versions.
+    pub server_version_num: i32,
+
+    /// When the `EXPLAIN` was started.
+    pub captured_at: SystemTime,
+
+    /// The options the plan was captured with.
+    pub options: ExplainOptions,
+
+    /// The query identifier of the statement. Reported with
+    /// [`ExplainOptions::verbose`] when `compute_query_id` is on.
+    #[serde(default)]
+    pub query_identifier: Option<i64>,
+
+    /// Cumulative statistics of the statement from `pg_stat_statements`, filled
+    /// by [`ExplainReport::attach_statement_stats()`].
+    #[serde(default)]
+    pub statement_stats: Option<statements::StatementStats>,
+
+    /// Problems found in the plan by [`lint::check()`].
+    #[serde(default)]
+    pub findings: Vec<lint::Finding>,
+}
+
+#[cfg(feature = "diesel-2")]
+const SERVER_VERSION_SQL: &str = "SELECT current_setting('server_version') AS version, \
+     current_setting('server_version_num')::int4 AS version_num";
+
+#[cfg(feature = "diesel-2")]
+#[derive(QueryableByName)]
+struct ServerVersion {
+    #[diesel(sql_type = Text)]
+    version: String,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    version_num: i32,
+}
+
+/// A parsed plan together with the JSON it was parsed from,
+/// returned by [`Explain::explain_with_raw()`].
+#[derive(Debug)]
+pub struct ExplainResult {
+    /// The root node of the plan tree.
+    pub plan: ExplainPlan,
+
+    /// The query identifier of the statement, see [`ExplainItem::query_identifier`].
+    pub query_identifier: Option<i64>,
+
+    /// The `EXPLAIN (FORMAT JSON)` output exactly as returned by PostgreSQL.
+    pub raw_json: String,
+}
+
+/// Parses the rows returned by an `EXPLAIN (FORMAT JSON)` query into the root plan node,
+/// which must be the only one.
+#[cfg(feature = "diesel-2")]
+fn plan_from_rows(rows: Vec<String>, mode: ParseMode) -> Result<ExplainPlan, ExplainError> {
+    let items = parse_items(&json_from_rows(rows)?, mode)?;
+    Ok(single_item(items)?.plan)
+}
+
+/// Parses the rows returned by an `EXPLAIN (FORMAT JSON)` query into all reported plans.
+#[cfg(feature = "diesel-2")]
+fn plans_from_rows(rows: Vec<String>, mode: ParseMode) -> Result<Vec<ExplainPlan>, ExplainError> {
+    let mut plans = Vec::new();
+    for row in rows {
+        plans.extend(parse_items(&row, mode)?.into_iter().map(|item| item.plan));
     }
+    if plans.is_empty() {
+        return Err(ExplainError::EmptyResult);
+    }
+    Ok(plans)
+}
+
+/// Extracts the JSON document from the rows returned by an `EXPLAIN (FORMAT JSON)` query,
+/// which must consist of exactly one row.
+#[cfg(feature = "diesel-2")]
+fn json_from_rows(rows: Vec<String>) -> Result<String, ExplainError> {
+    if rows.len() > 1 {
+        return Err(ExplainError::MultipleResults { count: rows.len() });
+    }
+    rows.into_iter().next().ok_or(ExplainError::EmptyResult)
+}
+
+/// Returns the only top-level item of an `EXPLAIN (FORMAT JSON)` document.
+fn single_item<T>(items: Vec<T>) -> Result<T, ExplainError> {
+    if items.len() > 1 {
+        return Err(ExplainError::MultipleResults { count: items.len() });
+    }
+    items.into_iter().next().ok_or(ExplainError::EmptyResult)
 }
 
 /// A trait that allows any Diesel query to be wrapped
 /// in an `EXPLAIN (FORMAT JSON)` call using the [`Explain`] wrapper.
 ///
 /// This is implemented for all query types.
+#[cfg(feature = "diesel-2")]
 pub trait ExplainWrapped: Sized {
     /// Wraps the query into an `EXPLAIN` wrapper, allowing it to be analyzed
     /// using [`Explain::explain()`].
@@ -176,8 +1013,234 @@ pub trait ExplainWrapped: Sized {
     fn wrap_explain(&self) -> Explain<&Self>;
 }
 
+#[cfg(feature = "diesel-2")]
 impl<Q> ExplainWrapped for Q {
     fn wrap_explain(&self) -> Explain<&Self> {
-        Explain(self)
+        Explain::new(self)
+    }
+}
+
+#[cfg(all(test, feature = "diesel-2"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::RESULT_ITEM;
+
+    #[test]
+    fn no_rows_is_empty_result() {
+        let err = plan_from_rows(Vec::new(), ParseMode::Lenient).unwrap_err();
+        assert!(matches!(err, ExplainError::EmptyResult));
+    }
+
+    #[test]
+    fn empty_json_array_is_empty_result() {
+        let err = plan_from_rows(vec!["[]".to_owned()], ParseMode::Lenient).unwrap_err();
+        assert!(matches!(err, ExplainError::EmptyResult));
+    }
+
+    #[test]
+    fn several_plans_are_multiple_results() {
+        let two = format!("[{RESULT_ITEM}, {RESULT_ITEM}]");
+
+        let err = plan_from_rows(vec![two.clone()], ParseMode::Lenient).unwrap_err();
+        assert!(matches!(err, ExplainError::MultipleResults { count: 2 }));
+        assert_eq!(
+            plans_from_rows(vec![two], ParseMode::Lenient)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn analyze_is_guarded_by_lock_timeout() {
+        let explain = Explain::new(diesel::sql_query("DELETE FROM users"))
+            .lock_timeout(Some(Duration::from_millis(500)))
+            .setting("work_mem", "64MB");
+        assert_eq!(
+            explain.local_settings(),
+            vec![("work_mem".to_owned(), "64MB".to_owned())]
+        );
+
+        let explain = explain.options(ExplainOptions::default().analyze(true));
+        assert_eq!(
+            explain.local_settings(),
+            vec![
+                ("lock_timeout".to_owned(), "500ms".to_owned()),
+                ("work_mem".to_owned(), "64MB".to_owned()),
+            ]
+        );
+        assert_eq!(explain.lock_timeout(None).local_settings().len(), 1);
+    }
+
+    #[test]
+    fn queries_are_wrapped_and_unwrapped() {
+        let explain = Explain::from("SELECT 1");
+        assert_eq!(*explain.query(), "SELECT 1");
+        assert_eq!(explain.into_query(), "SELECT 1");
+    }
+
+    #[test]
+    fn unknown_fields_are_collected_or_rejected() {
+        let json = RESULT_ITEM.replace("\"Plan Width\": 4", "\"Plan Width\": 4, \"Memory\": 1");
+        let rows = vec![format!("[{json}]")];
+
+        let plan = plan_from_rows(rows.clone(), ParseMode::Lenient).unwrap();
+        assert_eq!(plan.extra["Memory"], 1);
+
+        match plan_from_rows(rows, ParseMode::Strict).unwrap_err() {
+            ExplainError::UnknownFields { fields } => assert_eq!(fields, ["[0].Plan.Memory"]),
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn parse_errors_report_path_and_snippet() {
+        let json = RESULT_ITEM.replace("\"Plan Rows\": 1", "\"Plan Rows\": \"many\"");
+        let rows = vec![format!("[{json}]")];
+
+        match plan_from_rows(rows, ParseMode::Lenient).unwrap_err() {
+            ExplainError::Parse { path, snippet, .. } => {
+                assert_eq!(path, "[0].Plan.Plan Rows");
+                assert!(snippet.contains("\"many\""), "{snippet}");
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn planning_errors_are_classified() {
+        let database_error = |message: &str| {
+            diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::Unknown,
+                Box::new(message.to_owned()),
+            )
+        };
+
+        let err = ExplainError::from(database_error("permission denied for table users"));
+        assert!(matches!(err, ExplainError::InsufficientPrivilege { .. }));
+        let err = ExplainError::from(database_error("relation \"usrs\" does not exist"));
+        assert!(matches!(err, ExplainError::UndefinedRelation { .. }));
+        let err = ExplainError::from(database_error(
+            "canceling statement due to statement timeout",
+        ));
+        assert!(matches!(err, ExplainError::Cancelled { .. }) && err.is_transient());
+        let err = ExplainError::from(database_error("syntax error at or near \"SELEC\""));
+        assert!(matches!(err, ExplainError::Diesel(_)) && !err.is_transient());
+    }
+
+    #[test]
+    fn sql_query_binds_are_kept() {
+        let query = diesel::sql_query("SELECT id FROM users WHERE id = $1")
+            .bind::<diesel::sql_types::Int4, _>(7);
+        let explain = query.wrap_explain();
+
+        let sql = diesel::debug_query::<Pg, _>(&explain).to_string();
+        assert_eq!(
+            sql,
+            "EXPLAIN (FORMAT JSON) SELECT id FROM users WHERE id = $1 -- binds: [7]"
+        );
+    }
+
+    diesel::table! {
+        users (id) {
+            id -> Int4,
+            name -> Text,
+        }
+    }
+
+    #[test]
+    fn dml_statements_can_be_wrapped() {
+        let update = diesel::update(users::table.find(1)).set(users::name.eq("x"));
+        let explain = update.wrap_explain();
+
+        let sql = diesel::debug_query::<Pg, _>(&explain).to_string();
+        assert_eq!(
+            sql,
+            "EXPLAIN (FORMAT JSON) UPDATE \"users\" SET \"name\" = $1 \
+             WHERE (\"users\".\"id\" = $2) -- binds: [\"x\", 1]"
+        );
+    }
+
+    /// Fails to compile unless `explain` accepts the wrapped query.
+    fn assert_explainable<'a, Q>(_: &Explain<Q>)
+    where
+        Explain<Q>: LoadQuery<'a, PgConnection, String>,
+    {
+    }
+
+    #[test]
+    fn boxed_queries_can_be_wrapped() {
+        let name = String::from("x");
+        let query = users::table
+            .select(users::id)
+            .filter(users::name.eq(&name))
+            .into_boxed::<Pg>()
+            .filter(users::id.gt(1));
+        let expected = "EXPLAIN (FORMAT JSON) SELECT \"users\".\"id\" FROM \"users\" \
+                        WHERE ((\"users\".\"name\" = $1) AND (\"users\".\"id\" > $2)) \
+                        -- binds: [\"x\", 1]";
+
+        let borrowed = query.wrap_explain();
+        assert_explainable(&borrowed);
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&borrowed).to_string(),
+            expected
+        );
+
+        let owned = Explain::new(query);
+        assert_explainable(&owned);
+        assert_eq!(diesel::debug_query::<Pg, _>(&owned).to_string(), expected);
+    }
+
+    #[test]
+    fn search_path_quotes_schemas() {
+        let explain = Explain::new(()).search_path(["Tenant1", "we\"ird", "public"]);
+        assert_eq!(
+            explain.settings,
+            [(
+                "search_path".to_owned(),
+                r#""Tenant1", "we""ird", "public""#.to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn hints_prefix_the_statement() {
+        let hints = Hints::new()
+            .index_scan("users", ["Users_Name_idx"])
+            .leading(["users", "posts"])
+            .rows(["users", "posts"], 10)
+            .set("enable_seqscan", "off");
+        let query = diesel::sql_query("SELECT 1");
+        let explain = query.wrap_explain().hints(hints);
+
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&explain).to_string(),
+            "/*+ IndexScan(users \"Users_Name_idx\") Leading(users posts) \
+             Rows(users posts #10) Set(enable_seqscan off) */ \
+             EXPLAIN (FORMAT JSON) SELECT 1 -- binds: []"
+        );
+    }
+
+    #[test]
+    fn raw_statements_lose_trailing_semicolons() {
+        for sql in ["SELECT 1", "SELECT 1;  \n", "SELECT 1;;", "SELECT 1 ; ;\n"] {
+            let explain = Explain::new(RawSql::statement(sql));
+            assert_eq!(
+                diesel::debug_query::<Pg, _>(&explain).to_string(),
+                "EXPLAIN (FORMAT JSON) SELECT 1 -- binds: []"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_result_converts_to_deserialization_error() {
+        let err: diesel::result::Error = ExplainError::EmptyResult.into();
+        assert!(matches!(
+            err,
+            diesel::result::Error::DeserializationError(_)
+        ));
     }
 }
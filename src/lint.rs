@@ -0,0 +1,979 @@
+//! Lints flagging common problems in plans.
+//!
+//! Each lint inspects a plan and returns [`Finding`]s describing what looks wrong
+//! and how to fix it. [`check()`] runs all of them; findings of plans captured
+//! with [`Explain::explain_report()`](crate::Explain::explain_report) are included
+//! in the report.
+//!
+//! Example:
+//! ```rust
+//! let plan = query.wrap_explain().explain_analyze(&mut conn)?;
+//! for finding in lint::check(&plan) {
+//!     println!("{finding}");
+//! }
+//! ```
+//!
+//! With the `cli` feature, lints plug into an audit:
+//! ```rust
+//! registry.lint(|plan: &ExplainPlan| lint::check(plan).iter().map(ToString::to_string).collect());
+//! ```
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis;
+use crate::expr::{self, Expr};
+use crate::ExplainPlan;
+
+/// A problem found in a plan by a lint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Finding {
+    /// The name of the lint, e.g. `work_mem`.
+    pub lint: String,
+
+    /// What is wrong and how to fix it.
+    pub message: String,
+}
+
+impl Finding {
+    pub(crate) fn new(lint: &str, message: String) -> Self {
+        Self {
+            lint: lint.to_owned(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.lint, self.message)
+    }
+}
+
+/// All lints, run by [`check()`].
+pub const LINTS: &[fn(&ExplainPlan) -> Vec<Finding>] = &[
+    work_mem,
+    cross_join,
+    distinct_strategy,
+    bitmap_or,
+    leading_wildcard,
+    late_filter,
+    redundant_sort,
+    limit_startup,
+    correlated_subplan,
+];
+
+/// Runs all lints on `plan`.
+pub fn check(plan: &ExplainPlan) -> Vec<Finding> {
+    LINTS.iter().flat_map(|lint| lint(plan)).collect()
+}
+
+/// Flags sorts, hash tables and bitmaps which spilled to disk or went lossy, and
+/// recommends a `work_mem` with which they would fit into memory, see
+/// [`analysis::recommended_work_mem()`]. Assumes the default
+/// `hash_mem_multiplier` of 2.0.
+pub fn work_mem(plan: &ExplainPlan) -> Vec<Finding> {
+    const HASH_MEM_MULTIPLIER: f64 = 2.0;
+    let Some(recommended) = analysis::recommended_work_mem(plan, HASH_MEM_MULTIPLIER) else {
+        return Vec::new();
+    };
+    let spilled: Vec<_> = plan
+        .nodes()
+        .filter(|node| analysis::work_mem_needed(node, HASH_MEM_MULTIPLIER).is_some())
+        .map(|node| match &node.relation_name {
+            Some(relation) => format!("{} on {relation}", node.node_type),
+            None => node.node_type.clone(),
+        })
+        .collect();
+    vec![Finding::new(
+        "work_mem",
+        format!(
+            "{} exceeded work_mem; set work_mem to at least {}",
+            spilled.join(", "),
+            format_kb(recommended)
+        ),
+    )]
+}
+
+/// Flags nested loops joining every row of one input with every row of the
+/// other: without a `Join Filter` and with an inner input which doesn't look up
+/// rows by values of the outer one. Such a cross join is usually accidental, e.g.
+/// a boxed query missing a join condition. Joins where an input is estimated to
+/// return at most one row are left out, as they are cheap.
+pub fn cross_join(plan: &ExplainPlan) -> Vec<Finding> {
+    plan.nodes()
+        .filter(|node| node.node_type == "Nested Loop" && node.join_filter.is_none())
+        .filter_map(|node| {
+            let mut inputs = node.plans.iter().filter(|child| !analysis::is_subplan(child));
+            let (outer, inner) = (inputs.next()?, inputs.next()?);
+            if outer.plan_rows <= 1 || inner.plan_rows <= 1 || is_parameterized(inner, outer) {
+                return None;
+            }
+            Some(Finding::new(
+                "cross_join",
+                format!(
+                    "Nested Loop joins {} with {} without a join condition, combining {} by {} \
+                     estimated rows; add the missing join condition or make the cross join explicit",
+                    relations(outer),
+                    relations(inner),
+                    outer.plan_rows,
+                    inner.plan_rows
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// Flags duplicates removed by an expensive strategy: `Unique` or a sorted
+/// `GroupAggregate` over a `Sort` which spilled to disk or sorted many rows into
+/// few groups, where hashing would only keep the groups in memory, and a
+/// `HashAggregate` which spilled to disk, where reading the rows in order would
+/// need no memory.
+pub fn distinct_strategy(plan: &ExplainPlan) -> Vec<Finding> {
+    plan.nodes()
+        .filter_map(|node| expensive_sorted_grouping(node).or_else(|| spilled_hash_grouping(node)))
+        .map(|message| Finding::new("distinct_strategy", message))
+        .collect()
+}
+
+fn expensive_sorted_grouping(node: &ExplainPlan) -> Option<String> {
+    const MIN_SORTED_ROWS: u64 = 100_000;
+    const MAX_GROUP_FRACTION: u64 = 10;
+    let sorted = node.node_type == "Unique"
+        || (node.node_type == "Aggregate" && node.strategy.as_deref() == Some("Sorted"));
+    if !sorted {
+        return None;
+    }
+    let sort = input(node).filter(|child| child.node_type == "Sort")?;
+    let on_disk = sort.sort_space_type.as_deref() == Some("Disk");
+    let few_groups = sort.plan_rows >= MIN_SORTED_ROWS
+        && node.plan_rows.saturating_mul(MAX_GROUP_FRACTION) <= sort.plan_rows;
+    if !on_disk && !few_groups {
+        return None;
+    }
+    let keys = sort.sort_key.as_deref().unwrap_or_default().join(", ");
+    Some(format!(
+        "{} sorts {} estimated rows{} to find {} groups of {keys}; raise work_mem so \
+         the planner can hash the groups instead, check that enable_hashagg is on, or add \
+         an index on ({keys}) to read the rows in order",
+        node.node_type,
+        sort.plan_rows,
+        if on_disk { " on disk" } else { "" },
+        node.plan_rows
+    ))
+}
+
+fn spilled_hash_grouping(node: &ExplainPlan) -> Option<String> {
+    let hashed = node.node_type == "Aggregate" && node.strategy.as_deref() == Some("Hashed");
+    let spilled = node.disk_usage.is_some_and(|kb| kb > 0)
+        || node.hashagg_batches.is_some_and(|batches| batches > 1);
+    if !hashed || !spilled {
+        return None;
+    }
+    let keys = node.group_key.as_deref().unwrap_or_default().join(", ");
+    Some(format!(
+        "HashAggregate of {keys} spilled {} kB to disk in {} batches; raise work_mem or \
+         hash_mem_multiplier, or add an index on ({keys}) so the groups can be aggregated \
+         in order without a hash table",
+        node.disk_usage.unwrap_or_default(),
+        node.hashagg_batches.unwrap_or(1)
+    ))
+}
+
+/// Flags bitmap heap scans combining many index scans with `BitmapOr` into a
+/// large number of rows, the plan of long `OR` chains such as generated
+/// filters produce. Suggests comparing with an array when all branches use one
+/// index, or a `UNION` of queries which can each use an index selectively.
+pub fn bitmap_or(plan: &ExplainPlan) -> Vec<Finding> {
+    const MIN_BRANCHES: usize = 5;
+    const MIN_ROWS: f64 = 10_000.0;
+    plan.nodes()
+        .filter(|node| node.node_type == "Bitmap Heap Scan")
+        .filter_map(|heap_scan| {
+            let bitmap_or = heap_scan
+                .plans
+                .iter()
+                .find(|child| child.node_type == "BitmapOr")?;
+            let branches = bitmap_or.plans.len();
+            let index_scans: Vec<_> = bitmap_or
+                .nodes()
+                .filter(|node| node.node_type == "Bitmap Index Scan")
+                .collect();
+            let rows: f64 = index_scans
+                .iter()
+                .map(|scan| scan.actual_rows.unwrap_or(scan.plan_rows as f64))
+                .sum();
+            if branches < MIN_BRANCHES || rows < MIN_ROWS {
+                return None;
+            }
+            let mut indexes: Vec<&str> = Vec::new();
+            for index in index_scans
+                .iter()
+                .filter_map(|scan| scan.index_name.as_deref())
+            {
+                if !indexes.contains(&index) {
+                    indexes.push(index);
+                }
+            }
+            let rewrite = match indexes.as_slice() {
+                [index] => format!(
+                    "all of them use {index}, so compare its column with an array, e.g. \
+                     `column = ANY($1)`, instead of chaining OR conditions"
+                ),
+                _ => "rewrite the OR conditions as a UNION of queries which can each use \
+                      an index selectively, or compare single columns with arrays"
+                    .to_owned(),
+            };
+            Some(Finding::new(
+                "bitmap_or",
+                format!(
+                    "Bitmap Heap Scan on {} combines {branches} index scans with BitmapOr, \
+                     matching {rows:.0} rows; {rewrite}",
+                    heap_scan.relation_name.as_deref().unwrap_or("a relation"),
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// Flags sequential scans filtering with `LIKE` or `ILIKE` patterns starting
+/// with a wildcard, e.g. `name LIKE '%smith'`, which B-tree indexes can't serve,
+/// and suggests a trigram index of the `pg_trgm` extension, which can.
+pub fn leading_wildcard(plan: &ExplainPlan) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for node in plan
+        .nodes()
+        .filter(|node| node.node_type.ends_with("Seq Scan"))
+    {
+        let Some(Ok(filter)) = node.filter.as_deref().map(expr::parse) else {
+            continue;
+        };
+        for expr in filter.nodes() {
+            let Expr::BinaryOp { op, left, right } = expr else {
+                continue;
+            };
+            let Expr::Const { value: pattern, .. } = &**right else {
+                continue;
+            };
+            if !matches!(op.as_str(), "~~" | "~~*") || !pattern.starts_with(['%', '_']) {
+                continue;
+            }
+            let Some(indexed) = indexed_expr(left) else {
+                continue;
+            };
+            let relation = node.relation_name.as_deref().unwrap_or("the relation");
+            findings.push(Finding::new(
+                "leading_wildcard",
+                format!(
+                    "{} on {relation} filters {indexed} with the pattern '{pattern}', whose \
+                     leading wildcard rules out B-tree indexes; add a trigram index with \
+                     `CREATE EXTENSION pg_trgm` and `CREATE INDEX ON {relation} USING gin \
+                     ({indexed} gin_trgm_ops)`",
+                    node.node_type
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+/// Flags joins, subquery scans and CTE scans discarding most of the rows they
+/// produce with a `Filter` or `Join Filter`: a selective condition which couldn't
+/// be pushed down to the scans below, so all rows are read and joined first.
+/// Requires `ANALYZE`.
+pub fn late_filter(plan: &ExplainPlan) -> Vec<Finding> {
+    const MIN_REMOVED_ROWS: f64 = 10_000.0;
+    const MIN_REMOVED_FRACTION: f64 = 0.9;
+    plan.nodes()
+        .filter(|node| {
+            let scan = node.node_type.ends_with(" Scan")
+                && !matches!(node.node_type.as_str(), "Subquery Scan" | "CTE Scan");
+            let grouping = matches!(node.node_type.as_str(), "Aggregate" | "Group");
+            !scan && !grouping
+        })
+        .filter_map(|node| {
+            let loops = node.actual_loops.unwrap_or(1) as f64;
+            let removed = (node.rows_removed_by_filter.unwrap_or_default()
+                + node.rows_removed_by_join_filter.unwrap_or_default())
+                * loops;
+            let total = removed + node.actual_rows? * loops;
+            if removed < MIN_REMOVED_ROWS || removed < MIN_REMOVED_FRACTION * total {
+                return None;
+            }
+            let conditions = [&node.join_filter, &node.filter]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(" and ");
+            Some(Finding::new(
+                "late_filter",
+                format!(
+                    "{} discards {removed:.0} of {total:.0} rows with {conditions} after \
+                     producing them; the condition couldn't be pushed down to the scans, e.g. \
+                     because it combines columns of several relations with OR, calls a \
+                     volatile function or filters a subquery with LIMIT, DISTINCT or window \
+                     functions; rewrite it so each relation can be filtered on its own",
+                    node.node_type
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// Flags sorts of rows which are, or could cheaply be, in order already: an
+/// `Incremental Sort` whose input is sorted by all of its keys, a sort of rows
+/// sorted by the same keys below, e.g. by an `ORDER BY` in a subquery, and a sort
+/// of many rows of an index or bitmap scan looking up equal values, which an
+/// index on these columns followed by the sort keys would return in order.
+pub fn redundant_sort(plan: &ExplainPlan) -> Vec<Finding> {
+    plan.nodes()
+        .filter(|node| matches!(node.node_type.as_str(), "Sort" | "Incremental Sort"))
+        .filter_map(redundant_sort_message)
+        .map(|message| Finding::new("redundant_sort", message))
+        .collect()
+}
+
+fn redundant_sort_message(sort: &ExplainPlan) -> Option<String> {
+    // Sorting fewer rows is cheap, and often cheaper than an ordered index scan.
+    const MIN_SORTED_ROWS: u64 = 10_000;
+    let keys = sort.sort_key.as_deref()?;
+    let presorted = sort.presorted_key.as_deref().unwrap_or_default();
+    if sort.node_type == "Incremental Sort" && presorted.len() >= keys.len() {
+        return Some(format!(
+            "Incremental Sort by {} receives rows sorted by all of its keys already; the \
+             sort can be dropped, e.g. by removing a duplicate ORDER BY",
+            keys.join(", ")
+        ));
+    }
+    let below = ordered_input(sort)?;
+    match below.node_type.as_str() {
+        "Sort" | "Incremental Sort" => {
+            let below_keys = below.sort_key.as_deref()?;
+            is_prefix(keys, below_keys).then(|| {
+                format!(
+                    "{} by {} sorts rows which a {} below sorted by {} already; remove the \
+                     inner ORDER BY, e.g. of a subquery or CTE",
+                    sort.node_type,
+                    keys.join(", "),
+                    below.node_type,
+                    below_keys.join(", ")
+                )
+            })
+        }
+        "Index Scan" | "Index Only Scan" | "Bitmap Heap Scan"
+            if sort.plan_rows >= MIN_SORTED_ROWS =>
+        {
+            let relation = below.relation_name.as_deref()?;
+            let condition = below.index_cond.as_ref().or(below.recheck_cond.as_ref())?;
+            let mut columns = equality_columns(condition)?;
+            columns.extend(keys.iter().map(|key| unqualified(key).to_owned()));
+            Some(format!(
+                "{} by {} sorts rows of a {} on {relation} looking up equal values; an index \
+                 on these columns followed by the sort keys would return them in order: \
+                 `CREATE INDEX ON {relation} ({})`",
+                sort.node_type,
+                keys.join(", "),
+                below.node_type,
+                columns.join(", ")
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Flags `Limit` nodes whose input has to do most of its work before returning
+/// the first row, e.g. sort or hash millions of rows, so the limit saves little.
+/// For a sort, suggests an index on the sort keys, which would let the scan
+/// return rows in order and stop after the limit.
+pub fn limit_startup(plan: &ExplainPlan) -> Vec<Finding> {
+    const MIN_STARTUP_COST: f64 = 10_000.0;
+    const MIN_STARTUP_FRACTION: f64 = 0.5;
+    plan.nodes()
+        .filter(|node| node.node_type == "Limit")
+        .filter_map(|limit| {
+            let child = input(limit)?;
+            if child.startup_cost < MIN_STARTUP_COST
+                || child.startup_cost < MIN_STARTUP_FRACTION * child.total_cost
+            {
+                return None;
+            }
+            let sort = std::iter::successors(Some(child), |node| input(node))
+                .take_while(|node| node.startup_cost >= MIN_STARTUP_COST)
+                .find(|node| node.node_type == "Sort")
+                // A sort of e.g. aggregated rows can't be replaced by an index.
+                .filter(|sort| {
+                    input(sort).is_none_or(|below| {
+                        below.startup_cost < MIN_STARTUP_FRACTION * sort.startup_cost
+                    })
+                });
+            let fix = match sort.and_then(|sort| sort.sort_key.as_deref()) {
+                Some(keys) => format!(
+                    "an index on ({}) would return the rows in order, so the scan could stop \
+                     after the limit",
+                    keys.iter()
+                        .map(|key| unqualified(key))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                None => "an index providing the required order, or filtering before the \
+                         expensive step, would let the limit stop early"
+                    .to_owned(),
+            };
+            Some(Finding::new(
+                "limit_startup",
+                format!(
+                    "Limit keeps {} of {} estimated rows, but its input {} costs {:.0} of {:.0} \
+                     before returning the first row; {fix}",
+                    limit.plan_rows,
+                    child.plan_rows,
+                    child.node_type,
+                    child.startup_cost,
+                    child.total_cost
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// Flags `SubPlan`s executed for many rows of the outer query with a
+/// non-trivial cost each, the signature of correlated subqueries, e.g. in the
+/// select list or in `WHERE x > (SELECT ...)`, which are usually faster as a
+/// join. Requires `ANALYZE`.
+pub fn correlated_subplan(plan: &ExplainPlan) -> Vec<Finding> {
+    const MIN_LOOPS: u64 = 1_000;
+    const MIN_COST_PER_LOOP: f64 = 10.0;
+    plan.nodes()
+        .flat_map(|node| &node.plans)
+        .filter(|child| child.parent_relationship.as_deref() == Some("SubPlan"))
+        .filter_map(|subplan| {
+            let loops = subplan.actual_loops.filter(|loops| *loops >= MIN_LOOPS)?;
+            if subplan.total_cost < MIN_COST_PER_LOOP {
+                return None;
+            }
+            let time = subplan
+                .total_actual_time()
+                .map(|ms| format!(", taking {ms:.1} ms in total"))
+                .unwrap_or_default();
+            Some(Finding::new(
+                "correlated_subplan",
+                format!(
+                    "{} ran {loops} times at an estimated cost of {:.0} each{time}; rewrite \
+                     the correlated subquery as a join, e.g. with a grouped subquery, so it \
+                     is evaluated once rather than per row",
+                    subplan.subplan_name.as_deref().unwrap_or("SubPlan"),
+                    subplan.total_cost
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// Returns the node below `sort` which determines the order of its input,
+/// skipping nodes which keep the order of their single input.
+fn ordered_input(sort: &ExplainPlan) -> Option<&ExplainPlan> {
+    let mut node = input(sort)?;
+    while matches!(
+        node.node_type.as_str(),
+        "Subquery Scan" | "Result" | "Materialize" | "Limit" | "Unique" | "Gather Merge"
+    ) {
+        node = input(node)?;
+    }
+    Some(node)
+}
+
+/// Returns the first child of `node` which is not a `SubPlan` or `InitPlan`.
+fn input(node: &ExplainPlan) -> Option<&ExplainPlan> {
+    node.plans.iter().find(|child| !analysis::is_subplan(child))
+}
+
+/// Returns `true` if `keys` are the first keys of `input_keys`, ignoring how
+/// columns are qualified.
+fn is_prefix(keys: &[String], input_keys: &[String]) -> bool {
+    keys.len() <= input_keys.len()
+        && keys
+            .iter()
+            .zip(input_keys)
+            .all(|(key, input_key)| unqualified(key) == unqualified(input_key))
+}
+
+/// Strips the relation qualifying a sort key such as `users.name DESC`.
+fn unqualified(key: &str) -> &str {
+    match key.split_once('.') {
+        Some((relation, rest))
+            if !relation.is_empty()
+                && relation
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '"') =>
+        {
+            rest
+        }
+        _ => key,
+    }
+}
+
+/// Returns the columns an index condition compares with single values, or
+/// `None` if it has other comparisons or can't be parsed.
+fn equality_columns(index_cond: &str) -> Option<Vec<String>> {
+    let condition = expr::parse(index_cond).ok()?;
+    let comparisons = match condition {
+        Expr::And(comparisons) => comparisons,
+        comparison => vec![comparison],
+    };
+    comparisons
+        .iter()
+        .map(|comparison| match comparison {
+            Expr::BinaryOp { op, left, .. } if op == "=" => match &**left {
+                Expr::Column(column) => Some(column.name.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns what an index would be built on for `expr`: a column, or a function
+/// of one such as `lower(name)`, ignoring casts.
+fn indexed_expr(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Column(column) => Some(column.name.clone()),
+        Expr::Cast { operand, .. } => indexed_expr(operand),
+        Expr::Function { name, args } => match args.as_slice() {
+            [arg @ (Expr::Column(_) | Expr::Cast { .. })] => {
+                Some(format!("{name}({})", indexed_expr(arg)?))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns `true` if the conditions of `inner` or a node below it refer to a
+/// relation of `outer`, i.e. `inner` is rescanned for each outer row with its
+/// values. Unparsable conditions are searched for qualified names as text.
+fn is_parameterized(inner: &ExplainPlan, outer: &ExplainPlan) -> bool {
+    let aliases: Vec<&str> = outer
+        .nodes()
+        .flat_map(|node| [node.alias.as_deref(), node.relation_name.as_deref()])
+        .flatten()
+        .collect();
+    inner.nodes().any(|node| {
+        node.node_type == "Memoize"
+            || expr::conditions(node).any(|(_, condition)| refers_to(condition, &aliases))
+    })
+}
+
+/// Returns `true` if `condition` has a column qualified by one of `aliases`.
+fn refers_to(condition: &str, aliases: &[&str]) -> bool {
+    let Ok(parsed) = expr::parse(condition) else {
+        return aliases
+            .iter()
+            .any(|alias| condition.contains(&format!("{alias}.")));
+    };
+    let refers = parsed.nodes().any(|expr| match expr {
+        Expr::Column(column) => column
+            .relation
+            .as_deref()
+            .is_some_and(|relation| aliases.contains(&relation)),
+        _ => false,
+    });
+    refers
+}
+
+/// Names the relations scanned by `node` and its children, or its node type if
+/// there are none.
+fn relations(node: &ExplainPlan) -> String {
+    let mut relations: Vec<&str> = Vec::new();
+    for relation in node
+        .nodes()
+        .filter_map(|node| node.relation_name.as_deref())
+    {
+        if !relations.contains(&relation) {
+            relations.push(relation);
+        }
+    }
+    if relations.is_empty() {
+        node.node_type.clone()
+    } else {
+        relations.join(", ")
+    }
+}
+
+/// Formats kilobytes the way PostgreSQL accepts them in settings, e.g. `64MB`.
+fn format_kb(kb: u64) -> String {
+    match kb {
+        kb if kb % (1024 * 1024) == 0 => format!("{}GB", kb / (1024 * 1024)),
+        kb if kb % 1024 == 0 => format!("{}MB", kb / 1024),
+        kb => format!("{kb}kB"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::node;
+
+    #[test]
+    fn spills_recommend_work_mem() {
+        let sort = ExplainPlan {
+            node_type: "Sort".to_owned(),
+            sort_space_type: Some("Disk".to_owned()),
+            sort_space_used: Some(3000),
+            ..ExplainPlan::default()
+        };
+        let hash = ExplainPlan {
+            node_type: "Hash".to_owned(),
+            hash_batches: Some(8),
+            peak_memory_usage: Some(4000),
+            ..ExplainPlan::default()
+        };
+        let plan = ExplainPlan {
+            node_type: "Hash Join".to_owned(),
+            plans: vec![sort, hash],
+            ..ExplainPlan::default()
+        };
+
+        assert_eq!(analysis::recommended_work_mem(&plan, 2.0), Some(16 * 1024));
+        assert_eq!(analysis::recommended_work_mem(&plan, 1.0), Some(32 * 1024));
+        assert_eq!(
+            work_mem(&plan),
+            [Finding {
+                lint: "work_mem".to_owned(),
+                message: "Sort, Hash exceeded work_mem; set work_mem to at least 16MB".to_owned(),
+            }]
+        );
+        assert_eq!(
+            analysis::recommended_work_mem(&ExplainPlan::default(), 2.0),
+            None
+        );
+    }
+
+    #[test]
+    fn nested_loops_without_join_condition_are_cross_joins() {
+        let nested_loop = |inner| ExplainPlan {
+            plans: vec![
+                ExplainPlan {
+                    alias: Some("u".to_owned()),
+                    plan_rows: 100,
+                    ..ExplainPlan::scan("Seq Scan", "users")
+                },
+                inner,
+            ],
+            ..ExplainPlan::new("Nested Loop")
+        };
+        let orders = |index_cond: Option<&str>| ExplainPlan {
+            plan_rows: 20,
+            index_cond: index_cond.map(str::to_owned),
+            ..ExplainPlan::scan("Index Scan", "orders")
+        };
+
+        let cross = nested_loop(ExplainPlan {
+            plan_rows: 20,
+            plans: vec![orders(None)],
+            ..ExplainPlan::new("Materialize")
+        });
+        assert_eq!(
+            cross_join(&cross),
+            [Finding {
+                lint: "cross_join".to_owned(),
+                message: "Nested Loop joins users with orders without a join condition, \
+                          combining 100 by 20 estimated rows; add the missing join condition \
+                          or make the cross join explicit"
+                    .to_owned(),
+            }]
+        );
+        assert!(check(&cross)
+            .iter()
+            .any(|finding| finding.lint == "cross_join"));
+
+        let parameterized = nested_loop(orders(Some("(user_id = u.id)")));
+        assert!(cross_join(&parameterized).is_empty());
+        let filtered = ExplainPlan {
+            join_filter: Some("(o.total > u.limit)".to_owned()),
+            ..cross.clone()
+        };
+        assert!(cross_join(&filtered).is_empty());
+    }
+
+    #[test]
+    fn expensive_distinct_strategies_are_flagged() {
+        let unique = |sort_rows: u64, space_type: &str| ExplainPlan {
+            plan_rows: 200,
+            plans: vec![ExplainPlan {
+                plan_rows: sort_rows,
+                sort_key: Some(vec!["status".to_owned()]),
+                sort_space_type: Some(space_type.to_owned()),
+                ..ExplainPlan::new("Sort")
+            }],
+            ..ExplainPlan::new("Unique")
+        };
+        assert_eq!(
+            distinct_strategy(&unique(300_000, "Memory")),
+            [Finding {
+                lint: "distinct_strategy".to_owned(),
+                message: "Unique sorts 300000 estimated rows to find 200 groups of status; \
+                          raise work_mem so the planner can hash the groups instead, check \
+                          that enable_hashagg is on, or add an index on (status) to read the \
+                          rows in order"
+                    .to_owned(),
+            }]
+        );
+        assert_eq!(distinct_strategy(&unique(1_000, "Disk")).len(), 1);
+        assert!(distinct_strategy(&unique(1_000, "Memory")).is_empty());
+
+        let hash_aggregate = ExplainPlan {
+            strategy: Some("Hashed".to_owned()),
+            group_key: Some(vec!["user_id".to_owned()]),
+            disk_usage: Some(8032),
+            hashagg_batches: Some(5),
+            ..ExplainPlan::new("Aggregate")
+        };
+        assert_eq!(
+            distinct_strategy(&hash_aggregate)[0].message,
+            "HashAggregate of user_id spilled 8032 kB to disk in 5 batches; raise work_mem \
+             or hash_mem_multiplier, or add an index on (user_id) so the groups can be \
+             aggregated in order without a hash table"
+        );
+        assert!(distinct_strategy(&ExplainPlan {
+            disk_usage: Some(0),
+            hashagg_batches: Some(1),
+            ..hash_aggregate
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn large_bitmap_ors_are_flagged() {
+        let heap_scan = |indexes: &[&str], rows: u64| ExplainPlan {
+            plans: vec![ExplainPlan {
+                plans: indexes
+                    .iter()
+                    .map(|index| ExplainPlan {
+                        index_name: Some((*index).to_owned()),
+                        plan_rows: rows,
+                        ..ExplainPlan::new("Bitmap Index Scan")
+                    })
+                    .collect(),
+                ..ExplainPlan::new("BitmapOr")
+            }],
+            ..ExplainPlan::scan("Bitmap Heap Scan", "orders")
+        };
+        let one_index = ["orders_status_idx"; 6];
+        assert_eq!(
+            bitmap_or(&heap_scan(&one_index, 5_000)),
+            [Finding {
+                lint: "bitmap_or".to_owned(),
+                message: "Bitmap Heap Scan on orders combines 6 index scans with BitmapOr, \
+                          matching 30000 rows; all of them use orders_status_idx, so compare \
+                          its column with an array, e.g. `column = ANY($1)`, instead of \
+                          chaining OR conditions"
+                    .to_owned(),
+            }]
+        );
+        let mixed = heap_scan(&["a_idx", "b_idx", "a_idx", "b_idx", "c_idx"], 5_000);
+        assert!(bitmap_or(&mixed)[0].message.contains("UNION"));
+        assert!(bitmap_or(&heap_scan(&one_index, 10)).is_empty());
+        assert!(bitmap_or(&heap_scan(&one_index[..2], 50_000)).is_empty());
+    }
+
+    #[test]
+    fn leading_wildcards_suggest_trigram_indexes() {
+        let seq_scan = |filter: &str| ExplainPlan {
+            filter: Some(filter.to_owned()),
+            ..ExplainPlan::scan("Seq Scan", "users")
+        };
+        assert_eq!(
+            leading_wildcard(&seq_scan("((name ~~ '%smith'::text) AND (active = true))")),
+            [Finding {
+                lint: "leading_wildcard".to_owned(),
+                message: "Seq Scan on users filters name with the pattern '%smith', whose \
+                          leading wildcard rules out B-tree indexes; add a trigram index with \
+                          `CREATE EXTENSION pg_trgm` and `CREATE INDEX ON users USING gin \
+                          (name gin_trgm_ops)`"
+                    .to_owned(),
+            }]
+        );
+        assert!(
+            leading_wildcard(&seq_scan("(lower((email)::text) ~~* '_x%'::text)"))[0]
+                .message
+                .contains("(lower(email) gin_trgm_ops)")
+        );
+        assert!(leading_wildcard(&seq_scan("(name ~~ 'smith%'::text)")).is_empty());
+        assert!(leading_wildcard(&seq_scan("(name !~~ '%smith'::text)")).is_empty());
+        assert!(check(&seq_scan("(name ~~ '%smith'::text)"))
+            .iter()
+            .any(|finding| finding.lint == "leading_wildcard"));
+    }
+
+    #[test]
+    fn filters_above_joins_are_flagged() {
+        let hash_join = |removed: f64| ExplainPlan {
+            filter: Some("((u.id = 1) OR (o.total > 100))".to_owned()),
+            rows_removed_by_filter: Some(removed),
+            actual_rows: Some(10.0),
+            actual_loops: Some(1),
+            plans: vec![ExplainPlan {
+                filter: Some("(active = true)".to_owned()),
+                rows_removed_by_filter: Some(50_000.0),
+                actual_rows: Some(100.0),
+                actual_loops: Some(1),
+                ..ExplainPlan::scan("Seq Scan", "users")
+            }],
+            ..ExplainPlan::new("Hash Join")
+        };
+        assert_eq!(
+            late_filter(&hash_join(20_000.0)),
+            [Finding {
+                lint: "late_filter".to_owned(),
+                message: "Hash Join discards 20000 of 20010 rows with ((u.id = 1) OR \
+                          (o.total > 100)) after producing them; the condition couldn't be \
+                          pushed down to the scans, e.g. because it combines columns of \
+                          several relations with OR, calls a volatile function or filters a \
+                          subquery with LIMIT, DISTINCT or window functions; rewrite it so \
+                          each relation can be filtered on its own"
+                    .to_owned(),
+            }]
+        );
+        assert!(late_filter(&hash_join(50.0)).is_empty());
+        assert!(check(&hash_join(20_000.0))
+            .iter()
+            .any(|finding| finding.lint == "late_filter"));
+        assert!(late_filter(&ExplainPlan {
+            actual_rows: None,
+            ..hash_join(20_000.0)
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn redundant_sorts_are_flagged() {
+        let sort = |keys: &[&str], plans| ExplainPlan {
+            sort_key: Some(keys.iter().map(|key| (*key).to_owned()).collect()),
+            plan_rows: 50_000,
+            plans,
+            ..ExplainPlan::new("Sort")
+        };
+        let resorted = sort(
+            &["s.name"],
+            vec![node(
+                "Subquery Scan",
+                vec![sort(&["users.name", "users.id"], Vec::new())],
+            )],
+        );
+        assert_eq!(
+            redundant_sort(&resorted),
+            [Finding {
+                lint: "redundant_sort".to_owned(),
+                message: "Sort by s.name sorts rows which a Sort below sorted by users.name, \
+                          users.id already; remove the inner ORDER BY, e.g. of a subquery or CTE"
+                    .to_owned(),
+            }]
+        );
+
+        let index_scan = sort(
+            &["created_at DESC"],
+            vec![ExplainPlan {
+                index_cond: Some("((user_id = 42) AND (status = 'paid'::text))".to_owned()),
+                ..ExplainPlan::scan("Index Scan", "orders")
+            }],
+        );
+        assert!(redundant_sort(&index_scan)[0]
+            .message
+            .ends_with("`CREATE INDEX ON orders (user_id, status, created_at DESC)`"));
+        assert!(redundant_sort(&ExplainPlan {
+            plan_rows: 40,
+            ..index_scan
+        })
+        .is_empty());
+
+        let incremental = ExplainPlan {
+            node_type: "Incremental Sort".to_owned(),
+            presorted_key: Some(vec!["id".to_owned()]),
+            ..sort(&["id"], Vec::new())
+        };
+        assert_eq!(redundant_sort(&incremental).len(), 1);
+        assert!(check(&incremental)
+            .iter()
+            .any(|finding| finding.lint == "redundant_sort"));
+        assert!(redundant_sort(&sort(&["id"], vec![sort(&["name"], Vec::new())])).is_empty());
+    }
+
+    #[test]
+    fn limits_over_expensive_startup_are_flagged() {
+        let limit = |child| ExplainPlan {
+            plan_rows: 10,
+            plans: vec![child],
+            ..ExplainPlan::new("Limit")
+        };
+        let sort = |input: ExplainPlan| ExplainPlan {
+            startup_cost: input.total_cost + 21_600.0,
+            total_cost: input.total_cost + 24_100.0,
+            plan_rows: 1_000_000,
+            sort_key: Some(vec!["orders.created_at DESC".to_owned()]),
+            plans: vec![input],
+            ..ExplainPlan::new("Sort")
+        };
+        let scan = ExplainPlan {
+            total_cost: 10_000.0,
+            ..ExplainPlan::scan("Seq Scan", "orders")
+        };
+        assert_eq!(
+            limit_startup(&limit(sort(scan.clone()))),
+            [Finding {
+                lint: "limit_startup".to_owned(),
+                message: "Limit keeps 10 of 1000000 estimated rows, but its input Sort costs \
+                          31600 of 34100 before returning the first row; an index on \
+                          (created_at DESC) would return the rows in order, so the scan could \
+                          stop after the limit"
+                    .to_owned(),
+            }]
+        );
+
+        let aggregate = ExplainPlan {
+            startup_cost: 50_000.0,
+            total_cost: 50_000.0,
+            ..ExplainPlan::new("Aggregate")
+        };
+        assert!(limit_startup(&limit(sort(aggregate)))[0]
+            .message
+            .ends_with("an index providing the required order, or filtering before the expensive step, would let the limit stop early"));
+        assert_eq!(
+            check(&limit(sort(scan.clone())))
+                .iter()
+                .filter(|finding| finding.lint == "limit_startup")
+                .count(),
+            1
+        );
+        assert!(limit_startup(&limit(scan)).is_empty());
+    }
+
+    #[test]
+    fn correlated_subplans_are_flagged() {
+        let outer = |loops: u64| ExplainPlan {
+            plans: vec![ExplainPlan {
+                parent_relationship: Some("SubPlan".to_owned()),
+                subplan_name: Some("SubPlan 1".to_owned()),
+                total_cost: 25.0,
+                actual_total_time: Some(0.5),
+                actual_rows: Some(1.0),
+                actual_loops: Some(loops),
+                ..ExplainPlan::new("Aggregate")
+            }],
+            ..ExplainPlan::scan("Seq Scan", "users")
+        };
+        assert_eq!(
+            correlated_subplan(&outer(20_000)),
+            [Finding {
+                lint: "correlated_subplan".to_owned(),
+                message: "SubPlan 1 ran 20000 times at an estimated cost of 25 each, taking \
+                          10000.0 ms in total; rewrite the correlated subquery as a join, e.g. \
+                          with a grouped subquery, so it is evaluated once rather than per row"
+                    .to_owned(),
+            }]
+        );
+        assert!(correlated_subplan(&outer(10)).is_empty());
+        assert_eq!(check(&outer(20_000)), correlated_subplan(&outer(20_000)));
+    }
+}
@@ -0,0 +1,140 @@
+//! Support for the [`log`] crate: emitting rendered plans only for queries
+//! which exceed a cost or execution time threshold.
+
+use diesel::query_dsl::methods::LoadQuery;
+use log::Level;
+
+use crate::{render, Explain, ExplainConnection, ExplainError, ExplainPlan};
+
+/// Decides which plans [`Explain::maybe_log_plan()`] logs, and at which level.
+///
+/// A plan is logged when it exceeds any of the configured limits. With no
+/// limits configured nothing is logged.
+///
+/// Example:
+/// ```rust
+/// let threshold = LogThreshold::total_cost(10_000.0).level(log::Level::Warn);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct LogThreshold {
+    /// Minimum estimated total cost of the root plan node.
+    pub min_total_cost: Option<f64>,
+
+    /// Minimum execution time in milliseconds, as reported by `EXPLAIN ANALYZE`.
+    /// Ignored for plans captured without `ANALYZE`.
+    pub min_execution_time_ms: Option<f64>,
+
+    /// Level used to log plans which exceed the threshold.
+    pub level: Level,
+}
+
+impl Default for LogThreshold {
+    fn default() -> Self {
+        Self {
+            min_total_cost: None,
+            min_execution_time_ms: None,
+            level: Level::Info,
+        }
+    }
+}
+
+impl LogThreshold {
+    /// Logs plans whose estimated total cost is at least `min_total_cost`.
+    pub fn total_cost(min_total_cost: f64) -> Self {
+        Self {
+            min_total_cost: Some(min_total_cost),
+            ..Self::default()
+        }
+    }
+
+    /// Logs plans whose execution took at least `min_execution_time_ms` milliseconds.
+    pub fn execution_time_ms(min_execution_time_ms: f64) -> Self {
+        Self {
+            min_execution_time_ms: Some(min_execution_time_ms),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the level used to log plans which exceed the threshold.
+    pub fn level(self, level: Level) -> Self {
+        Self { level, ..self }
+    }
+
+    fn is_exceeded(&self, total_cost: f64, execution_time_ms: Option<f64>) -> bool {
+        let cost_exceeded = self.min_total_cost.is_some_and(|min| total_cost >= min);
+        let time_exceeded = self
+            .min_execution_time_ms
+            .zip(execution_time_ms)
+            .is_some_and(|(min, time)| time >= min);
+        cost_exceeded || time_exceeded
+    }
+}
+
+impl<Q> Explain<Q> {
+    /// Same as [`Explain::explain()`], but additionally logs the plan rendered
+    /// with [`render::tree()`] when it exceeds `threshold`.
+    ///
+    /// Example:
+    /// ```rust
+    /// let plan = my_query
+    ///     .wrap_explain()
+    ///     .maybe_log_plan(&mut conn, LogThreshold::total_cost(10_000.0))?;
+    /// ```
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn maybe_log_plan<'a, C>(
+        self,
+        conn: &mut C,
+        threshold: LogThreshold,
+    ) -> Result<ExplainPlan, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let item = self.explain_item(conn)?;
+
+        if threshold.is_exceeded(item.plan.total_cost, item.execution_time)
+            && log::log_enabled!(threshold.level)
+        {
+            let execution_time = item
+                .execution_time
+                .map_or_else(|| "n/a".to_owned(), |time| format!("{time:.3} ms"));
+            log::log!(
+                threshold.level,
+                "query plan exceeded threshold (total cost {:.2}, execution time {}):\n{}",
+                item.plan.total_cost,
+                execution_time,
+                render::tree(&item.plan)
+            );
+        }
+
+        Ok(item.plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_are_exceeded_by_cost_or_time() {
+        let cost = LogThreshold::total_cost(1000.0);
+        assert!(!cost.is_exceeded(999.0, Some(60_000.0)));
+        assert!(cost.is_exceeded(1000.0, None));
+
+        let time = LogThreshold::execution_time_ms(50.0);
+        assert!(!time.is_exceeded(1e9, Some(49.9)));
+        assert!(time.is_exceeded(0.0, Some(50.0)));
+        assert!(!time.is_exceeded(1e9, None));
+
+        let both = LogThreshold {
+            min_total_cost: Some(1000.0),
+            ..time
+        };
+        assert!(both.is_exceeded(10.0, Some(80.0)));
+        assert!(both.is_exceeded(2000.0, None));
+        assert!(!both.is_exceeded(10.0, None));
+        assert!(!LogThreshold::default().is_exceeded(1e9, Some(1e9)));
+    }
+}
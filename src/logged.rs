@@ -0,0 +1,213 @@
+//! Plans logged by the server-side `auto_explain` extension.
+
+use serde::Deserialize;
+
+use crate::plan::snippet;
+use crate::{ExplainError, ExplainItem, ExplainPlan};
+
+/// A plan found in `auto_explain` log output by [`ExplainPlan::from_auto_explain_log()`].
+#[derive(Debug)]
+pub struct LoggedPlan {
+    /// Execution time of the statement in milliseconds, as logged.
+    pub duration_ms: Option<f64>,
+
+    /// The statement, as logged by `auto_explain`.
+    pub query_text: Option<String>,
+
+    /// The query identifier of the statement, reported since PostgreSQL 16 when
+    /// `auto_explain.log_verbose` and `compute_query_id` are on.
+    pub query_identifier: Option<i64>,
+
+    /// The root node of the plan tree.
+    pub plan: ExplainPlan,
+}
+
+#[derive(Deserialize)]
+struct AutoExplainEntry {
+    #[serde(rename = "Query Text", default)]
+    query_text: Option<String>,
+    #[serde(rename = "Query Identifier", default)]
+    query_identifier: Option<i64>,
+    #[serde(rename = "Plan")]
+    plan: ExplainPlan,
+}
+
+/// Ends the message prefix `duration: 1.234 ms  plan:` written by `auto_explain`.
+const MARKER: &str = " ms  plan:";
+const DURATION: &str = "duration: ";
+
+impl ExplainPlan {
+    /// Extracts the plans logged by `auto_explain` from `log`, e.g. a chunk of a
+    /// server log file or a single log message.
+    ///
+    /// Plans logged with `auto_explain.log_format` `json` or `text`, the default,
+    /// are returned; other formats are skipped. Text plans are parsed with
+    /// [`ExplainItem::from_text()`], so carry less detail. Log line prefixes and the
+    /// tabs PostgreSQL puts in front of continuation lines are ignored.
+    ///
+    /// Example:
+    /// ```rust
+    /// let log = std::fs::read_to_string("/var/log/postgresql/postgresql.log")?;
+    /// for logged in ExplainPlan::from_auto_explain_log(&log)? {
+    ///     println!("{:?} ms: {}", logged.duration_ms, logged.plan.node_type);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Parse`] if a JSON plan is malformed, e.g. truncated,
+    /// or [`ExplainError::ParseText`] if a text plan is.
+    pub fn from_auto_explain_log(log: &str) -> Result<Vec<LoggedPlan>, ExplainError> {
+        let mut plans = Vec::new();
+        let mut rest = log;
+        while let Some(at) = rest.find(MARKER) {
+            let line = &rest[rest[..at].rfind('\n').map_or(0, |i| i + 1)..at];
+            let duration_ms = line
+                .rfind(DURATION)
+                .and_then(|start| line[start + DURATION.len()..].parse().ok());
+
+            let body = &rest[at + MARKER.len()..];
+            let json = body.trim_start();
+            if !json.starts_with('{') {
+                let (text, consumed) = text_entry(body);
+                if let Some(logged) = text_plan(&text, duration_ms)? {
+                    plans.push(logged);
+                }
+                rest = &body[consumed..];
+                continue;
+            }
+            let mut entries = serde_json::Deserializer::from_str(json).into_iter();
+            match entries.next() {
+                Some(Ok(AutoExplainEntry {
+                    query_text,
+                    query_identifier,
+                    plan,
+                })) => plans.push(LoggedPlan {
+                    duration_ms,
+                    query_text,
+                    query_identifier,
+                    plan,
+                }),
+                Some(Err(source)) => {
+                    return Err(ExplainError::Parse {
+                        snippet: snippet(json, source.line(), source.column()),
+                        source,
+                        path: String::new(),
+                        raw_json: json.to_owned(),
+                    })
+                }
+                None => break,
+            }
+            rest = &json[entries.byte_offset()..];
+        }
+        Ok(plans)
+    }
+}
+
+/// Collects the continuation lines of a log message following `plan:`, without
+/// their leading tab, and returns them with the number of bytes they span.
+fn text_entry(body: &str) -> (Vec<&str>, usize) {
+    let Some(start) = body.find('\n') else {
+        return (Vec::new(), body.len());
+    };
+    let mut lines = Vec::new();
+    let mut end = start + 1;
+    for line in body[start + 1..].split_inclusive('\n') {
+        let Some(content) = line.strip_prefix('\t') else {
+            break;
+        };
+        lines.push(content.trim_end_matches(['\n', '\r']));
+        end += line.len();
+    }
+    (lines, end)
+}
+
+/// Parses a plan logged in the text format, preceded by `Query Text: ...`.
+fn text_plan(lines: &[&str], duration_ms: Option<f64>) -> Result<Option<LoggedPlan>, ExplainError> {
+    let Some(first) = lines.first() else {
+        return Ok(None);
+    };
+    let (query_text, plan) = match first.strip_prefix("Query Text: ") {
+        // The query may span several lines; the plan starts with the first line
+        // which looks like a plan node.
+        Some(query) => {
+            let start = lines
+                .iter()
+                .skip(1)
+                .position(|line| is_node(line))
+                .map_or(1, |i| i + 1);
+            let query = std::iter::once(query)
+                .chain(lines[1..start].iter().copied())
+                .collect::<Vec<_>>()
+                .join("\n");
+            (Some(query), &lines[start..])
+        }
+        None if is_node(first) => (None, lines),
+        // Neither text nor JSON, e.g. `xml` or `yaml`.
+        None => return Ok(None),
+    };
+    if plan.is_empty() {
+        return Ok(None);
+    }
+    let item = ExplainItem::from_text(&plan.join("\n"))?;
+    Ok(Some(LoggedPlan {
+        duration_ms,
+        query_text,
+        query_identifier: item.query_identifier,
+        plan: item.plan,
+    }))
+}
+
+fn is_node(line: &str) -> bool {
+    !line.starts_with(' ')
+        && ["  (cost=", " (actual ", " (never executed)"]
+            .iter()
+            .any(|stats| line.contains(stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_explain_json_plans_are_extracted() {
+        let log = concat!(
+            "2026-10-15 08:57:13.749 UTC [3586] LOG:  duration: 4.627 ms  plan:\n",
+            "\t{\n",
+            "\t  \"Query Text\": \"SELECT count(*) FROM users WHERE name = E'a\\\"b';\",\n",
+            "\t  \"Plan\": {\n",
+            "\t    \"Node Type\": \"Aggregate\",\n",
+            "\t    \"Parallel Aware\": false,\n",
+            "\t    \"Startup Cost\": 1093.00,\n",
+            "\t    \"Total Cost\": 1093.01,\n",
+            "\t    \"Plan Rows\": 1,\n",
+            "\t    \"Plan Width\": 8\n",
+            "\t  },\n",
+            "\t  \"Query Identifier\": -7761898202277349869\n",
+            "\t}\n",
+            "2026-10-15 08:57:13.750 UTC [3586] LOG:  duration: 0.009 ms  plan:\n",
+            "\tQuery Text: SELECT id FROM users WHERE id < 3;\n",
+            "\tIndex Only Scan using users_pkey on users  (cost=0.29..4.32 rows=2 width=4)\n",
+        );
+
+        let logged = ExplainPlan::from_auto_explain_log(log).unwrap();
+        assert_eq!(logged.len(), 2);
+        assert_eq!(logged[0].duration_ms, Some(4.627));
+        assert_eq!(
+            logged[0].query_text.as_deref(),
+            Some("SELECT count(*) FROM users WHERE name = E'a\"b';")
+        );
+        assert_eq!(logged[0].plan.node_type, "Aggregate");
+        assert_eq!(logged[0].query_identifier, Some(-7761898202277349869));
+        assert_eq!(
+            logged[1].query_text.as_deref(),
+            Some("SELECT id FROM users WHERE id < 3;")
+        );
+        assert_eq!(logged[1].plan.index_name.as_deref(), Some("users_pkey"));
+
+        let truncated = &log[..log.find("\"Plan Rows\"").unwrap()];
+        assert!(matches!(
+            ExplainPlan::from_auto_explain_log(truncated),
+            Err(ExplainError::Parse { .. })
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+//! Mining server log files for plans logged by `auto_explain`.
+//!
+//! [`scan()`] reads a log in any of the formats PostgreSQL writes, `stderr`,
+//! `csvlog` or `jsonlog` (PostgreSQL 15+), finds the messages logged by
+//! `auto_explain` with `log_format` `json` or `text`, and yields their plans
+//! together with the statements. Logs are read line by line, so files of any size
+//! can be scanned.
+//!
+//! Example:
+//! ```rust
+//! for logged in logscan::scan_file("/var/log/postgresql/postgresql.csv")? {
+//!     let logged = logged?;
+//!     println!("{:?}: {:?} ms", logged.query_text, logged.duration_ms);
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::{ExplainError, ExplainPlan, LoggedPlan};
+
+/// The format of a server log, set with `log_destination`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Plain text, with continuation lines of a message indented by a tab.
+    #[default]
+    Stderr,
+
+    /// CSV, one record per message, see `csvlog`.
+    Csv,
+
+    /// JSON, one object per line, see `jsonlog`.
+    Json,
+}
+
+impl LogFormat {
+    /// Guesses the format from the file extension PostgreSQL uses: `.csv` for
+    /// `csvlog`, `.json` for `jsonlog` and anything else for `stderr`.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => LogFormat::Csv,
+            Some("json") => LogFormat::Json,
+            _ => LogFormat::Stderr,
+        }
+    }
+}
+
+/// Index of the `message` column of a `csvlog` record.
+const CSV_MESSAGE: usize = 13;
+
+/// Scans the log file at `path`, guessing its format with
+/// [`LogFormat::from_path()`].
+///
+/// # Errors
+/// Returns [`ExplainError::Io`] if the file can't be opened.
+pub fn scan_file(path: impl AsRef<Path>) -> Result<LogScanner<BufReader<File>>, ExplainError> {
+    let format = LogFormat::from_path(&path);
+    let file = File::open(path).map_err(|source| ExplainError::Io { source })?;
+    Ok(scan(BufReader::new(file), format))
+}
+
+/// Scans the log read from `reader`, which is in `format`.
+pub fn scan<R: BufRead>(reader: R, format: LogFormat) -> LogScanner<R> {
+    LogScanner {
+        reader,
+        format,
+        line: String::new(),
+        message: String::new(),
+        plans: VecDeque::new(),
+        done: false,
+    }
+}
+
+/// An iterator over the plans found in a log, created by [`scan()`].
+///
+/// A message which can't be parsed, e.g. because the log was truncated, yields an
+/// error, and scanning carries on with the next message. A read error ends the
+/// iteration.
+pub struct LogScanner<R> {
+    reader: R,
+    format: LogFormat,
+    line: String,
+    /// The `stderr` message being collected, or the `csvlog` record being read.
+    message: String,
+    plans: VecDeque<LoggedPlan>,
+    done: bool,
+}
+
+impl<R: BufRead> LogScanner<R> {
+    /// Reads the next line into `self.line`, returning `false` at the end.
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        Ok(self.reader.read_line(&mut self.line)? > 0)
+    }
+
+    /// Reads until the next complete message, returning `None` at the end.
+    fn next_message(&mut self) -> io::Result<Option<String>> {
+        match self.format {
+            LogFormat::Stderr => loop {
+                if !self.read_line()? {
+                    return Ok(
+                        (!self.message.is_empty()).then(|| std::mem::take(&mut self.message))
+                    );
+                }
+                if self.line.starts_with('\t') || self.message.is_empty() {
+                    self.message.push_str(&self.line);
+                    continue;
+                }
+                let message = std::mem::replace(&mut self.message, self.line.clone());
+                return Ok(Some(message));
+            },
+            LogFormat::Csv => loop {
+                if !self.read_line()? {
+                    let record = std::mem::take(&mut self.message);
+                    return Ok((!record.is_empty()).then(|| csv_message(&record)));
+                }
+                self.message.push_str(&self.line);
+                // A record is complete once its quotes are balanced; quoted fields
+                // may span lines.
+                if self.message.matches('"').count().is_multiple_of(2) {
+                    let record = std::mem::take(&mut self.message);
+                    return Ok(Some(csv_message(&record)));
+                }
+            },
+            LogFormat::Json => loop {
+                if !self.read_line()? {
+                    return Ok(None);
+                }
+                let message = serde_json::from_str::<serde_json::Value>(&self.line)
+                    .ok()
+                    .and_then(|entry| entry.get("message")?.as_str().map(indent_message));
+                if let Some(message) = message {
+                    return Ok(Some(message));
+                }
+            },
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LogScanner<R> {
+    type Item = Result<LoggedPlan, ExplainError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(plan) = self.plans.pop_front() {
+                return Some(Ok(plan));
+            }
+            if self.done {
+                return None;
+            }
+            let message = match self.next_message() {
+                Ok(Some(message)) => message,
+                Ok(None) => {
+                    self.done = true;
+                    continue;
+                }
+                Err(source) => {
+                    self.done = true;
+                    return Some(Err(ExplainError::Io { source }));
+                }
+            };
+            match ExplainPlan::from_auto_explain_log(&message) {
+                Ok(plans) => self.plans.extend(plans),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Returns the message of a `csvlog` record, formatted like in a `stderr` log.
+fn csv_message(record: &str) -> String {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.trim_end_matches(['\n', '\r']).chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+        .get(CSV_MESSAGE)
+        .map(|message| indent_message(message))
+        .unwrap_or_default()
+}
+
+/// Indents the continuation lines of `message` with a tab, as PostgreSQL does in
+/// `stderr` logs, which [`ExplainPlan::from_auto_explain_log()`] expects.
+fn indent_message(message: &str) -> String {
+    message.replace('\n', "\n\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_are_scanned_from_all_log_formats() {
+        let stderr = concat!(
+            "2026-10-15 09:25:40.001 UTC [1] LOG:  duration: 0.031 ms  plan:\n",
+            "\tQuery Text: SELECT 1;\n",
+            "\tResult  (cost=0.00..0.01 rows=1 width=4)\n",
+            "2026-10-15 09:25:40.002 UTC [1] LOG:  checkpoint starting: time\n",
+            "2026-10-15 09:25:40.003 UTC [1] LOG:  duration: 0.192 ms  plan:\n",
+            "\t{\"Query Text\": \"SELECT 2;\", \"Plan\": {\"Node Type\": \"Result\",\n",
+            "\t  \"Parallel Aware\": false, \"Startup Cost\": 0.0, \"Total Cost\": 0.01,\n",
+            "\t  \"Plan Rows\": 1, \"Plan Width\": 4}}\n",
+        );
+        let csv = concat!(
+            "2026-10-15 09:25:40.001 UTC,\"postgres\",\"postgres\",1,\"[local]\",1.1,1,",
+            "\"SELECT\",2026-10-15 09:25:39 UTC,3/2,0,LOG,00000,\"duration: 0.031 ms  plan:\n",
+            "Query Text: SELECT \"\"a\"\";\n",
+            "Result  (cost=0.00..0.01 rows=1 width=4)\",,,,,,,,,\"psql\",\"client backend\",,0\n",
+            "2026-10-15 09:25:40.002 UTC,,,1,,1.2,1,,2026-10-15 09:25:39 UTC,,0,LOG,00000,",
+            "\"checkpoint starting: time\",,,,,,,,,\"\",\"checkpointer\",,0\n",
+        );
+        let json = concat!(
+            r#"{"timestamp":"2026-10-15 09:25:40.001 UTC","error_severity":"LOG","#,
+            r#""message":"duration: 0.031 ms  plan:\nQuery Text: SELECT 1;\n"#,
+            r#"Result  (cost=0.00..0.01 rows=1 width=4)"}"#,
+            "\n",
+            r#"{"timestamp":"2026-10-15 09:25:40.002 UTC","message":"checkpoint starting"}"#,
+            "\n",
+        );
+
+        let scan = |log: &str, format| {
+            scan(log.as_bytes(), format)
+                .map(|logged| logged.unwrap().query_text.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(scan(stderr, LogFormat::Stderr), ["SELECT 1;", "SELECT 2;"]);
+        assert_eq!(scan(csv, LogFormat::Csv), ["SELECT \"a\";"]);
+        assert_eq!(scan(json, LogFormat::Json), ["SELECT 1;"]);
+        assert_eq!(
+            LogFormat::from_path("/var/log/postgresql/postgresql.csv"),
+            LogFormat::Csv
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Support for the [`metrics`] crate: reporting the health of captured plans
+//! through the application's metrics pipeline.
+//!
+//! Every captured plan updates the following metrics, labelled with
+//! `query` set to the caller-supplied query name:
+//!
+//! - `diesel_pg_explain_plans_captured_total` (counter): number of captured plans;
+//! - `diesel_pg_explain_plan_total_cost` (histogram): estimated total cost of the root node;
+//! - `diesel_pg_explain_execution_time_seconds` (histogram): execution time, for
+//!   plans captured with `ANALYZE`;
+//! - `diesel_pg_explain_seq_scans_total` (counter): number of `Seq Scan` nodes;
+//! - `diesel_pg_explain_shared_blocks_read_total` (counter): shared blocks read
+//!   from disk, for plans captured with `BUFFERS`.
+
+#[cfg(feature = "diesel-2")]
+use diesel::query_dsl::methods::LoadQuery;
+
+use crate::ExplainPlan;
+#[cfg(feature = "diesel-2")]
+use crate::{Explain, ExplainConnection, ExplainError};
+
+/// Updates the plan metrics described in the [module documentation](self).
+///
+/// Use this when plans are captured by other means than [`Explain::explain_metered()`].
+pub fn record_plan(query_name: &str, plan: &ExplainPlan, execution_time_ms: Option<f64>) {
+    let labels = [("query", query_name.to_owned())];
+
+    metrics::counter!("diesel_pg_explain_plans_captured_total", &labels).increment(1);
+    metrics::histogram!("diesel_pg_explain_plan_total_cost", &labels).record(plan.total_cost);
+    if let Some(execution_time_ms) = execution_time_ms {
+        metrics::histogram!("diesel_pg_explain_execution_time_seconds", &labels)
+            .record(execution_time_ms / 1000.0);
+    }
+
+    let seq_scans = plan
+        .nodes()
+        .filter(|node| node.node_type == "Seq Scan")
+        .count();
+    metrics::counter!("diesel_pg_explain_seq_scans_total", &labels).increment(seq_scans as u64);
+
+    if let Some(blocks_read) = plan.shared_read_blocks {
+        metrics::counter!("diesel_pg_explain_shared_blocks_read_total", &labels)
+            .increment(blocks_read);
+    }
+}
+
+#[cfg(feature = "diesel-2")]
+impl<Q> Explain<Q> {
+    /// Same as [`Explain::explain()`], but additionally reports the plan through
+    /// [`record_plan()`] under the given query name.
+    ///
+    /// Example:
+    /// ```rust
+    /// let plan = my_query
+    ///     .wrap_explain()
+    ///     .explain_metered(&mut conn, "users_by_age")?;
+    /// ```
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn explain_metered<'a, C>(
+        self,
+        conn: &mut C,
+        query_name: &str,
+    ) -> Result<ExplainPlan, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let item = self.explain_item(conn)?;
+        record_plan(query_name, &item.plan, item.execution_time);
+        Ok(item.plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics_util::debugging::DebuggingRecorder;
+
+    use super::*;
+    use crate::testing::node;
+
+    #[test]
+    fn plans_are_recorded_with_query_labels() {
+        let plan = ExplainPlan {
+            total_cost: 42.5,
+            shared_read_blocks: Some(7),
+            ..node("Hash Join", vec![ExplainPlan::scan("Seq Scan", "users")])
+        };
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            record_plan("users_by_age", &plan, Some(250.0));
+        });
+        let mut metrics = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let labels = key.key().labels().map(|label| (label.key(), label.value()));
+                assert_eq!(labels.collect::<Vec<_>>(), [("query", "users_by_age")]);
+                format!("{} {value:?}", key.key().name())
+            })
+            .collect::<Vec<_>>();
+        metrics.sort();
+        assert_eq!(
+            metrics,
+            [
+                "diesel_pg_explain_execution_time_seconds Histogram([0.25])",
+                "diesel_pg_explain_plan_total_cost Histogram([42.5])",
+                "diesel_pg_explain_plans_captured_total Counter(1)",
+                "diesel_pg_explain_seq_scans_total Counter(1)",
+                "diesel_pg_explain_shared_blocks_read_total Counter(7)",
+            ]
+        );
+    }
+}
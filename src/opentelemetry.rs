@@ -0,0 +1,90 @@
+//! Support for [`opentelemetry`]: converting plans into span attributes.
+
+use opentelemetry::KeyValue;
+
+use crate::ExplainPlan;
+
+/// Default limit, in bytes, for the plan JSON attached by [`span_attributes()`].
+pub const DEFAULT_MAX_JSON_LEN: usize = 4096;
+
+/// Converts a plan into span attributes, attaching at most
+/// [`DEFAULT_MAX_JSON_LEN`] bytes of its JSON.
+///
+/// See [`span_attributes_with_limit()`] for the list of attributes.
+///
+/// Example:
+/// ```rust
+/// let plan = my_query.wrap_explain().explain(&mut conn)?;
+/// span.set_attributes(diesel_pg_explain::opentelemetry::span_attributes(&plan));
+/// ```
+pub fn span_attributes(plan: &ExplainPlan) -> Vec<KeyValue> {
+    span_attributes_with_limit(plan, DEFAULT_MAX_JSON_LEN)
+}
+
+/// Converts a plan into the following span attributes:
+///
+/// - `db.plan.top_node`: node type of the root plan node;
+/// - `db.plan.total_cost`: estimated total cost of the root plan node;
+/// - `db.plan.node_count`: number of nodes in the plan tree;
+/// - `db.plan.json`: the plan serialized as JSON, truncated to at most
+///   `max_json_len` bytes;
+/// - `db.plan.json_truncated`: whether `db.plan.json` was truncated.
+pub fn span_attributes_with_limit(plan: &ExplainPlan, max_json_len: usize) -> Vec<KeyValue> {
+    let mut json = serde_json::to_string(plan).unwrap_or_default();
+    let truncated = json.len() > max_json_len;
+    if truncated {
+        let mut end = max_json_len;
+        while !json.is_char_boundary(end) {
+            end -= 1;
+        }
+        json.truncate(end);
+    }
+
+    vec![
+        KeyValue::new("db.plan.top_node", plan.node_type.clone()),
+        KeyValue::new("db.plan.total_cost", plan.total_cost),
+        KeyValue::new("db.plan.node_count", plan.nodes().count() as i64),
+        KeyValue::new("db.plan.json", json),
+        KeyValue::new("db.plan.json_truncated", truncated),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute<'a>(attributes: &'a [KeyValue], key: &str) -> &'a opentelemetry::Value {
+        &attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == key)
+            .unwrap()
+            .value
+    }
+
+    #[test]
+    fn json_is_truncated_at_char_boundaries() {
+        let plan = ExplainPlan::scan("Seq Scan", "città");
+        let full = serde_json::to_string(&plan).unwrap();
+        let accent = full.find('à').unwrap();
+        for limit in [accent + 1, accent + 2, full.len()] {
+            let attributes = span_attributes_with_limit(&plan, limit);
+            let json = attribute(&attributes, "db.plan.json").as_str();
+            assert!(json.len() <= limit);
+            assert!(full.starts_with(json.as_ref()));
+            assert!(std::str::from_utf8(json.as_bytes()).is_ok());
+            assert_eq!(
+                *attribute(&attributes, "db.plan.json_truncated"),
+                opentelemetry::Value::Bool(limit < full.len())
+            );
+        }
+        let attributes = span_attributes_with_limit(&plan, accent + 1);
+        assert_eq!(
+            attribute(&attributes, "db.plan.json").as_str(),
+            full[..accent]
+        );
+        assert_eq!(
+            *attribute(&attributes, "db.plan.node_count"),
+            opentelemetry::Value::I64(1)
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Options of the `EXPLAIN` statement.
+
+use serde::{Deserialize, Serialize};
+
+/// Options passed to `EXPLAIN` in addition to `FORMAT JSON`.
+///
+/// Defaults match PostgreSQL's own defaults, so `ExplainOptions::default()`
+/// produces a plain `EXPLAIN (FORMAT JSON)`.
+///
+/// Example:
+/// ```rust
+/// let options = ExplainOptions::default().analyze(true).buffers(true);
+/// let plan = my_query.wrap_explain().options(options).explain(&mut conn)?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ExplainOptions {
+    /// Executes the query and reports actual row counts and times.
+    ///
+    /// This runs the statement; it is wrapped into a transaction which is always
+    /// rolled back, so changes made by DML are discarded.
+    pub analyze: bool,
+
+    /// Reports output columns, schema-qualified names and other details.
+    pub verbose: bool,
+
+    /// Reports estimated costs, row counts and widths.
+    pub costs: bool,
+
+    /// Reports planner settings which differ from their defaults.
+    /// Requires PostgreSQL 12.
+    pub settings: bool,
+
+    /// Reports buffer usage.
+    pub buffers: bool,
+
+    /// Reports WAL usage. Requires `analyze` and PostgreSQL 13.
+    pub wal: bool,
+
+    /// Reports actual startup and total times of each node. Only meaningful with `analyze`.
+    pub timing: bool,
+
+    /// Reports planning and execution times. PostgreSQL enables it by default
+    /// together with `analyze`; `None` keeps that behavior.
+    pub summary: Option<bool>,
+}
+
+impl Default for ExplainOptions {
+    fn default() -> Self {
+        Self {
+            analyze: false,
+            verbose: false,
+            costs: true,
+            settings: false,
+            buffers: false,
+            wal: false,
+            timing: true,
+            summary: None,
+        }
+    }
+}
+
+impl ExplainOptions {
+    /// Sets the `ANALYZE` option.
+    pub fn analyze(self, analyze: bool) -> Self {
+        Self { analyze, ..self }
+    }
+
+    /// Sets the `VERBOSE` option.
+    pub fn verbose(self, verbose: bool) -> Self {
+        Self { verbose, ..self }
+    }
+
+    /// Sets the `COSTS` option.
+    pub fn costs(self, costs: bool) -> Self {
+        Self { costs, ..self }
+    }
+
+    /// Sets the `SETTINGS` option.
+    pub fn settings(self, settings: bool) -> Self {
+        Self { settings, ..self }
+    }
+
+    /// Sets the `BUFFERS` option.
+    pub fn buffers(self, buffers: bool) -> Self {
+        Self { buffers, ..self }
+    }
+
+    /// Sets the `WAL` option.
+    pub fn wal(self, wal: bool) -> Self {
+        Self { wal, ..self }
+    }
+
+    /// Sets the `TIMING` option.
+    pub fn timing(self, timing: bool) -> Self {
+        Self { timing, ..self }
+    }
+
+    /// Sets the `SUMMARY` option.
+    pub fn summary(self, summary: bool) -> Self {
+        Self {
+            summary: Some(summary),
+            ..self
+        }
+    }
+
+    /// Returns the `EXPLAIN (...)` prefix, including a trailing space, listing only
+    /// the options which differ from PostgreSQL's defaults.
+    #[cfg(feature = "diesel-2")]
+    pub(crate) fn to_sql(self) -> String {
+        let defaults = Self::default();
+        let flags = [
+            ("ANALYZE", self.analyze, defaults.analyze),
+            ("VERBOSE", self.verbose, defaults.verbose),
+            ("COSTS", self.costs, defaults.costs),
+            ("SETTINGS", self.settings, defaults.settings),
+            ("BUFFERS", self.buffers, defaults.buffers),
+            ("WAL", self.wal, defaults.wal),
+            ("TIMING", self.timing, defaults.timing),
+        ];
+
+        let mut sql = String::from("EXPLAIN (FORMAT JSON");
+        for (name, value, default) in flags {
+            if value != default {
+                sql.push_str(&format!(", {name} {value}"));
+            }
+        }
+        if let Some(summary) = self.summary {
+            sql.push_str(&format!(", SUMMARY {summary}"));
+        }
+        sql.push_str(") ");
+        sql
+    }
+}
@@ -0,0 +1,110 @@
+//! Importing plans shared through [pev2](https://github.com/dalibo/pev2) and
+//! [explain.dalibo.com](https://explain.dalibo.com).
+//!
+//! These tools keep a plan in an envelope together with its title and query,
+//! e.g. `{"title": "...", "plan": "...", "query": "..."}`, where the plan is the
+//! `EXPLAIN` output pasted by the user, in the JSON or the text format. [`parse()`]
+//! turns such an envelope back into the crate's types, so plans shared by DBAs can
+//! be linted and diffed like captured ones.
+//!
+//! Example:
+//! ```rust
+//! let shared = pev2::parse(&std::fs::read_to_string("slow-report.json")?)?;
+//! for finding in lint::check(&shared.item.plan) {
+//!     println!("{}: {finding}", shared.title.as_deref().unwrap_or("untitled"));
+//! }
+//! ```
+
+use serde::Deserialize;
+
+use crate::{deserialize, render, single_item, ExplainError, ExplainItem};
+
+/// A plan imported from pev2, see [`parse()`].
+#[derive(Debug)]
+pub struct SharedPlan {
+    /// The title given to the plan, if any.
+    pub title: Option<String>,
+
+    /// The query the plan belongs to, if it was shared.
+    pub query: Option<String>,
+
+    /// The plan.
+    pub item: ExplainItem,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    title: Option<String>,
+    plan: serde_json::Value,
+    #[serde(default)]
+    query: Option<String>,
+}
+
+/// Parses a pev2 envelope. The plan may be given as a string holding `EXPLAIN`
+/// output in the JSON or text format, as pev2 stores it, or as the JSON document
+/// itself. Empty titles and queries are reported as `None`.
+///
+/// # Errors
+/// Returns [`ExplainError::Parse`] if `json` is not an envelope or holds an
+/// invalid JSON plan, [`ExplainError::ParseText`] if it holds an invalid text
+/// plan, and [`ExplainError::MultipleResults`] if it holds several plans.
+pub fn parse(json: &str) -> Result<SharedPlan, ExplainError> {
+    let envelope: Envelope = deserialize(json)?;
+    let item = match &envelope.plan {
+        serde_json::Value::String(source) => parse_source(source)?,
+        plan => single_item(render::parse_output(&plan.to_string())?)?,
+    };
+    let non_empty = |text: Option<String>| text.filter(|text| !text.trim().is_empty());
+    Ok(SharedPlan {
+        title: non_empty(envelope.title),
+        query: non_empty(envelope.query),
+        item,
+    })
+}
+
+/// Parses `EXPLAIN` output in either format, telling them apart by the first
+/// character after `psql`'s `QUERY PLAN` header.
+fn parse_source(source: &str) -> Result<ExplainItem, ExplainError> {
+    let body = source
+        .lines()
+        .map(str::trim)
+        .find(|line| {
+            !line.is_empty() && *line != "QUERY PLAN" && !line.chars().all(|c| c == '-' || c == '+')
+        })
+        .unwrap_or_default();
+    if body.starts_with(['[', '{']) {
+        single_item(render::parse_output(source)?)
+    } else {
+        ExplainItem::from_text(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pev2_envelopes_are_imported() {
+        let text = r#"{"title": "slow users", "query": "SELECT * FROM users",
+            "plan": "Seq Scan on users  (cost=0.00..35.50 rows=2550 width=4)\n  Filter: (age > 30)"}"#;
+        let shared = parse(text).unwrap();
+        assert_eq!(shared.title.as_deref(), Some("slow users"));
+        assert_eq!(shared.query.as_deref(), Some("SELECT * FROM users"));
+        assert_eq!(shared.item.plan.filter.as_deref(), Some("(age > 30)"));
+
+        let plan = r#"{"Plan": {"Node Type": "Result", "Parallel Aware": false,
+            "Startup Cost": 0.0, "Total Cost": 0.01, "Plan Rows": 1, "Plan Width": 4}}"#;
+        let psql = format!("QUERY PLAN\n----------\n [{plan}]\n(1 row)");
+        let json = serde_json::json!({"title": "", "plan": psql}).to_string();
+        let shared = parse(&json).unwrap();
+        assert_eq!(
+            (shared.title, shared.item.plan.node_type.as_str()),
+            (None, "Result")
+        );
+
+        let nested = format!(r#"{{"plan": [{plan}]}}"#);
+        assert_eq!(parse(&nested).unwrap().item.plan.total_cost, 0.01);
+        assert!(matches!(parse("{}"), Err(ExplainError::Parse { .. })));
+    }
+}
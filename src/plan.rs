@@ -0,0 +1,1454 @@
+//! Types describing the `EXPLAIN (FORMAT JSON)` output.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{single_item, ExplainError};
+
+/// Recursive struct which describes the plan of a query
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ExplainPlan {
+    /// The type of the plan node (e.g., "Seq Scan", "Nested Loop", "Hash Join").
+    /// Indicates the operation performed at this step in the query execution plan.
+    #[serde(rename = "Node Type")]
+    pub node_type: String,
+
+    /// Implementation strategy of an `Aggregate` or `SetOp` node:
+    /// "Plain", "Sorted", "Hashed" or "Mixed".
+    #[serde(rename = "Strategy", default)]
+    pub strategy: Option<String>,
+
+    /// Aggregation mode of an `Aggregate` node in a parallel plan:
+    /// "Simple", "Partial" or "Finalize".
+    #[serde(rename = "Partial Mode", default)]
+    pub partial_mode: Option<String>,
+
+    /// Operation performed by a `ModifyTable` node:
+    /// "Insert", "Update", "Delete" or "Merge".
+    #[serde(rename = "Operation", default)]
+    pub operation: Option<String>,
+
+    /// The relationship of this node to its parent in the plan tree.
+    /// Common values include:
+    /// - "Outer": This node is the outer input to a join (e.g., Nested Loop).
+    /// - "Inner": This node is the inner input to a join.
+    /// - "Subquery": This node is part of a subquery.
+    /// - "InitPlan", "SubPlan", "Member": Special plan node roles.
+    ///
+    /// May be `None` for root nodes or when not applicable.
+    #[serde(rename = "Parent Relationship", default)]
+    pub parent_relationship: Option<String>,
+
+    /// Name of the subplan rooted at this node, e.g. "SubPlan 1" or "CTE x".
+    #[serde(rename = "Subplan Name", default)]
+    pub subplan_name: Option<String>,
+
+    /// Name of the extension providing a `Custom Scan` node.
+    #[serde(rename = "Custom Plan Provider", default)]
+    pub custom_plan_provider: Option<String>,
+
+    /// Indicates whether the plan node is aware of parallel query execution.
+    /// If true, the node may participate in or benefit from parallelism.
+    #[serde(rename = "Parallel Aware")]
+    pub parallel_aware: bool,
+
+    /// Indicates whether the node supports asynchronous execution.
+    /// Async-capable nodes can execute operations concurrently with others,
+    /// improving performance in some plans (especially with I/O or remote sources).
+    ///
+    /// Reported since PostgreSQL 14; `false` for plans from older servers.
+    #[serde(rename = "Async Capable", default)]
+    pub async_capable: bool,
+
+    /// Type of a join node: "Inner", "Left", "Full", "Right", "Semi", "Anti", ...
+    #[serde(rename = "Join Type", default)]
+    pub join_type: Option<String>,
+
+    /// Whether the planner proved that at most one inner row matches each outer row.
+    #[serde(rename = "Inner Unique", default)]
+    pub inner_unique: Option<bool>,
+
+    /// Set operation of a `SetOp` node, e.g. "Intersect" or "Except All".
+    #[serde(rename = "Command", default)]
+    pub command: Option<String>,
+
+    /// Direction of an index scan: "Forward", "Backward" or "NoMovement".
+    #[serde(rename = "Scan Direction", default)]
+    pub scan_direction: Option<String>,
+
+    /// Name of the index used by an index scan.
+    #[serde(rename = "Index Name", default)]
+    pub index_name: Option<String>,
+
+    /// Name of the table read or modified by this node.
+    #[serde(rename = "Relation Name", default)]
+    pub relation_name: Option<String>,
+
+    /// Name of the set-returning function read by a `Function Scan`.
+    #[serde(rename = "Function Name", default)]
+    pub function_name: Option<String>,
+
+    /// Name of the table function read by a `Table Function Scan`.
+    #[serde(rename = "Table Function Name", default)]
+    pub table_function_name: Option<String>,
+
+    /// Name of the common table expression read by a `CTE Scan`.
+    #[serde(rename = "CTE Name", default)]
+    pub cte_name: Option<String>,
+
+    /// Name of the tuplestore read by a `Named Tuplestore Scan`.
+    #[serde(rename = "Tuplestore Name", default)]
+    pub tuplestore_name: Option<String>,
+
+    /// Schema of `relation_name`. Reported with `VERBOSE`.
+    #[serde(rename = "Schema", default)]
+    pub schema: Option<String>,
+
+    /// Alias under which the relation appears in the query.
+    #[serde(rename = "Alias", default)]
+    pub alias: Option<String>,
+
+    /// The estimated cost of starting this plan node.
+    /// This typically includes one-time setup costs, like initializing data structures.
+    #[serde(rename = "Startup Cost")]
+    pub startup_cost: f64,
+
+    /// The estimated total cost of fully executing this plan node,
+    /// including startup and all tuple processing.
+    #[serde(rename = "Total Cost")]
+    pub total_cost: f64,
+
+    /// The estimated number of rows this plan node will output.
+    /// This is a planner estimate, not an actual runtime value.
+    #[serde(rename = "Plan Rows")]
+    pub plan_rows: u64,
+
+    /// The estimated average width (in bytes) of each row produced by this node.
+    /// Useful for understanding memory and I/O implications.
+    #[serde(rename = "Plan Width")]
+    pub plan_width: u64,
+
+    /// Time in milliseconds until this node returned its first row, averaged per loop.
+    ///
+    /// This and the following actual values are reported with `ANALYZE`; times are
+    /// omitted when `TIMING` is off.
+    #[serde(rename = "Actual Startup Time", default)]
+    pub actual_startup_time: Option<f64>,
+
+    /// Time in milliseconds spent in this node and its children, averaged per loop.
+    /// See [`total_actual_time()`](Self::total_actual_time) for the time over all loops.
+    #[serde(rename = "Actual Total Time", default)]
+    pub actual_total_time: Option<f64>,
+
+    /// Number of rows returned by this node, averaged per loop.
+    /// See [`total_actual_rows()`](Self::total_actual_rows) for the rows over all loops.
+    #[serde(rename = "Actual Rows", default)]
+    pub actual_rows: Option<f64>,
+
+    /// Number of times this node was executed.
+    #[serde(rename = "Actual Loops", default)]
+    pub actual_loops: Option<u64>,
+
+    /// Output column expressions of this node. Reported with `VERBOSE`.
+    #[serde(rename = "Output", default)]
+    pub output: Option<Vec<String>>,
+
+    /// Sort key expressions of a `Sort`, `Incremental Sort` or `Merge Append` node.
+    #[serde(rename = "Sort Key", default)]
+    pub sort_key: Option<Vec<String>>,
+
+    /// Leading sort keys already provided by the input of an `Incremental Sort`.
+    #[serde(rename = "Presorted Key", default)]
+    pub presorted_key: Option<Vec<String>>,
+
+    /// Grouping expressions of an `Aggregate` or `Group` node.
+    #[serde(rename = "Group Key", default)]
+    pub group_key: Option<Vec<String>>,
+
+    /// Grouping sets of an `Aggregate` node using `GROUPING SETS`, `CUBE` or `ROLLUP`.
+    #[serde(rename = "Grouping Sets", default)]
+    pub grouping_sets: Option<serde_json::Value>,
+
+    /// Join condition of a `Merge Join`.
+    #[serde(rename = "Merge Cond", default)]
+    pub merge_cond: Option<String>,
+
+    /// Join condition of a `Hash Join`.
+    #[serde(rename = "Hash Cond", default)]
+    pub hash_cond: Option<String>,
+
+    /// Condition applied to joined rows which could not be used as the join condition.
+    #[serde(rename = "Join Filter", default)]
+    pub join_filter: Option<String>,
+
+    /// Rows discarded by `join_filter`, averaged per loop. Reported with `ANALYZE`.
+    #[serde(rename = "Rows Removed by Join Filter", default)]
+    pub rows_removed_by_join_filter: Option<f64>,
+
+    /// Condition applied to rows produced by this node.
+    #[serde(rename = "Filter", default)]
+    pub filter: Option<String>,
+
+    /// Rows discarded by `filter`, averaged per loop. Reported with `ANALYZE`.
+    #[serde(rename = "Rows Removed by Filter", default)]
+    pub rows_removed_by_filter: Option<f64>,
+
+    /// Condition evaluated using the index of an index scan.
+    #[serde(rename = "Index Cond", default)]
+    pub index_cond: Option<String>,
+
+    /// Rows discarded when rechecking `index_cond` or `recheck_cond` against the heap,
+    /// averaged per loop. Reported with `ANALYZE`.
+    #[serde(rename = "Rows Removed by Index Recheck", default)]
+    pub rows_removed_by_index_recheck: Option<f64>,
+
+    /// Condition rechecked against heap rows by a `Bitmap Heap Scan`.
+    #[serde(rename = "Recheck Cond", default)]
+    pub recheck_cond: Option<String>,
+
+    /// Ordering operators evaluated by the index, e.g. for nearest-neighbour searches.
+    #[serde(rename = "Order By", default)]
+    pub order_by: Option<String>,
+
+    /// Condition on `ctid` evaluated by a `Tid Scan` or `Tid Range Scan`.
+    #[serde(rename = "TID Cond", default)]
+    pub tid_cond: Option<String>,
+
+    /// Condition evaluated once to decide whether the node runs at all.
+    #[serde(rename = "One-Time Filter", default)]
+    pub one_time_filter: Option<String>,
+
+    /// Condition which stops a `WindowAgg` node early.
+    #[serde(rename = "Run Condition", default)]
+    pub run_condition: Option<String>,
+
+    /// Number of heap rows fetched by an `Index Only Scan` because of the visibility map.
+    /// Reported with `ANALYZE`.
+    #[serde(rename = "Heap Fetches", default)]
+    pub heap_fetches: Option<u64>,
+
+    /// Number of parallel workers planned by a `Gather` or `Gather Merge` node.
+    #[serde(rename = "Workers Planned", default)]
+    pub workers_planned: Option<u64>,
+
+    /// Number of parallel workers actually launched. Reported with `ANALYZE`.
+    #[serde(rename = "Workers Launched", default)]
+    pub workers_launched: Option<u64>,
+
+    /// Whether a `Gather` node runs its child in a single process only.
+    #[serde(rename = "Single Copy", default)]
+    pub single_copy: Option<bool>,
+
+    /// Algorithm used by a `Sort` node, e.g. "quicksort", "top-N heapsort" or
+    /// "external merge". Reported with `ANALYZE`.
+    #[serde(rename = "Sort Method", default)]
+    pub sort_method: Option<String>,
+
+    /// Memory or disk space in kilobytes used by a `Sort` node. Reported with `ANALYZE`.
+    #[serde(rename = "Sort Space Used", default)]
+    pub sort_space_used: Option<u64>,
+
+    /// Where the sort took place: "Memory" or "Disk". Reported with `ANALYZE`.
+    #[serde(rename = "Sort Space Type", default)]
+    pub sort_space_type: Option<String>,
+
+    /// Statistics of groups sorted in full by an `Incremental Sort`. Reported with `ANALYZE`.
+    #[serde(rename = "Full-sort Groups", default)]
+    pub full_sort_groups: Option<serde_json::Value>,
+
+    /// Statistics of presorted groups of an `Incremental Sort`. Reported with `ANALYZE`.
+    #[serde(rename = "Pre-sorted Groups", default)]
+    pub pre_sorted_groups: Option<serde_json::Value>,
+
+    /// Number of buckets of the hash table built by a `Hash` node. Reported with `ANALYZE`.
+    #[serde(rename = "Hash Buckets", default)]
+    pub hash_buckets: Option<u64>,
+
+    /// Number of hash buckets planned before the hash table had to grow.
+    #[serde(rename = "Original Hash Buckets", default)]
+    pub original_hash_buckets: Option<u64>,
+
+    /// Number of batches the hash table was split into; more than one means it
+    /// spilled to disk. Reported with `ANALYZE`.
+    #[serde(rename = "Hash Batches", default)]
+    pub hash_batches: Option<u64>,
+
+    /// Number of hash batches planned before the hash table had to grow.
+    #[serde(rename = "Original Hash Batches", default)]
+    pub original_hash_batches: Option<u64>,
+
+    /// Peak memory in kilobytes used by a `Hash`, `HashAggregate` or `Memoize` node.
+    /// Reported with `ANALYZE`.
+    #[serde(rename = "Peak Memory Usage", default)]
+    pub peak_memory_usage: Option<u64>,
+
+    /// Disk space in kilobytes used by a spilling `HashAggregate`. Reported with `ANALYZE`.
+    #[serde(rename = "Disk Usage", default)]
+    pub disk_usage: Option<u64>,
+
+    /// Number of batches of a spilling `HashAggregate`. Reported with `ANALYZE`.
+    #[serde(rename = "HashAgg Batches", default)]
+    pub hashagg_batches: Option<u64>,
+
+    /// Number of partitions a `HashAggregate` planned to spill into.
+    #[serde(rename = "Planned Partitions", default)]
+    pub planned_partitions: Option<u64>,
+
+    /// Heap blocks of a `Bitmap Heap Scan` tracked exactly. Reported with `ANALYZE`.
+    #[serde(rename = "Exact Heap Blocks", default)]
+    pub exact_heap_blocks: Option<u64>,
+
+    /// Heap blocks of a `Bitmap Heap Scan` tracked lossily because the bitmap
+    /// exceeded `work_mem`. Reported with `ANALYZE`.
+    #[serde(rename = "Lossy Heap Blocks", default)]
+    pub lossy_heap_blocks: Option<u64>,
+
+    /// Number of `Append` or `Merge Append` children removed by partition pruning.
+    #[serde(rename = "Subplans Removed", default)]
+    pub subplans_removed: Option<u64>,
+
+    /// Parameters used as the cache key of a `Memoize` node.
+    #[serde(rename = "Cache Key", default)]
+    pub cache_key: Option<String>,
+
+    /// Cache mode of a `Memoize` node: "logical" or "binary".
+    #[serde(rename = "Cache Mode", default)]
+    pub cache_mode: Option<String>,
+
+    /// Number of cache hits of a `Memoize` node. Reported with `ANALYZE`.
+    #[serde(rename = "Cache Hits", default)]
+    pub cache_hits: Option<u64>,
+
+    /// Number of cache misses of a `Memoize` node. Reported with `ANALYZE`.
+    #[serde(rename = "Cache Misses", default)]
+    pub cache_misses: Option<u64>,
+
+    /// Number of cache evictions of a `Memoize` node. Reported with `ANALYZE`.
+    #[serde(rename = "Cache Evictions", default)]
+    pub cache_evictions: Option<u64>,
+
+    /// Number of times a `Memoize` cache entry did not fit into memory.
+    /// Reported with `ANALYZE`.
+    #[serde(rename = "Cache Overflows", default)]
+    pub cache_overflows: Option<u64>,
+
+    /// Where a `Material`, `CTE Scan` or `WindowAgg` tuplestore lived: "Memory" or
+    /// "Disk". Reported with `ANALYZE` since PostgreSQL 17.
+    #[serde(rename = "Storage", default)]
+    pub storage: Option<String>,
+
+    /// Maximum tuplestore size in kilobytes. Reported with `ANALYZE` since PostgreSQL 17.
+    #[serde(rename = "Maximum Storage", default)]
+    pub maximum_storage: Option<u64>,
+
+    /// Sampling method of a `Sample Scan`, e.g. "bernoulli".
+    #[serde(rename = "Sampling Method", default)]
+    pub sampling_method: Option<String>,
+
+    /// Arguments of the sampling method of a `Sample Scan`.
+    #[serde(rename = "Sampling Parameters", default)]
+    pub sampling_parameters: Option<Vec<String>>,
+
+    /// `REPEATABLE` seed of a `Sample Scan`.
+    #[serde(rename = "Repeatable Seed", default)]
+    pub repeatable_seed: Option<String>,
+
+    /// Function call expression of a `Function Scan`. Reported with `VERBOSE`.
+    #[serde(rename = "Function Call", default)]
+    pub function_call: Option<String>,
+
+    /// Table function expression of a `Table Function Scan`. Reported with `VERBOSE`.
+    #[serde(rename = "Table Function Call", default)]
+    pub table_function_call: Option<String>,
+
+    /// SQL sent to the remote server by a `postgres_fdw` `Foreign Scan`.
+    /// Reported with `VERBOSE`.
+    #[serde(rename = "Remote SQL", default)]
+    pub remote_sql: Option<String>,
+
+    /// `ON CONFLICT` action of an `INSERT`: "NOTHING" or "UPDATE".
+    #[serde(rename = "Conflict Resolution", default)]
+    pub conflict_resolution: Option<String>,
+
+    /// Indexes used to detect `ON CONFLICT` conflicts.
+    #[serde(rename = "Conflict Arbiter Indexes", default)]
+    pub conflict_arbiter_indexes: Option<Vec<String>>,
+
+    /// `WHERE` condition of an `ON CONFLICT DO UPDATE` clause.
+    #[serde(rename = "Conflict Filter", default)]
+    pub conflict_filter: Option<String>,
+
+    /// Conflicting rows skipped because of `conflict_filter`. Reported with `ANALYZE`.
+    #[serde(rename = "Rows Removed by Conflict Filter", default)]
+    pub rows_removed_by_conflict_filter: Option<f64>,
+
+    /// Rows inserted by an `INSERT ... ON CONFLICT`. Reported with `ANALYZE`.
+    #[serde(rename = "Tuples Inserted", default)]
+    pub tuples_inserted: Option<f64>,
+
+    /// Rows which hit a conflict in an `INSERT ... ON CONFLICT`. Reported with `ANALYZE`.
+    #[serde(rename = "Conflicting Tuples", default)]
+    pub conflicting_tuples: Option<f64>,
+
+    /// Tables modified by a `ModifyTable` node on an inheritance or partitioning tree.
+    #[serde(rename = "Target Tables", default)]
+    pub target_tables: Option<serde_json::Value>,
+
+    /// Number of shared buffer blocks found in the buffer cache.
+    ///
+    /// This and the following block counters are reported with the `BUFFERS`
+    /// option and include the counts of all child nodes.
+    #[serde(rename = "Shared Hit Blocks", default)]
+    pub shared_hit_blocks: Option<u64>,
+
+    /// Number of shared buffer blocks read from disk (or the OS cache).
+    #[serde(rename = "Shared Read Blocks", default)]
+    pub shared_read_blocks: Option<u64>,
+
+    /// Number of previously unmodified shared blocks changed by this node.
+    #[serde(rename = "Shared Dirtied Blocks", default)]
+    pub shared_dirtied_blocks: Option<u64>,
+
+    /// Number of shared blocks evicted from the cache and written out by this node.
+    #[serde(rename = "Shared Written Blocks", default)]
+    pub shared_written_blocks: Option<u64>,
+
+    /// Number of local (temporary table) blocks found in the buffer cache.
+    #[serde(rename = "Local Hit Blocks", default)]
+    pub local_hit_blocks: Option<u64>,
+
+    /// Number of local (temporary table) blocks read from disk.
+    #[serde(rename = "Local Read Blocks", default)]
+    pub local_read_blocks: Option<u64>,
+
+    /// Number of previously unmodified local blocks changed by this node.
+    #[serde(rename = "Local Dirtied Blocks", default)]
+    pub local_dirtied_blocks: Option<u64>,
+
+    /// Number of local blocks evicted from the cache and written out by this node.
+    #[serde(rename = "Local Written Blocks", default)]
+    pub local_written_blocks: Option<u64>,
+
+    /// Number of blocks of short-term working data (sorts, hashes) read from disk.
+    #[serde(rename = "Temp Read Blocks", default)]
+    pub temp_read_blocks: Option<u64>,
+
+    /// Number of blocks of short-term working data (sorts, hashes) written to disk.
+    #[serde(rename = "Temp Written Blocks", default)]
+    pub temp_written_blocks: Option<u64>,
+
+    /// Time in milliseconds spent reading data blocks. Reported with `BUFFERS` when
+    /// `track_io_timing` is on, up to PostgreSQL 16.
+    #[serde(rename = "I/O Read Time", default)]
+    pub io_read_time: Option<f64>,
+
+    /// Time in milliseconds spent writing data blocks, up to PostgreSQL 16.
+    #[serde(rename = "I/O Write Time", default)]
+    pub io_write_time: Option<f64>,
+
+    /// Time in milliseconds spent reading shared blocks. Replaces `io_read_time`
+    /// since PostgreSQL 17.
+    #[serde(rename = "Shared I/O Read Time", default)]
+    pub shared_io_read_time: Option<f64>,
+
+    /// Time in milliseconds spent writing shared blocks, since PostgreSQL 17.
+    #[serde(rename = "Shared I/O Write Time", default)]
+    pub shared_io_write_time: Option<f64>,
+
+    /// Time in milliseconds spent reading local blocks, since PostgreSQL 17.
+    #[serde(rename = "Local I/O Read Time", default)]
+    pub local_io_read_time: Option<f64>,
+
+    /// Time in milliseconds spent writing local blocks, since PostgreSQL 17.
+    #[serde(rename = "Local I/O Write Time", default)]
+    pub local_io_write_time: Option<f64>,
+
+    /// Time in milliseconds spent reading temporary blocks, since PostgreSQL 15.
+    #[serde(rename = "Temp I/O Read Time", default)]
+    pub temp_io_read_time: Option<f64>,
+
+    /// Time in milliseconds spent writing temporary blocks, since PostgreSQL 15.
+    #[serde(rename = "Temp I/O Write Time", default)]
+    pub temp_io_write_time: Option<f64>,
+
+    /// Number of WAL records generated. Reported with `WAL`.
+    #[serde(rename = "WAL Records", default)]
+    pub wal_records: Option<u64>,
+
+    /// Number of WAL full page images generated. Reported with `WAL`.
+    #[serde(rename = "WAL FPI", default)]
+    pub wal_fpi: Option<u64>,
+
+    /// Amount of WAL generated in bytes. Reported with `WAL`.
+    #[serde(rename = "WAL Bytes", default)]
+    pub wal_bytes: Option<u64>,
+
+    /// Per-worker statistics of a node executed by parallel workers.
+    /// Reported with `ANALYZE` and `VERBOSE`. See
+    /// [`worker_details()`](Self::worker_details) for typed access.
+    #[serde(rename = "Workers", default)]
+    pub workers: Option<Vec<serde_json::Value>>,
+
+    /// Child plan nodes that this node depends on or drives.
+    /// For example, a join node will typically have two child plans (inner and outer).
+    #[serde(rename = "Plans", default)]
+    pub plans: Vec<ExplainPlan>,
+
+    /// Fields reported by PostgreSQL which are not modelled above, keyed by their
+    /// original names. Always empty for plans parsed in [`ParseMode::Strict`].
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl ExplainPlan {
+    /// Creates a node of `node_type` with zero costs and no children, e.g. to build
+    /// plans for tests. Set further fields with struct update syntax:
+    /// ```rust
+    /// let plan = ExplainPlan {
+    ///     total_cost: 42.0,
+    ///     plans: vec![ExplainPlan::scan("Seq Scan", "users")],
+    ///     ..ExplainPlan::new("Limit")
+    /// };
+    /// ```
+    pub fn new(node_type: impl Into<String>) -> Self {
+        Self {
+            node_type: node_type.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a node of `node_type` scanning `relation`, aliased by its name as
+    /// PostgreSQL does for tables without an alias in the query.
+    pub fn scan(node_type: impl Into<String>, relation: impl Into<String>) -> Self {
+        let relation = relation.into();
+        Self {
+            alias: Some(relation.clone()),
+            relation_name: Some(relation),
+            ..Self::new(node_type)
+        }
+    }
+
+    /// Iterates over this node and all of its descendants in depth-first pre-order.
+    pub fn nodes(&self) -> impl Iterator<Item = &ExplainPlan> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.plans.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// Returns `true` if `other` has the same shape as this plan: the same node
+    /// types, accessing the same relations, indexes and functions with the same
+    /// join types and strategies, nested the same way. Costs, row counts, actual
+    /// statistics and aliases are ignored, unlike with `==`.
+    ///
+    /// Example:
+    /// ```rust
+    /// let before = query.wrap_explain().explain(&mut conn)?;
+    /// conn.batch_execute("ANALYZE users")?;
+    /// let after = query.wrap_explain().explain(&mut conn)?;
+    /// assert!(before.structurally_eq(&after), "ANALYZE changed the plan");
+    /// ```
+    pub fn structurally_eq(&self, other: &ExplainPlan) -> bool {
+        self.shape() == other.shape()
+            && self.plans.len() == other.plans.len()
+            && self
+                .plans
+                .iter()
+                .zip(&other.plans)
+                .all(|(a, b)| a.structurally_eq(b))
+    }
+
+    /// Returns the time in milliseconds spent in this node and its children over
+    /// all loops, i.e. [`actual_time_per_loop()`](Self::actual_time_per_loop)
+    /// times the number of loops. `None` without `ANALYZE` or timing.
+    ///
+    /// For the inner side of a `Nested Loop` this is the time that side really
+    /// took, while the reported time is that of a single loop. Below a `Gather`,
+    /// loops include the parallel workers, so the result is the work done by all
+    /// processes together rather than elapsed time.
+    pub fn total_actual_time(&self) -> Option<f64> {
+        Some(self.actual_total_time? * self.actual_loops.unwrap_or(1) as f64)
+    }
+
+    /// Returns the time in milliseconds spent in this node and its children,
+    /// averaged per loop, as reported by PostgreSQL in `Actual Total Time`.
+    pub fn actual_time_per_loop(&self) -> Option<f64> {
+        self.actual_total_time
+    }
+
+    /// Returns the time in milliseconds until this node returned its first row,
+    /// averaged per loop, as reported by PostgreSQL in `Actual Startup Time`.
+    pub fn actual_startup_time_per_loop(&self) -> Option<f64> {
+        self.actual_startup_time
+    }
+
+    /// Returns the number of rows this node returned over all loops, i.e.
+    /// [`actual_rows_per_loop()`](Self::actual_rows_per_loop) times the number of
+    /// loops. `None` without `ANALYZE`.
+    pub fn total_actual_rows(&self) -> Option<f64> {
+        Some(self.actual_rows? * self.actual_loops.unwrap_or(1) as f64)
+    }
+
+    /// Returns the number of rows this node returned, averaged per loop, as
+    /// reported by PostgreSQL in `Actual Rows`.
+    pub fn actual_rows_per_loop(&self) -> Option<f64> {
+        self.actual_rows
+    }
+
+    /// Returns by how many times the row estimate is off in either direction,
+    /// ignoring differences below one row, see
+    /// [`worst_misestimates()`](Self::worst_misestimates).
+    pub(crate) fn misestimate_factor(&self) -> Option<f64> {
+        self.estimate_ratio()?;
+        let actual = self.actual_rows?.max(1.0);
+        let estimated = self.plan_rows.max(1) as f64;
+        Some((actual / estimated).max(estimated / actual))
+    }
+
+    /// Parses the per-worker statistics in [`workers`](Self::workers), e.g. to
+    /// compare the sort methods and memory of the workers of a parallel `Sort`.
+    /// Empty unless the plan was captured with `ANALYZE` and `VERBOSE` and the
+    /// node ran in parallel workers.
+    ///
+    /// Example:
+    /// ```rust
+    /// for node in plan.nodes() {
+    ///     for worker in node.worker_details()? {
+    ///         if worker.sort_space_type.as_deref() == Some("Disk") {
+    ///             println!("worker {:?} of {} spilled", worker.worker_number, node.node_type);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Parse`] if an entry has an unexpected shape.
+    pub fn worker_details(&self) -> Result<Vec<WorkerDetail>, ExplainError> {
+        let Some(workers) = &self.workers else {
+            return Ok(Vec::new());
+        };
+        workers
+            .iter()
+            .enumerate()
+            .map(|(i, worker)| {
+                parse_section(Some(worker), &format!("Workers[{i}]")).map(Option::unwrap_or_default)
+            })
+            .collect()
+    }
+
+    /// Returns how many times more rows this node produced than the planner
+    /// estimated, e.g. `100.0` for a node estimated at 10 rows returning 1000, or
+    /// `0.01` the other way round. `None` for plans captured without `ANALYZE` and
+    /// for nodes which were never executed.
+    ///
+    /// Both `Plan Rows` and `Actual Rows` are per loop, so this is the same as
+    /// comparing the totals over all loops, i.e. `actual_rows * loops` against
+    /// `plan_rows * loops`. Estimates of zero rows, which PostgreSQL never makes
+    /// but plans captured with `COSTS OFF` contain, are treated as one row.
+    pub fn estimate_ratio(&self) -> Option<f64> {
+        let actual = self.actual_rows?;
+        if self.actual_loops == Some(0) {
+            return None;
+        }
+        Some(actual / self.plan_rows.max(1) as f64)
+    }
+
+    /// Returns up to `n` nodes of this plan with their
+    /// [`estimate_ratio()`](Self::estimate_ratio), the worst row count
+    /// misestimates first, whether over- or underestimates. Differences below one
+    /// row, e.g. 0.5 rows per loop estimated as 1, don't count as misestimates.
+    ///
+    /// Example:
+    /// ```rust
+    /// let plan = query.wrap_explain().explain_analyze(&mut conn)?;
+    /// for (node, ratio) in plan.worst_misestimates(3) {
+    ///     println!("{}: {ratio:.2}x the estimated rows", node.node_type);
+    /// }
+    /// ```
+    pub fn worst_misestimates(&self, n: usize) -> Vec<(&ExplainPlan, f64)> {
+        let mut nodes = self
+            .nodes()
+            .filter_map(|node| Some((node, node.estimate_ratio()?, node.misestimate_factor()?)))
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| b.2.total_cmp(&a.2));
+        nodes
+            .into_iter()
+            .take(n)
+            .map(|(node, ratio, _)| (node, ratio))
+            .collect()
+    }
+
+    /// Summarizes this plan as a flat JSON object for log pipelines such as Loki or
+    /// Elasticsearch, which handle multi-megabyte raw plans poorly:
+    ///
+    /// - `top_node`: node type of the root node;
+    /// - `total_cost` and `plan_rows`: estimates of the root node;
+    /// - `execution_time_ms`: actual total time of the root node, for plans
+    ///   captured with `ANALYZE`;
+    /// - `node_count`: number of nodes in the plan tree;
+    /// - `nodes.<type>`: number of nodes of each type, in snake case, e.g.
+    ///   `nodes.seq_scan`;
+    /// - `top_relation`: the relation scanned by the most expensive scan, by
+    ///   actual time if known and by estimated cost otherwise;
+    /// - `fingerprint`: the [`corpus::fingerprint()`](crate::corpus::fingerprint)
+    ///   of the plan in hexadecimal, identifying its shape.
+    ///
+    /// Example:
+    /// ```rust
+    /// let plan = query.wrap_explain().explain_analyze(&mut conn)?;
+    /// println!("{}", plan.to_log_record());
+    /// // {"fingerprint":"3f0c…","node_count":2,"nodes.limit":1,"nodes.seq_scan":1,…}
+    /// ```
+    pub fn to_log_record(&self) -> serde_json::Value {
+        let mut record = serde_json::Map::new();
+        record.insert("top_node".to_owned(), self.node_type.clone().into());
+        record.insert("total_cost".to_owned(), self.total_cost.into());
+        record.insert("plan_rows".to_owned(), self.plan_rows.into());
+        if let Some(time) = self.actual_total_time {
+            record.insert("execution_time_ms".to_owned(), time.into());
+        }
+        record.insert("node_count".to_owned(), self.nodes().count().into());
+        let mut counts = BTreeMap::<String, u64>::new();
+        for node in self.nodes() {
+            let key = format!("nodes.{}", node.node_type.to_lowercase().replace(' ', "_"));
+            *counts.entry(key).or_default() += 1;
+        }
+        record.extend(counts.into_iter().map(|(key, count)| (key, count.into())));
+        let analyzed = self.actual_total_time.is_some();
+        let top_relation = self
+            .nodes()
+            .filter(|node| node.relation_name.is_some())
+            .map(|node| {
+                let weight = if analyzed {
+                    crate::corpus::own_time_ms(node)
+                } else {
+                    node.total_cost
+                };
+                (weight, node)
+            })
+            .max_by(|(a, _), (b, _)| a.total_cmp(b));
+        if let Some((_, node)) = top_relation {
+            record.insert("top_relation".to_owned(), node.relation_name.clone().into());
+        }
+        record.insert(
+            "fingerprint".to_owned(),
+            format!("{:016x}", crate::corpus::fingerprint(self)).into(),
+        );
+        serde_json::Value::Object(record)
+    }
+
+    /// The properties of this node compared by [`ExplainPlan::structurally_eq()`]
+    /// and hashed by [`corpus::fingerprint()`](crate::corpus::fingerprint).
+    pub(crate) fn shape(&self) -> impl Eq + std::hash::Hash + '_ {
+        (
+            &self.node_type,
+            &self.strategy,
+            &self.partial_mode,
+            &self.operation,
+            &self.parent_relationship,
+            &self.subplan_name,
+            (
+                &self.join_type,
+                &self.scan_direction,
+                &self.relation_name,
+                &self.index_name,
+                &self.function_name,
+                &self.cte_name,
+            ),
+        )
+    }
+}
+
+/// A complete item of the `EXPLAIN (FORMAT JSON)` output: the plan tree together
+/// with the summary sections reported next to it.
+///
+/// Rarely used sections such as `JIT` and `Settings` are kept as raw JSON values
+/// and only turned into typed structures when requested through the accessor
+/// methods, so the common path of looking at the plan stays fast.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ExplainItem {
+    /// The root node of the plan tree.
+    #[serde(rename = "Plan")]
+    pub plan: ExplainPlan,
+
+    /// Buffer usage during planning. Reported with `BUFFERS` since PostgreSQL 13.
+    #[serde(rename = "Planning", default)]
+    pub planning: Option<serde_json::Value>,
+
+    /// Time spent planning the query, in milliseconds. Reported with `ANALYZE` or `SUMMARY`.
+    #[serde(rename = "Planning Time", default)]
+    pub planning_time: Option<f64>,
+
+    /// Per-trigger execution statistics. Reported with `ANALYZE`.
+    #[serde(rename = "Triggers", default)]
+    pub triggers: Option<serde_json::Value>,
+
+    /// JIT compilation statistics. Reported with `ANALYZE` when JIT was used.
+    #[serde(rename = "JIT", default)]
+    pub jit: Option<serde_json::Value>,
+
+    /// Planner settings which differ from their defaults. Reported with `SETTINGS`.
+    #[serde(rename = "Settings", default)]
+    pub settings: Option<serde_json::Value>,
+
+    /// Query identifier. Reported with `VERBOSE` when `compute_query_id` is on.
+    #[serde(rename = "Query Identifier", default)]
+    pub query_identifier: Option<i64>,
+
+    /// Cost of serializing the result. Reported with `SERIALIZE` since PostgreSQL 17.
+    #[serde(rename = "Serialization", default)]
+    pub serialization: Option<serde_json::Value>,
+
+    /// Time spent executing the query, in milliseconds. Reported with `ANALYZE`.
+    #[serde(rename = "Execution Time", default)]
+    pub execution_time: Option<f64>,
+
+    /// Top-level sections not modelled above.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl ExplainItem {
+    /// Parses the `JIT` section. Returns `None` if JIT compilation was not used
+    /// or `ANALYZE` was off.
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Parse`] if the section has an unexpected shape.
+    pub fn jit(&self) -> Result<Option<Jit>, ExplainError> {
+        parse_section(self.jit.as_ref(), "JIT")
+    }
+
+    /// Parses the `Settings` section into a map from parameter names to values.
+    /// Returns `None` unless the `SETTINGS` option was used.
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Parse`] if the section has an unexpected shape.
+    pub fn settings(&self) -> Result<Option<BTreeMap<String, String>>, ExplainError> {
+        parse_section(self.settings.as_ref(), "Settings")
+    }
+
+    /// Parses the `Triggers` section. Returns an empty vector unless `ANALYZE`
+    /// was on and the statement fired triggers, e.g. foreign key checks.
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Parse`] if the section has an unexpected shape.
+    pub fn triggers(&self) -> Result<Vec<Trigger>, ExplainError> {
+        Ok(parse_section(self.triggers.as_ref(), "Triggers")?.unwrap_or_default())
+    }
+}
+
+/// Statistics of one parallel worker executing a node, see
+/// [`ExplainPlan::worker_details()`]. The leader's share is not listed; it is
+/// the difference between the node's statistics and the sum over its workers.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct WorkerDetail {
+    /// Number of the worker, starting at 0.
+    #[serde(rename = "Worker Number", default)]
+    pub worker_number: Option<u64>,
+
+    /// Time in milliseconds until the node returned its first row in this
+    /// worker, averaged per loop. Omitted when `TIMING` is off.
+    #[serde(rename = "Actual Startup Time", default)]
+    pub actual_startup_time: Option<f64>,
+
+    /// Time in milliseconds spent in the node in this worker, averaged per loop.
+    #[serde(rename = "Actual Total Time", default)]
+    pub actual_total_time: Option<f64>,
+
+    /// Number of rows the node returned in this worker, averaged per loop.
+    #[serde(rename = "Actual Rows", default)]
+    pub actual_rows: Option<f64>,
+
+    /// Number of times the node was executed in this worker.
+    #[serde(rename = "Actual Loops", default)]
+    pub actual_loops: Option<u64>,
+
+    /// Algorithm used by a `Sort` node in this worker, e.g. "quicksort" or
+    /// "external merge".
+    #[serde(rename = "Sort Method", default)]
+    pub sort_method: Option<String>,
+
+    /// Memory or disk space in kilobytes used by a `Sort` node in this worker.
+    #[serde(rename = "Sort Space Used", default)]
+    pub sort_space_used: Option<u64>,
+
+    /// Where the sort took place in this worker: "Memory" or "Disk".
+    #[serde(rename = "Sort Space Type", default)]
+    pub sort_space_type: Option<String>,
+
+    /// Peak memory in kilobytes used by a `Hash` node in this worker.
+    #[serde(rename = "Peak Memory Usage", default)]
+    pub peak_memory_usage: Option<u64>,
+
+    /// Number of shared buffer blocks found in the buffer cache by this worker.
+    /// This and the following block counters are reported with `BUFFERS`.
+    #[serde(rename = "Shared Hit Blocks", default)]
+    pub shared_hit_blocks: Option<u64>,
+
+    /// Number of shared buffer blocks read from disk (or the OS cache).
+    #[serde(rename = "Shared Read Blocks", default)]
+    pub shared_read_blocks: Option<u64>,
+
+    /// Number of previously unmodified shared blocks changed.
+    #[serde(rename = "Shared Dirtied Blocks", default)]
+    pub shared_dirtied_blocks: Option<u64>,
+
+    /// Number of shared blocks evicted from the cache and written out.
+    #[serde(rename = "Shared Written Blocks", default)]
+    pub shared_written_blocks: Option<u64>,
+
+    /// Number of blocks of short-term working data read from disk.
+    #[serde(rename = "Temp Read Blocks", default)]
+    pub temp_read_blocks: Option<u64>,
+
+    /// Number of blocks of short-term working data written to disk.
+    #[serde(rename = "Temp Written Blocks", default)]
+    pub temp_written_blocks: Option<u64>,
+
+    /// Fields not modelled above, e.g. local block counters, I/O timings or the
+    /// `JIT` section of the worker, keyed by their original names.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl WorkerDetail {
+    /// Returns the time in milliseconds this worker spent in the node over all
+    /// loops, like [`ExplainPlan::total_actual_time()`].
+    pub fn total_actual_time(&self) -> Option<f64> {
+        Some(self.actual_total_time? * self.actual_loops.unwrap_or(1) as f64)
+    }
+}
+
+/// JIT compilation statistics of a query, see [`ExplainItem::jit()`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Jit {
+    /// Number of functions compiled.
+    #[serde(rename = "Functions")]
+    pub functions: u64,
+
+    /// JIT features by name, e.g. `Inlining` or `Deforming`, and whether they were enabled.
+    #[serde(rename = "Options", default)]
+    pub options: BTreeMap<String, bool>,
+
+    /// Time spent compiling. Reported unless `TIMING` is off.
+    #[serde(rename = "Timing", default)]
+    pub timing: Option<JitTiming>,
+}
+
+/// Time in milliseconds spent in the phases of JIT compilation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct JitTiming {
+    /// Generating code. PostgreSQL 17 breaks this down further; only the total is kept.
+    #[serde(rename = "Generation", deserialize_with = "phase_total")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Phase"))]
+    pub generation: f64,
+
+    /// Inlining functions.
+    #[serde(rename = "Inlining")]
+    pub inlining: f64,
+
+    /// Optimizing the generated code.
+    #[serde(rename = "Optimization")]
+    pub optimization: f64,
+
+    /// Emitting machine code.
+    #[serde(rename = "Emission")]
+    pub emission: f64,
+
+    /// All phases together.
+    #[serde(rename = "Total")]
+    pub total: f64,
+}
+
+/// Execution statistics of one trigger, see [`ExplainItem::triggers()`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Trigger {
+    /// Name of the trigger, e.g. `RI_ConstraintTrigger_c_16411` for foreign keys.
+    #[serde(rename = "Trigger Name")]
+    pub name: String,
+
+    /// Name of the constraint the trigger enforces, e.g. a foreign key.
+    #[serde(rename = "Constraint Name", default)]
+    pub constraint_name: Option<String>,
+
+    /// The relation the trigger is defined on.
+    #[serde(rename = "Relation", default)]
+    pub relation: Option<String>,
+
+    /// Time in milliseconds spent in the trigger over all calls. Omitted when
+    /// `TIMING` is off.
+    #[serde(rename = "Time", default)]
+    pub time: Option<f64>,
+
+    /// Number of times the trigger fired.
+    #[serde(rename = "Calls")]
+    pub calls: u64,
+}
+
+/// Accepts either a number or an object with a `Total`.
+fn phase_total<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Ok(match Phase::deserialize(deserializer)? {
+        Phase::Total(total) | Phase::Detailed { total } => total,
+    })
+}
+
+/// The time of a JIT phase, as accepted by [`phase_total()`].
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+enum Phase {
+    Total(f64),
+    Detailed {
+        #[serde(rename = "Total")]
+        total: f64,
+    },
+}
+
+/// Deserializes a section kept as a raw value, reporting failures as
+/// [`ExplainError::Parse`] with paths starting at the section `name`.
+fn parse_section<T: DeserializeOwned>(
+    value: Option<&serde_json::Value>,
+    name: &str,
+) -> Result<Option<T>, ExplainError> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    serde_path_to_error::deserialize(value)
+        .map(Some)
+        .map_err(|e| ExplainError::Parse {
+            path: format!("{name}.{}", e.path()),
+            snippet: String::new(),
+            source: e.into_inner(),
+            raw_json: value.to_string(),
+        })
+}
+
+/// How to treat fields of the `EXPLAIN` output which [`ExplainPlan`] does not model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fails with [`ExplainError::UnknownFields`] listing every unknown field.
+    /// Useful in tests to notice when PostgreSQL reports something new.
+    Strict,
+
+    /// Keeps unknown fields in [`ExplainPlan::extra`] and carries on.
+    #[default]
+    Lenient,
+}
+
+impl ExplainItem {
+    /// Parses all items of an `EXPLAIN (FORMAT JSON)` document obtained elsewhere,
+    /// e.g. for multi-statement rules reporting several plans. Use
+    /// [`str::parse()`] for documents with a single item.
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::Parse`] if `json` is not a valid document.
+    pub fn parse_all(json: &str) -> Result<Vec<ExplainItem>, ExplainError> {
+        parse_items(json, ParseMode::Lenient)
+    }
+}
+
+/// Parses an `EXPLAIN (FORMAT JSON)` document with a single item, or the item
+/// without the enclosing array.
+///
+/// ```rust
+/// let item: ExplainItem = std::fs::read_to_string("plan.json")?.parse()?;
+/// ```
+impl FromStr for ExplainItem {
+    type Err = ExplainError;
+
+    fn from_str(json: &str) -> Result<Self, Self::Err> {
+        if json.trim_start().starts_with('{') {
+            deserialize_owned(json)
+        } else {
+            single_item(ExplainItem::parse_all(json)?)
+        }
+    }
+}
+
+impl TryFrom<&str> for ExplainItem {
+    type Error = ExplainError;
+
+    fn try_from(json: &str) -> Result<Self, Self::Error> {
+        json.parse()
+    }
+}
+
+/// Parses the root plan node of an `EXPLAIN (FORMAT JSON)` document with a single
+/// item, see [`ExplainItem`'s implementation](ExplainItem#impl-FromStr-for-ExplainItem).
+///
+/// ```rust
+/// let plan: ExplainPlan = json.parse()?;
+/// ```
+impl FromStr for ExplainPlan {
+    type Err = ExplainError;
+
+    fn from_str(json: &str) -> Result<Self, Self::Err> {
+        Ok(json.parse::<ExplainItem>()?.plan)
+    }
+}
+
+impl TryFrom<&str> for ExplainPlan {
+    type Error = ExplainError;
+
+    fn try_from(json: &str) -> Result<Self, Self::Error> {
+        json.parse()
+    }
+}
+
+/// Parses an `EXPLAIN (FORMAT JSON)` document into its top-level items.
+pub(crate) fn parse_items(json: &str, mode: ParseMode) -> Result<Vec<ExplainItem>, ExplainError> {
+    let items: Vec<ExplainItem> = deserialize_owned(json)?;
+    if mode == ParseMode::Strict {
+        let mut fields = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let path = format!("[{index}]");
+            fields.extend(item.extra.keys().map(|key| format!("{path}.{key}")));
+            unknown_fields(&item.plan, &format!("{path}.Plan"), &mut fields);
+        }
+        if !fields.is_empty() {
+            return Err(ExplainError::UnknownFields { fields });
+        }
+    }
+    Ok(items)
+}
+
+/// Deserializes `json`, reporting failures as [`ExplainError::Parse`].
+pub(crate) fn deserialize<'de, T: Deserialize<'de>>(json: &'de str) -> Result<T, ExplainError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(json);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        let source = e.into_inner();
+        ExplainError::Parse {
+            snippet: snippet(json, source.line(), source.column()),
+            source,
+            path,
+            raw_json: json.to_owned(),
+        }
+    })
+}
+
+/// Same as [`deserialize()`] for types which don't borrow from `json`, which can
+/// be parsed with simd-json when the `simd-json` feature is enabled. Documents
+/// simd-json rejects are parsed again with `serde_json` for a detailed error.
+#[cfg(feature = "simd-json")]
+fn deserialize_owned<T: DeserializeOwned>(json: &str) -> Result<T, ExplainError> {
+    let mut bytes = json.as_bytes().to_vec();
+    simd_json::serde::from_slice(&mut bytes).or_else(|_| deserialize(json))
+}
+
+#[cfg(not(feature = "simd-json"))]
+fn deserialize_owned<T: DeserializeOwned>(json: &str) -> Result<T, ExplainError> {
+    deserialize(json)
+}
+
+/// Number of characters of context shown on each side of a parse error.
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Returns the text around the 1-based `line` and `column` reported by `serde_json`.
+pub(crate) fn snippet(json: &str, line: usize, column: usize) -> String {
+    let Some(text) = json.lines().nth(line.saturating_sub(1)) else {
+        return String::new();
+    };
+    let chars: Vec<char> = text.chars().collect();
+    let at = column.saturating_sub(1).min(chars.len());
+    let start = at.saturating_sub(SNIPPET_CONTEXT);
+    let end = (at + SNIPPET_CONTEXT).min(chars.len());
+    let excerpt: String = chars[start..end].iter().collect();
+    let ellipsis = |truncated: bool| if truncated { "…" } else { "" };
+    format!(
+        "{}{}{}",
+        ellipsis(start > 0),
+        excerpt.trim(),
+        ellipsis(end < chars.len())
+    )
+}
+
+/// Collects the paths of all unknown fields of `plan` and its descendants.
+fn unknown_fields(plan: &ExplainPlan, path: &str, fields: &mut Vec<String>) {
+    fields.extend(plan.extra.keys().map(|key| format!("{path}.{key}")));
+    for (index, child) in plan.plans.iter().enumerate() {
+        unknown_fields(child, &format!("{path}.Plans[{index}]"), fields);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::RESULT_ITEM;
+    use crate::{corpus, render};
+
+    #[test]
+    fn summary_sections_are_parsed_on_demand() {
+        let sections = r#"{"Settings": {"work_mem": "64kB"}, "JIT": {"Functions": 3,
+            "Options": {"Inlining": false}, "Timing": {"Generation": {"Deform": 0.1,
+            "Total": 0.5}, "Inlining": 0.0, "Optimization": 0.2, "Emission": 4.0, "Total": 4.7}},"#;
+        let json = format!("[{}]", RESULT_ITEM.replacen('{', sections, 1));
+        let item = single_item(parse_items(&json, ParseMode::Strict).unwrap()).unwrap();
+
+        let jit = item.jit().unwrap().unwrap();
+        assert_eq!(jit.functions, 3);
+        assert_eq!(jit.timing.unwrap().generation, 0.5);
+        assert_eq!(item.settings().unwrap().unwrap()["work_mem"], "64kB");
+
+        let json = format!("[{RESULT_ITEM}]");
+        let item = single_item(parse_items(&json, ParseMode::Strict).unwrap()).unwrap();
+        assert!(item.jit().unwrap().is_none());
+    }
+
+    #[test]
+    fn plans_are_compared_structurally() {
+        let plan = ExplainPlan {
+            node_type: "Hash Join".to_owned(),
+            join_type: Some("Inner".to_owned()),
+            total_cost: 10.0,
+            plans: vec![ExplainPlan {
+                node_type: "Seq Scan".to_owned(),
+                relation_name: Some("users".to_owned()),
+                alias: Some("u".to_owned()),
+                ..ExplainPlan::default()
+            }],
+            ..ExplainPlan::default()
+        };
+        assert_eq!(plan.clone(), plan);
+
+        let mut analyzed = plan.clone();
+        analyzed.total_cost = 12.0;
+        analyzed.actual_rows = Some(3.0);
+        analyzed.plans[0].alias = Some("users".to_owned());
+        assert_ne!(analyzed, plan);
+        assert!(analyzed.structurally_eq(&plan));
+        assert_eq!(corpus::fingerprint(&analyzed), corpus::fingerprint(&plan));
+
+        let mut other = plan.clone();
+        other.plans[0].node_type = "Index Scan".to_owned();
+        assert!(!other.structurally_eq(&plan));
+        other.plans.clear();
+        assert!(!other.structurally_eq(&plan));
+    }
+
+    #[test]
+    fn plans_are_built_with_constructors() {
+        let plan = ExplainPlan {
+            total_cost: 42.0,
+            plans: vec![ExplainPlan::scan("Seq Scan", "users")],
+            ..ExplainPlan::new("Limit")
+        };
+        assert_eq!(plan.node_type, "Limit");
+        assert_eq!(plan.startup_cost, 0.0);
+        assert_eq!(plan.plans[0].relation_name.as_deref(), Some("users"));
+        assert_eq!(plan.plans[0].alias.as_deref(), Some("users"));
+        assert_eq!(
+            render::tree_with(&plan, &render::RenderOptions::default().costs(false)),
+            "Limit\n  ->  Seq Scan\n"
+        );
+    }
+
+    #[test]
+    fn plans_are_parsed_from_strings() {
+        let item = r#"{"Plan": {"Node Type": "Result", "Parallel Aware": false,
+            "Startup Cost": 0.0, "Total Cost": 0.01, "Plan Rows": 1, "Plan Width": 4},
+            "Planning Time": 0.1}"#;
+        let plan: ExplainPlan = format!("[{item}]").parse().unwrap();
+        assert_eq!(plan.node_type, "Result");
+        assert_eq!(ExplainPlan::try_from(item).unwrap(), plan);
+        let parsed: ExplainItem = item.parse().unwrap();
+        assert_eq!(parsed.planning_time, Some(0.1));
+
+        let two = format!("[{item}, {item}]");
+        assert_eq!(ExplainItem::parse_all(&two).unwrap().len(), 2);
+        assert!(matches!(
+            two.parse::<ExplainPlan>(),
+            Err(ExplainError::MultipleResults { count: 2 })
+        ));
+        assert!(matches!(
+            "[]".parse::<ExplainPlan>(),
+            Err(ExplainError::EmptyResult)
+        ));
+        assert!(matches!(
+            "[{".parse::<ExplainPlan>(),
+            Err(ExplainError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn plans_are_summarized_as_log_records() {
+        let plan = ExplainPlan {
+            total_cost: 20.0,
+            plans: vec![
+                ExplainPlan {
+                    total_cost: 5.0,
+                    ..ExplainPlan::scan("Seq Scan", "users")
+                },
+                ExplainPlan {
+                    total_cost: 12.0,
+                    ..ExplainPlan::scan("Index Only Scan", "orders")
+                },
+            ],
+            ..ExplainPlan::new("Nested Loop")
+        };
+
+        let record = plan.to_log_record();
+        assert_eq!(record["top_node"], "Nested Loop");
+        assert_eq!(record["total_cost"], 20.0);
+        assert_eq!(record["node_count"], 3);
+        assert_eq!(record["nodes.seq_scan"], 1);
+        assert_eq!(record["nodes.index_only_scan"], 1);
+        assert_eq!(record["top_relation"], "orders");
+        assert_eq!(
+            record["fingerprint"],
+            format!("{:016x}", corpus::fingerprint(&plan))
+        );
+        assert!(record.get("execution_time_ms").is_none());
+        assert!(record.as_object().unwrap().values().all(|v| !v.is_object()));
+
+        let mut analyzed = plan.clone();
+        analyzed.actual_total_time = Some(9.0);
+        analyzed.plans[0].actual_total_time = Some(8.0);
+        analyzed.plans[1].actual_total_time = Some(0.5);
+        let record = analyzed.to_log_record();
+        assert_eq!(record["execution_time_ms"], 9.0);
+        assert_eq!(record["top_relation"], "users");
+    }
+
+    #[test]
+    fn misestimates_are_ranked() {
+        let plan = ExplainPlan {
+            plan_rows: 10,
+            actual_rows: Some(10.0),
+            actual_loops: Some(1),
+            plans: vec![
+                ExplainPlan {
+                    plan_rows: 1,
+                    actual_rows: Some(50.0),
+                    actual_loops: Some(10),
+                    ..ExplainPlan::scan("Index Scan", "orders")
+                },
+                ExplainPlan {
+                    plan_rows: 1000,
+                    actual_rows: Some(5.0),
+                    actual_loops: Some(1),
+                    ..ExplainPlan::scan("Seq Scan", "users")
+                },
+                ExplainPlan {
+                    plan_rows: 1,
+                    actual_rows: Some(0.0),
+                    actual_loops: Some(0),
+                    ..ExplainPlan::scan("Index Scan", "items")
+                },
+            ],
+            ..ExplainPlan::new("Nested Loop")
+        };
+        assert_eq!(plan.estimate_ratio(), Some(1.0));
+        assert_eq!(plan.plans[0].estimate_ratio(), Some(50.0));
+        assert_eq!(plan.plans[1].estimate_ratio(), Some(0.005));
+        assert_eq!(plan.plans[2].estimate_ratio(), None);
+        assert_eq!(ExplainPlan::new("Result").estimate_ratio(), None);
+
+        let worst = plan.worst_misestimates(2);
+        let worst = worst
+            .iter()
+            .map(|(node, ratio)| (node.relation_name.as_deref(), *ratio))
+            .collect::<Vec<_>>();
+        assert_eq!(worst, [(Some("users"), 0.005), (Some("orders"), 50.0)]);
+    }
+
+    #[test]
+    fn actual_times_are_normalized_per_loop() {
+        let inner = ExplainPlan {
+            actual_startup_time: Some(0.01),
+            actual_total_time: Some(0.02),
+            actual_rows: Some(1.5),
+            actual_loops: Some(1000),
+            ..ExplainPlan::scan("Index Scan", "orders")
+        };
+        assert_eq!(inner.actual_time_per_loop(), Some(0.02));
+        assert_eq!(inner.actual_startup_time_per_loop(), Some(0.01));
+        assert_eq!(inner.total_actual_time(), Some(20.0));
+        assert_eq!(inner.actual_rows_per_loop(), Some(1.5));
+        assert_eq!(inner.total_actual_rows(), Some(1500.0));
+
+        let estimated = ExplainPlan::new("Seq Scan");
+        assert_eq!(estimated.total_actual_time(), None);
+        assert_eq!(estimated.total_actual_rows(), None);
+    }
+
+    #[test]
+    fn worker_details_are_typed() {
+        let plan = ExplainPlan {
+            workers: Some(vec![
+                serde_json::json!({
+                    "Worker Number": 0,
+                    "Actual Total Time": 40.0,
+                    "Actual Loops": 1,
+                    "Sort Method": "external merge",
+                    "Sort Space Used": 2048,
+                    "Sort Space Type": "Disk",
+                    "Temp Written Blocks": 256,
+                    "Local Hit Blocks": 0
+                }),
+                serde_json::json!({
+                    "Worker Number": 1,
+                    "Actual Total Time": 10.0,
+                    "Actual Loops": 1,
+                    "Sort Method": "quicksort",
+                    "Sort Space Used": 900,
+                    "Sort Space Type": "Memory"
+                }),
+            ]),
+            ..ExplainPlan::new("Sort")
+        };
+        let workers = plan.worker_details().unwrap();
+        assert_eq!(workers.len(), 2);
+        assert_eq!(workers[0].sort_method.as_deref(), Some("external merge"));
+        assert_eq!(workers[0].temp_written_blocks, Some(256));
+        assert_eq!(workers[0].extra["Local Hit Blocks"], 0);
+        assert_eq!(workers[1].worker_number, Some(1));
+        assert_eq!(workers[1].sort_space_type.as_deref(), Some("Memory"));
+        assert_eq!(workers[1].total_actual_time(), Some(10.0));
+        assert!(ExplainPlan::new("Sort")
+            .worker_details()
+            .unwrap()
+            .is_empty());
+
+        let malformed = ExplainPlan {
+            workers: Some(vec![serde_json::json!({"Worker Number": "first"})]),
+            ..ExplainPlan::new("Sort")
+        };
+        let Err(ExplainError::Parse { path, .. }) = malformed.worker_details() else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(path, "Workers[0].Worker Number");
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn related_types_have_json_schemas() {
+        let report = serde_json::to_value(schemars::schema_for!(crate::ExplainReport)).unwrap();
+        for name in ["ExplainPlan", "ExplainOptions", "Finding", "StatementStats"] {
+            assert!(report["$defs"].get(name).is_some(), "{name}");
+        }
+        let jit = serde_json::to_value(schemars::schema_for!(Jit)).unwrap();
+        assert_eq!(
+            jit["$defs"]["JitTiming"]["properties"]["Generation"]["$ref"],
+            "#/$defs/Phase"
+        );
+        let item = serde_json::to_value(schemars::schema_for!(ExplainItem)).unwrap();
+        assert!(item["properties"].get("Execution Time").is_some());
+        let trigger = serde_json::to_value(schemars::schema_for!(Trigger)).unwrap();
+        assert_eq!(
+            trigger["required"],
+            serde_json::json!(["Trigger Name", "Calls"])
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Aggregation of captured plans into statistics exposed in the
+//! [Prometheus text format](https://prometheus.io/docs/instrumenting/exposition_formats/).
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, PoisonError};
+
+use crate::ExplainPlan;
+
+/// Statistics accumulated for a single query.
+#[derive(Debug, Clone, Default)]
+pub struct QueryPlanStats {
+    /// Number of plans captured for the query.
+    pub plans_captured: u64,
+
+    /// Estimated total cost of the most recently captured plan.
+    pub last_total_cost: f64,
+
+    /// Lowest estimated total cost seen for the query.
+    pub min_total_cost: f64,
+
+    /// Highest estimated total cost seen for the query.
+    pub max_total_cost: f64,
+
+    /// Number of plans which reported an execution time (captured with `ANALYZE`).
+    pub executions: u64,
+
+    /// Sum of reported execution times, in milliseconds.
+    pub execution_time_ms_sum: f64,
+
+    /// Total number of `Seq Scan` nodes over all captured plans.
+    pub seq_scans: u64,
+
+    /// Total number of shared blocks read from disk over all captured plans.
+    pub shared_blocks_read: u64,
+}
+
+impl QueryPlanStats {
+    fn record(&mut self, plan: &ExplainPlan, execution_time_ms: Option<f64>) {
+        let cost = plan.total_cost;
+        if self.plans_captured == 0 {
+            self.min_total_cost = cost;
+            self.max_total_cost = cost;
+        } else {
+            self.min_total_cost = self.min_total_cost.min(cost);
+            self.max_total_cost = self.max_total_cost.max(cost);
+        }
+        self.plans_captured += 1;
+        self.last_total_cost = cost;
+
+        if let Some(execution_time_ms) = execution_time_ms {
+            self.executions += 1;
+            self.execution_time_ms_sum += execution_time_ms;
+        }
+
+        let seq_scans = plan
+            .nodes()
+            .filter(|node| node.node_type == "Seq Scan")
+            .count();
+        self.seq_scans += seq_scans as u64;
+        self.shared_blocks_read += plan.shared_read_blocks.unwrap_or(0);
+    }
+}
+
+/// A metric family with one sample per query, derived from [`QueryPlanStats`].
+struct MetricFamily {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    value: fn(&QueryPlanStats) -> f64,
+}
+
+const FAMILIES: &[MetricFamily] = &[
+    MetricFamily {
+        name: "diesel_pg_explain_plans_captured_total",
+        kind: "counter",
+        help: "Number of captured plans.",
+        value: |s| s.plans_captured as f64,
+    },
+    MetricFamily {
+        name: "diesel_pg_explain_plan_total_cost",
+        kind: "gauge",
+        help: "Estimated total cost of the most recently captured plan.",
+        value: |s| s.last_total_cost,
+    },
+    MetricFamily {
+        name: "diesel_pg_explain_plan_total_cost_min",
+        kind: "gauge",
+        help: "Lowest estimated total cost of captured plans.",
+        value: |s| s.min_total_cost,
+    },
+    MetricFamily {
+        name: "diesel_pg_explain_plan_total_cost_max",
+        kind: "gauge",
+        help: "Highest estimated total cost of captured plans.",
+        value: |s| s.max_total_cost,
+    },
+    MetricFamily {
+        name: "diesel_pg_explain_seq_scans_total",
+        kind: "counter",
+        help: "Number of Seq Scan nodes in captured plans.",
+        value: |s| s.seq_scans as f64,
+    },
+    MetricFamily {
+        name: "diesel_pg_explain_shared_blocks_read_total",
+        kind: "counter",
+        help: "Number of shared blocks read from disk by captured plans.",
+        value: |s| s.shared_blocks_read as f64,
+    },
+];
+
+/// Thread-safe registry accumulating [`QueryPlanStats`] per query.
+///
+/// Queries are identified by a caller-supplied key, typically a query name or
+/// a fingerprint of the normalized SQL. The registry is meant to be shared
+/// between the code capturing plans and a `/metrics` endpoint which serves
+/// [`PlanMetricsRegistry::render()`].
+///
+/// Example:
+/// ```rust
+/// static PLAN_METRICS: PlanMetricsRegistry = PlanMetricsRegistry::new();
+///
+/// let plan = my_query.wrap_explain().explain(&mut conn)?;
+/// PLAN_METRICS.record("users_by_age", &plan, None);
+///
+/// // In the /metrics handler:
+/// let body = PLAN_METRICS.render();
+/// ```
+#[derive(Debug, Default)]
+pub struct PlanMetricsRegistry {
+    queries: Mutex<BTreeMap<String, QueryPlanStats>>,
+}
+
+impl PlanMetricsRegistry {
+    /// Creates an empty registry.
+    pub const fn new() -> Self {
+        Self {
+            queries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Adds a captured plan to the statistics of `query`.
+    ///
+    /// `execution_time_ms` is the execution time reported by `EXPLAIN ANALYZE`, if any.
+    pub fn record(&self, query: &str, plan: &ExplainPlan, execution_time_ms: Option<f64>) {
+        let mut queries = self.queries.lock().unwrap_or_else(PoisonError::into_inner);
+        match queries.get_mut(query) {
+            Some(stats) => stats.record(plan, execution_time_ms),
+            None => {
+                let mut stats = QueryPlanStats::default();
+                stats.record(plan, execution_time_ms);
+                queries.insert(query.to_owned(), stats);
+            }
+        }
+    }
+
+    /// Returns the statistics accumulated for `query`, if any plan was recorded for it.
+    pub fn stats(&self, query: &str) -> Option<QueryPlanStats> {
+        let queries = self.queries.lock().unwrap_or_else(PoisonError::into_inner);
+        queries.get(query).cloned()
+    }
+
+    /// Forgets all accumulated statistics.
+    pub fn clear(&self) {
+        self.queries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Renders the accumulated statistics in the Prometheus text exposition
+    /// format, with one sample per query labelled `query`.
+    pub fn render(&self) -> String {
+        let queries = self.queries.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut out = String::new();
+
+        for family in FAMILIES {
+            write_header(&mut out, family.name, family.kind, family.help);
+            for (query, stats) in queries.iter() {
+                write_sample(&mut out, family.name, query, (family.value)(stats));
+            }
+        }
+
+        let name = "diesel_pg_explain_execution_time_seconds";
+        write_header(
+            &mut out,
+            name,
+            "summary",
+            "Execution time of plans captured with ANALYZE.",
+        );
+        for (query, stats) in queries.iter() {
+            let sum = stats.execution_time_ms_sum / 1000.0;
+            write_sample(&mut out, &format!("{name}_sum"), query, sum);
+            write_sample(
+                &mut out,
+                &format!("{name}_count"),
+                query,
+                stats.executions as f64,
+            );
+        }
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn write_sample(out: &mut String, name: &str, query: &str, value: f64) {
+    let _ = write!(out, "{name}{{query=\"");
+    for c in query.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    let _ = writeln!(out, "\"}} {value}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::node;
+
+    #[test]
+    fn stats_are_rendered_in_exposition_format() {
+        let registry = PlanMetricsRegistry::new();
+        let seq_scan = ExplainPlan {
+            total_cost: 10.0,
+            shared_read_blocks: Some(3),
+            ..node("Hash Join", vec![ExplainPlan::scan("Seq Scan", "users")])
+        };
+        registry.record("users", &seq_scan, Some(250.0));
+        let index_scan = ExplainPlan {
+            total_cost: 4.0,
+            ..ExplainPlan::scan("Index Scan", "users")
+        };
+        registry.record("users", &index_scan, None);
+        let result = ExplainPlan {
+            total_cost: 1.5,
+            ..ExplainPlan::new("Result")
+        };
+        registry.record("say \"hi\"\\\n", &result, None);
+
+        let stats = registry.stats("users").unwrap();
+        assert_eq!((stats.plans_captured, stats.executions), (2, 1));
+        assert_eq!(
+            registry.render(),
+            r#"# HELP diesel_pg_explain_plans_captured_total Number of captured plans.
+# TYPE diesel_pg_explain_plans_captured_total counter
+diesel_pg_explain_plans_captured_total{query="say \"hi\"\\\n"} 1
+diesel_pg_explain_plans_captured_total{query="users"} 2
+# HELP diesel_pg_explain_plan_total_cost Estimated total cost of the most recently captured plan.
+# TYPE diesel_pg_explain_plan_total_cost gauge
+diesel_pg_explain_plan_total_cost{query="say \"hi\"\\\n"} 1.5
+diesel_pg_explain_plan_total_cost{query="users"} 4
+# HELP diesel_pg_explain_plan_total_cost_min Lowest estimated total cost of captured plans.
+# TYPE diesel_pg_explain_plan_total_cost_min gauge
+diesel_pg_explain_plan_total_cost_min{query="say \"hi\"\\\n"} 1.5
+diesel_pg_explain_plan_total_cost_min{query="users"} 4
+# HELP diesel_pg_explain_plan_total_cost_max Highest estimated total cost of captured plans.
+# TYPE diesel_pg_explain_plan_total_cost_max gauge
+diesel_pg_explain_plan_total_cost_max{query="say \"hi\"\\\n"} 1.5
+diesel_pg_explain_plan_total_cost_max{query="users"} 10
+# HELP diesel_pg_explain_seq_scans_total Number of Seq Scan nodes in captured plans.
+# TYPE diesel_pg_explain_seq_scans_total counter
+diesel_pg_explain_seq_scans_total{query="say \"hi\"\\\n"} 0
+diesel_pg_explain_seq_scans_total{query="users"} 1
+# HELP diesel_pg_explain_shared_blocks_read_total Number of shared blocks read from disk by captured plans.
+# TYPE diesel_pg_explain_shared_blocks_read_total counter
+diesel_pg_explain_shared_blocks_read_total{query="say \"hi\"\\\n"} 0
+diesel_pg_explain_shared_blocks_read_total{query="users"} 3
+# HELP diesel_pg_explain_execution_time_seconds Execution time of plans captured with ANALYZE.
+# TYPE diesel_pg_explain_execution_time_seconds summary
+diesel_pg_explain_execution_time_seconds_sum{query="say \"hi\"\\\n"} 0
+diesel_pg_explain_execution_time_seconds_count{query="say \"hi\"\\\n"} 0
+diesel_pg_explain_execution_time_seconds_sum{query="users"} 0.25
+diesel_pg_explain_execution_time_seconds_count{query="users"} 1
+"#
+        );
+
+        registry.clear();
+        assert!(registry.stats("users").is_none());
+    }
+}
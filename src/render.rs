@@ -0,0 +1,662 @@
+//! Human-readable rendering of plan trees.
+
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+use std::io;
+
+use crate::expr;
+use crate::{parse_items, ExplainConfig, ExplainError, ExplainItem, ExplainPlan, ParseMode};
+
+/// The output formats of [`render()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Indented text, see [`tree()`].
+    #[default]
+    Tree,
+
+    /// A Markdown table, see [`markdown()`].
+    Markdown,
+
+    /// A Graphviz graph, see [`dot()`].
+    Dot,
+}
+
+/// Renders `plan` in `format`.
+pub fn render(plan: &ExplainPlan, format: Format) -> String {
+    match format {
+        Format::Tree => tree(plan),
+        Format::Markdown => markdown(plan),
+        Format::Dot => dot(plan),
+    }
+}
+
+/// Same as [`render()`], but writes into `out` instead of returning a string,
+/// see [`render_to_io()`] for files and sockets.
+///
+/// # Errors
+/// Returns the first error of `out`.
+pub fn render_to(out: &mut impl Write, plan: &ExplainPlan, format: Format) -> fmt::Result {
+    match format {
+        Format::Tree => tree_to(out, plan, &ExplainConfig::global().render),
+        Format::Markdown => markdown_to(out, plan),
+        Format::Dot => dot_to(out, plan),
+    }
+}
+
+/// Same as [`render()`], but streams the output into `out`, e.g. a file or a
+/// socket, without building it in memory first. Wrap unbuffered writers into an
+/// [`io::BufWriter`].
+///
+/// Example:
+/// ```rust
+/// let mut file = BufWriter::new(File::create("plan.dot")?);
+/// render::render_to_io(&mut file, &plan, render::Format::Dot)?;
+/// file.flush()?;
+/// ```
+///
+/// # Errors
+/// Returns the first error of `out`.
+pub fn render_to_io(
+    out: &mut impl io::Write,
+    plan: &ExplainPlan,
+    format: Format,
+) -> io::Result<()> {
+    let mut adapter = IoAdapter { out, error: None };
+    match render_to(&mut adapter, plan, format) {
+        Ok(()) => Ok(()),
+        Err(fmt::Error) => Err(adapter
+            .error
+            .unwrap_or_else(|| io::Error::other("formatting failed"))),
+    }
+}
+
+/// Forwards formatted text to an [`io::Write`], keeping the error `fmt::Write`
+/// can't carry.
+struct IoAdapter<'a, W> {
+    out: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.out.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+/// Renders the plans of an `EXPLAIN (FORMAT JSON)` document in `format`, without
+/// a database connection, e.g. for output copied from `psql`.
+///
+/// Besides the plain JSON document, a single item of it and `psql`'s aligned
+/// output, including the `QUERY PLAN` header, `+` line continuations and the
+/// row count footer, are accepted. Several plans are separated by an empty line.
+///
+/// # Errors
+/// Returns [`ExplainError::Parse`] if `input` doesn't contain a valid document,
+/// and [`ExplainError::EmptyResult`] if it contains no plan.
+pub fn render_json(input: &str, format: Format) -> Result<String, ExplainError> {
+    Ok(parse_output(input)?
+        .iter()
+        .map(|item| render(&item.plan, format))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Parses `EXPLAIN (FORMAT JSON)` output as accepted by [`render_json()`].
+pub(crate) fn parse_output(input: &str) -> Result<Vec<ExplainItem>, ExplainError> {
+    let json = strip_psql_decorations(input);
+    let json = if json.trim_start().starts_with('{') {
+        format!("[{json}]")
+    } else {
+        json
+    };
+    let items = parse_items(&json, ParseMode::Lenient)?;
+    if items.is_empty() {
+        return Err(ExplainError::EmptyResult);
+    }
+    Ok(items)
+}
+
+/// Removes what `psql` prints around the JSON document: everything before it,
+/// the `+` marking continued lines and the `(1 row)` footer.
+fn strip_psql_decorations(input: &str) -> String {
+    let start = input.find(['[', '{']).unwrap_or(input.len());
+    input[start..]
+        .lines()
+        .map(|line| line.trim_end().trim_end_matches('+'))
+        .filter(|line| {
+            let line = line.trim();
+            !(line.starts_with('(') && (line.ends_with(" row)") || line.ends_with(" rows)")))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Controls what [`tree_with()`] includes for each node and how values are
+/// formatted, e.g. compact output for application logs or every detail for DBAs.
+///
+/// The defaults are used by [`tree()`] unless a different
+/// [`ExplainConfig::render`] is installed, and match `psql`'s layout.
+///
+/// Example:
+/// ```rust
+/// let options = RenderOptions::default()
+///     .time_unit(render::TimeUnit::Seconds)
+///     .buffers(Some(render::BufferUnit::Bytes))
+///     .conditions(true)
+///     .max_condition_len(Some(80));
+/// println!("{}", render::tree_with(&plan, &options));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Shows estimated costs, row counts and widths. Enabled by default.
+    pub costs: bool,
+
+    /// Shows actual times, row counts and loops of analyzed plans. Enabled by default.
+    pub actual: bool,
+
+    /// The unit of actual times. Milliseconds by default, as in `psql`.
+    pub time_unit: TimeUnit,
+
+    /// Digits after the decimal point of costs, times and sizes. `None`, the
+    /// default, uses 2 for costs, 3 for times and 1 for sizes, as `psql` does.
+    pub precision: Option<usize>,
+
+    /// Shows a `Buffers:` line below nodes reporting buffer usage, counting
+    /// blocks or their size. Hidden by default.
+    pub buffers: Option<BufferUnit>,
+
+    /// Shows conditions such as `Filter` or `Index Cond` below their nodes.
+    /// Hidden by default.
+    pub conditions: bool,
+
+    /// Shortens conditions longer than this many characters, ending them with
+    /// `...`. Not limited by default.
+    pub max_condition_len: Option<usize>,
+}
+
+/// The unit of times, see [`RenderOptions::time_unit`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeUnit {
+    /// Milliseconds, written without a unit as `psql` does.
+    #[default]
+    Milliseconds,
+
+    /// Seconds, written with an `s` suffix.
+    Seconds,
+}
+
+/// The unit of buffer usage, see [`RenderOptions::buffers`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BufferUnit {
+    /// Numbers of blocks, as `psql` shows them.
+    #[default]
+    Blocks,
+
+    /// Sizes such as `1.5 MB`, assuming PostgreSQL's default block size of 8 kB.
+    Bytes,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            costs: true,
+            actual: true,
+            time_unit: TimeUnit::default(),
+            precision: None,
+            buffers: None,
+            conditions: false,
+            max_condition_len: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Sets whether estimates are shown.
+    pub fn costs(self, costs: bool) -> Self {
+        Self { costs, ..self }
+    }
+
+    /// Sets whether actual statistics are shown.
+    pub fn actual(self, actual: bool) -> Self {
+        Self { actual, ..self }
+    }
+
+    /// Sets the unit of actual times.
+    pub fn time_unit(self, time_unit: TimeUnit) -> Self {
+        Self { time_unit, ..self }
+    }
+
+    /// Sets the digits after the decimal point, or restores `psql`'s with `None`.
+    pub fn precision(self, precision: Option<usize>) -> Self {
+        Self { precision, ..self }
+    }
+
+    /// Sets whether and how buffer usage is shown.
+    pub fn buffers(self, buffers: Option<BufferUnit>) -> Self {
+        Self { buffers, ..self }
+    }
+
+    /// Sets whether conditions are shown.
+    pub fn conditions(self, conditions: bool) -> Self {
+        Self { conditions, ..self }
+    }
+
+    /// Sets the length conditions are shortened to, or lifts the limit with `None`.
+    pub fn max_condition_len(self, max_condition_len: Option<usize>) -> Self {
+        Self {
+            max_condition_len,
+            ..self
+        }
+    }
+
+    fn time(&self, ms: f64) -> String {
+        let precision = self.precision.unwrap_or(3);
+        match self.time_unit {
+            TimeUnit::Milliseconds => format!("{ms:.precision$}"),
+            TimeUnit::Seconds => format!("{:.precision$}s", ms / 1000.0),
+        }
+    }
+
+    fn blocks(&self, unit: BufferUnit, blocks: u64) -> String {
+        match unit {
+            BufferUnit::Blocks => blocks.to_string(),
+            BufferUnit::Bytes => {
+                let precision = self.precision.unwrap_or(1);
+                let mut size = (blocks * BLOCK_SIZE) as f64 / 1024.0;
+                let mut units = ["MB", "GB", "TB"].into_iter();
+                let mut unit = "kB";
+                while size >= 1024.0 {
+                    let Some(next) = units.next() else { break };
+                    size /= 1024.0;
+                    unit = next;
+                }
+                format!("{size:.precision$} {unit}")
+            }
+        }
+    }
+
+    fn condition<'a>(&self, condition: &'a str) -> Cow<'a, str> {
+        match self.max_condition_len {
+            Some(max) if condition.chars().count() > max => {
+                let kept: String = condition.chars().take(max).collect();
+                Cow::Owned(format!("{kept}..."))
+            }
+            _ => Cow::Borrowed(condition),
+        }
+    }
+}
+
+/// PostgreSQL's default block size in bytes.
+const BLOCK_SIZE: u64 = 8192;
+
+/// Renders a plan tree in the indented layout used by `psql` for
+/// `EXPLAIN` text output, with the [installed](ExplainConfig::install) render options.
+///
+/// Example output:
+/// ```text
+/// Hash Join  (cost=1.09..2.24 rows=4 width=36)
+///   ->  Seq Scan  (cost=0.00..1.09 rows=9 width=36)
+///   ->  Hash  (cost=1.04..1.04 rows=4 width=4)
+///         ->  Seq Scan  (cost=0.00..1.04 rows=4 width=4)
+/// ```
+pub fn tree(plan: &ExplainPlan) -> String {
+    tree_with(plan, &ExplainConfig::global().render)
+}
+
+/// Same as [`tree()`], with explicit `options`.
+pub fn tree_with(plan: &ExplainPlan, options: &RenderOptions) -> String {
+    let mut out = String::new();
+    let _ = tree_to(&mut out, plan, options);
+    out
+}
+
+/// Same as [`tree_with()`], but writes into `out`.
+///
+/// # Errors
+/// Returns the first error of `out`.
+pub fn tree_to(out: &mut impl Write, plan: &ExplainPlan, options: &RenderOptions) -> fmt::Result {
+    write_tree_node(out, plan, options, 0)
+}
+
+fn write_tree_node(
+    out: &mut impl Write,
+    plan: &ExplainPlan,
+    options: &RenderOptions,
+    depth: usize,
+) -> fmt::Result {
+    if depth > 0 {
+        write!(out, "{:indent$}->  ", "", indent = 6 * (depth - 1) + 2)?;
+    }
+    write!(out, "{}", plan.node_type)?;
+    if options.costs {
+        let precision = options.precision.unwrap_or(2);
+        write!(
+            out,
+            "  (cost={:.precision$}..{:.precision$} rows={} width={})",
+            plan.startup_cost, plan.total_cost, plan.plan_rows, plan.plan_width
+        )?;
+    }
+    if options.actual {
+        if let (Some(startup), Some(total), Some(rows), Some(loops)) = (
+            plan.actual_startup_time,
+            plan.actual_total_time,
+            plan.actual_rows,
+            plan.actual_loops,
+        ) {
+            write!(
+                out,
+                " (actual time={}..{} rows={rows} loops={loops})",
+                options.time(startup),
+                options.time(total)
+            )?;
+        }
+    }
+    out.write_char('\n')?;
+    let indent = 6 * depth + 2;
+    if options.conditions {
+        for (name, condition) in expr::conditions(plan) {
+            writeln!(
+                out,
+                "{:indent$}{name}: {}",
+                "",
+                options.condition(condition)
+            )?;
+        }
+    }
+    if let Some(buffers) = options
+        .buffers
+        .and_then(|unit| buffers(plan, options, unit))
+    {
+        writeln!(out, "{:indent$}Buffers: {buffers}", "")?;
+    }
+    for child in &plan.plans {
+        write_tree_node(out, child, options, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Returns the `Buffers:` line of `plan` without its label, listing non-zero
+/// counters as `psql` does, or `None` if there are none.
+fn buffers(plan: &ExplainPlan, options: &RenderOptions, unit: BufferUnit) -> Option<String> {
+    let group = |group: &str, counters: &[(&str, Option<u64>)]| {
+        let counters = counters
+            .iter()
+            .filter_map(|(name, blocks)| {
+                let blocks = blocks.filter(|blocks| *blocks > 0)?;
+                Some(format!("{name}={}", options.blocks(unit, blocks)))
+            })
+            .collect::<Vec<_>>();
+        (!counters.is_empty()).then(|| format!("{group} {}", counters.join(" ")))
+    };
+    let buffers = [
+        group(
+            "shared",
+            &[
+                ("hit", plan.shared_hit_blocks),
+                ("read", plan.shared_read_blocks),
+                ("dirtied", plan.shared_dirtied_blocks),
+                ("written", plan.shared_written_blocks),
+            ],
+        ),
+        group(
+            "local",
+            &[
+                ("hit", plan.local_hit_blocks),
+                ("read", plan.local_read_blocks),
+                ("dirtied", plan.local_dirtied_blocks),
+                ("written", plan.local_written_blocks),
+            ],
+        ),
+        group(
+            "temp",
+            &[
+                ("read", plan.temp_read_blocks),
+                ("written", plan.temp_written_blocks),
+            ],
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    (!buffers.is_empty()).then(|| buffers.join(", "))
+}
+
+/// Renders a plan tree as a Markdown table with one row per node, indenting node
+/// types by depth. Actual statistics are left empty for plans captured without
+/// `ANALYZE`.
+///
+/// Example output:
+/// ```text
+/// | Node | Relation | Cost | Rows | Actual time (ms) | Actual rows | Loops |
+/// |------|----------|-----:|-----:|-----------------:|------------:|------:|
+/// | Hash Join |  | 1.09..2.24 | 4 |  |  |  |
+/// | &nbsp;&nbsp;&nbsp;&nbsp;Seq Scan | users | 0.00..1.09 | 9 |  |  |  |
+/// ```
+pub fn markdown(plan: &ExplainPlan) -> String {
+    let mut out = String::new();
+    let _ = markdown_to(&mut out, plan);
+    out
+}
+
+/// Same as [`markdown()`], but writes into `out`.
+///
+/// # Errors
+/// Returns the first error of `out`.
+pub fn markdown_to(out: &mut impl Write, plan: &ExplainPlan) -> fmt::Result {
+    out.write_str(
+        "| Node | Relation | Cost | Rows | Actual time (ms) | Actual rows | Loops |\n\
+         |------|----------|-----:|-----:|-----------------:|------------:|------:|\n",
+    )?;
+    write_markdown_row(out, plan, 0)
+}
+
+fn write_markdown_row(out: &mut impl Write, plan: &ExplainPlan, depth: usize) -> fmt::Result {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    writeln!(
+        out,
+        "| {}{} | {} | {:.2}..{:.2} | {} | {} | {} | {} |",
+        "&nbsp;".repeat(4 * depth),
+        markdown_escape(&plan.node_type),
+        markdown_escape(plan.relation_name.as_deref().unwrap_or_default()),
+        plan.startup_cost,
+        plan.total_cost,
+        plan.plan_rows,
+        optional(
+            plan.actual_startup_time
+                .zip(plan.actual_total_time)
+                .map(|(startup, total)| format!("{startup:.3}..{total:.3}"))
+        ),
+        optional(plan.actual_rows.map(|rows| rows.to_string())),
+        optional(plan.actual_loops.map(|loops| loops.to_string())),
+    )?;
+    for child in &plan.plans {
+        write_markdown_row(out, child, depth + 1)?;
+    }
+    Ok(())
+}
+
+fn markdown_escape(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Renders a plan tree as a Graphviz `digraph`, with an edge from every node to
+/// each of its children. Render it with e.g. `dot -Tsvg`.
+///
+/// Example output:
+/// ```text
+/// digraph plan {
+///   node [shape=box];
+///   n0 [label="Hash Join\ncost=1.09..2.24 rows=4"];
+///   n1 [label="Seq Scan on users\ncost=0.00..1.09 rows=9"];
+///   n0 -> n1;
+/// }
+/// ```
+pub fn dot(plan: &ExplainPlan) -> String {
+    let mut out = String::new();
+    let _ = dot_to(&mut out, plan);
+    out
+}
+
+/// Same as [`dot()`], but writes into `out`.
+///
+/// # Errors
+/// Returns the first error of `out`.
+pub fn dot_to(out: &mut impl Write, plan: &ExplainPlan) -> fmt::Result {
+    out.write_str("digraph plan {\n  node [shape=box];\n")?;
+    write_dot_node(out, plan, &mut 0)?;
+    out.write_str("}\n")
+}
+
+fn write_dot_node(
+    out: &mut impl Write,
+    plan: &ExplainPlan,
+    next_id: &mut usize,
+) -> Result<usize, fmt::Error> {
+    let id = *next_id;
+    *next_id += 1;
+    let mut label = plan.node_type.clone();
+    if let Some(relation) = &plan.relation_name {
+        let _ = write!(label, " on {relation}");
+    }
+    let _ = write!(
+        label,
+        "\ncost={:.2}..{:.2} rows={}",
+        plan.startup_cost, plan.total_cost, plan.plan_rows
+    );
+    if let (Some(total), Some(rows)) = (plan.actual_total_time, plan.actual_rows) {
+        let _ = write!(label, "\nactual time={total:.3} rows={rows}");
+    }
+    let label = label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    writeln!(out, "  n{id} [label=\"{label}\"];")?;
+    for child in &plan.plans {
+        let child_id = write_dot_node(out, child, next_id)?;
+        writeln!(out, "  n{id} -> n{child_id};")?;
+    }
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::node;
+
+    #[test]
+    fn render_options_select_shown_statistics() {
+        let plan = ExplainPlan {
+            node_type: "Seq Scan".to_owned(),
+            total_cost: 1.5,
+            plan_rows: 3,
+            actual_startup_time: Some(0.01),
+            actual_total_time: Some(0.02),
+            actual_rows: Some(3.0),
+            actual_loops: Some(1),
+            ..ExplainPlan::default()
+        };
+
+        assert_eq!(
+            tree_with(&plan, &RenderOptions::default()),
+            "Seq Scan  (cost=0.00..1.50 rows=3 width=0) \
+             (actual time=0.010..0.020 rows=3 loops=1)\n"
+        );
+        assert_eq!(
+            tree_with(&plan, &RenderOptions::default().actual(false)),
+            "Seq Scan  (cost=0.00..1.50 rows=3 width=0)\n"
+        );
+        assert_eq!(
+            tree_with(&plan, &RenderOptions::default().costs(false)),
+            "Seq Scan (actual time=0.010..0.020 rows=3 loops=1)\n"
+        );
+        assert_eq!(ExplainConfig::global().render, RenderOptions::default());
+    }
+
+    #[test]
+    fn render_options_format_units_and_conditions() {
+        let plan = ExplainPlan {
+            total_cost: 1.5,
+            actual_startup_time: Some(12.0),
+            actual_total_time: Some(1500.0),
+            actual_rows: Some(3.0),
+            actual_loops: Some(1),
+            filter: Some("(name ~~ 'a very long pattern%'::text)".to_owned()),
+            shared_hit_blocks: Some(256),
+            shared_read_blocks: Some(0),
+            temp_written_blocks: Some(1),
+            plans: vec![ExplainPlan {
+                index_cond: Some("(id = 1)".to_owned()),
+                ..ExplainPlan::new("Index Scan")
+            }],
+            ..ExplainPlan::new("Seq Scan")
+        };
+
+        let options = RenderOptions::default()
+            .time_unit(TimeUnit::Seconds)
+            .precision(Some(1))
+            .buffers(Some(BufferUnit::Bytes))
+            .conditions(true)
+            .max_condition_len(Some(12));
+        assert_eq!(
+            tree_with(&plan, &options),
+            "Seq Scan  (cost=0.0..1.5 rows=0 width=0) (actual time=0.0s..1.5s rows=3 loops=1)\n  \
+             Filter: (name ~~ 'a ...\n  \
+             Buffers: shared hit=2.0 MB, temp written=8.0 kB\n  \
+             ->  Index Scan  (cost=0.0..0.0 rows=0 width=0)\n        \
+             Index Cond: (id = 1)\n"
+        );
+        assert!(tree_with(
+            &plan,
+            &RenderOptions::default().buffers(Some(BufferUnit::Blocks))
+        )
+        .contains("  Buffers: shared hit=256, temp written=1\n"));
+    }
+
+    #[test]
+    fn renderers_stream_into_writers() {
+        let plan = node("Hash Join", vec![ExplainPlan::scan("Seq Scan", "users")]);
+        for format in [Format::Tree, Format::Markdown, Format::Dot] {
+            let mut text = String::new();
+            render_to(&mut text, &plan, format).unwrap();
+            assert_eq!(text, render(&plan, format));
+
+            let mut bytes = Vec::new();
+            render_to_io(&mut bytes, &plan, format).unwrap();
+            assert_eq!(String::from_utf8(bytes).unwrap(), text);
+        }
+
+        let mut full = [0u8; 8];
+        let err = render_to_io(&mut &mut full[..], &plan, Format::Dot).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn psql_output_is_rendered_without_connection() {
+        let psql = " QUERY PLAN \n\
+                    ------------\n \
+                    [          +\n   \
+                    {\"Plan\": {\"Node Type\": \"Seq Scan\", \"Relation Name\": \"a|b\",    +\n     \
+                    \"Startup Cost\": 0.0, \"Total Cost\": 1.5, \"Plan Rows\": 3, +\n     \
+                    \"Plan Width\": 4, \"Parallel Aware\": false}}+\n \
+                    ]\n\
+                    (1 row)\n";
+
+        assert_eq!(
+            render_json(psql, Format::Tree).unwrap(),
+            "Seq Scan  (cost=0.00..1.50 rows=3 width=4)\n"
+        );
+        assert!(render_json(psql, Format::Markdown)
+            .unwrap()
+            .ends_with("| Seq Scan | a\\|b | 0.00..1.50 | 3 |  |  |  |\n"));
+        assert!(render_json(psql, Format::Dot)
+            .unwrap()
+            .contains("n0 [label=\"Seq Scan on a|b\\ncost=0.00..1.50 rows=3\"];"));
+        assert!(matches!(
+            render_json("(0 rows)", Format::Tree),
+            Err(ExplainError::Parse { .. })
+        ));
+    }
+}
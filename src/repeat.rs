@@ -0,0 +1,189 @@
+//! Timings aggregated over repeated `EXPLAIN ANALYZE` runs.
+//!
+//! A single `EXPLAIN ANALYZE` is easily skewed by a cold cache or a concurrent
+//! load spike. [`Explain::explain_analyze_n()`](crate::Explain::explain_analyze_n)
+//! runs the statement several times and [`summarize()`] reduces the actual
+//! times of each node to their minimum, median and maximum.
+
+use crate::ExplainPlan;
+
+/// The minimum, median and maximum of a series of measurements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    /// The fastest measurement.
+    pub min: f64,
+
+    /// The middle measurement, or the mean of the two middle ones for an even
+    /// number of measurements.
+    pub median: f64,
+
+    /// The slowest measurement.
+    pub max: f64,
+}
+
+impl Summary {
+    /// Summarizes `samples`, or returns `None` if there are none.
+    pub fn from_samples(mut samples: Vec<f64>) -> Option<Self> {
+        samples.sort_by(f64::total_cmp);
+        let (first, last) = (*samples.first()?, *samples.last()?);
+        let middle = samples.len() / 2;
+        let median = if samples.len().is_multiple_of(2) {
+            (samples[middle - 1] + samples[middle]) / 2.0
+        } else {
+            samples[middle]
+        };
+        Some(Self {
+            min: first,
+            median,
+            max: last,
+        })
+    }
+}
+
+/// The timings of one node over all runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeTimings {
+    /// Position of the node in [`ExplainPlan::nodes()`] order.
+    pub index: usize,
+
+    /// The node type, e.g. `Seq Scan`.
+    pub node_type: String,
+
+    /// The relation scanned by the node, if any.
+    pub relation_name: Option<String>,
+
+    /// Time in milliseconds spent in the node and its children over all loops,
+    /// see [`ExplainPlan::total_actual_time()`]. `None` if timing was off.
+    pub time: Option<Summary>,
+
+    /// Rows returned by the node over all loops, see
+    /// [`ExplainPlan::total_actual_rows()`].
+    pub rows: Option<Summary>,
+}
+
+/// The result of [`summarize()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatedAnalyze {
+    /// The plan of the first run.
+    pub plan: ExplainPlan,
+
+    /// Number of runs the timings were aggregated over.
+    pub runs: usize,
+
+    /// Number of runs left out because their plan differed from the first one,
+    /// e.g. after switching to a generic plan.
+    pub skipped_runs: usize,
+
+    /// Timings of each node, in [`ExplainPlan::nodes()`] order.
+    pub nodes: Vec<NodeTimings>,
+}
+
+impl RepeatedAnalyze {
+    /// Returns the timings of the root node, i.e. of the whole statement.
+    pub fn total(&self) -> Option<Summary> {
+        self.nodes.first()?.time
+    }
+}
+
+/// Aggregates the actual times and row counts of the nodes of `plans`, captured
+/// with `ANALYZE` from the same statement. Plans which are not
+/// [structurally equal](ExplainPlan::structurally_eq) to the first one are
+/// skipped, as their nodes can't be matched. Returns `None` if `plans` is empty.
+pub fn summarize(plans: Vec<ExplainPlan>) -> Option<RepeatedAnalyze> {
+    let mut plans = plans.into_iter();
+    let first = plans.next()?;
+    let (matching, skipped): (Vec<_>, Vec<_>) =
+        plans.partition(|plan| plan.structurally_eq(&first));
+    let runs = std::iter::once(&first).chain(&matching).collect::<Vec<_>>();
+    let run_nodes = runs
+        .iter()
+        .map(|plan| plan.nodes().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let nodes = first
+        .nodes()
+        .enumerate()
+        .map(|(index, node)| {
+            let samples = |value: fn(&ExplainPlan) -> Option<f64>| {
+                Summary::from_samples(
+                    run_nodes
+                        .iter()
+                        .filter_map(|nodes| value(nodes[index]))
+                        .collect(),
+                )
+            };
+            NodeTimings {
+                index,
+                node_type: node.node_type.clone(),
+                relation_name: node.relation_name.clone(),
+                time: samples(ExplainPlan::total_actual_time),
+                rows: samples(ExplainPlan::total_actual_rows),
+            }
+        })
+        .collect();
+    Some(RepeatedAnalyze {
+        runs: runs.len(),
+        skipped_runs: skipped.len(),
+        nodes,
+        plan: first,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_runs_are_summarized_per_node() {
+        let run = |root: f64, scan: f64| ExplainPlan {
+            actual_total_time: Some(root),
+            actual_rows: Some(10.0),
+            actual_loops: Some(1),
+            plans: vec![ExplainPlan {
+                actual_total_time: Some(scan),
+                actual_rows: Some(5.0),
+                actual_loops: Some(2),
+                ..ExplainPlan::scan("Index Scan", "users")
+            }],
+            ..ExplainPlan::new("Nested Loop")
+        };
+        let other = ExplainPlan {
+            actual_total_time: Some(100.0),
+            ..ExplainPlan::scan("Seq Scan", "users")
+        };
+        let runs = summarize(vec![
+            run(9.0, 2.0),
+            run(3.0, 1.0),
+            other,
+            run(5.0, 1.5),
+            run(4.0, 1.0),
+        ])
+        .unwrap();
+        assert_eq!(runs.runs, 4);
+        assert_eq!(runs.skipped_runs, 1);
+        assert_eq!(runs.plan.actual_total_time, Some(9.0));
+        assert_eq!(
+            runs.total(),
+            Some(Summary {
+                min: 3.0,
+                median: 4.5,
+                max: 9.0
+            })
+        );
+        let scan = &runs.nodes[1];
+        assert_eq!(scan.relation_name.as_deref(), Some("users"));
+        assert_eq!(
+            scan.time,
+            Some(Summary {
+                min: 2.0,
+                median: 2.5,
+                max: 4.0
+            })
+        );
+        assert_eq!(scan.rows.map(|rows| rows.median), Some(10.0));
+        assert_eq!(summarize(Vec::new()), None);
+        assert_eq!(
+            Summary::from_samples(vec![2.0, 7.0, 1.0]).map(|s| s.median),
+            Some(2.0)
+        );
+    }
+}
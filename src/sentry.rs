@@ -0,0 +1,82 @@
+//! Support for Sentry through the [`sentry_core`] crate: attaching plans to the
+//! events reported for slow or failing queries.
+//!
+//! Example:
+//! ```rust
+//! let plan = query.wrap_explain().explain_analyze(&mut conn)?;
+//! if plan.actual_total_time.unwrap_or_default() > 1000.0 {
+//!     diesel_pg_explain::sentry::attach_plan(&plan, true);
+//!     sentry::capture_message("slow query", sentry::Level::Warning);
+//! }
+//! ```
+
+use sentry_core::protocol::{Attachment, Breadcrumb, Context, Map, Value};
+
+use crate::{render, ExplainPlan};
+
+/// Key of the context set by [`attach_plan()`].
+pub const CONTEXT_KEY: &str = "plan";
+
+/// Sets the summary of `plan` as the [`CONTEXT_KEY`] context of the current
+/// scope and records a breadcrumb for it, so the next event reported, e.g. the
+/// error of the query, shows the plan. With `include_json`, the full plan is
+/// attached as `plan.json` as well.
+///
+/// The context stays set until the scope ends, so call this within
+/// `sentry::with_scope()` to attach the plan to one event only.
+pub fn attach_plan(plan: &ExplainPlan, include_json: bool) {
+    sentry_core::add_breadcrumb(plan_breadcrumb(plan));
+    sentry_core::configure_scope(|scope| {
+        scope.set_context(CONTEXT_KEY, plan_context(plan));
+        if include_json {
+            scope.add_attachment(plan_attachment(plan));
+        }
+    });
+}
+
+/// Returns a compact summary of `plan` with the following keys:
+///
+/// - `top_node`: node type of the root plan node;
+/// - `total_cost`: estimated total cost of the root plan node;
+/// - `node_count`: number of nodes in the plan tree;
+/// - `execution_time_ms`: actual total time of the root node, for plans captured
+///   with `ANALYZE`;
+/// - `tree`: the plan rendered by [`render::tree()`].
+pub fn plan_context(plan: &ExplainPlan) -> Context {
+    let mut map = Map::new();
+    map.insert("top_node".to_owned(), plan.node_type.clone().into());
+    map.insert("total_cost".to_owned(), plan.total_cost.into());
+    map.insert("node_count".to_owned(), plan.nodes().count().into());
+    if let Some(time) = plan.actual_total_time {
+        map.insert("execution_time_ms".to_owned(), time.into());
+    }
+    map.insert("tree".to_owned(), render::tree(plan).into());
+    Context::Other(map)
+}
+
+/// Returns a breadcrumb of category `db.plan` naming the root node of `plan`,
+/// with its cost and execution time as data.
+pub fn plan_breadcrumb(plan: &ExplainPlan) -> Breadcrumb {
+    let mut data = Map::new();
+    data.insert("total_cost".to_owned(), plan.total_cost.into());
+    if let Some(time) = plan.actual_total_time {
+        data.insert("execution_time_ms".to_owned(), Value::from(time));
+    }
+    Breadcrumb {
+        ty: "query".to_owned(),
+        category: Some("db.plan".to_owned()),
+        message: Some(format!("EXPLAIN: {}", plan.node_type)),
+        data,
+        ..Breadcrumb::default()
+    }
+}
+
+/// Returns `plan` serialized as JSON, as an attachment named `plan.json`.
+pub fn plan_attachment(plan: &ExplainPlan) -> Attachment {
+    Attachment {
+        buffer: serde_json::to_vec_pretty(plan).unwrap_or_default(),
+        filename: "plan.json".to_owned(),
+        content_type: Some("application/json".to_owned()),
+        ..Attachment::default()
+    }
+}
@@ -0,0 +1,185 @@
+//! Explaining many queries over one connection with shared defaults.
+
+use std::time::{Duration, SystemTime};
+
+use diesel::pg::Pg;
+use diesel::query_builder::QueryFragment;
+use diesel::query_dsl::methods::LoadQuery;
+use diesel::RunQueryDsl;
+
+use crate::hints::Hints;
+use crate::{
+    json_from_rows, parse_items, single_item, Explain, ExplainConfig, ExplainConnection,
+    ExplainError, ExplainItem, ExplainOptions, ExplainPlan, ExplainReport, ParseMode, RawSql,
+    ServerVersion, SERVER_VERSION_SQL,
+};
+
+/// A connection together with the options, settings and hints applied to every
+/// query explained through it, for tools explaining hundreds of queries in a
+/// loop such as audits and regression suites.
+///
+/// The session copies the JSON document of every plan into one buffer, which
+/// keeps its capacity across calls and is available through
+/// [`ExplainSession::last_json()`], and looks up the server version for
+/// [`ExplainSession::report()`] only once.
+///
+/// Example:
+/// ```rust
+/// let mut session = ExplainSession::new(&mut conn)
+///     .options(ExplainOptions::default().buffers(true))
+///     .setting("work_mem", "64MB");
+/// for query in queries {
+///     let plan = session.explain(query)?;
+///     store(&plan, session.last_json());
+/// }
+/// ```
+pub struct ExplainSession<'c, C> {
+    conn: &'c mut C,
+    options: ExplainOptions,
+    explicit_options: bool,
+    mode: ParseMode,
+    settings: Vec<(String, String)>,
+    lock_timeout: Option<Duration>,
+    hints: Hints,
+    server: Option<ServerVersion>,
+    json: String,
+}
+
+impl<'c, C: ExplainConnection> ExplainSession<'c, C> {
+    /// Starts a session on `conn` with the options and parse mode of the installed
+    /// [`ExplainConfig`].
+    pub fn new(conn: &'c mut C) -> Self {
+        let config = ExplainConfig::global();
+        Self {
+            conn,
+            options: config.options,
+            explicit_options: false,
+            mode: config.parse_mode,
+            settings: Vec::new(),
+            lock_timeout: config.lock_timeout,
+            hints: Hints::new(),
+            server: None,
+            json: String::new(),
+        }
+    }
+
+    /// Sets the options passed to every `EXPLAIN`, see [`Explain::options()`].
+    pub fn options(mut self, options: ExplainOptions) -> Self {
+        self.options = options;
+        self.explicit_options = true;
+        self
+    }
+
+    /// Sets how unknown fields are handled, see [`Explain::parse_mode()`].
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets a run-time parameter for every `EXPLAIN`, see [`Explain::setting()`].
+    pub fn setting(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.settings.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets how long an `ANALYZE` may wait for a lock, see [`Explain::lock_timeout()`].
+    pub fn lock_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Prefixes every statement with pg_hint_plan hints, see [`Explain::hints()`].
+    pub fn hints(mut self, hints: Hints) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    /// Explains `query` with the session's defaults.
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn explain<'a, Q>(&mut self, query: Q) -> Result<ExplainPlan, ExplainError>
+    where
+        Explain<Q>: LoadQuery<'a, C, String>,
+    {
+        let explain = self.wrap(query);
+        Ok(self.run(explain)?.plan)
+    }
+
+    /// Explains a raw SQL statement with the session's defaults,
+    /// see [`explain_raw()`](crate::explain_raw).
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn explain_raw(&mut self, sql: &str) -> Result<ExplainPlan, ExplainError> {
+        self.explain(RawSql::statement(sql))
+    }
+
+    /// Same as [`Explain::explain_report()`], with the session's defaults.
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn report<'a, Q>(&mut self, query: Q) -> Result<ExplainReport, ExplainError>
+    where
+        Q: QueryFragment<Pg>,
+        Explain<Q>: LoadQuery<'a, C, String>,
+    {
+        let sql = diesel::debug_query::<Pg, _>(&query).to_string();
+        let server = match &self.server {
+            Some(server) => server,
+            None => self
+                .server
+                .insert(diesel::sql_query(SERVER_VERSION_SQL).get_result(self.conn)?),
+        };
+        let (server_version, server_version_num) = (server.version.clone(), server.version_num);
+        let explain = self.wrap(query).with_connection_defaults(self.conn)?;
+        let options = explain.options;
+        let captured_at = SystemTime::now();
+        let item = self.run(explain)?;
+        Ok(ExplainReport {
+            sql,
+            server_version,
+            server_version_num,
+            captured_at,
+            options,
+            query_identifier: item.query_identifier,
+            statement_stats: None,
+            findings: crate::lint::check(&item.plan),
+            plan: item.plan,
+        })
+    }
+
+    /// Returns the JSON document returned by the last `EXPLAIN`, even if it failed
+    /// to parse, or an empty string if nothing was explained yet or the last
+    /// `EXPLAIN` didn't return exactly one document.
+    pub fn last_json(&self) -> &str {
+        &self.json
+    }
+
+    /// Returns the underlying connection, e.g. to run other queries between explains.
+    pub fn connection(&mut self) -> &mut C {
+        self.conn
+    }
+
+    fn run<'a, Q>(&mut self, explain: Explain<Q>) -> Result<ExplainItem, ExplainError>
+    where
+        Explain<Q>: LoadQuery<'a, C, String>,
+    {
+        self.json.clear();
+        let json = json_from_rows(explain.load_rows(self.conn)?)?;
+        self.json.push_str(&json);
+        single_item(parse_items(&self.json, self.mode)?)
+    }
+
+    fn wrap<Q>(&self, query: Q) -> Explain<Q> {
+        Explain {
+            query,
+            options: self.options,
+            explicit_options: self.explicit_options,
+            mode: self.mode,
+            settings: self.settings.clone(),
+            lock_timeout: self.lock_timeout,
+            hints: self.hints.clone(),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! A common destination for captured plans.
+//!
+//! [`PlanSink`] receives plans together with a [`CaptureContext`] describing
+//! where they come from. Logging, storage, alerting or metrics are plugged in
+//! by implementing it, or by passing a closure.
+//!
+//! Example:
+//! ```rust
+//! use diesel_pg_explain::sink::{CaptureContext, PlanSink};
+//!
+//! struct SlowPlanAlert;
+//!
+//! impl PlanSink for SlowPlanAlert {
+//!     fn on_plan(&self, ctx: &CaptureContext, plan: &ExplainPlan) {
+//!         if plan.total_cost > 1e6 {
+//!             alert(&ctx.sql, plan);
+//!         }
+//!     }
+//! }
+//!
+//! let plan = query.wrap_explain().explain_to(&mut conn, &SlowPlanAlert)?;
+//! ```
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "diesel-2")]
+use diesel::pg::{Pg, PgQueryBuilder};
+#[cfg(feature = "diesel-2")]
+use diesel::query_builder::{QueryBuilder, QueryFragment};
+#[cfg(feature = "diesel-2")]
+use diesel::query_dsl::methods::LoadQuery;
+
+#[cfg(feature = "diesel-2")]
+use crate::{Explain, ExplainConnection, ExplainError};
+use crate::{ExplainOptions, ExplainPlan};
+
+/// Where and how a plan passed to a [`PlanSink`] was captured.
+#[derive(Debug, Clone)]
+pub struct CaptureContext {
+    /// The explained statement, with `$n` placeholders for bind values.
+    pub sql: String,
+
+    /// The options the plan was captured with.
+    pub options: ExplainOptions,
+
+    /// How long the statement took, if it was measured, e.g. by
+    /// [`AutoExplainConnection::min_duration()`](crate::auto::AutoExplainConnection::min_duration).
+    pub duration: Option<Duration>,
+
+    /// When the plan was captured.
+    pub captured_at: SystemTime,
+
+    /// The query identifier of the statement, for joining the plan with
+    /// `pg_stat_statements` and server logs. Reported with
+    /// [`ExplainOptions::verbose`] when `compute_query_id` is on.
+    pub query_identifier: Option<i64>,
+}
+
+impl CaptureContext {
+    /// Creates a context for a plan of `sql` captured now with default options.
+    pub fn new(sql: impl Into<String>) -> Self {
+        Self {
+            sql: sql.into(),
+            options: ExplainOptions::default(),
+            duration: None,
+            captured_at: SystemTime::now(),
+            query_identifier: None,
+        }
+    }
+}
+
+/// Receives captured plans.
+///
+/// Implemented for closures taking `(&CaptureContext, &ExplainPlan)` and for
+/// shared sinks behind an [`Arc`].
+pub trait PlanSink: Send + Sync {
+    /// Called with every captured plan. Should return quickly, as it runs on the
+    /// thread which explained the query.
+    fn on_plan(&self, ctx: &CaptureContext, plan: &ExplainPlan);
+}
+
+impl<F> PlanSink for F
+where
+    F: Fn(&CaptureContext, &ExplainPlan) + Send + Sync,
+{
+    fn on_plan(&self, ctx: &CaptureContext, plan: &ExplainPlan) {
+        self(ctx, plan)
+    }
+}
+
+impl<S: PlanSink + ?Sized> PlanSink for Arc<S> {
+    fn on_plan(&self, ctx: &CaptureContext, plan: &ExplainPlan) {
+        (**self).on_plan(ctx, plan)
+    }
+}
+
+#[cfg(feature = "diesel-2")]
+impl<Q> Explain<Q> {
+    /// Same as [`Explain::explain()`], but also passes the plan to `sink`.
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`]. Failed explains are not reported to `sink`.
+    pub fn explain_to<'a, C>(
+        self,
+        conn: &mut C,
+        sink: &dyn PlanSink,
+    ) -> Result<ExplainPlan, ExplainError>
+    where
+        C: ExplainConnection,
+        Q: QueryFragment<Pg>,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let explain = self.with_connection_defaults(conn)?;
+        let mut query_builder = PgQueryBuilder::default();
+        explain.query.to_sql(&mut query_builder, &Pg)?;
+        let options = explain.options;
+        let item = explain.explain_item(conn)?;
+        let ctx = CaptureContext {
+            options,
+            query_identifier: item.query_identifier,
+            ..CaptureContext::new(query_builder.finish())
+        };
+        sink.on_plan(&ctx, &item.plan);
+        Ok(item.plan)
+    }
+}
@@ -0,0 +1,66 @@
+//! Support for the [`slog`] crate: logging plans as structured fields.
+//!
+//! Example:
+//! ```rust
+//! use diesel_pg_explain::slog::PlanSummary;
+//!
+//! let plan = my_query.wrap_explain().explain(&mut conn)?;
+//! slog::info!(logger, "explained query"; "query" => "users_by_age", PlanSummary(&plan));
+//! // or, as a single field:
+//! slog::info!(logger, "explained query"; "plan" => &plan);
+//! ```
+
+use slog::{Key, Record, Serializer, Value, KV};
+
+use crate::ExplainPlan;
+
+/// Logs the summary of a plan as separate fields:
+///
+/// - `plan.top_node`: node type of the root plan node;
+/// - `plan.total_cost`: estimated total cost of the root plan node;
+/// - `plan.rows`: estimated number of rows returned;
+/// - `plan.node_count`: number of nodes in the plan tree;
+/// - `plan.execution_time_ms`: actual total time of the root node, for plans
+///   captured with `ANALYZE`;
+/// - `plan.shared_read_blocks`: shared blocks read, for plans captured with
+///   `BUFFERS`.
+#[derive(Debug, Clone, Copy)]
+pub struct PlanSummary<'a>(pub &'a ExplainPlan);
+
+impl KV for PlanSummary<'_> {
+    fn serialize(&self, _record: &Record<'_>, serializer: &mut dyn Serializer) -> slog::Result {
+        let plan = self.0;
+        serializer.emit_str("plan.top_node", &plan.node_type)?;
+        serializer.emit_f64("plan.total_cost", plan.total_cost)?;
+        serializer.emit_u64("plan.rows", plan.plan_rows)?;
+        serializer.emit_usize("plan.node_count", plan.nodes().count())?;
+        if let Some(time) = plan.actual_total_time {
+            serializer.emit_f64("plan.execution_time_ms", time)?;
+        }
+        if let Some(blocks) = plan.shared_read_blocks {
+            serializer.emit_u64("plan.shared_read_blocks", blocks)?;
+        }
+        Ok(())
+    }
+}
+
+/// Logs a plan as a one-line summary, e.g. `Hash Join (cost=2.24, 4 nodes)`;
+/// use [`PlanSummary`] for separate fields.
+impl Value for ExplainPlan {
+    fn serialize(
+        &self,
+        _record: &Record<'_>,
+        key: Key,
+        serializer: &mut dyn Serializer,
+    ) -> slog::Result {
+        serializer.emit_arguments(
+            key,
+            &format_args!(
+                "{} (cost={:.2}, {} nodes)",
+                self.node_type,
+                self.total_cost,
+                self.nodes().count()
+            ),
+        )
+    }
+}
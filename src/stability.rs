@@ -0,0 +1,126 @@
+//! Checks whether the planner sticks to one plan for a statement.
+//!
+//! The plan of a query may flip between strategies depending on its parameter
+//! values, e.g. an index scan for rare values and a sequential scan for common
+//! ones. [`explain_stability()`](crate::explain_stability) explains the same
+//! query several times, optionally with different bind values, and [`check()`]
+//! groups the plans by shape and describes how the shapes differ.
+
+use crate::corpus;
+use crate::diff::{self, PlanChange};
+use crate::ExplainPlan;
+
+/// Runs which produced plans of the same shape, see [`Stability::shapes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    /// The [`corpus::fingerprint()`] shared by the plans.
+    pub fingerprint: u64,
+
+    /// Positions of the runs in [`Stability::plans`], in ascending order.
+    pub runs: Vec<usize>,
+
+    /// How the plans of this shape choose different strategies than those of
+    /// the most frequent shape: changed node types and indexes, and added or
+    /// removed nodes. Empty for the most frequent shape itself.
+    pub changes: Vec<PlanChange>,
+}
+
+/// The result of [`check()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stability {
+    /// The plans of all runs, in order.
+    pub plans: Vec<ExplainPlan>,
+
+    /// The distinct shapes of the plans, most frequent first. Shapes equally
+    /// frequent are ordered by their first run.
+    pub shapes: Vec<Shape>,
+}
+
+impl Stability {
+    /// Returns `true` if all runs produced plans of the same shape.
+    pub fn is_stable(&self) -> bool {
+        self.shapes.len() <= 1
+    }
+
+    /// Returns the plan of the first run of `shape`.
+    pub fn example(&self, shape: &Shape) -> &ExplainPlan {
+        &self.plans[shape.runs[0]]
+    }
+}
+
+/// Groups `plans`, explained from the same statement, by their shape. Estimates
+/// such as row counts and costs are ignored, so only plans choosing different
+/// strategies make the result [unstable](Stability::is_stable).
+///
+/// Example:
+/// ```rust
+/// let stability = stability::check(plans);
+/// for shape in stability.shapes.iter().skip(1) {
+///     println!("runs {:?} planned differently:", shape.runs);
+///     for change in &shape.changes {
+///         println!("  {change}");
+///     }
+/// }
+/// ```
+pub fn check(plans: Vec<ExplainPlan>) -> Stability {
+    let clusters = corpus::clusters(&plans);
+    let shapes = match clusters.first() {
+        Some(dominant) => clusters
+            .iter()
+            .map(|cluster| Shape {
+                fingerprint: cluster.fingerprint,
+                runs: cluster.members.clone(),
+                changes: diff::compare(dominant.example, cluster.example)
+                    .into_iter()
+                    .filter(is_strategy_change)
+                    .collect(),
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    Stability { plans, shapes }
+}
+
+fn is_strategy_change(change: &PlanChange) -> bool {
+    !matches!(
+        change,
+        PlanChange::Rows { .. } | PlanChange::TotalCost { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unstable_plans_are_grouped_by_shape() {
+        let index_scan = |rows: u64| ExplainPlan {
+            index_name: Some("orders_status_idx".into()),
+            plan_rows: rows,
+            ..ExplainPlan::scan("Index Scan", "orders")
+        };
+        let stable = check(vec![index_scan(10), index_scan(50)]);
+        assert!(stable.is_stable());
+        assert_eq!(stable.shapes[0].runs, vec![0, 1]);
+        assert!(stable.shapes[0].changes.is_empty());
+
+        let unstable = check(vec![
+            index_scan(10),
+            ExplainPlan::scan("Seq Scan", "orders"),
+            index_scan(20),
+        ]);
+        assert!(!unstable.is_stable());
+        assert_eq!(unstable.shapes[0].runs, vec![0, 2]);
+        assert_eq!(unstable.shapes[1].runs, vec![1]);
+        assert_eq!(unstable.example(&unstable.shapes[1]).node_type, "Seq Scan");
+        assert_eq!(
+            unstable.shapes[1].changes,
+            vec![diff::PlanChange::NodeType {
+                path: vec![],
+                before: "Index Scan".into(),
+                after: "Seq Scan".into(),
+            }]
+        );
+        assert!(check(Vec::new()).is_stable());
+    }
+}
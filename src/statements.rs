@@ -0,0 +1,172 @@
+//! Linking plans to the cumulative statistics collected by `pg_stat_statements`.
+//!
+//! A plan shows how one execution of a statement is carried out, while
+//! `pg_stat_statements` shows how the statement behaves over time. Both are
+//! identified by the query identifier, which `EXPLAIN VERBOSE` reports when
+//! `compute_query_id` is enabled, as it is by default once `pg_stat_statements`
+//! is loaded.
+//!
+//! Example:
+//! ```rust
+//! let options = ExplainOptions::default().verbose(true);
+//! let mut report = query.wrap_explain().options(options).explain_report(&mut conn)?;
+//! report.attach_statement_stats(&mut conn)?;
+//! if let Some(stats) = &report.statement_stats {
+//!     println!("{} calls, {:.3} ms on average", stats.calls, stats.mean_exec_time);
+//! }
+//! ```
+
+#[cfg(feature = "diesel-2")]
+use diesel::sql_types::{BigInt, Double};
+#[cfg(feature = "diesel-2")]
+use diesel::{QueryableByName, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "diesel-2")]
+use crate::{ExplainConnection, ExplainError, ExplainItem, ExplainReport};
+
+/// Cumulative statistics of a statement from `pg_stat_statements`, summed over
+/// all roles which ran it in the current database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel-2", derive(QueryableByName))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct StatementStats {
+    /// The query identifier the statistics belong to.
+    #[cfg_attr(feature = "diesel-2", diesel(sql_type = BigInt))]
+    pub query_id: i64,
+
+    /// Number of times the statement was executed.
+    #[cfg_attr(feature = "diesel-2", diesel(sql_type = BigInt))]
+    pub calls: i64,
+
+    /// Total time spent executing the statement, in milliseconds.
+    #[cfg_attr(feature = "diesel-2", diesel(sql_type = Double))]
+    pub total_exec_time: f64,
+
+    /// Mean time spent executing the statement, in milliseconds.
+    #[cfg_attr(feature = "diesel-2", diesel(sql_type = Double))]
+    pub mean_exec_time: f64,
+
+    /// Total number of rows retrieved or affected.
+    #[cfg_attr(feature = "diesel-2", diesel(sql_type = BigInt))]
+    pub rows: i64,
+
+    /// Total number of shared blocks found in the buffer cache.
+    #[cfg_attr(feature = "diesel-2", diesel(sql_type = BigInt))]
+    pub shared_blks_hit: i64,
+
+    /// Total number of shared blocks read from disk or the OS cache.
+    #[cfg_attr(feature = "diesel-2", diesel(sql_type = BigInt))]
+    pub shared_blks_read: i64,
+}
+
+// Columns as of pg_stat_statements 1.8, shipped with PostgreSQL 13.
+#[cfg(feature = "diesel-2")]
+const STATEMENT_STATS_SQL: &str = "\
+SELECT queryid AS query_id,
+       sum(calls)::int8 AS calls,
+       sum(total_exec_time) AS total_exec_time,
+       sum(total_exec_time) / sum(calls) AS mean_exec_time,
+       sum(rows)::int8 AS rows,
+       sum(shared_blks_hit)::int8 AS shared_blks_hit,
+       sum(shared_blks_read)::int8 AS shared_blks_read
+  FROM pg_stat_statements
+ WHERE queryid = $1
+   AND dbid = (SELECT oid FROM pg_database WHERE datname = current_database())
+ GROUP BY queryid
+HAVING sum(calls) > 0";
+
+/// Looks up the statistics of the statement identified by `query_id`. Returns
+/// `None` if the statement was not executed since the statistics were reset.
+///
+/// Requires the `pg_stat_statements` extension, version 1.8 (PostgreSQL 13) or
+/// later, to be loaded and created in the database.
+///
+/// # Errors
+/// Returns [`ExplainError::UndefinedRelation`] if the extension is not created,
+/// or [`ExplainError::Diesel`] if the lookup fails for another reason.
+#[cfg(feature = "diesel-2")]
+pub fn lookup<C: ExplainConnection>(
+    conn: &mut C,
+    query_id: i64,
+) -> Result<Option<StatementStats>, ExplainError> {
+    let mut rows = diesel::sql_query(STATEMENT_STATS_SQL)
+        .bind::<BigInt, _>(query_id)
+        .load::<StatementStats>(conn)?;
+    Ok(rows.pop())
+}
+
+#[cfg(feature = "diesel-2")]
+impl ExplainItem {
+    /// Looks up the `pg_stat_statements` statistics of the explained statement,
+    /// see [`lookup()`]. Returns `None` if the plan has no query identifier.
+    ///
+    /// # Errors
+    /// Same as [`lookup()`].
+    pub fn statement_stats<C: ExplainConnection>(
+        &self,
+        conn: &mut C,
+    ) -> Result<Option<StatementStats>, ExplainError> {
+        match self.query_identifier {
+            Some(query_id) => lookup(conn, query_id),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(feature = "diesel-2")]
+impl ExplainReport {
+    /// Fills [`ExplainReport::statement_stats`] with the current statistics of the
+    /// explained statement, see [`lookup()`]. Leaves it empty if the plan has no
+    /// query identifier.
+    ///
+    /// # Errors
+    /// Same as [`lookup()`].
+    pub fn attach_statement_stats<C: ExplainConnection>(
+        &mut self,
+        conn: &mut C,
+    ) -> Result<(), ExplainError> {
+        self.statement_stats = match self.query_identifier {
+            Some(query_id) => lookup(conn, query_id)?,
+            None => None,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "diesel-2"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_field_is_selected_by_name() {
+        let stats = StatementStats {
+            query_id: 1,
+            calls: 2,
+            total_exec_time: 3.0,
+            mean_exec_time: 1.5,
+            rows: 4,
+            shared_blks_hit: 5,
+            shared_blks_read: 6,
+        };
+        let serde_json::Value::Object(fields) = serde_json::to_value(stats).unwrap() else {
+            panic!("statistics serialize to an object");
+        };
+        for name in fields.keys() {
+            assert!(
+                STATEMENT_STATS_SQL.contains(&format!(" AS {name},"))
+                    || STATEMENT_STATS_SQL.contains(&format!(" AS {name}\n")),
+                "{name} is not selected"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_extension_is_an_undefined_relation() {
+        let err = ExplainError::from(diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::Unknown,
+            Box::new("relation \"pg_stat_statements\" does not exist".to_owned()),
+        ));
+        assert!(matches!(err, ExplainError::UndefinedRelation { .. }));
+    }
+}
@@ -0,0 +1,349 @@
+//! Streaming parser for very large `EXPLAIN (FORMAT JSON)` documents.
+//!
+//! Plans of queries over thousands of partitions can have tens of thousands of
+//! nodes. [`for_each_node()`] reads such a document from any [`io::Read`] and
+//! hands each node to a callback as soon as it is complete, without building
+//! the tree: memory use is bounded by the depth of the plan rather than by its
+//! size. [`read_plans()`] builds the trees from such a document node by node,
+//! without holding the document or an intermediate `serde_json::Value` of it in
+//! memory next to the trees.
+//!
+//! Example:
+//! ```rust
+//! use diesel_pg_explain::stream;
+//!
+//! let file = std::io::BufReader::new(std::fs::File::open("plan.json")?);
+//! let mut seq_scans = 0;
+//! stream::for_each_node(file, |node, _depth| {
+//!     if node.node_type == "Seq Scan" {
+//!         seq_scans += 1;
+//!     }
+//! })?;
+//! ```
+
+use std::fmt;
+use std::io;
+
+use serde::de::{self, DeserializeSeed, Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{ExplainError, ExplainPlan};
+
+/// Parses the plans of the `EXPLAIN (FORMAT JSON)` document read from `reader`
+/// and calls `f` with every node and its depth, the root having depth 0.
+///
+/// Nodes are visited in post-order: children before their parent, so a node's
+/// `plans` are always empty; use [`read_plans()`] to get the trees. Top-level
+/// sections such as `Planning Time` are skipped. Unknown fields are kept in
+/// [`ExplainPlan::extra`].
+///
+/// # Errors
+/// Returns [`ExplainError::Parse`] if the document is malformed or cannot be
+/// read. `snippet` and `raw_json` are empty since the document is not kept.
+pub fn for_each_node<R, F>(reader: R, mut f: F) -> Result<(), ExplainError>
+where
+    R: io::Read,
+    F: FnMut(&ExplainPlan, usize),
+{
+    read(reader, &mut f, false).map(drop)
+}
+
+/// Parses the plans of the `EXPLAIN (FORMAT JSON)` document read from `reader`,
+/// one per item, attaching every node to its parent as soon as it is complete.
+///
+/// Top-level sections such as `Planning Time` are skipped, use
+/// [`ExplainItem::parse_all()`](crate::ExplainItem::parse_all) to keep them.
+///
+/// # Errors
+/// Same as [`for_each_node()`].
+pub fn read_plans<R: io::Read>(reader: R) -> Result<Vec<ExplainPlan>, ExplainError> {
+    read(reader, &mut |_: &ExplainPlan, _| {}, true)
+}
+
+/// Parses the document read from `reader`, calling `f` with every node, and
+/// returns the root plans, with their children if `keep_children` is set.
+fn read<R, F>(reader: R, f: &mut F, keep_children: bool) -> Result<Vec<ExplainPlan>, ExplainError>
+where
+    R: io::Read,
+    F: FnMut(&ExplainPlan, usize),
+{
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let mut track = serde_path_to_error::Track::new();
+    let result = Items { f, keep_children }
+        .deserialize(serde_path_to_error::Deserializer::new(
+            &mut deserializer,
+            &mut track,
+        ))
+        .and_then(|plans| deserializer.end().map(|()| plans));
+    result.map_err(|source| ExplainError::Parse {
+        source,
+        path: track.path().to_string(),
+        snippet: String::new(),
+        raw_json: String::new(),
+    })
+}
+
+/// The top-level array of an `EXPLAIN (FORMAT JSON)` document.
+struct Items<'f, F> {
+    f: &'f mut F,
+    keep_children: bool,
+}
+
+impl<'de, F: FnMut(&ExplainPlan, usize)> DeserializeSeed<'de> for Items<'_, F> {
+    type Value = Vec<ExplainPlan>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(&ExplainPlan, usize)> Visitor<'de> for Items<'_, F> {
+    type Value = Vec<ExplainPlan>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of EXPLAIN items")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut plans = Vec::new();
+        while let Some(plan) = seq.next_element_seed(Item {
+            f: &mut *self.f,
+            keep_children: self.keep_children,
+        })? {
+            plans.push(plan);
+        }
+        Ok(plans)
+    }
+}
+
+/// An item of the top-level array, holding the `Plan` and summary sections.
+struct Item<'f, F> {
+    f: &'f mut F,
+    keep_children: bool,
+}
+
+impl<'de, F: FnMut(&ExplainPlan, usize)> DeserializeSeed<'de> for Item<'_, F> {
+    type Value = ExplainPlan;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F: FnMut(&ExplainPlan, usize)> Visitor<'de> for Item<'_, F> {
+    type Value = ExplainPlan;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an EXPLAIN item")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut plan = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "Plan" {
+                plan = Some(map.next_value_seed(Node {
+                    f: &mut *self.f,
+                    depth: 0,
+                    keep_children: self.keep_children,
+                })?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        plan.ok_or_else(|| A::Error::missing_field("Plan"))
+    }
+}
+
+/// A plan node at `depth`. Its fields are collected, its children are visited
+/// as they are read and kept only if `keep_children` is set.
+struct Node<'f, F> {
+    f: &'f mut F,
+    depth: usize,
+    keep_children: bool,
+}
+
+impl<'de, F: FnMut(&ExplainPlan, usize)> DeserializeSeed<'de> for Node<'_, F> {
+    type Value = ExplainPlan;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F: FnMut(&ExplainPlan, usize)> Visitor<'de> for Node<'_, F> {
+    type Value = ExplainPlan;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a plan node")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut fields = Map::new();
+        let mut plans = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "Plans" {
+                plans = map.next_value_seed(Children {
+                    f: &mut *self.f,
+                    depth: self.depth + 1,
+                    keep_children: self.keep_children,
+                })?;
+            } else {
+                fields.insert(key, map.next_value()?);
+            }
+        }
+        let mut node = ExplainPlan::deserialize(Value::Object(fields)).map_err(A::Error::custom)?;
+        (self.f)(&node, self.depth);
+        node.plans = plans;
+        Ok(node)
+    }
+}
+
+/// The `Plans` array of a node, whose elements are at `depth`. The elements are
+/// returned only if `keep_children` is set.
+struct Children<'f, F> {
+    f: &'f mut F,
+    depth: usize,
+    keep_children: bool,
+}
+
+impl<'de, F: FnMut(&ExplainPlan, usize)> DeserializeSeed<'de> for Children<'_, F> {
+    type Value = Vec<ExplainPlan>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(&ExplainPlan, usize)> Visitor<'de> for Children<'_, F> {
+    type Value = Vec<ExplainPlan>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of plan nodes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut plans = Vec::new();
+        while let Some(plan) = seq.next_element_seed(Node {
+            f: &mut *self.f,
+            depth: self.depth,
+            keep_children: self.keep_children,
+        })? {
+            if self.keep_children {
+                plans.push(plan);
+            }
+        }
+        Ok(plans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::ExplainItem;
+
+    fn json_node(node_type: &str, plans: Vec<Value>) -> Value {
+        json!({
+            "Node Type": node_type,
+            "Parallel Aware": false,
+            "Startup Cost": 0.0,
+            "Total Cost": 10.0,
+            "Plan Rows": 1,
+            "Plan Width": 4,
+            "Plans": plans,
+        })
+    }
+
+    fn document() -> String {
+        let mut init_plan = json_node("Seq Scan", Vec::new());
+        init_plan["Parent Relationship"] = json!("InitPlan");
+        init_plan["Subplan Name"] = json!("InitPlan 1 (returns $0)");
+        let mut sub_plan = json_node("Index Scan", Vec::new());
+        sub_plan["Parent Relationship"] = json!("SubPlan");
+        sub_plan["Subplan Name"] = json!("SubPlan 2");
+        let join = json_node(
+            "Nested Loop",
+            vec![json_node("Seq Scan", Vec::new()), sub_plan],
+        );
+        json!([
+            {
+                "Plan": json_node("Result", vec![init_plan, join]),
+                "Planning Time": 0.1,
+            },
+            {"Plan": json_node("Result", Vec::new())},
+        ])
+        .to_string()
+    }
+
+    #[test]
+    fn nodes_are_visited_children_first() {
+        let mut visited = Vec::new();
+        for_each_node(document().as_bytes(), |node, depth| {
+            assert!(node.plans.is_empty());
+            let relationship = node.parent_relationship.clone();
+            visited.push((node.node_type.clone(), depth, relationship));
+        })
+        .unwrap();
+        let relationship = |name: &str| Some(name.to_owned());
+        assert_eq!(
+            visited,
+            [
+                ("Seq Scan".to_owned(), 1, relationship("InitPlan")),
+                ("Seq Scan".to_owned(), 2, None),
+                ("Index Scan".to_owned(), 2, relationship("SubPlan")),
+                ("Nested Loop".to_owned(), 1, None),
+                ("Result".to_owned(), 0, None),
+                ("Result".to_owned(), 0, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn plans_are_read_as_trees() {
+        let json = document();
+        let plans = read_plans(json.as_bytes()).unwrap();
+        let items = ExplainItem::parse_all(&json).unwrap();
+        assert_eq!(
+            plans,
+            items.into_iter().map(|item| item.plan).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            plans[0].plans[1].plans[1].subplan_name.as_deref(),
+            Some("SubPlan 2")
+        );
+    }
+
+    #[test]
+    fn malformed_documents_are_parse_errors() {
+        let json = document();
+        let truncated = &json[..json.len() / 2];
+        assert!(matches!(
+            read_plans(truncated.as_bytes()),
+            Err(ExplainError::Parse { .. })
+        ));
+
+        let wrong_type = json.replacen("\"Plan Rows\":1", "\"Plan Rows\":\"many\"", 2);
+        let Err(ExplainError::Parse { path, .. }) = for_each_node(wrong_type.as_bytes(), |_, _| {})
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(path, "[0].Plan.Plans[0]");
+
+        let err = read_plans(r#"[{"Planning Time": 0.1}]"#.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("missing field `Plan`"), "{err}");
+    }
+}
@@ -0,0 +1,31 @@
+//! Helpers shared by the unit tests of the modules.
+
+use crate::{ExplainItem, ExplainPlan};
+
+/// The JSON of an `EXPLAIN` item consisting of a single `Result` node.
+pub(crate) const RESULT_ITEM: &str = r#"{"Plan": {"Node Type": "Result", "Parallel Aware": false,
+        "Startup Cost": 0.0, "Total Cost": 0.01, "Plan Rows": 1, "Plan Width": 4}}"#;
+
+/// Creates a node of `node_type` with the children `plans`.
+pub(crate) fn node(node_type: &str, plans: Vec<ExplainPlan>) -> ExplainPlan {
+    ExplainPlan {
+        plans,
+        ..ExplainPlan::new(node_type)
+    }
+}
+
+/// Wraps `plan` into an item without any summary sections.
+pub(crate) fn item(plan: ExplainPlan) -> ExplainItem {
+    ExplainItem {
+        plan,
+        planning: None,
+        planning_time: None,
+        triggers: None,
+        jit: None,
+        settings: None,
+        query_identifier: None,
+        serialization: None,
+        execution_time: None,
+        extra: Default::default(),
+    }
+}
@@ -0,0 +1,967 @@
+//! Parsing plans in the `EXPLAIN` text format.
+//!
+//! The text format carries mostly the same information as the JSON format, under
+//! the same names, so each node is turned into the JSON object PostgreSQL would
+//! have produced and deserialized like any other plan.
+
+use serde_json::{Map, Value};
+
+use crate::{deserialize, ExplainError, ExplainItem};
+
+impl ExplainItem {
+    /// Parses a plan in the default `EXPLAIN` text format, with or without
+    /// `ANALYZE`, e.g. copied from `psql` or a log file, into the same types as
+    /// a JSON plan.
+    ///
+    /// `psql` decorations, i.e. the `QUERY PLAN` header, the dashed line, the
+    /// leading space and the `(N rows)` footer, are stripped. Some details are
+    /// only reported in the JSON format and are missing from plans parsed from
+    /// text, e.g. `Parent Relationship` is inferred from the position of a node,
+    /// and plans captured with `COSTS OFF` get zero costs. Lines which can't be
+    /// mapped to a JSON field are kept as strings in [`ExplainPlan::extra`].
+    ///
+    /// Example:
+    /// ```rust
+    /// let item = ExplainItem::from_text(
+    ///     "Seq Scan on users  (cost=0.00..35.50 rows=2550 width=4)\n  Filter: (age > 30)",
+    /// )?;
+    /// assert_eq!(item.plan.relation_name.as_deref(), Some("users"));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`ExplainError::ParseText`] if a line doesn't look like part of a
+    /// text plan, or [`ExplainError::Parse`] if a value doesn't fit its field, e.g.
+    /// a negative row count.
+    ///
+    /// [`ExplainPlan::extra`]: crate::ExplainPlan::extra
+    pub fn from_text(text: &str) -> Result<Self, ExplainError> {
+        let item = Parser::default().parse(text)?;
+        deserialize(&Value::Object(item).to_string())
+    }
+}
+
+/// What the lines indented below a header line belong to.
+enum Kind {
+    Node,
+    Worker,
+    Section(String),
+}
+
+struct Frame {
+    /// Indentation of the lines belonging to this frame.
+    indent: usize,
+    kind: Kind,
+    object: Map<String, Value>,
+}
+
+#[derive(Default)]
+struct Parser {
+    item: Map<String, Value>,
+    stack: Vec<Frame>,
+    /// `InitPlan`, `SubPlan` or `CTE` label applying to the next node.
+    label: Option<(&'static str, String)>,
+}
+
+impl Parser {
+    fn parse(mut self, text: &str) -> Result<Map<String, Value>, ExplainError> {
+        let lines = plan_lines(text);
+        let margin = lines
+            .iter()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(_, line)| indentation(line))
+            .min()
+            .unwrap_or_default();
+        for (number, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line = line[margin..].trim_end();
+            self.line(line).map_err(|message| ExplainError::ParseText {
+                line: number,
+                message,
+            })?;
+        }
+        self.close(0)
+            .map_err(|message| ExplainError::ParseText { line: 0, message })?;
+        if !self.item.contains_key("Plan") {
+            return Err(ExplainError::EmptyResult);
+        }
+        Ok(self.item)
+    }
+
+    fn line(&mut self, line: &str) -> Result<(), String> {
+        let indent = indentation(line);
+        let content = &line[indent..];
+
+        if let Some(header) = content.strip_prefix("->  ") {
+            self.close(indent)?;
+            return self.push_node(indent + 6, header);
+        }
+        if indent == 0 && self.stack.is_empty() && !self.item.contains_key("Plan") {
+            return self.push_node(2, content);
+        }
+        self.close(indent)?;
+
+        if let Some(label) = subplan_label(content) {
+            self.label = Some(label);
+            return Ok(());
+        }
+        if let Some(worker) = content.strip_prefix("Worker ") {
+            let (number, rest) = worker
+                .split_once(':')
+                .ok_or_else(|| format!("expected `Worker N:`, found `{content}`"))?;
+            let mut object = Map::new();
+            object.insert("Worker Number".to_owned(), scalar("", number));
+            let rest = rest.trim_start();
+            if let Some(actual) = rest.strip_prefix("actual ") {
+                actual_stats(&mut object, actual)?;
+            } else if !rest.is_empty() {
+                add_property(&mut object, rest)?;
+            }
+            self.stack.push(Frame {
+                indent: indent + 2,
+                kind: Kind::Worker,
+                object,
+            });
+            return Ok(());
+        }
+
+        match self.stack.last_mut() {
+            Some(_) if is_section(content) => {
+                self.push_section(indent + 2, content);
+                Ok(())
+            }
+            Some(frame) => add_property(&mut frame.object, content),
+            None => self.add_summary(content),
+        }
+    }
+
+    /// Opens a section like `JIT:`, whose lines follow indented by `indent`.
+    fn push_section(&mut self, indent: usize, content: &str) {
+        self.stack.push(Frame {
+            indent,
+            kind: Kind::Section(content.trim_end_matches(':').to_owned()),
+            object: Map::new(),
+        });
+    }
+
+    /// Adds a line following the plan tree, e.g. `Execution Time: 1.234 ms`.
+    fn add_summary(&mut self, content: &str) -> Result<(), String> {
+        if is_section(content) {
+            self.push_section(2, content);
+            return Ok(());
+        }
+        if content.starts_with("Trigger ") {
+            push_to(&mut self.item, "Triggers", trigger(content)?);
+            return Ok(());
+        }
+        let (key, value) = split_property(content)?;
+        let value = match key {
+            "Planning Time" | "Execution Time" => scalar(key, value.trim_end_matches(" ms")),
+            "Settings" => settings(value),
+            _ => scalar(key, value),
+        };
+        self.item.insert(key.to_owned(), value);
+        Ok(())
+    }
+
+    fn push_node(&mut self, indent: usize, header: &str) -> Result<(), String> {
+        let mut object = parse_header(header)?;
+        if let Some((relationship, name)) = self.label.take() {
+            object.insert("Parent Relationship".to_owned(), relationship.into());
+            object.insert("Subplan Name".to_owned(), name.into());
+        }
+        self.stack.push(Frame {
+            indent,
+            kind: Kind::Node,
+            object,
+        });
+        Ok(())
+    }
+
+    /// Closes the frames whose lines are indented deeper than `indent`.
+    fn close(&mut self, indent: usize) -> Result<(), String> {
+        while self.stack.last().is_some_and(|frame| frame.indent > indent) {
+            self.pop()?;
+        }
+        Ok(())
+    }
+
+    /// Closes the innermost frame, attaching it to its parent.
+    fn pop(&mut self) -> Result<(), String> {
+        let Some(Frame {
+            kind, mut object, ..
+        }) = self.stack.pop()
+        else {
+            return Ok(());
+        };
+        match (kind, self.stack.last_mut()) {
+            (Kind::Node, None) => {
+                if self.item.contains_key("Plan") {
+                    return Err("found more than one plan".to_owned());
+                }
+                self.item.insert("Plan".to_owned(), Value::Object(object));
+            }
+            (Kind::Node, Some(parent)) => {
+                if !object.contains_key("Parent Relationship") {
+                    let relationship = relationship(&parent.object);
+                    object.insert("Parent Relationship".to_owned(), relationship.into());
+                }
+                push_to(&mut parent.object, "Plans", Value::Object(object));
+            }
+            (Kind::Worker, Some(parent)) => {
+                push_to(&mut parent.object, "Workers", Value::Object(object));
+            }
+            (Kind::Worker, None) => return Err("worker statistics outside of a node".to_owned()),
+            (Kind::Section(name), parent) => {
+                let value = match name.as_str() {
+                    "JIT" => jit(object)?,
+                    _ => Value::Object(object),
+                };
+                match parent {
+                    Some(parent) => parent.object.insert(name, value),
+                    None => self.item.insert(name, value),
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_section(content: &str) -> bool {
+    content.ends_with(':') && !content.contains(": ")
+}
+
+/// Returns the lines of `text` with their 1-based numbers, without `psql`
+/// decorations.
+fn plan_lines(text: &str) -> Vec<(usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| {
+            let trimmed = line.trim();
+            let is_footer = trimmed.starts_with('(')
+                && (trimmed.ends_with(" rows)") || trimmed.ends_with(" row)"))
+                && trimmed[1..].starts_with(|c: char| c.is_ascii_digit());
+            trimmed != "QUERY PLAN"
+                && !is_footer
+                && !(trimmed.starts_with('-') && trimmed.chars().all(|c| c == '-' || c == '+'))
+        })
+        .collect()
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn push_to(object: &mut Map<String, Value>, key: &str, value: Value) {
+    if let Value::Array(values) = object
+        .entry(key)
+        .or_insert_with(|| Value::Array(Vec::new()))
+    {
+        values.push(value);
+    }
+}
+
+/// Returns the relationship of the next child of `parent`, which the text format
+/// only shows for subplans.
+fn relationship(parent: &Map<String, Value>) -> &'static str {
+    let children = parent
+        .get("Plans")
+        .and_then(Value::as_array)
+        .map_or(0, |children| {
+            children
+                .iter()
+                .filter(|child| {
+                    !matches!(
+                        child.get("Parent Relationship").and_then(Value::as_str),
+                        Some("InitPlan" | "SubPlan")
+                    )
+                })
+                .count()
+        });
+    match parent.get("Node Type").and_then(Value::as_str) {
+        Some("Append" | "Merge Append" | "BitmapAnd" | "BitmapOr" | "Recursive Union") => "Member",
+        Some("Subquery Scan") => "Subquery",
+        _ if children == 0 => "Outer",
+        _ => "Inner",
+    }
+}
+
+/// Recognizes the `InitPlan 1 (returns $0)`, `SubPlan 2` and `CTE name` lines
+/// preceding subplans.
+fn subplan_label(content: &str) -> Option<(&'static str, String)> {
+    if content.contains(": ") {
+        return None;
+    }
+    let relationship = match content.split_once(' ')?.0 {
+        "InitPlan" | "CTE" => "InitPlan",
+        "SubPlan" => "SubPlan",
+        _ => return None,
+    };
+    Some((relationship, content.to_owned()))
+}
+
+/// Parses a node line, e.g.
+/// `Index Scan using users_pkey on users u  (cost=0.29..8.30 rows=1 width=4)`.
+fn parse_header(header: &str) -> Result<Map<String, Value>, String> {
+    let stats_at = header
+        .find("  (")
+        .or_else(|| header.find(" (actual "))
+        .or_else(|| header.find(" (never executed)"))
+        .unwrap_or(header.len());
+    let (description, stats) = header.split_at(stats_at);
+
+    let mut object = describe(description.trim());
+    object.insert("Startup Cost".to_owned(), 0.into());
+    object.insert("Total Cost".to_owned(), 0.into());
+    object.insert("Plan Rows".to_owned(), 0.into());
+    object.insert("Plan Width".to_owned(), 0.into());
+
+    let mut rest = stats.trim();
+    while let Some(group) = rest.strip_prefix('(') {
+        let end = group
+            .find(')')
+            .ok_or_else(|| format!("unbalanced parentheses in `{header}`"))?;
+        let stats = &group[..end];
+        if let Some(cost) = stats.strip_prefix("cost=") {
+            let mut words = cost.split(' ');
+            let (startup, total) = words
+                .next()
+                .and_then(|range| range.split_once(".."))
+                .ok_or_else(|| format!("invalid cost in `{header}`"))?;
+            object.insert("Startup Cost".to_owned(), number(startup)?);
+            object.insert("Total Cost".to_owned(), number(total)?);
+            for (name, value) in words.filter_map(|word| word.split_once('=')) {
+                match name {
+                    "rows" => object.insert("Plan Rows".to_owned(), number(value)?),
+                    "width" => object.insert("Plan Width".to_owned(), number(value)?),
+                    _ => None,
+                };
+            }
+        } else if let Some(actual) = stats.strip_prefix("actual ") {
+            actual_stats(&mut object, actual)?;
+        } else if stats == "never executed" {
+            object.insert("Actual Rows".to_owned(), 0.into());
+            object.insert("Actual Loops".to_owned(), 0.into());
+        } else {
+            return Err(format!("unexpected `({stats})` in `{header}`"));
+        }
+        rest = group[end + 1..].trim_start();
+    }
+    if !rest.is_empty() {
+        return Err(format!("unexpected `{rest}` in `{header}`"));
+    }
+    Ok(object)
+}
+
+/// Parses `time=0.01..1.23 rows=10 loops=1`, or `rows=10 loops=1` without timing.
+fn actual_stats(object: &mut Map<String, Value>, stats: &str) -> Result<(), String> {
+    for (name, value) in stats
+        .split_whitespace()
+        .filter_map(|word| word.split_once('='))
+    {
+        match name {
+            "time" => {
+                let (startup, total) = value
+                    .split_once("..")
+                    .ok_or_else(|| format!("invalid time `{value}`"))?;
+                object.insert("Actual Startup Time".to_owned(), number(startup)?);
+                object.insert("Actual Total Time".to_owned(), number(total)?);
+            }
+            "rows" => {
+                object.insert("Actual Rows".to_owned(), number(value)?);
+            }
+            "loops" => {
+                object.insert("Actual Loops".to_owned(), number(value)?);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Turns a node description, e.g. `Parallel Hash Left Join` or
+/// `Index Scan Backward using users_pkey on public.users u`, into the fields of
+/// the JSON format.
+fn describe(description: &str) -> Map<String, Value> {
+    let mut object = Map::new();
+    let mut set = |key: &str, value: Value| {
+        object.insert(key.to_owned(), value);
+    };
+
+    let (head, target) = match description.split_once(" on ") {
+        Some((head, target)) => (head, Some(target)),
+        None => (description, None),
+    };
+    let (head, index) = match head.split_once(" using ") {
+        Some((head, index)) => (head, Some(index)),
+        None => (head, None),
+    };
+    let (head, parallel) = match head.strip_prefix("Parallel ") {
+        Some(head) => (head, true),
+        None => (head, false),
+    };
+    let (head, is_async) = match head.strip_prefix("Async ") {
+        Some(head) => (head, true),
+        None => (head, false),
+    };
+    let (head, backward) = match head.strip_suffix(" Backward") {
+        Some(head) => (head, true),
+        None => (head, false),
+    };
+
+    let node_type = if let Some((mode, aggregate)) = head
+        .split_once(' ')
+        .filter(|(mode, _)| matches!(*mode, "Partial" | "Finalize"))
+        .filter(|(_, aggregate)| aggregate.ends_with("Aggregate"))
+    {
+        set("Partial Mode", mode.into());
+        set("Strategy", strategy(aggregate).into());
+        "Aggregate"
+    } else if matches!(
+        head,
+        "Aggregate" | "GroupAggregate" | "HashAggregate" | "MixedAggregate"
+    ) {
+        set("Strategy", strategy(head).into());
+        "Aggregate"
+    } else if let Some(command) = head.strip_prefix("HashSetOp ") {
+        set("Strategy", "Hashed".into());
+        set("Command", command.into());
+        "SetOp"
+    } else if let Some(command) = head.strip_prefix("SetOp ") {
+        set("Strategy", "Sorted".into());
+        set("Command", command.into());
+        "SetOp"
+    } else if let Some((node_type, join_type)) = join(head) {
+        set("Join Type", join_type.into());
+        node_type
+    } else if matches!(head, "Insert" | "Update" | "Delete" | "Merge") && target.is_some() {
+        set("Operation", head.into());
+        "ModifyTable"
+    } else if let Some(provider) = head
+        .strip_prefix("Custom Scan (")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        set("Custom Plan Provider", provider.into());
+        "Custom Scan"
+    } else {
+        head
+    };
+    set("Node Type", node_type.into());
+    set("Parallel Aware", parallel.into());
+    set("Async Capable", is_async.into());
+
+    if matches!(node_type, "Index Scan" | "Index Only Scan") {
+        set(
+            "Scan Direction",
+            if backward { "Backward" } else { "Forward" }.into(),
+        );
+    }
+    if let Some(index) = index {
+        set("Index Name", unquote(index).into());
+    }
+    if let Some(target) = target {
+        let mut words = split_words(target).into_iter();
+        let name = words.next().unwrap_or_default();
+        let alias = words.next();
+        let (schema, name) = match name.split_once('.') {
+            Some((schema, name)) if !name.is_empty() => (Some(unquote(schema)), unquote(name)),
+            _ => (None, unquote(&name)),
+        };
+        let name_key = match node_type {
+            "Bitmap Index Scan" => Some("Index Name"),
+            "Function Scan" => Some("Function Name"),
+            "Table Function Scan" => Some("Table Function Name"),
+            "CTE Scan" | "WorkTable Scan" => Some("CTE Name"),
+            "Named Tuplestore Scan" => Some("Tuplestore Name"),
+            "Subquery Scan" | "Values Scan" | "Result" => None,
+            _ => Some("Relation Name"),
+        };
+        match name_key {
+            Some("Index Name") => set("Index Name", name.into()),
+            Some(key) => {
+                set(key, name.clone().into());
+                if let Some(schema) = schema {
+                    set("Schema", schema.into());
+                }
+                set("Alias", alias.as_deref().map_or(name, unquote).into());
+            }
+            None => set("Alias", name.into()),
+        }
+    }
+    object
+}
+
+fn strategy(aggregate: &str) -> &'static str {
+    match aggregate {
+        "GroupAggregate" => "Sorted",
+        "HashAggregate" => "Hashed",
+        "MixedAggregate" => "Mixed",
+        _ => "Plain",
+    }
+}
+
+/// Splits e.g. `Hash Right Anti Join` into `Hash Join` and `Right Anti`.
+fn join(head: &str) -> Option<(&'static str, &str)> {
+    let (node_type, rest) = [
+        ("Nested Loop", "Nested Loop"),
+        ("Hash", "Hash Join"),
+        ("Merge", "Merge Join"),
+    ]
+    .into_iter()
+    .find_map(|(prefix, node_type)| Some((node_type, head.strip_prefix(prefix)?)))?;
+    let join_type = match rest.strip_suffix(" Join") {
+        Some(join_type) => join_type.trim_start(),
+        None if rest.is_empty() && node_type == "Nested Loop" => "",
+        None => return None,
+    };
+    if join_type.is_empty() {
+        return Some((node_type, "Inner"));
+    }
+    if !rest.starts_with(' ') {
+        return None;
+    }
+    Some((node_type, join_type))
+}
+
+/// Splits `text` at spaces outside double quotes.
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.push(c);
+            }
+            ' ' if !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            _ => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Removes the double quotes around an identifier.
+fn unquote(identifier: &str) -> String {
+    match identifier
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        Some(inner) => inner.replace("\"\"", "\""),
+        None => identifier.to_owned(),
+    }
+}
+
+/// Splits a `Key: value` line.
+fn split_property(line: &str) -> Result<(&str, &str), String> {
+    line.split_once(": ")
+        .map(|(key, value)| (key, value.trim()))
+        .ok_or_else(|| format!("expected `key: value`, found `{line}`"))
+}
+
+/// Properties whose values are lists.
+const LISTS: &[&str] = &[
+    "Output",
+    "Sort Key",
+    "Presorted Key",
+    "Group Key",
+    "Sampling Parameters",
+    "Conflict Arbiter Indexes",
+];
+
+/// Properties printed together on one line, separated by two spaces, keyed by the
+/// first of them.
+const COMBINED: &[&str] = &[
+    "Sort Method",
+    "Buckets",
+    "Batches",
+    "Planned Partitions",
+    "Hits",
+    "Storage",
+    "Full-sort Groups",
+    "Pre-sorted Groups",
+];
+
+fn add_property(object: &mut Map<String, Value>, line: &str) -> Result<(), String> {
+    let (key, value) = split_property(line)?;
+    let mut insert = |key: &str, value: Value| {
+        object.insert(key.to_owned(), value);
+    };
+    match key {
+        "Buffers" => {
+            for (kind, op, value) in counters(value) {
+                insert(
+                    &format!("{} {} Blocks", capitalize(kind), capitalize(op)),
+                    number(value)?,
+                );
+            }
+        }
+        "I/O Timings" => {
+            for (kind, op, value) in counters(value) {
+                let key = match kind {
+                    "" | "shared/local" => format!("I/O {} Time", capitalize(op)),
+                    kind => format!("{} I/O {} Time", capitalize(kind), capitalize(op)),
+                };
+                insert(&key, number(value)?);
+            }
+        }
+        "WAL" => {
+            for (_, name, value) in counters(value) {
+                let key = match name {
+                    "fpi" => "WAL FPI".to_owned(),
+                    name => format!("WAL {}", capitalize(name)),
+                };
+                insert(&key, number(value)?);
+            }
+        }
+        "Heap Blocks" => {
+            for (_, name, value) in counters(value) {
+                insert(&format!("{} Heap Blocks", capitalize(name)), number(value)?);
+            }
+        }
+        "Full-sort Groups" | "Pre-sorted Groups" => {
+            insert(key, sort_groups(line)?);
+        }
+        key if COMBINED.contains(&key) => {
+            let pairs = line
+                .split("  ")
+                .map(split_property)
+                .collect::<Result<Vec<_>, _>>()?;
+            let hash = pairs.iter().any(|(key, _)| *key == "Buckets");
+            for (key, value) in pairs {
+                match key {
+                    "Memory" | "Disk" => {
+                        insert("Sort Space Used", kilobytes(value)?);
+                        insert("Sort Space Type", key.into());
+                    }
+                    "Buckets" | "Batches" if hash => {
+                        let name = format!("Hash {key}");
+                        let (current, original) = match value.split_once(" (originally ") {
+                            Some((current, original)) => (current, original.trim_end_matches(')')),
+                            None => (value, value),
+                        };
+                        insert(&name, number(current)?);
+                        insert(&format!("Original {name}"), number(original)?);
+                    }
+                    "Batches" => insert("HashAgg Batches", number(value)?),
+                    "Memory Usage" => insert("Peak Memory Usage", kilobytes(value)?),
+                    "Disk Usage" | "Maximum Storage" => insert(key, kilobytes(value)?),
+                    "Hits" | "Misses" | "Evictions" | "Overflows" => {
+                        insert(&format!("Cache {key}"), number(value)?)
+                    }
+                    key => insert(key, scalar(key, value)),
+                }
+            }
+        }
+        key if LISTS.contains(&key) => {
+            let items = split_list(value).into_iter().map(Value::from).collect();
+            insert(key, Value::Array(items));
+        }
+        key => insert(key, scalar(key, value)),
+    }
+    Ok(())
+}
+
+/// Parses e.g. `shared hit=10 read=2, temp written=5` into
+/// `(kind, counter, value)` triples. Counters without a kind, as in
+/// `exact=3 lossy=1`, get an empty kind.
+fn counters(value: &str) -> Vec<(&str, &str, &str)> {
+    let mut counters = Vec::new();
+    for group in value.split(", ") {
+        let mut kind = "";
+        for word in group.split_whitespace() {
+            match word.split_once('=') {
+                Some((name, value)) => counters.push((kind, name, value)),
+                None => kind = word,
+            }
+        }
+    }
+    counters
+}
+
+/// Parses the statistics of the groups of an `Incremental Sort`, e.g.
+/// `Full-sort Groups: 1  Sort Method: quicksort  Average Memory: 26kB  Peak Memory: 26kB`.
+fn sort_groups(line: &str) -> Result<Value, String> {
+    let mut object = Map::new();
+    for (key, value) in line
+        .split("  ")
+        .map(split_property)
+        .collect::<Result<Vec<_>, _>>()?
+    {
+        if key.ends_with("Groups") {
+            object.insert("Group Count".to_owned(), number(value)?);
+        } else if key.starts_with("Sort Method") {
+            let methods = value.split(", ").map(Value::from).collect();
+            object.insert("Sort Methods Used".to_owned(), Value::Array(methods));
+        } else if let Some((statistic, space_type)) = key.split_once(' ') {
+            let section = format!("Sort Space {space_type}");
+            if let Value::Object(space) = object
+                .entry(section)
+                .or_insert_with(|| Value::Object(Map::new()))
+            {
+                space.insert(format!("{statistic} Sort Space Used"), kilobytes(value)?);
+            }
+        }
+    }
+    Ok(Value::Object(object))
+}
+
+/// Splits a comma-separated list of expressions, ignoring commas inside
+/// parentheses, brackets and quotes.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth = depth.saturating_sub(1),
+            (None, ',') if depth == 0 && chars.peek() == Some(&' ') => {
+                chars.next();
+                items.push(std::mem::take(&mut item));
+                continue;
+            }
+            _ => {}
+        }
+        item.push(c);
+    }
+    items.push(item);
+    items
+}
+
+/// Properties holding expressions or names, which are kept as strings even when
+/// they look like numbers.
+fn is_text(key: &str) -> bool {
+    if key.starts_with("Rows Removed") {
+        return false;
+    }
+    key.ends_with("Cond")
+        || key.ends_with("Filter")
+        || key.ends_with("Key")
+        || key.ends_with("Condition")
+        || matches!(
+            key,
+            "Order By" | "Repeatable Seed" | "Function Call" | "Table Function Call" | "Remote SQL"
+        )
+}
+
+fn scalar(key: &str, value: &str) -> Value {
+    if is_text(key) {
+        return value.into();
+    }
+    if let Ok(n) = value.parse::<i64>() {
+        return n.into();
+    }
+    if let Ok(n) = value.parse::<f64>() {
+        return n.into();
+    }
+    match value {
+        "true" => true.into(),
+        "false" => false.into(),
+        value => value.into(),
+    }
+}
+
+fn number(value: &str) -> Result<Value, String> {
+    match scalar("", value) {
+        n @ Value::Number(_) => Ok(n),
+        _ => Err(format!("expected a number, found `{value}`")),
+    }
+}
+
+fn kilobytes(value: &str) -> Result<Value, String> {
+    number(value.trim_end_matches("kB"))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Parses `work_mem = '64MB', enable_seqscan = 'off'`.
+fn settings(value: &str) -> Value {
+    let mut settings = Map::new();
+    let mut rest = value;
+    while let Some((name, after)) = rest.split_once(" = '") {
+        let mut setting = String::new();
+        let mut chars = after.char_indices().peekable();
+        let mut end = after.len();
+        while let Some((i, c)) = chars.next() {
+            if c == '\'' {
+                if chars.peek().map(|(_, c)| *c) == Some('\'') {
+                    chars.next();
+                } else {
+                    end = i + 1;
+                    break;
+                }
+            }
+            setting.push(c);
+        }
+        settings.insert(name.trim().to_owned(), setting.into());
+        rest = after[end..].trim_start_matches(", ");
+    }
+    Value::Object(settings)
+}
+
+/// Parses e.g. `Trigger audit on public.users: time=0.123 calls=3` or
+/// `Trigger for constraint orders_user_id_fkey: time=0.5 calls=1`.
+fn trigger(content: &str) -> Result<Value, String> {
+    let (head, stats) = content
+        .rsplit_once(": ")
+        .ok_or_else(|| format!("expected trigger statistics, found `{content}`"))?;
+    let mut object = Map::new();
+    let head = head.trim_start_matches("Trigger").trim_start();
+    let (head, relation) = match head.rsplit_once(" on ") {
+        Some((head, relation)) => (head, Some(relation)),
+        None => (head, None),
+    };
+    let (name, constraint) = match head.split_once("for constraint ") {
+        Some((name, constraint)) => (name.trim(), Some(constraint)),
+        None => (head, None),
+    };
+    object.insert("Trigger Name".to_owned(), name.into());
+    if let Some(constraint) = constraint {
+        object.insert("Constraint Name".to_owned(), constraint.into());
+    }
+    if let Some(relation) = relation {
+        object.insert("Relation".to_owned(), relation.into());
+    }
+    for (_, name, value) in counters(stats) {
+        object.insert(capitalize(name), number(value)?);
+    }
+    Ok(Value::Object(object))
+}
+
+/// Converts the lines of the `JIT:` section into the JSON structure, e.g.
+/// `Options: Inlining false, Optimization false` into an object of booleans.
+fn jit(section: Map<String, Value>) -> Result<Value, String> {
+    let mut object = Map::new();
+    for (key, value) in section {
+        let value = match (key.as_str(), value) {
+            ("Options" | "Timing", Value::String(list)) => {
+                let mut entries = Map::new();
+                for entry in split_list(&list) {
+                    let mut words = entry.split_whitespace();
+                    let (Some(name), Some(value)) = (words.next(), words.next()) else {
+                        return Err(format!("invalid JIT {key} `{entry}`"));
+                    };
+                    entries.insert(name.to_owned(), scalar("", value));
+                }
+                Value::Object(entries)
+            }
+            (_, value) => value,
+        };
+        object.insert(key, value);
+    }
+    Ok(Value::Object(object))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_plans_are_parsed() {
+        let text = r#"                                  QUERY PLAN
+------------------------------------------------------------------------------
+ Limit  (cost=0.32..33.25 rows=3 width=73) (actual time=3.976..3.994 rows=3 loops=1)
+   InitPlan 1 (returns $0)
+     ->  Result  (cost=0.00..0.01 rows=1 width=4) (actual time=0.002..0.003 rows=1 loops=1)
+   ->  Hash Left Join  (cost=1.05..2.10 rows=3 width=8) (never executed)
+         Hash Cond: (u.id = p.user_id)
+         ->  Parallel Index Scan Backward using users_pkey on public.users u  (cost=0.29..8.30 rows=1 width=4)
+               Filter: ((name)::text ~~ '%a, b%'::text)
+               Rows Removed by Filter: 5
+         ->  Hash  (cost=1.02..1.02 rows=2 width=8) (actual time=0.1..0.1 rows=2 loops=1)
+               Output: p.user_id, p.id
+               Buckets: 1024 (originally 512)  Batches: 2 (originally 1)  Memory Usage: 9kB
+               Buffers: shared hit=3 read=1, temp written=2
+               Worker 0:  actual time=0.1..0.2 rows=1 loops=1
+                 Sort Method: external merge  Disk: 400kB
+               ->  Seq Scan on posts p  (cost=0.00..1.02 rows=2 width=8)
+ Settings: work_mem = '64kB'
+ Planning Time: 0.321 ms
+ Trigger audit on users: time=0.5 calls=2
+ Execution Time: 4.446 ms
+(19 rows)
+"#;
+        let item = ExplainItem::from_text(text).unwrap();
+        assert_eq!(item.planning_time, Some(0.321));
+        assert_eq!(item.execution_time, Some(4.446));
+        assert_eq!(item.settings().unwrap().unwrap()["work_mem"], "64kB");
+        assert_eq!(item.triggers.unwrap()[0]["Relation"], "users");
+
+        let limit = item.plan;
+        assert_eq!((limit.total_cost, limit.actual_loops), (33.25, Some(1)));
+        let [init, join] = &limit.plans[..] else {
+            panic!("expected two children, got {:?}", limit.plans);
+        };
+        assert_eq!(init.parent_relationship.as_deref(), Some("InitPlan"));
+        assert_eq!(
+            init.subplan_name.as_deref(),
+            Some("InitPlan 1 (returns $0)")
+        );
+        assert_eq!(join.parent_relationship.as_deref(), Some("Outer"));
+        assert_eq!(
+            (join.node_type.as_str(), join.join_type.as_deref()),
+            ("Hash Join", Some("Left"))
+        );
+        assert_eq!(join.actual_loops, Some(0));
+
+        let scan = &join.plans[0];
+        assert_eq!(scan.node_type, "Index Scan");
+        assert!(scan.parallel_aware);
+        assert_eq!(scan.scan_direction.as_deref(), Some("Backward"));
+        assert_eq!(scan.index_name.as_deref(), Some("users_pkey"));
+        assert_eq!(scan.schema.as_deref(), Some("public"));
+        assert_eq!(scan.alias.as_deref(), Some("u"));
+        assert_eq!(scan.rows_removed_by_filter, Some(5.0));
+        assert_eq!(
+            scan.filter.as_deref(),
+            Some("((name)::text ~~ '%a, b%'::text)")
+        );
+
+        let hash = &join.plans[1];
+        assert_eq!(hash.parent_relationship.as_deref(), Some("Inner"));
+        assert_eq!(
+            hash.output.as_deref(),
+            Some(&["p.user_id".to_owned(), "p.id".to_owned()][..])
+        );
+        assert_eq!(
+            (hash.hash_buckets, hash.original_hash_buckets),
+            (Some(1024), Some(512))
+        );
+        assert_eq!(
+            (hash.hash_batches, hash.peak_memory_usage),
+            (Some(2), Some(9))
+        );
+        assert_eq!(
+            (hash.shared_read_blocks, hash.temp_written_blocks),
+            (Some(1), Some(2))
+        );
+        assert_eq!(hash.workers.as_ref().unwrap()[0]["Sort Space Used"], 400);
+        assert_eq!(hash.plans[0].relation_name.as_deref(), Some("posts"));
+
+        assert!(matches!(
+            ExplainItem::from_text("Seq Scan on users  (cost=0.00..1.00 rows=1 width=4)\n  oops"),
+            Err(ExplainError::ParseText { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,63 @@
+//! Support for [`tracing`]: capturing plans inside a span so that they are
+//! correlated with the surrounding trace.
+
+use diesel::query_dsl::methods::LoadQuery;
+use tracing::field::Empty;
+
+use crate::{Explain, ExplainConnection, ExplainError, ExplainPlan};
+
+impl<Q> Explain<Q> {
+    /// Same as [`Explain::explain()`], but runs inside an `explain` span which
+    /// is a child of the current span.
+    ///
+    /// Once the plan is parsed, the span records:
+    /// - `db.plan.top_node`: node type of the root plan node,
+    /// - `db.plan.total_cost`: estimated total cost of the root plan node,
+    /// - `db.plan.planning_time_ms` and `db.plan.execution_time_ms`, when
+    ///   reported by the server.
+    ///
+    /// The same fields are emitted as a `DEBUG` event, and the parsed `EXPLAIN`
+    /// output, serialized back to JSON, is emitted as a `TRACE` event.
+    ///
+    /// # Errors
+    /// Same as [`Explain::explain()`].
+    pub fn explain_traced<'a, C>(self, conn: &mut C) -> Result<ExplainPlan, ExplainError>
+    where
+        C: ExplainConnection,
+        Self: LoadQuery<'a, C, String>,
+    {
+        let span = tracing::info_span!(
+            "explain",
+            db.plan.top_node = Empty,
+            db.plan.total_cost = Empty,
+            db.plan.planning_time_ms = Empty,
+            db.plan.execution_time_ms = Empty,
+        );
+        let _entered = span.enter();
+
+        let item = self.explain_item(conn)?;
+        if tracing::enabled!(tracing::Level::TRACE) {
+            if let Ok(json) = serde_json::to_string(&item) {
+                tracing::trace!(plan_json = %json, "explain output");
+            }
+        }
+
+        span.record("db.plan.top_node", item.plan.node_type.as_str());
+        span.record("db.plan.total_cost", item.plan.total_cost);
+        if let Some(planning_time) = item.planning_time {
+            span.record("db.plan.planning_time_ms", planning_time);
+        }
+        if let Some(execution_time) = item.execution_time {
+            span.record("db.plan.execution_time_ms", execution_time);
+        }
+        tracing::debug!(
+            db.plan.top_node = %item.plan.node_type,
+            db.plan.total_cost = item.plan.total_cost,
+            db.plan.planning_time_ms = item.planning_time,
+            db.plan.execution_time_ms = item.execution_time,
+            "captured query plan"
+        );
+
+        Ok(item.plan)
+    }
+}
@@ -0,0 +1,176 @@
+//! "What if" experiments with hypothetical indexes provided by the
+//! [HypoPG](https://hypopg.readthedocs.io) extension.
+//!
+//! Hypothetical indexes exist only in the planner of the current session: no
+//! index is built and no DDL is executed, so they are cheap to try even on
+//! production databases. The extension must be installed with
+//! `CREATE EXTENSION hypopg`.
+//!
+//! Example:
+//! ```rust
+//! use diesel_pg_explain::whatif;
+//!
+//! let query = users::table.filter(users::age.gt(30)).select(users::id);
+//! let report = whatif::with_hypothetical_index(
+//!     &mut conn,
+//!     "CREATE INDEX ON users (age)",
+//!     query,
+//! )?;
+//! if report.used {
+//!     println!("{} would cut the cost to {}", report.index_name, report.plan.total_cost);
+//! }
+//! ```
+
+use diesel::pg::Pg;
+use diesel::query_builder::{BoxedSqlQuery, SqlQuery};
+use diesel::query_dsl::methods::LoadQuery;
+use diesel::sql_types::{Oid, Text};
+use diesel::{QueryableByName, RunQueryDsl};
+
+use crate::{Explain, ExplainConnection, ExplainError, ExplainPlan};
+
+/// The outcome of [`with_hypothetical_index()`].
+#[derive(Debug)]
+pub struct HypotheticalIndexReport {
+    /// Name HypoPG gave the hypothetical index, e.g. `<16391>btree_users_age`.
+    pub index_name: String,
+
+    /// The plan chosen while the hypothetical index existed.
+    pub plan: ExplainPlan,
+
+    /// Whether any node of `plan` scans the hypothetical index.
+    pub used: bool,
+}
+
+#[derive(QueryableByName)]
+struct HypotheticalIndex {
+    #[diesel(sql_type = Oid)]
+    indexrelid: u32,
+    #[diesel(sql_type = Text)]
+    indexname: String,
+}
+
+/// Creates the hypothetical index described by `create_index`, a regular
+/// `CREATE INDEX` statement, explains `query` and drops the index again.
+///
+/// Hypothetical indexes are only considered by plain `EXPLAIN`, so `query` is
+/// explained without `ANALYZE`.
+///
+/// # Errors
+/// Returns [`ExplainError::Diesel`] if HypoPG is not installed or rejects
+/// `create_index`, and otherwise fails the same way as [`Explain::explain()`].
+pub fn with_hypothetical_index<'a, C, Q>(
+    conn: &mut C,
+    create_index: &str,
+    query: Q,
+) -> Result<HypotheticalIndexReport, ExplainError>
+where
+    C: ExplainConnection,
+    Explain<Q>: LoadQuery<'a, C, String>,
+{
+    let (index, plan) = with_index(
+        conn,
+        |conn| Ok(create_query(create_index).get_result::<HypotheticalIndex>(conn)?),
+        |conn| Explain::new(query).explain(conn),
+        |conn, index| {
+            drop_query(index.indexrelid).execute(conn)?;
+            Ok(())
+        },
+    )?;
+
+    let used = plan
+        .nodes()
+        .any(|node| node.index_name.as_deref() == Some(index.indexname.as_str()));
+    Ok(HypotheticalIndexReport {
+        index_name: index.indexname,
+        plan,
+        used,
+    })
+}
+
+fn create_query(create_index: &str) -> BoxedSqlQuery<'_, Pg, SqlQuery> {
+    diesel::sql_query("SELECT indexrelid, indexname FROM hypopg_create_index($1)")
+        .into_boxed()
+        .bind::<Text, _>(create_index)
+}
+
+fn drop_query(indexrelid: u32) -> BoxedSqlQuery<'static, Pg, SqlQuery> {
+    diesel::sql_query("SELECT hypopg_drop_index($1)")
+        .into_boxed()
+        .bind::<Oid, _>(indexrelid)
+}
+
+/// Creates a hypothetical index, runs `explain` and drops the index again
+/// whatever the outcome, since hypothetical indexes are not transactional.
+/// Errors of `explain` take precedence over those of dropping the index.
+fn with_index<C, I, T>(
+    conn: &mut C,
+    create: impl FnOnce(&mut C) -> Result<I, ExplainError>,
+    explain: impl FnOnce(&mut C) -> Result<T, ExplainError>,
+    drop_index: impl FnOnce(&mut C, &I) -> Result<(), ExplainError>,
+) -> Result<(I, T), ExplainError> {
+    let index = create(conn)?;
+    let explained = explain(conn);
+    let dropped = drop_index(conn, &index);
+    let explained = explained?;
+    dropped?;
+    Ok((index, explained))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hypopg_functions_are_called_with_bind_parameters() {
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&create_query("CREATE INDEX ON users (age)")).to_string(),
+            r#"SELECT indexrelid, indexname FROM hypopg_create_index($1) -- binds: ["CREATE INDEX ON users (age)"]"#
+        );
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&drop_query(16391)).to_string(),
+            "SELECT hypopg_drop_index($1) -- binds: [16391]"
+        );
+    }
+
+    #[test]
+    fn hypothetical_indexes_are_dropped_after_failures() {
+        fn call(calls: &mut Vec<&str>, name: &'static str) -> Result<(), ExplainError> {
+            calls.push(name);
+            Ok(())
+        }
+        let run = |explained: Result<(), ExplainError>, dropped: Result<(), ExplainError>| {
+            let mut calls = Vec::new();
+            let result = with_index(
+                &mut calls,
+                |calls| call(calls, "create"),
+                |calls| call(calls, "explain").and(explained),
+                |calls, ()| call(calls, "drop").and(dropped),
+            );
+            (result, calls)
+        };
+        let (result, calls) = run(Ok(()), Ok(()));
+        assert!(result.is_ok());
+        assert_eq!(calls, ["create", "explain", "drop"]);
+
+        let (result, calls) = run(
+            Err(ExplainError::EmptyResult),
+            Err(ExplainError::MultipleResults { count: 2 }),
+        );
+        assert!(matches!(result, Err(ExplainError::EmptyResult)));
+        assert_eq!(calls, ["create", "explain", "drop"]);
+
+        let (result, _) = run(Ok(()), Err(ExplainError::MultipleResults { count: 2 }));
+        assert!(matches!(result, Err(ExplainError::MultipleResults { .. })));
+
+        let mut calls = Vec::new();
+        let result = with_index(
+            &mut calls,
+            |_| Err::<(), _>(ExplainError::EmptyResult),
+            |calls| call(calls, "explain"),
+            |calls, ()| call(calls, "drop"),
+        );
+        assert!(result.is_err());
+        assert!(calls.is_empty());
+    }
+}
@@ -0,0 +1,29 @@
+#![cfg(feature = "proptest")]
+
+use diesel_pg_explain::ExplainPlan;
+use proptest::prelude::*;
+
+fn check_costs(plan: &ExplainPlan) {
+    assert!(plan.startup_cost <= plan.total_cost);
+    for child in &plan.plans {
+        assert!(child.parent_relationship.is_some());
+        assert!(child.total_cost <= plan.total_cost);
+        check_costs(child);
+    }
+}
+
+proptest! {
+    #[test]
+    fn generated_plans_are_consistent(plan in any::<ExplainPlan>()) {
+        prop_assert!(plan.parent_relationship.is_none());
+        check_costs(&plan);
+    }
+
+    #[test]
+    fn generated_plans_survive_json_roundtrip(plan in any::<ExplainPlan>()) {
+        let json = serde_json::to_string(&plan).unwrap();
+        let parsed: ExplainPlan = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(parsed.node_type, plan.node_type);
+        prop_assert_eq!(parsed.plans.len(), plan.plans.len());
+    }
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "arrow")]
+
+use arrow::array::{Array, AsArray};
+use arrow::datatypes::UInt64Type;
+use diesel_pg_explain::{export, ExplainPlan};
+
+fn plan(relation: &str) -> ExplainPlan {
+    ExplainPlan {
+        node_type: "Limit".to_owned(),
+        plans: vec![ExplainPlan {
+            node_type: "Seq Scan".to_owned(),
+            relation_name: Some(relation.to_owned()),
+            actual_loops: Some(1),
+            ..ExplainPlan::default()
+        }],
+        ..ExplainPlan::default()
+    }
+}
+
+#[test]
+fn nodes_of_all_plans_become_rows() {
+    let plans = [plan("users"), plan("orders")];
+    let batch = export::to_record_batch(&plans).unwrap();
+    assert_eq!(batch.schema(), export::schema());
+    assert_eq!(batch.num_rows(), 4);
+
+    let plan_ids = batch
+        .column_by_name("plan_id")
+        .unwrap()
+        .as_primitive::<UInt64Type>();
+    assert_eq!(plan_ids.values().to_vec(), [0, 0, 1, 1]);
+    let relations = batch
+        .column_by_name("relation_name")
+        .unwrap()
+        .as_string::<i32>();
+    assert!(relations.is_null(0));
+    assert_eq!(relations.value(3), "orders");
+    let loops = batch.column_by_name("actual_loops").unwrap();
+    assert_eq!(loops.null_count(), 2);
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn plans_are_written_as_parquet() {
+    let mut file = Vec::new();
+    export::to_parquet(&[plan("users")], &mut file).unwrap();
+    assert!(file.starts_with(b"PAR1") && file.ends_with(b"PAR1"));
+}
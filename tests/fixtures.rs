@@ -0,0 +1,63 @@
+#![cfg(feature = "fixtures")]
+
+use diesel_pg_explain::{fixtures, stream, ExplainPlan, ParseMode};
+
+#[test]
+fn every_fixture_parses() {
+    for fixture in fixtures::all() {
+        let plans = fixture.plans().unwrap_or_else(|e| {
+            panic!(
+                "pg{}/{} failed to parse: {e}",
+                fixture.server_version, fixture.name
+            )
+        });
+        assert!(!plans.is_empty());
+    }
+}
+
+#[test]
+fn every_fixture_field_is_modelled() {
+    for fixture in fixtures::all() {
+        if let Err(e) = fixture.plans_with_mode(ParseMode::Strict) {
+            panic!("pg{}/{}: {e}", fixture.server_version, fixture.name);
+        }
+    }
+}
+
+#[test]
+fn streaming_visits_every_node() {
+    for fixture in fixtures::all() {
+        let mut streamed = Vec::new();
+        stream::for_each_node(fixture.json.as_bytes(), |node, depth| {
+            streamed.push((node.node_type.clone(), depth));
+        })
+        .unwrap();
+
+        let mut expected = Vec::new();
+        for plan in fixture.plans().unwrap() {
+            post_order(&plan, 0, &mut expected);
+        }
+        assert_eq!(
+            streamed, expected,
+            "pg{}/{}",
+            fixture.server_version, fixture.name
+        );
+    }
+}
+
+fn post_order(plan: &ExplainPlan, depth: usize, out: &mut Vec<(String, usize)>) {
+    for child in &plan.plans {
+        post_order(child, depth + 1, out);
+    }
+    out.push((plan.node_type.clone(), depth));
+}
+
+#[test]
+fn every_supported_version_is_covered() {
+    for version in 12..=17 {
+        assert!(
+            fixtures::for_version(version).next().is_some(),
+            "no fixtures for PostgreSQL {version}"
+        );
+    }
+}
@@ -0,0 +1,30 @@
+#![cfg(feature = "sentry")]
+
+use diesel_pg_explain::{sentry, ExplainPlan};
+use sentry_core::protocol::Context;
+
+#[test]
+fn plans_are_summarized_for_events() {
+    let plan = ExplainPlan {
+        total_cost: 12.5,
+        actual_total_time: Some(3.0),
+        plans: vec![ExplainPlan::scan("Seq Scan", "users")],
+        ..ExplainPlan::new("Limit")
+    };
+
+    let Context::Other(context) = sentry::plan_context(&plan) else {
+        panic!("unexpected context type");
+    };
+    assert_eq!(context["top_node"], "Limit");
+    assert_eq!(context["node_count"], 2);
+    assert_eq!(context["execution_time_ms"], 3.0);
+    assert!(context["tree"].as_str().unwrap().contains("->  Seq Scan"));
+
+    let breadcrumb = sentry::plan_breadcrumb(&plan);
+    assert_eq!(breadcrumb.category.as_deref(), Some("db.plan"));
+    assert_eq!(breadcrumb.data["total_cost"], 12.5);
+
+    let attachment = sentry::plan_attachment(&plan);
+    let json: ExplainPlan = serde_json::from_slice(&attachment.buffer).unwrap();
+    assert_eq!(json, plan);
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "slog")]
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use diesel_pg_explain::slog::PlanSummary;
+use diesel_pg_explain::ExplainPlan;
+use slog::{o, Drain, Key, Logger, OwnedKVList, Record, Serializer, KV};
+
+/// Collects the fields of all logged records.
+#[derive(Clone, Default)]
+struct Fields(Arc<Mutex<Vec<(String, String)>>>);
+
+impl Serializer for Fields {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments<'_>) -> slog::Result {
+        self.0
+            .lock()
+            .unwrap()
+            .push((key.to_string(), val.to_string()));
+        Ok(())
+    }
+}
+
+impl Drain for Fields {
+    type Ok = ();
+    type Err = slog::Error;
+
+    fn log(&self, record: &Record<'_>, _values: &OwnedKVList) -> Result<(), slog::Error> {
+        record.kv().serialize(record, &mut self.clone())
+    }
+}
+
+#[test]
+fn plans_are_logged_as_fields() {
+    let fields = Fields::default();
+    let logger = Logger::root(fields.clone().fuse(), o!());
+    let plan = ExplainPlan {
+        total_cost: 2.5,
+        actual_total_time: Some(0.25),
+        plans: vec![ExplainPlan::scan("Seq Scan", "users")],
+        ..ExplainPlan::new("Limit")
+    };
+
+    slog::info!(logger, "explained"; PlanSummary(&plan));
+    slog::info!(logger, "explained"; "plan" => &plan);
+
+    let fields = fields.0.lock().unwrap();
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    assert_eq!(field("plan.top_node"), Some("Limit"));
+    assert_eq!(field("plan.total_cost"), Some("2.5"));
+    assert_eq!(field("plan.node_count"), Some("2"));
+    assert_eq!(field("plan.execution_time_ms"), Some("0.25"));
+    assert_eq!(field("plan.shared_read_blocks"), None);
+    assert_eq!(field("plan"), Some("Limit (cost=2.50, 2 nodes)"));
+}